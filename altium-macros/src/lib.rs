@@ -105,6 +105,131 @@ fn inner(tokens: TokenStream2) -> syn::Result<TokenStream2> {
     Ok(ret)
 }
 
+/// Derive `ToRecord` for a type, the inverse of `FromRecord`. This uses the
+/// same `from_record` attributes so both directions agree on key names.
+///
+/// Fields that are equal to their default value are skipped, which is what
+/// Altium does. The only supported `convert` function is `mils_to_nm`.
+#[proc_macro_derive(ToRecord, attributes(from_record))]
+pub fn derive_torecord(tokens: TokenStream) -> TokenStream {
+    to_record_inner(tokens.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn to_record_inner(tokens: TokenStream2) -> syn::Result<TokenStream2> {
+    let parsed: DeriveInput = parse2(tokens)?;
+    let struct_ident = parsed.ident;
+    let Data::Struct(data) = parsed.data else {
+        panic!("only usable on structs");
+    };
+
+    let mut struct_attr_map = parse_attrs(parsed.attrs).expect("attribute with `id = n` required");
+    let TokenTree::Literal(id) = struct_attr_map.remove("id").expect("record ID required") else {
+        panic!("record id should be a literal");
+    };
+
+    // These only affect how we create a `SchRecord`
+    struct_attr_map.remove("use_box");
+    struct_attr_map.remove("record_variant");
+    error_if_map_not_empty(&struct_attr_map);
+
+    let write_stmts: Vec<TokenStream2> = data.fields.into_iter().map(write_field).collect();
+
+    let ret = quote! {
+        impl crate::write::ToRecord for #struct_ident {
+            fn to_record(&self, writer: &mut crate::write::MapWriter) {
+                writer.field(b"RECORD", &#id);
+                #(#write_stmts)*
+            }
+        }
+    };
+
+    Ok(ret)
+}
+
+/// Create the statement that writes a single field
+fn write_field(field: Field) -> TokenStream2 {
+    let Type::Path(path) = field.ty else {
+        panic!("invalid type")
+    };
+
+    let field_ident = field.ident.unwrap();
+    let field_attr_map = parse_attrs(field.attrs).unwrap_or_default();
+
+    // Undo the conversion that was applied when parsing
+    let convert = |val: TokenStream2| match field_attr_map.get("convert") {
+        Some(TokenTree::Ident(conv_fn)) if conv_fn == "mils_to_nm" => {
+            quote! { crate::common::nm_to_mils(#val) }
+        }
+        Some(v) => panic!("no inverse known for conversion {v}"),
+        None => quote! { #val },
+    };
+
+    if let Some(arr_map) = field_attr_map.get("array_map") {
+        let Some(TokenTree::Literal(count_pat)) = field_attr_map.get("count") else {
+            panic!("expected a literal for `count`");
+        };
+        let index_base = array_index_base(field_attr_map.get("index_base"));
+
+        let item_stmts = parse_attr_map(arr_map.clone())
+            .into_iter()
+            .map(|(key, member)| {
+                let key_bstr = Literal::byte_string(key.to_string().as_bytes());
+                let val = convert(quote! { item.#member });
+                quote! { writer.indexed_field(#key_bstr, idx + #index_base, &#val); }
+            })
+            .collect::<Vec<_>>();
+
+        return quote! {
+            writer.nondefault_field(#count_pat, &self.#field_ident.len());
+            for (idx, item) in self.#field_ident.iter().enumerate() {
+                #(#item_stmts)*
+            }
+        };
+    }
+
+    let path_str = path.to_token_stream().to_string();
+
+    // `Location` and `LocationFract` are split into `.X` and `.Y` keys
+    let is_location_fract = path_str.contains("LocationFract");
+    if is_location_fract || path_str.contains("Location") {
+        let base_field_str = field_ident.to_string().to_case(Case::Pascal);
+        let mut stmts = Vec::new();
+
+        for axis in ["X", "Y"] {
+            let member = Ident::new(&axis.to_lowercase(), Span::call_site());
+            let key = Literal::byte_string(format!("{base_field_str}.{axis}").as_bytes());
+            stmts.push(quote! {
+                writer.nondefault_field(
+                    #key,
+                    &crate::common::nm_to_mils(self.#field_ident.#member),
+                );
+            });
+
+            if is_location_fract {
+                let member =
+                    Ident::new(&format!("{}_fract", axis.to_lowercase()), Span::call_site());
+                let key = Literal::byte_string(format!("{base_field_str}.{axis}_Frac").as_bytes());
+                stmts.push(quote! {
+                    writer.nondefault_field(#key, &self.#field_ident.#member);
+                });
+            }
+        }
+
+        return quote! { #(#stmts)* };
+    }
+
+    let key = match field_attr_map.get("rename") {
+        Some(TokenTree::Literal(v)) => v.clone(),
+        Some(v) => panic!("expected literal, got {v:?}"),
+        None => create_key_name(&field_ident),
+    };
+    let val = convert(quote! { self.#field_ident });
+
+    quote! { writer.nondefault_field(#key, &#val); }
+}
+
 fn handle_field(
     field: Field,
     struct_ident: &Ident,
@@ -131,12 +256,14 @@ fn handle_field(
         let count_ident = field_attr_map
             .remove("count")
             .expect("missing 'count' attribute");
+        let index_base = array_index_base(field_attr_map.remove("index_base").as_ref());
 
         process_array(
             struct_ident,
             &field_ident,
             count_ident,
             arr_map,
+            &index_base,
             convert,
             match_arms,
         );
@@ -382,12 +509,27 @@ fn process_location(
     }
 }
 
+/// The index of the first array item in key names, `X1` by default. Some
+/// records count from 0 instead, e.g. `ModelDatafileEntity0`.
+fn array_index_base(base: Option<&TokenTree>) -> Literal {
+    match base {
+        Some(TokenTree::Literal(v)) => Literal::usize_unsuffixed(
+            v.to_string()
+                .parse()
+                .expect("expected an integer for `index_base`"),
+        ),
+        Some(v) => panic!("expected a literal for `index_base`, got {v:?}"),
+        None => Literal::usize_unsuffixed(1),
+    }
+}
+
 /// Setup handling of `X1 = 1234, Y1 = 909`
 fn process_array(
     struct_ident: &Ident,
     field_ident: &Ident,
     count_ident_tt: TokenTree,
     arr_map_tt: TokenTree,
+    index_base: &Literal,
     convert: TokenStream2,
     match_stmts: &mut Vec<TokenStream2>,
 ) {
@@ -433,7 +575,7 @@ fn process_array(
                         stringify!(#struct_ident)
                     ))?;
 
-                ret.#field_ident[idx - 1].#assign_value = parsed_val;
+                ret.#field_ident[idx - #index_base].#assign_value = parsed_val;
             },
         };
         match_stmts.push(item_match);
@@ -1,11 +1,12 @@
 use std::{fmt, str};
 
-use num_traits::CheckedMul;
+use num_traits::{CheckedMul, PrimInt};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::error::{AddContext, ErrorKind, Result, TruncBuf};
 use crate::parse::{FromUtf8, ParseUtf8};
+use crate::write::ToUtf8;
 
 /// Separator in textlike streams
 const SEP: u8 = b'|';
@@ -50,8 +51,9 @@ impl Location {
         self.y as f32
     }
 
+    /// A location from x and y in nm
     #[must_use]
-    pub(crate) fn new(x: i32, y: i32) -> Self {
+    pub fn new(x: i32, y: i32) -> Self {
         Self { x, y }
     }
 
@@ -81,6 +83,36 @@ impl LocationFract {
             y: self.y,
         }
     }
+
+    /// Create a location from nm values that may not land on a whole unit,
+    /// storing the remainder in the fraction fields (1/100000 of a unit)
+    pub(crate) fn from_nm(x: i32, y: i32) -> Self {
+        const UNIT: i64 = 25400;
+        const FRACT_PER_UNIT: i64 = 100_000;
+
+        let split = |val: i32| -> (i32, i32) {
+            let val = i64::from(val);
+            let whole = val.div_euclid(UNIT);
+            let fract = (val.rem_euclid(UNIT) * FRACT_PER_UNIT + UNIT / 2) / UNIT;
+            let (whole, fract) = if fract >= FRACT_PER_UNIT {
+                (whole + 1, 0)
+            } else {
+                (whole, fract)
+            };
+            let whole =
+                i32::try_from(whole * UNIT).unwrap_or(if whole < 0 { i32::MIN } else { i32::MAX });
+            (whole, i32::try_from(fract).unwrap())
+        };
+
+        let (x, x_fract) = split(x);
+        let (y, y_fract) = split(y);
+        Self {
+            x,
+            x_fract,
+            y,
+            y_fract,
+        }
+    }
 }
 
 impl From<(i32, i32)> for Location {
@@ -120,6 +152,16 @@ pub enum UniqueId {
 }
 
 impl UniqueId {
+    /// Create a new random ID in Altium's simple 8-letter format
+    pub(crate) fn new_random() -> Self {
+        let mut ret = [0u8; 8];
+        let bytes = Uuid::new_v4().into_bytes();
+        for (dst, src) in ret.iter_mut().zip(bytes) {
+            *dst = b'A' + src % 26;
+        }
+        Self::Simple(ret)
+    }
+
     #[allow(unused)]
     fn from_slice<S: AsRef<[u8]>>(buf: S) -> Option<Self> {
         buf.as_ref()
@@ -164,6 +206,12 @@ impl FromUtf8<'_> for UniqueId {
     }
 }
 
+impl ToUtf8 for UniqueId {
+    fn to_utf8(&self) -> String {
+        self.to_string()
+    }
+}
+
 mod unique_id_serde {
     use serde::{de::Error, Deserialize, Deserializer, Serializer};
 
@@ -271,6 +319,13 @@ impl FromUtf8<'_> for Rgb {
     }
 }
 
+impl ToUtf8 for Rgb {
+    fn to_utf8(&self) -> String {
+        let num = u32::from(self.r) | (u32::from(self.g) << 8) | (u32::from(self.b) << 16);
+        num.to_string()
+    }
+}
+
 /// Rotation when only 4 values are allowed
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Rotation90 {
@@ -326,6 +381,12 @@ impl FromUtf8<'_> for ReadOnlyState {
     }
 }
 
+impl ToUtf8 for ReadOnlyState {
+    fn to_utf8(&self) -> String {
+        (*self as u8).to_string()
+    }
+}
+
 /// Horizontal alignment
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum PosHoriz {
@@ -358,6 +419,45 @@ pub fn is_number_pattern(s: &[u8], prefix: &[u8]) -> bool {
     false
 }
 
+/// Length of the number at the start of `s`, including any exponent
+pub(crate) fn number_len(s: &str) -> usize {
+    let b = s.as_bytes();
+    let digits = |mut i: usize| {
+        while b.get(i).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+        }
+        i
+    };
+
+    let mut i = usize::from(matches!(b.first(), Some(b'-' | b'+')));
+    i = digits(i);
+    if b.get(i) == Some(&b'.') {
+        i = digits(i + 1);
+    }
+    if matches!(b.get(i), Some(b'e' | b'E')) {
+        let exp = i + 1 + usize::from(matches!(b.get(i + 1), Some(b'-' | b'+')));
+        if b.get(exp).is_some_and(u8::is_ascii_digit) {
+            i = digits(exp);
+        }
+    }
+    i
+}
+
+/// Meters per unit of length
+pub(crate) fn length_factor(unit: &str) -> Option<f64> {
+    let factor = match unit {
+        "m" => 1.0,
+        "cm" => 1e-2,
+        "mm" => 1e-3,
+        "um" | "µm" => 1e-6,
+        "nm" => 1e-9,
+        "mil" => 25.4e-6,
+        "in" | "inch" => 25.4e-3,
+        _ => return None,
+    };
+    Some(factor)
+}
+
 /// Infallible conversion
 pub fn mils_to_nm<T>(mils: T) -> Result<T>
 where
@@ -370,3 +470,15 @@ where
         ErrorKind::Overflow(mils.into(), FACTOR.into(), '*').context("converting units")
     })
 }
+
+/// Inverse of [`mils_to_nm`], rounding to the nearest mil
+pub fn nm_to_mils<T: PrimInt>(nm: T) -> T {
+    let factor = T::from(25400).expect("type too small for unit conversion");
+    let half = T::from(12700).unwrap();
+
+    if nm < T::zero() {
+        nm.saturating_sub(half) / factor
+    } else {
+        nm.saturating_add(half) / factor
+    }
+}
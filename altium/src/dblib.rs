@@ -0,0 +1,458 @@
+//! Database libraries (`.DbLib`)
+//!
+//! A database library is an INI file that says which database tables hold
+//! parts and how their fields map to a symbol, footprints and parameters. Parts
+//! are resolved against a [`DbBackend`] and merged into a component from the
+//! referenced `SchLib`.
+//!
+//! The parts of the format that are read:
+//!
+//! ```ini
+//! [DatabaseLinks]
+//! ConnectionString=Driver={SQLite3 ODBC Driver};Database=Parts.db
+//!
+//! [Table1]
+//! TableName=Resistors
+//! Enabled=True
+//! Key=Part Number
+//! ; Optional, see below
+//! FieldMapping1=Symbol|Library Ref
+//! FieldMapping2=Resistance|Value
+//! ```
+//!
+//! Without `FieldMapping` entries, fields named `Library Ref`, `Library Path`,
+//! `Footprint Ref` and `Footprint Path` (with ` 2`, ` 3`... for more
+//! footprints) locate the symbol and footprints, and every other field becomes
+//! a parameter with the same name. With mappings, only mapped fields are used.
+
+mod backend;
+mod csv;
+mod sqlite;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub use backend::{DbBackend, DbRow, MemoryBackend};
+pub use csv::CsvBackend;
+use ini::{Ini, ParseOption};
+pub use sqlite::SqliteBackend;
+
+use crate::error::AddContext;
+use crate::sch::Component;
+use crate::{Error, ErrorKind, SchLib};
+
+/// Key field used when a table doesn't name one
+const DEFAULT_KEY: &str = "Part Number";
+
+/// A database library file
+#[derive(Clone, Debug, Default)]
+pub struct DbLib {
+    connection_string: Box<str>,
+    tables: Vec<DbTable>,
+    /// Directory of the `.DbLib`, relative paths are resolved from here
+    dir: Option<PathBuf>,
+}
+
+impl DbLib {
+    /// Open a `.DbLib` file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let mut ret =
+            Self::from_string(&text).or_context(|| format!("with file {}", path.display()))?;
+        ret.dir = path.parent().map(Path::to_path_buf);
+        Ok(ret)
+    }
+
+    /// Parse a `.DbLib` from text. Relative paths are resolved from the
+    /// current directory.
+    pub fn from_string(s: &str) -> Result<Self, Error> {
+        // Paths use backslashes so escapes must be off
+        let opt = ParseOption {
+            enabled_quote: false,
+            enabled_escape: false,
+            ..ParseOption::default()
+        };
+        let ini = Ini::load_from_str_opt(s, opt).map_err(ErrorKind::from)?;
+
+        let mut ret = Self::default();
+        for (name, props) in &ini {
+            let Some(name) = name else { continue };
+            let get = |key: &str| {
+                props
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                    .map(|(_, v)| v.trim())
+            };
+
+            if name.eq_ignore_ascii_case("DatabaseLinks") {
+                ret.connection_string = get("ConnectionString").unwrap_or_default().into();
+            } else if is_table_section(name) {
+                ret.tables.push(DbTable {
+                    name: get("TableName").unwrap_or_default().into(),
+                    enabled: get("Enabled").is_none_or(|v| !v.eq_ignore_ascii_case("False")),
+                    key: get("Key")
+                        .filter(|v| !v.is_empty())
+                        .unwrap_or(DEFAULT_KEY)
+                        .into(),
+                    mappings: props
+                        .iter()
+                        .filter(|(k, _)| starts_with_ignore_case(k, "FieldMapping"))
+                        .filter_map(|(_, v)| FieldMapping::parse(v))
+                        .collect(),
+                });
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// The connection string as written, e.g. an OLE DB or ODBC string
+    pub fn connection_string(&self) -> &str {
+        &self.connection_string
+    }
+
+    /// Every table, enabled or not
+    pub fn tables(&self) -> &[DbTable] {
+        &self.tables
+    }
+
+    /// Open a local backend for the connection string's data source. `SQLite`
+    /// files (`.db`, `.sqlite`, `.sqlite3`, `.db3`), a `.csv` file or a
+    /// directory of `.csv` files are supported.
+    pub fn open_backend(&self) -> Result<Box<dyn DbBackend>, Error> {
+        let Some(source) = data_source(&self.connection_string) else {
+            return Err(ErrorKind::Database(
+                format!("no data source in `{}`", self.connection_string).into(),
+            )
+            .into());
+        };
+
+        let path = self.resolve(source);
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+
+        match ext.as_deref() {
+            _ if path.is_dir() => Ok(Box::new(CsvBackend::open_dir(&path)?)),
+            Some("db" | "sqlite" | "sqlite3" | "db3") => Ok(Box::new(SqliteBackend::open(&path)?)),
+            Some("csv") => {
+                let mut csv = CsvBackend::new();
+                csv.add_file(&path)?;
+                Ok(Box::new(csv))
+            }
+            _ => Err(ErrorKind::Database(
+                format!("unsupported data source `{}`", path.display()).into(),
+            )
+            .into()),
+        }
+    }
+
+    /// Look up a part by its key in each enabled table, in order
+    pub fn part(&self, backend: &dyn DbBackend, key: &str) -> Result<Option<DbPart>, Error> {
+        for table in self.tables.iter().filter(|t| t.enabled) {
+            let row = backend
+                .find_row(&table.name, &table.key, key)
+                .or_context(|| format!("looking up `{key}` in `{}`", table.name))?;
+            if let Some(row) = row {
+                return Ok(Some(table.part(&row)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Every part in every enabled table
+    pub fn parts(&self, backend: &dyn DbBackend) -> Result<Vec<DbPart>, Error> {
+        let mut ret = Vec::new();
+        for table in self.tables.iter().filter(|t| t.enabled) {
+            let rows = backend
+                .rows(&table.name)
+                .or_context(|| format!("reading `{}`", table.name))?;
+            ret.extend(rows.iter().map(|row| table.part(row)));
+        }
+        Ok(ret)
+    }
+
+    /// Look up a part and load its symbol from the `SchLib` at its library
+    /// path, with the part's parameters and footprints merged in
+    pub fn component(
+        &self,
+        backend: &dyn DbBackend,
+        key: &str,
+    ) -> Result<Option<Component>, Error> {
+        let Some(part) = self.part(backend, key)? else {
+            return Ok(None);
+        };
+        if part.library_path.is_empty() {
+            return Err(ErrorKind::Database(format!("`{key}` has no library path").into()).into());
+        }
+
+        let path = self.resolve(&part.library_path);
+        let schlib = SchLib::open(&path)?;
+        part.component_from(&schlib)
+    }
+
+    /// Resolve a path from the file, which may use Windows separators
+    fn resolve(&self, path: &str) -> PathBuf {
+        let path = PathBuf::from(path.replace('\\', "/"));
+        match &self.dir {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path,
+        }
+    }
+}
+
+/// A table of parts
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DbTable {
+    name: Box<str>,
+    enabled: bool,
+    key: Box<str>,
+    mappings: Vec<FieldMapping>,
+}
+
+impl DbTable {
+    /// Name of the table in the database
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether parts are looked up in this table
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Field that identifies a part
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Explicit field mappings. If empty, fields are mapped by name.
+    pub fn mappings(&self) -> &[FieldMapping] {
+        &self.mappings
+    }
+
+    /// Apply the field mappings to a row
+    fn part(&self, row: &DbRow) -> DbPart {
+        let mut ret = DbPart {
+            table: self.name.clone(),
+            key: row.get(&self.key).unwrap_or_default().into(),
+            ..Default::default()
+        };
+
+        let mut apply = |target: &MappingTarget, value: &str| match target {
+            MappingTarget::LibraryRef => ret.library_ref = value.into(),
+            MappingTarget::LibraryPath => ret.library_path = value.into(),
+            MappingTarget::FootprintRef(idx) => {
+                footprint_at(&mut ret.footprints, *idx).name = value.into();
+            }
+            MappingTarget::FootprintPath(idx) => {
+                footprint_at(&mut ret.footprints, *idx).path = value.into();
+            }
+            MappingTarget::Parameter(name) => ret.parameters.push((name.clone(), value.into())),
+        };
+
+        if self.mappings.is_empty() {
+            for (field, value) in row.iter() {
+                apply(&MappingTarget::from_name(field), value);
+            }
+        } else {
+            for mapping in &self.mappings {
+                if let Some(value) = row.get(&mapping.field) {
+                    apply(&mapping.target, value);
+                }
+            }
+        }
+
+        ret.footprints.retain(|fp| !fp.name.is_empty());
+        ret
+    }
+}
+
+/// Maps a database field to part of a component
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldMapping {
+    /// Name of the field in the database
+    pub field: Box<str>,
+    pub target: MappingTarget,
+}
+
+impl FieldMapping {
+    /// Parse `field|target`
+    fn parse(s: &str) -> Option<Self> {
+        let (field, target) = s.split_once('|')?;
+        let (field, target) = (field.trim(), target.trim());
+        (!field.is_empty() && !target.is_empty()).then(|| Self {
+            field: field.into(),
+            target: MappingTarget::from_name(target),
+        })
+    }
+}
+
+/// What a database field provides
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MappingTarget {
+    /// Libref of the symbol
+    LibraryRef,
+    /// Path to the `SchLib` holding the symbol
+    LibraryPath,
+    /// Name of the `n`th footprint, starting at 1
+    FootprintRef(usize),
+    /// Path to the `PcbLib` holding the `n`th footprint, starting at 1
+    FootprintPath(usize),
+    /// A parameter with this name
+    Parameter(Box<str>),
+}
+
+impl MappingTarget {
+    /// Interpret Altium's names for mapped fields
+    fn from_name(name: &str) -> Self {
+        let name = name.trim_matches(['[', ']']);
+        if name.eq_ignore_ascii_case("Library Ref") {
+            return Self::LibraryRef;
+        }
+        if name.eq_ignore_ascii_case("Library Path") {
+            return Self::LibraryPath;
+        }
+        if let Some(idx) = numbered(name, "Footprint Ref") {
+            return Self::FootprintRef(idx);
+        }
+        if let Some(idx) = numbered(name, "Footprint Path") {
+            return Self::FootprintPath(idx);
+        }
+        Self::Parameter(name.into())
+    }
+}
+
+/// A part found in a database
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DbPart {
+    table: Box<str>,
+    key: Box<str>,
+    library_ref: Box<str>,
+    library_path: Box<str>,
+    footprints: Vec<DbFootprint>,
+    parameters: Vec<(Box<str>, Box<str>)>,
+}
+
+impl DbPart {
+    /// Table the part was found in
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Value of the part's key field
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Libref of the symbol
+    pub fn library_ref(&self) -> &str {
+        &self.library_ref
+    }
+
+    /// Path to the `SchLib` with the symbol, as written
+    pub fn library_path(&self) -> &str {
+        &self.library_path
+    }
+
+    /// Footprints in order. The first is the current footprint.
+    pub fn footprints(&self) -> &[DbFootprint] {
+        &self.footprints
+    }
+
+    /// Parameters as `(name, value)`
+    pub fn parameters(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.parameters.iter().map(|(k, v)| (&**k, &**v))
+    }
+
+    /// Value of a parameter by name, ignoring case
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, val)| val)
+    }
+
+    /// Load this part's symbol from a library and merge in the part's
+    /// parameters and footprints. Parameters override those of the symbol.
+    pub fn component_from<F: std::io::Read + std::io::Seek>(
+        &self,
+        schlib: &SchLib<F>,
+    ) -> Result<Option<Component>, Error> {
+        let Some(mut comp) = schlib.try_get_component(&self.library_ref)? else {
+            return Ok(None);
+        };
+
+        for (name, value) in self.parameters() {
+            comp.set_parameter(name, value);
+        }
+        for (idx, footprint) in self.footprints.iter().enumerate() {
+            comp.link_footprint(&footprint.name, idx == 0);
+        }
+
+        Ok(Some(comp))
+    }
+}
+
+/// A footprint named by a part
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DbFootprint {
+    name: Box<str>,
+    path: Box<str>,
+}
+
+impl DbFootprint {
+    /// Name of the footprint
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Path to the `PcbLib` with the footprint, as written. May be empty.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// `Table1`, `Table2`, etc
+fn is_table_section(name: &str) -> bool {
+    starts_with_ignore_case(name, "Table")
+        && name.len() > 5
+        && name[5..].bytes().all(|b| b.is_ascii_digit())
+}
+
+fn starts_with_ignore_case(s: &str, prefix: &str) -> bool {
+    s.get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+/// Match `prefix` or `prefix n`, giving the 1-based `n`
+fn numbered(name: &str, prefix: &str) -> Option<usize> {
+    if !starts_with_ignore_case(name, prefix) {
+        return None;
+    }
+    let rest = name[prefix.len()..].trim();
+    if rest.is_empty() {
+        Some(1)
+    } else {
+        rest.parse().ok().filter(|n| *n > 0)
+    }
+}
+
+/// Get the footprint at a 1-based index, adding empty ones as needed
+fn footprint_at(footprints: &mut Vec<DbFootprint>, idx: usize) -> &mut DbFootprint {
+    if footprints.len() < idx {
+        footprints.resize(idx, DbFootprint::default());
+    }
+    &mut footprints[idx - 1]
+}
+
+/// Find the file in a connection string, e.g. `Data Source=parts.db`
+fn data_source(conn: &str) -> Option<&str> {
+    const KEYS: [&str; 4] = ["Data Source", "Database", "DBQ", "DefaultDir"];
+    conn.split(';')
+        .filter_map(|item| item.split_once('='))
+        .find(|(key, _)| KEYS.iter().any(|k| key.trim().eq_ignore_ascii_case(k)))
+        .map(|(_, val)| val.trim().trim_matches('"'))
+        .filter(|val| !val.is_empty())
+}
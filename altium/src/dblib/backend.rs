@@ -0,0 +1,105 @@
+//! Sources of database rows
+
+use std::collections::BTreeMap;
+
+use crate::{Error, ErrorKind};
+
+/// Somewhere that a database library's tables can be read from
+///
+/// Values are always text. Numbers are formatted as written and `NULL` is an
+/// empty string.
+pub trait DbBackend {
+    /// Every row of a table, in storage order. Table names are matched
+    /// ignoring case.
+    fn rows(&self, table: &str) -> Result<Vec<DbRow>, Error>;
+
+    /// The first row of a table where `field` is `value`
+    fn find_row(&self, table: &str, field: &str, value: &str) -> Result<Option<DbRow>, Error> {
+        Ok(self
+            .rows(table)?
+            .into_iter()
+            .find(|row| row.get(field) == Some(value)))
+    }
+}
+
+/// A single row as `(field, value)` pairs, in column order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DbRow(Vec<(Box<str>, Box<str>)>);
+
+impl DbRow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field to the end of the row
+    pub fn push(&mut self, field: &str, value: &str) {
+        self.0.push((field.into(), value.into()));
+    }
+
+    /// Value of a field, ignoring case
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(field))
+            .map(|(_, val)| &**val)
+    }
+
+    /// Iterate over `(field, value)` pairs
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(name, val)| (&**name, &**val))
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> FromIterator<(K, V)> for DbRow {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(k, v)| (k.as_ref().into(), v.as_ref().into()))
+                .collect(),
+        )
+    }
+}
+
+/// Tables held in memory, for tests or for data that comes from somewhere
+/// without a backend
+///
+/// ```
+/// use altium::dblib::{DbBackend, DbRow, MemoryBackend};
+///
+/// let mut db = MemoryBackend::new();
+/// db.insert("Resistors", [("Part Number", "R-0001"), ("Value", "10k")].into_iter().collect());
+/// let row = db.find_row("resistors", "Part Number", "R-0001").unwrap().unwrap();
+/// assert_eq!(row.get("value"), Some("10k"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBackend {
+    /// Keyed by lowercase table name
+    tables: BTreeMap<Box<str>, Vec<DbRow>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a row to a table, creating the table if needed
+    pub fn insert(&mut self, table: &str, row: DbRow) {
+        self.tables
+            .entry(table.to_ascii_lowercase().into())
+            .or_default()
+            .push(row);
+    }
+}
+
+impl DbBackend for MemoryBackend {
+    fn rows(&self, table: &str) -> Result<Vec<DbRow>, Error> {
+        self.tables
+            .get(&*table.to_ascii_lowercase())
+            .cloned()
+            .ok_or_else(|| missing_table(table))
+    }
+}
+
+pub(super) fn missing_table(table: &str) -> Error {
+    ErrorKind::Database(format!("no table named `{table}`").into()).into()
+}
@@ -0,0 +1,124 @@
+//! Tables exported as CSV files
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::backend::{missing_table, DbBackend, DbRow};
+use crate::error::AddContext;
+use crate::{Error, ErrorKind};
+
+/// Tables read from CSV files, one file per table
+///
+/// The first line of each file holds the field names. Fields may be quoted
+/// with `"`, with `""` for a literal quote.
+#[derive(Clone, Debug, Default)]
+pub struct CsvBackend {
+    /// Keyed by lowercase table name
+    tables: BTreeMap<Box<str>, Vec<DbRow>>,
+}
+
+impl CsvBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every `.csv` file in a directory. Table names are the file names
+    /// without the extension.
+    pub fn open_dir<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let mut ret = Self::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+            {
+                ret.add_file(&path)?;
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Load a single file as a table named after the file
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let table = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+        let text = fs::read_to_string(path)?;
+        self.add_table(&table, &text)
+            .or_context(|| format!("reading {}", path.display()))
+    }
+
+    /// Add a table from CSV text
+    pub fn add_table(&mut self, table: &str, text: &str) -> Result<(), Error> {
+        let mut lines = parse_csv(text.trim_start_matches('\u{feff}'))?.into_iter();
+        let header = lines.next().unwrap_or_default();
+        let rows = lines
+            // Blank lines parse to a single empty field
+            .filter(|fields| fields.len() > 1 || fields.first().is_some_and(|f| !f.is_empty()))
+            .map(|fields| {
+                header
+                    .iter()
+                    .zip(
+                        fields
+                            .iter()
+                            .map(String::as_str)
+                            .chain(std::iter::repeat("")),
+                    )
+                    .collect()
+            })
+            .collect();
+
+        self.tables.insert(table.to_ascii_lowercase().into(), rows);
+        Ok(())
+    }
+}
+
+impl DbBackend for CsvBackend {
+    fn rows(&self, table: &str) -> Result<Vec<DbRow>, Error> {
+        self.tables
+            .get(&*table.to_ascii_lowercase())
+            .cloned()
+            .ok_or_else(|| missing_table(table))
+    }
+}
+
+/// Split CSV text into lines of fields. Quoted fields may span lines.
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, ErrorKind> {
+    let mut ret = Vec::new();
+    let mut line = Vec::new();
+    let mut field = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if field.is_empty() => loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(ch) => field.push(ch),
+                    None => return Err(ErrorKind::Database("unterminated quote in CSV".into())),
+                }
+            },
+            ',' => line.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' => {
+                line.push(std::mem::take(&mut field));
+                ret.push(std::mem::take(&mut line));
+            }
+            _ => field.push(ch),
+        }
+    }
+
+    if !field.is_empty() || !line.is_empty() {
+        line.push(field);
+        ret.push(line);
+    }
+
+    Ok(ret)
+}
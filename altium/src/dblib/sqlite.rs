@@ -0,0 +1,454 @@
+//! A small read-only reader for `SQLite` database files
+//!
+//! Only what is needed to list the rows of ordinary tables is supported:
+//! table b-trees, overflow pages and the record format. Indices are ignored,
+//! and `WITHOUT ROWID` tables can't be read.
+
+use std::fs;
+use std::path::Path;
+
+use super::backend::{missing_table, DbBackend, DbRow};
+use crate::error::AddContext;
+use crate::{Error, ErrorKind};
+
+const MAGIC: &[u8] = b"SQLite format 3\0";
+const HEADER_LEN: usize = 100;
+/// Deeper trees than this mean the file is corrupt or cyclic
+const MAX_DEPTH: usize = 32;
+
+/// Page types
+const INTERIOR_TABLE: u8 = 0x05;
+const LEAF_TABLE: u8 = 0x0d;
+
+/// Tables read from a `SQLite` database file
+#[derive(Clone, Debug)]
+pub struct SqliteBackend {
+    buf: Vec<u8>,
+    page_size: usize,
+    /// Page size less the reserved bytes at the end of each page
+    usable_size: usize,
+    text: TextEncoding,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+/// A table listed in the schema
+struct TableSchema {
+    root_page: usize,
+    columns: Vec<Column>,
+}
+
+struct Column {
+    name: Box<str>,
+    /// `INTEGER PRIMARY KEY` columns are stored as the rowid
+    is_rowid: bool,
+}
+
+/// A value in a record
+#[derive(Debug, PartialEq)]
+enum Value<'a> {
+    Null,
+    Int(i64),
+    Float(f64),
+    Text(&'a [u8]),
+    Blob,
+}
+
+impl SqliteBackend {
+    /// Read a database from disk
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let buf = fs::read(&path)?;
+        Self::from_buffer(buf).or_context(|| format!("with file {}", path.as_ref().display()))
+    }
+
+    /// Read a database from memory
+    pub fn from_buffer(buf: Vec<u8>) -> Result<Self, Error> {
+        if !buf.starts_with(MAGIC) || buf.len() < HEADER_LEN {
+            return Err(ErrorKind::FileType("buffer".into(), "SQLite").into());
+        }
+
+        let page_size = match u16::from_be_bytes([buf[16], buf[17]]) {
+            1 => 65536,
+            n => usize::from(n),
+        };
+        let usable_size = page_size.saturating_sub(usize::from(buf[20]));
+        if usable_size < 480 {
+            return Err(corrupt("page size too small"));
+        }
+
+        let text = match u32::from_be_bytes([buf[56], buf[57], buf[58], buf[59]]) {
+            2 => TextEncoding::Utf16Le,
+            3 => TextEncoding::Utf16Be,
+            _ => TextEncoding::Utf8,
+        };
+
+        Ok(Self {
+            buf,
+            page_size,
+            usable_size,
+            text,
+        })
+    }
+
+    /// Names of every table in the database
+    pub fn table_names(&self) -> Result<Vec<String>, Error> {
+        let mut ret = Vec::new();
+        self.walk_schema(|values| {
+            if let [Value::Text(ty), Value::Text(name), ..] = values {
+                if *ty == b"table" {
+                    ret.push(self.text(name));
+                }
+            }
+        })?;
+        Ok(ret)
+    }
+
+    /// Find a table's root page and columns from `sqlite_master`
+    fn table_schema(&self, table: &str) -> Result<TableSchema, Error> {
+        let mut found = None;
+        self.walk_schema(|values| {
+            let [Value::Text(ty), Value::Text(name), _, root, sql, ..] = values else {
+                return;
+            };
+            if found.is_none() && *ty == b"table" && self.text(name).eq_ignore_ascii_case(table) {
+                let sql = match sql {
+                    Value::Text(sql) => self.text(sql),
+                    _ => String::new(),
+                };
+                found = Some((root_page(root), sql));
+            }
+        })?;
+
+        let (root_page, sql) = found.ok_or_else(|| missing_table(table))?;
+        let root_page = root_page.ok_or_else(|| corrupt("invalid root page"))?;
+        if sql.to_ascii_uppercase().contains("WITHOUT ROWID") {
+            return Err(ErrorKind::Database(
+                format!("`{table}` is a WITHOUT ROWID table, which isn't supported").into(),
+            )
+            .into());
+        }
+
+        Ok(TableSchema {
+            root_page,
+            columns: parse_columns(&sql),
+        })
+    }
+
+    /// Call `f` with each row of `sqlite_master`
+    fn walk_schema(&self, mut f: impl FnMut(&[Value])) -> Result<(), Error> {
+        self.walk_table(1, 0, &mut |_, values| f(values))
+    }
+
+    /// Visit every row of the table b-tree rooted at `page`, in rowid order
+    fn walk_table(
+        &self,
+        page: usize,
+        depth: usize,
+        f: &mut dyn FnMut(i64, &[Value]),
+    ) -> Result<(), Error> {
+        if depth > MAX_DEPTH {
+            return Err(corrupt("table b-tree is too deep"));
+        }
+
+        let data = self.page(page)?;
+        // The first page starts with the file header
+        let hdr = if page == 1 { HEADER_LEN } else { 0 };
+        let ty = *data.get(hdr).ok_or_else(|| corrupt("empty page"))?;
+        let cell_count = usize::from(be_u16(data, hdr + 3)?);
+        let ptrs_start = hdr + if ty == INTERIOR_TABLE { 12 } else { 8 };
+
+        for idx in 0..cell_count {
+            let cell = usize::from(be_u16(data, ptrs_start + idx * 2)?);
+            match ty {
+                INTERIOR_TABLE => {
+                    let child = be_u32(data, cell)?;
+                    self.walk_table(child, depth + 1, f)?;
+                }
+                LEAF_TABLE => {
+                    let (rowid, payload) = self.leaf_cell(data, cell)?;
+                    let values = parse_record(&payload)?;
+                    f(rowid, &values);
+                }
+                _ => return Err(corrupt("unexpected page type in table")),
+            }
+        }
+
+        if ty == INTERIOR_TABLE {
+            let right = be_u32(data, hdr + 8)?;
+            self.walk_table(right, depth + 1, f)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read the rowid and payload of a leaf cell, following overflow pages
+    fn leaf_cell(&self, data: &[u8], cell: usize) -> Result<(i64, Vec<u8>), Error> {
+        let rest = data
+            .get(cell..)
+            .ok_or_else(|| corrupt("cell out of bounds"))?;
+        let (payload_len, n1) = varint(rest)?;
+        let (rowid, n2) = varint(&rest[n1..])?;
+        #[allow(clippy::cast_possible_wrap)]
+        let rowid = rowid as i64;
+        let payload_len = usize::try_from(payload_len).map_err(|_| corrupt("payload too large"))?;
+        let rest = &rest[n1 + n2..];
+
+        // How much of the payload is on this page, from the file format docs
+        let usable = self.usable_size;
+        let max_local = usable - 35;
+        let min_local = (usable - 12) * 32 / 255 - 23;
+        let local = if payload_len <= max_local {
+            payload_len
+        } else {
+            let k = min_local + (payload_len - min_local) % (usable - 4);
+            if k <= max_local {
+                k
+            } else {
+                min_local
+            }
+        };
+
+        let mut payload = rest
+            .get(..local)
+            .ok_or_else(|| corrupt("payload out of bounds"))?
+            .to_vec();
+        let mut next = if local < payload_len {
+            be_u32(rest, local)?
+        } else {
+            0
+        };
+
+        let mut pages_read = 0;
+        while next != 0 && payload.len() < payload_len {
+            pages_read += 1;
+            if pages_read > self.buf.len() / self.page_size {
+                return Err(corrupt("overflow page cycle"));
+            }
+            let page = self.page(next)?;
+            let take = (payload_len - payload.len()).min(usable - 4);
+            payload.extend_from_slice(
+                page.get(4..4 + take)
+                    .ok_or_else(|| corrupt("overflow out of bounds"))?,
+            );
+            next = be_u32(page, 0)?;
+        }
+
+        if payload.len() < payload_len {
+            return Err(corrupt("payload is truncated"));
+        }
+        Ok((rowid, payload))
+    }
+
+    fn page(&self, page: usize) -> Result<&[u8], Error> {
+        let start = page
+            .checked_sub(1)
+            .map(|idx| idx * self.page_size)
+            .ok_or_else(|| corrupt("page 0 referenced"))?;
+        self.buf
+            .get(start..start + self.usable_size)
+            .ok_or_else(|| corrupt("page out of bounds"))
+    }
+
+    fn text(&self, buf: &[u8]) -> String {
+        let utf16 = |f: fn([u8; 2]) -> u16| {
+            let units: Vec<u16> = buf.chunks_exact(2).map(|c| f([c[0], c[1]])).collect();
+            String::from_utf16_lossy(&units)
+        };
+        match self.text {
+            TextEncoding::Utf8 => String::from_utf8_lossy(buf).into_owned(),
+            TextEncoding::Utf16Le => utf16(u16::from_le_bytes),
+            TextEncoding::Utf16Be => utf16(u16::from_be_bytes),
+        }
+    }
+
+    fn value_string(&self, value: &Value) -> String {
+        match value {
+            Value::Null | Value::Blob => String::new(),
+            Value::Int(v) => v.to_string(),
+            Value::Float(v) => v.to_string(),
+            Value::Text(v) => self.text(v),
+        }
+    }
+}
+
+impl DbBackend for SqliteBackend {
+    fn rows(&self, table: &str) -> Result<Vec<DbRow>, Error> {
+        let schema = self.table_schema(table)?;
+        let mut ret = Vec::new();
+
+        self.walk_table(schema.root_page, 0, &mut |rowid, values| {
+            let row = schema
+                .columns
+                .iter()
+                .enumerate()
+                .map(|(idx, col)| {
+                    // Columns added later are missing from older rows
+                    let value = match values.get(idx) {
+                        Some(Value::Null) | None if col.is_rowid => rowid.to_string(),
+                        Some(value) => self.value_string(value),
+                        None => String::new(),
+                    };
+                    (&*col.name, value)
+                })
+                .collect();
+            ret.push(row);
+        })
+        .or_context(|| format!("reading table `{table}`"))?;
+
+        Ok(ret)
+    }
+}
+
+/// Split a record into its values
+fn parse_record(buf: &[u8]) -> Result<Vec<Value<'_>>, Error> {
+    let (header_len, mut pos) = varint(buf)?;
+    let header_len = usize::try_from(header_len).map_err(|_| corrupt("bad record header"))?;
+    let mut body = header_len;
+    let mut ret = Vec::new();
+
+    while pos < header_len {
+        let (serial, n) = varint(buf.get(pos..).unwrap_or_default())?;
+        pos += n;
+
+        let len = match serial {
+            0 | 8 | 9 | 10 | 11 => 0,
+            1..=4 => usize::try_from(serial).unwrap(),
+            5 => 6,
+            6 | 7 => 8,
+            _ => usize::try_from((serial - 12) / 2).map_err(|_| corrupt("value too large"))?,
+        };
+        let data = buf
+            .get(body..body + len)
+            .ok_or_else(|| corrupt("record value out of bounds"))?;
+        body += len;
+
+        let value = match serial {
+            0 | 10 | 11 => Value::Null,
+            1..=6 => {
+                // Sign extend a big endian integer of any length
+                let mut bytes = [if data[0] & 0x80 == 0 { 0 } else { 0xff }; 8];
+                bytes[8 - len..].copy_from_slice(data);
+                Value::Int(i64::from_be_bytes(bytes))
+            }
+            7 => Value::Float(f64::from_be_bytes(data.try_into().unwrap())),
+            8 => Value::Int(0),
+            9 => Value::Int(1),
+            _ if serial % 2 == 0 => Value::Blob,
+            _ => Value::Text(data),
+        };
+        ret.push(value);
+    }
+
+    Ok(ret)
+}
+
+/// Column names from a `CREATE TABLE` statement
+fn parse_columns(sql: &str) -> Vec<Column> {
+    const CONSTRAINTS: [&str; 5] = ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"];
+
+    let Some(start) = sql.find('(') else {
+        return Vec::new();
+    };
+    let end = sql.rfind(')').unwrap_or(sql.len()).max(start + 1);
+    let body = &sql[start + 1..end];
+
+    // Split on commas that aren't nested in parentheses or quotes
+    let mut defs = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut last = 0;
+    for (idx, ch) in body.char_indices() {
+        match (quote, ch) {
+            (Some(q), _) if ch == q => quote = None,
+            (None, '"' | '\'' | '`') => quote = Some(ch),
+            (None, '[') => quote = Some(']'),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                defs.push(&body[last..idx]);
+                last = idx + 1;
+            }
+            _ => (),
+        }
+    }
+    defs.push(&body[last..]);
+
+    defs.into_iter()
+        .map(str::trim)
+        .filter(|def| {
+            let first = def.split_whitespace().next().unwrap_or_default();
+            !CONSTRAINTS.iter().any(|c| first.eq_ignore_ascii_case(c))
+        })
+        .map(|def| {
+            let (name, rest) = split_name(def);
+            let rest = rest.to_ascii_uppercase();
+            let words: Vec<_> = rest.split_whitespace().collect();
+            Column {
+                name: name.into(),
+                is_rowid: words.first() == Some(&"INTEGER")
+                    && words.windows(2).any(|w| w == ["PRIMARY", "KEY"]),
+            }
+        })
+        .collect()
+}
+
+/// Split a column definition into its unquoted name and the rest
+fn split_name(def: &str) -> (&str, &str) {
+    let close = match def.chars().next() {
+        Some('"') => '"',
+        Some('`') => '`',
+        Some('[') => ']',
+        _ => {
+            let end = def.find(char::is_whitespace).unwrap_or(def.len());
+            return (&def[..end], &def[end..]);
+        }
+    };
+    match def[1..].find(close) {
+        Some(end) => (&def[1..=end], &def[end + 2..]),
+        None => (&def[1..], ""),
+    }
+}
+
+fn root_page(value: &Value) -> Option<usize> {
+    match value {
+        Value::Int(page) => usize::try_from(*page).ok().filter(|p| *p > 0),
+        _ => None,
+    }
+}
+
+/// A `SQLite` varint: up to 9 bytes, big endian, 7 bits per byte except the
+/// last which has 8
+fn varint(buf: &[u8]) -> Result<(u64, usize), Error> {
+    let mut ret = 0u64;
+    for (idx, byte) in buf.iter().take(9).enumerate() {
+        if idx == 8 {
+            return Ok(((ret << 8) | u64::from(*byte), 9));
+        }
+        ret = (ret << 7) | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            return Ok((ret, idx + 1));
+        }
+    }
+    Err(corrupt("truncated varint"))
+}
+
+fn be_u16(buf: &[u8], pos: usize) -> Result<u16, Error> {
+    buf.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| corrupt("page header out of bounds"))
+}
+
+fn be_u32(buf: &[u8], pos: usize) -> Result<usize, Error> {
+    buf.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .ok_or_else(|| corrupt("page pointer out of bounds"))
+}
+
+fn corrupt(msg: &str) -> Error {
+    ErrorKind::Database(format!("corrupt SQLite file: {msg}").into()).into()
+}
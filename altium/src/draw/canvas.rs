@@ -1,5 +1,7 @@
+use std::sync::Arc;
+
 use crate::{
-    common::{Location, PosHoriz, PosVert, Rgb, Rotation90},
+    common::{Location, PosHoriz, PosVert, Rgb, Rotation90, UniqueId},
     font::Font,
 };
 
@@ -31,6 +33,12 @@ pub trait Canvas: crate::sealed::Sealed {
     }
     fn draw_arc(&mut self, item: DrawArc);
     fn add_comment<S: Into<String>>(&mut self, _comment: S) {}
+    /// Start a group of related items, such as everything drawn for one
+    /// record. Groups may be nested; each call is paired with [`end_group`].
+    ///
+    /// [`end_group`]: Canvas::end_group
+    fn start_group(&mut self, _group: DrawGroup) {}
+    fn end_group(&mut self) {}
 }
 
 /// Line ending.
@@ -114,6 +122,8 @@ pub struct DrawRectangle {
 #[derive(Clone, Copy, Debug, Default)]
 pub struct DrawPolygon<'a> {
     pub locations: &'a [Location],
+    /// Cutouts that are left unfilled, each a closed outline
+    pub holes: &'a [Vec<Location>],
     pub fill_color: Rgb,
     pub stroke_color: Rgb,
     pub stroke_width: u32,
@@ -138,4 +148,33 @@ pub struct DrawArc {
     pub line_join: LineJoin,
 }
 
-pub struct DrawImage {}
+/// An image placed in a rectangle. `(x, y)` is the bottom left corner.
+#[derive(Clone, Debug, Default)]
+pub struct DrawImage<'a> {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    /// If set, the image should be scaled uniformly to fit the rectangle
+    pub keep_aspect: bool,
+    /// PNG-encoded image data, if it was embedded in the file
+    pub data: Option<Arc<[u8]>>,
+    /// Path of the image as stored by Altium. This is usually a Windows path,
+    /// and may be used as a fallback if `data` is not available.
+    pub file_name: &'a str,
+}
+
+/// Information about a group of drawn items, for canvases that can annotate
+/// their output
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DrawGroup<'a> {
+    /// What the group represents, e.g. a record type or `Component`
+    pub kind: &'a str,
+    pub unique_id: Option<UniqueId>,
+    /// Name of the item, e.g. a component's library reference or a pin name
+    pub name: Option<&'a str>,
+    /// Component or pin designator
+    pub designator: Option<&'a str>,
+    /// Net that this item names or connects to
+    pub net: Option<&'a str>,
+}
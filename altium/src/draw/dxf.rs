@@ -0,0 +1,415 @@
+//! A [`Canvas`] that produces `AutoCAD` R12 DXF
+//!
+//! R12 is the oldest and most widely supported DXF flavor, and it doesn't need
+//! the handle bookkeeping of newer versions. Output is in millimeters. Only
+//! outlines are written; fills, line widths and images are skipped.
+
+use std::f32::consts::TAU;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write as _};
+use std::path::Path;
+
+use log::debug;
+
+use super::{canvas, Canvas};
+use crate::common::{Location, PosHoriz, PosVert, Rgb};
+
+/// Layer for anything drawn outside of a group
+const DEFAULT_LAYER: &str = "0";
+/// Ellipses and elliptical arcs don't exist in R12, so they get approximated
+/// with this many segments per full turn
+const ELLIPSE_SEGMENTS: f32 = 72.0;
+/// Physical text height per font point, before scaling by 7/10 to get the cap
+/// height. Altium draws a 10pt font about one 100 mil grid square tall. This
+/// is independent of the unit scale.
+const MM_PER_FONT_PT: f64 = 0.254;
+/// Font size to use if the font doesn't specify one
+const DEFAULT_FONT_SIZE: u16 = 10;
+
+#[derive(Clone, Debug)]
+pub struct DxfCtx {
+    /// Serialized `ENTITIES` section contents
+    entities: String,
+    /// All layers that have been used, in order of first use
+    layers: Vec<Box<str>>,
+    /// Layer names of the groups we are currently in, innermost last
+    group_layers: Vec<Box<str>>,
+    /// `(min, max)` of all points, in drawing units
+    extents: Option<(Location, Location)>,
+    /// Physical nm per drawing unit
+    unit_scale: f64,
+}
+
+impl DxfCtx {
+    pub fn new() -> Self {
+        Self {
+            entities: String::new(),
+            layers: Vec::new(),
+            group_layers: Vec::new(),
+            extents: None,
+            unit_scale: 1.0,
+        }
+    }
+
+    /// Set how many physical nm one drawing unit is. Defaults to 1, schematics
+    /// need [`SCH_UNIT_SCALE`](crate::sch::SCH_UNIT_SCALE).
+    pub fn set_unit_scale(&mut self, scale: i32) {
+        self.unit_scale = f64::from(scale);
+    }
+
+    /// Get the finished DXF document
+    pub fn dxf(&self) -> String {
+        let mut out = String::new();
+        self.write_fmt_to(&mut out).unwrap();
+        out
+    }
+
+    /// Write the finished DXF document to a writer
+    pub fn write_to<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.dxf().as_bytes())
+    }
+
+    /// Write the DXF document to a file, creating it if needed
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    fn write_fmt_to<W: std::fmt::Write>(&self, f: &mut W) -> std::fmt::Result {
+        let (min, max) = self.extents.unwrap_or_default();
+
+        // Header
+        pair(f, 0, "SECTION")?;
+        pair(f, 2, "HEADER")?;
+        pair(f, 9, "$ACADVER")?;
+        pair(f, 1, "AC1009")?;
+        pair(f, 9, "$EXTMIN")?;
+        point(f, 0, min, self.unit_scale)?;
+        pair(f, 9, "$EXTMAX")?;
+        point(f, 0, max, self.unit_scale)?;
+        pair(f, 0, "ENDSEC")?;
+
+        // Tables: line types and layers
+        pair(f, 0, "SECTION")?;
+        pair(f, 2, "TABLES")?;
+        pair(f, 0, "TABLE")?;
+        pair(f, 2, "LTYPE")?;
+        pair(f, 70, 1)?;
+        pair(f, 0, "LTYPE")?;
+        pair(f, 2, "CONTINUOUS")?;
+        pair(f, 70, 0)?;
+        pair(f, 3, "Solid line")?;
+        pair(f, 72, 65)?;
+        pair(f, 73, 0)?;
+        pair(f, 40, 0.0)?;
+        pair(f, 0, "ENDTAB")?;
+
+        pair(f, 0, "TABLE")?;
+        pair(f, 2, "LAYER")?;
+        pair(f, 70, self.layers.len() + 1)?;
+        for layer in [DEFAULT_LAYER]
+            .into_iter()
+            .chain(self.layers.iter().map(|l| &**l))
+        {
+            pair(f, 0, "LAYER")?;
+            pair(f, 2, layer)?;
+            pair(f, 70, 0)?;
+            pair(f, 62, 7)?;
+            pair(f, 6, "CONTINUOUS")?;
+        }
+        pair(f, 0, "ENDTAB")?;
+        pair(f, 0, "ENDSEC")?;
+
+        pair(f, 0, "SECTION")?;
+        pair(f, 2, "ENTITIES")?;
+        f.write_str(&self.entities)?;
+        pair(f, 0, "ENDSEC")?;
+        pair(f, 0, "EOF")
+    }
+
+    /// Layer that new entities go on
+    fn layer(&self) -> &str {
+        self.group_layers.last().map_or(DEFAULT_LAYER, |l| l)
+    }
+
+    /// Write the start of an entity, including its layer and color
+    fn start_entity(&mut self, kind: &str, color: Rgb) {
+        let layer = self.layer().to_owned();
+        let e = &mut self.entities;
+        pair(e, 0, kind).unwrap();
+        pair(e, 8, layer).unwrap();
+        pair(e, 62, aci_color(color)).unwrap();
+    }
+
+    /// Write a point with group codes `10 + offset` and `20 + offset`
+    fn point(&mut self, offset: u16, loc: Location) {
+        self.update_extents(loc);
+        point(&mut self.entities, offset, loc, self.unit_scale).unwrap();
+    }
+
+    fn update_extents(&mut self, loc: Location) {
+        let (min, max) = self.extents.get_or_insert((loc, loc));
+        min.x = min.x.min(loc.x);
+        min.y = min.y.min(loc.y);
+        max.x = max.x.max(loc.x);
+        max.y = max.y.max(loc.y);
+    }
+
+    /// Add a 2D polyline through `locations`
+    fn polyline(&mut self, locations: &[Location], color: Rgb, closed: bool) {
+        if locations.len() < 2 {
+            return;
+        }
+
+        self.start_entity("POLYLINE", color);
+        pair(&mut self.entities, 66, 1).unwrap(); // vertices follow
+        point(&mut self.entities, 0, Location::default(), self.unit_scale).unwrap();
+        pair(&mut self.entities, 70, u8::from(closed)).unwrap();
+
+        let layer = self.layer().to_owned();
+        for loc in locations {
+            pair(&mut self.entities, 0, "VERTEX").unwrap();
+            pair(&mut self.entities, 8, &layer).unwrap();
+            self.point(0, *loc);
+        }
+
+        pair(&mut self.entities, 0, "SEQEND").unwrap();
+        pair(&mut self.entities, 8, layer).unwrap();
+    }
+}
+
+impl crate::sealed::Sealed for DxfCtx {}
+
+impl Canvas for DxfCtx {
+    fn draw_text(&mut self, item: canvas::DrawText) {
+        if item.text.is_empty() {
+            return;
+        }
+
+        let size = if item.font.size == 0 {
+            DEFAULT_FONT_SIZE
+        } else {
+            item.font.size
+        };
+        let height_mm = f64::from(size) * MM_PER_FONT_PT * 0.7;
+
+        let halign = match item.anchor_h {
+            PosHoriz::Left => 0,
+            PosHoriz::Center => 1,
+            PosHoriz::Right => 2,
+        };
+        let valign = match item.anchor_v {
+            PosVert::Bottom => 1,
+            PosVert::Middle => 2,
+            PosVert::Top => 3,
+        };
+        let loc = Location::new(item.x, item.y);
+
+        self.start_entity("TEXT", item.color);
+        self.point(0, loc);
+        pair(&mut self.entities, 40, height_mm).unwrap();
+        pair(&mut self.entities, 1, escape_text(item.text)).unwrap();
+        pair(&mut self.entities, 50, item.rotation.as_int()).unwrap();
+        pair(&mut self.entities, 72, halign).unwrap();
+        // With any alignment other than left/baseline, the second point is the
+        // one that is used
+        self.point(1, loc);
+        pair(&mut self.entities, 73, valign).unwrap();
+    }
+
+    fn draw_line(&mut self, item: canvas::DrawLine) {
+        self.start_entity("LINE", item.color);
+        self.point(0, item.start);
+        self.point(1, item.end);
+    }
+
+    fn draw_polyline(&mut self, item: canvas::DrawPolyLine) {
+        self.polyline(item.locations, item.color, false);
+    }
+
+    fn draw_polygon(&mut self, item: canvas::DrawPolygon) {
+        self.polyline(item.locations, item.stroke_color, true);
+        for hole in item.holes {
+            self.polyline(hole, item.stroke_color, true);
+        }
+    }
+
+    fn draw_rectangle(&mut self, item: canvas::DrawRectangle) {
+        let (x, y) = (item.x, item.y);
+        let (x2, y2) = (item.x + item.width, item.y + item.height);
+        let corners = [
+            Location::new(x, y),
+            Location::new(x2, y),
+            Location::new(x2, y2),
+            Location::new(x, y2),
+        ];
+        self.polyline(&corners, item.stroke_color, true);
+    }
+
+    fn draw_image(&mut self, item: canvas::DrawImage) {
+        debug!("skipping image '{}' in DXF output", item.file_name);
+    }
+
+    fn draw_arc(&mut self, item: canvas::DrawArc) {
+        let mut sweep = (item.end_angle - item.start_angle).rem_euclid(TAU);
+        if sweep == 0.0 {
+            // Altium uses equal start and end angles for a full ellipse
+            sweep = TAU;
+        }
+        let full = (sweep - TAU).abs() < f32::EPSILON;
+
+        if item.x_radius != item.y_radius {
+            // Approximate the ellipse, always including the end point
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let segments = ((ELLIPSE_SEGMENTS * sweep / TAU).ceil() as u32).max(2);
+            let cx = f64::from(item.center.x);
+            let cy = f64::from(item.center.y);
+            let locations: Vec<_> = (0..=segments)
+                .map(|i| {
+                    #[allow(clippy::cast_precision_loss)]
+                    let angle = item.start_angle + sweep * (i as f32) / (segments as f32);
+                    let x = cx + f64::from(item.x_radius) * f64::from(angle.cos());
+                    let y = cy + f64::from(item.y_radius) * f64::from(angle.sin());
+                    #[allow(clippy::cast_possible_truncation)]
+                    Location::new(x.round() as i32, y.round() as i32)
+                })
+                .collect();
+            self.polyline(&locations, item.color, full);
+            return;
+        }
+
+        let radius = i32::try_from(item.x_radius).unwrap_or(i32::MAX);
+        self.update_extents(Location::new(
+            item.center.x - radius,
+            item.center.y - radius,
+        ));
+        self.update_extents(Location::new(
+            item.center.x + radius,
+            item.center.y + radius,
+        ));
+
+        self.start_entity(if full { "CIRCLE" } else { "ARC" }, item.color);
+        point(&mut self.entities, 0, item.center, self.unit_scale).unwrap();
+        pair(&mut self.entities, 40, to_mm(radius, self.unit_scale)).unwrap();
+        if !full {
+            // Both are counterclockwise with y up, so we only need degrees
+            let start = item.start_angle.to_degrees();
+            let end = (item.start_angle + sweep).to_degrees();
+            pair(&mut self.entities, 50, start).unwrap();
+            pair(&mut self.entities, 51, end).unwrap();
+        }
+    }
+
+    fn start_group(&mut self, group: canvas::DrawGroup) {
+        // Components are made of records, so they don't get their own layer
+        let layer = if group.kind == "Component" {
+            self.layer()
+        } else {
+            group.kind
+        };
+        let layer: Box<str> = layer.into();
+
+        if &*layer != DEFAULT_LAYER && !self.layers.contains(&layer) {
+            self.layers.push(layer.clone());
+        }
+        self.group_layers.push(layer);
+    }
+
+    fn end_group(&mut self) {
+        self.group_layers.pop();
+    }
+}
+
+/// Write a group code and value
+fn pair<W: std::fmt::Write>(
+    f: &mut W,
+    code: u16,
+    value: impl std::fmt::Display,
+) -> std::fmt::Result {
+    write!(f, "{code:>3}\n{value}\n")
+}
+
+/// Write a point as millimeters with group codes `10 + offset` and
+/// `20 + offset`
+fn point<W: std::fmt::Write>(
+    f: &mut W,
+    offset: u16,
+    loc: Location,
+    unit_scale: f64,
+) -> std::fmt::Result {
+    pair(f, 10 + offset, to_mm(loc.x, unit_scale))?;
+    pair(f, 20 + offset, to_mm(loc.y, unit_scale))
+}
+
+/// Drawing units to physical millimeters
+fn to_mm(val: i32, unit_scale: f64) -> f64 {
+    f64::from(val) * unit_scale / 1e6
+}
+
+/// DXF strings are single line, and R12 uses `\U+XXXX` for non-ASCII
+fn escape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\r' | '\n' => out.push(' '),
+            ch if ch.is_ascii() => out.push(ch),
+            ch => write!(out, "\\U+{:04X}", u32::from(ch)).unwrap(),
+        }
+    }
+    out
+}
+
+/// Find the closest of the basic `AutoCAD` color indices. Black maps to 7,
+/// which viewers show as black or white depending on the background.
+fn aci_color(color: Rgb) -> u8 {
+    const COLORS: [(u8, Rgb); 7] = [
+        (1, Rgb::red()),
+        (2, Rgb::from_hex(0xff, 0xff, 0x00)),
+        (3, Rgb::green()),
+        (4, Rgb::from_hex(0x00, 0xff, 0xff)),
+        (5, Rgb::blue()),
+        (6, Rgb::from_hex(0xff, 0x00, 0xff)),
+        (7, Rgb::black()),
+    ];
+
+    let dist = |a: Rgb| {
+        let d = |x: u8, y: u8| (i32::from(x) - i32::from(y)).pow(2);
+        d(a.r, color.r) + d(a.g, color.g) + d(a.b, color.b)
+    };
+
+    COLORS
+        .iter()
+        .min_by_key(|(_, c)| dist(*c))
+        .map_or(7, |(idx, _)| *idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::draw::{DrawGroup, DrawLine};
+
+    #[test]
+    fn test_line_on_layer() {
+        let mut dxf = DxfCtx::new();
+        dxf.start_group(DrawGroup {
+            kind: "Line",
+            ..Default::default()
+        });
+        dxf.draw_line(DrawLine {
+            start: Location::new(0, 0),
+            end: Location::new(2_540_000, -1_000_000),
+            ..Default::default()
+        });
+        dxf.end_group();
+
+        let out = dxf.dxf();
+        assert!(
+            out.contains("  0\nLINE\n  8\nLine\n 62\n7\n 10\n0\n 20\n0\n 11\n2.54\n 21\n-1\n"),
+            "{out}"
+        );
+        assert!(out.contains("  0\nLAYER\n  2\nLine\n"), "{out}");
+        assert!(out.ends_with("  0\nEOF\n"), "{out}");
+    }
+}
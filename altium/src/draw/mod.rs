@@ -1,11 +1,14 @@
 //! Tools related to drawing objects
 
 pub(crate) mod canvas;
+mod dxf;
+mod options;
 mod svg;
 
 pub use canvas::{
     Canvas,
     DrawArc,
+    DrawGroup,
     DrawImage,
     DrawLine,
     DrawPolyLine,
@@ -15,7 +18,10 @@ pub use canvas::{
     LineCap,
     LineJoin,
 };
+pub use drawsvg::Svg;
+pub use options::{RenderOptions, Theme};
 
+pub use self::dxf::DxfCtx;
 pub use self::svg::SvgCtx;
 pub use crate::common::{Location, PosHoriz, PosVert, Rgb};
 
@@ -0,0 +1,129 @@
+//! Options that control how items get rendered
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::Rgb;
+
+/// Color scheme to use when drawing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    /// Use the colors stored in the file
+    #[default]
+    Altium,
+    /// Black lines and text on a white background, suitable for printing
+    Monochrome,
+    /// Lightness-inverted colors on a dark background. Hues are kept.
+    Dark,
+}
+
+/// Options for rendering schematic items, passed to [`Draw`](super::Draw)
+/// implementations via their context.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderOptions {
+    pub theme: Theme,
+    /// `(from, to)` pairs of colors to replace. Remapping happens on the colors
+    /// from the file, before the theme is applied.
+    pub color_map: Vec<(Rgb, Rgb)>,
+    /// Draw pins that are marked hidden
+    pub show_hidden_pins: bool,
+    /// Draw parameters that are marked hidden
+    pub show_hidden_params: bool,
+    /// Draw pin designators (numbers). If false, they are never drawn; if true,
+    /// they are drawn according to the pin's visibility.
+    pub show_pin_numbers: bool,
+    /// Scale relative to physical size for outputs that have a fixed size (e.g.
+    /// SVG). `None` leaves the output unsized so viewers can fit it.
+    pub scale: Option<f32>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            color_map: Vec::new(),
+            show_hidden_pins: false,
+            show_hidden_params: false,
+            show_pin_numbers: true,
+            scale: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Options with the print-friendly [`Theme::Monochrome`]
+    pub fn monochrome() -> Self {
+        Self {
+            theme: Theme::Monochrome,
+            ..Default::default()
+        }
+    }
+
+    /// Options with [`Theme::Dark`]
+    pub fn dark() -> Self {
+        Self {
+            theme: Theme::Dark,
+            ..Default::default()
+        }
+    }
+
+    /// Color to use for a line or text that has color `color` in the file
+    pub fn stroke(&self, color: Rgb) -> Rgb {
+        match self.theme {
+            Theme::Altium => self.remap(color),
+            Theme::Monochrome => Rgb::black(),
+            Theme::Dark => invert_lightness(self.remap(color)),
+        }
+    }
+
+    /// Color to use for an area fill that has color `color` in the file
+    pub fn fill(&self, color: Rgb) -> Rgb {
+        match self.theme {
+            Theme::Altium => self.remap(color),
+            Theme::Monochrome => black_or_white(self.remap(color)),
+            Theme::Dark => invert_lightness(self.remap(color)),
+        }
+    }
+
+    /// Color of the sheet background
+    pub fn background(&self) -> Rgb {
+        match self.theme {
+            Theme::Altium | Theme::Monochrome => self.remap(Rgb::white()),
+            Theme::Dark => invert_lightness(self.remap(Rgb::white())),
+        }
+    }
+
+    /// Apply the color remap table
+    pub(crate) fn remap(&self, color: Rgb) -> Rgb {
+        self.color_map
+            .iter()
+            .find(|(from, _)| *from == color)
+            .map_or(color, |(_, to)| *to)
+    }
+}
+
+/// Black for dark colors and white for light ones, by HSL lightness
+fn black_or_white(color: Rgb) -> Rgb {
+    let Rgb { r, g, b } = color;
+    let sum = u16::from(r.max(g).max(b)) + u16::from(r.min(g).min(b));
+    if sum < 255 {
+        Rgb::black()
+    } else {
+        Rgb::white()
+    }
+}
+
+/// Flip a color's HSL lightness while keeping its hue and saturation, so black
+/// becomes white (and vice versa) but red stays red.
+fn invert_lightness(color: Rgb) -> Rgb {
+    let Rgb { r, g, b } = color;
+    let sum = u16::from(r.max(g).max(b)) + u16::from(r.min(g).min(b));
+    // Result is within `255 - max..=255 - min`, so this can't over/underflow
+    let flip = |ch: u8| u8::try_from(255 + u16::from(ch) - sum).unwrap();
+
+    Rgb {
+        r: flip(r),
+        g: flip(g),
+        b: flip(b),
+    }
+}
@@ -1,12 +1,16 @@
 use std::cmp::{max, min};
-use std::mem;
+use std::f32::consts::{PI, TAU};
 
-use svg::node::element::SVG as Svg;
-use svg::node::{element as el, Text};
-use svg::Node;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use drawsvg::{self as el, Element, Length, Number, PathData, Svg, ViewBox};
+use log::warn;
 
-use super::{canvas, Canvas};
-use crate::common::{PosHoriz, PosVert, Rotation90};
+use super::{canvas, Canvas, LineCap, LineJoin, RenderOptions, Theme};
+use crate::common::{Location, PosHoriz, PosVert, Rgb, Rotation90};
+
+/// Default largest image (after base64 encoding) that we will embed, 500k
+const MAX_EMBED_SIZE: usize = 500_000;
 
 #[derive(Clone, Debug)]
 pub struct SvgCtx {
@@ -17,6 +21,16 @@ pub struct SvgCtx {
     y_range: Option<(i32, i32)>,
     /// True if the image header has already been set
     has_embedded_images: bool,
+    /// Images larger than this are linked rather than embedded
+    max_embed_size: usize,
+    /// If set, size the output relative to its physical size
+    scale: Option<f32>,
+    /// Physical nm per drawing unit
+    unit_scale: i32,
+    /// If set, fill the background with this color
+    background: Option<Rgb>,
+    /// Groups that have been started but not yet ended, innermost last
+    groups: Vec<el::Group>,
 }
 
 impl SvgCtx {
@@ -26,19 +40,54 @@ impl SvgCtx {
             x_range: None,
             y_range: None,
             has_embedded_images: false,
+            max_embed_size: MAX_EMBED_SIZE,
+            scale: None,
+            unit_scale: 1,
+            background: None,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Create a context that applies the canvas-level settings from `options`
+    pub fn with_options(options: &RenderOptions) -> Self {
+        let mut ret = Self::new();
+        ret.scale = options.scale;
+        if options.theme != Theme::Altium {
+            ret.background = Some(options.background());
         }
+        ret
+    }
+
+    /// Set the output size to `scale` times the drawing's physical size. By
+    /// default no size is set, which lets viewers scale to fit.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = Some(scale);
+    }
+
+    /// Set how many physical nm one drawing unit is, used for the output size.
+    /// Defaults to 1, schematics need [`SCH_UNIT_SCALE`](crate::sch::SCH_UNIT_SCALE).
+    pub fn set_unit_scale(&mut self, scale: i32) {
+        self.unit_scale = scale;
+    }
+
+    /// Fill the background with `color`, rather than leaving it transparent
+    pub fn set_background(&mut self, color: Rgb) {
+        self.background = Some(color);
+    }
+
+    /// Set the largest size of an image (in bytes, after base64 encoding) that
+    /// will be embedded in the SVG. Larger images will be linked by their file
+    /// name instead. Setting this to 0 means images are never embedded.
+    pub fn set_max_embed_size(&mut self, size: usize) {
+        self.max_embed_size = size;
     }
 
     /// Add a node to this svg
-    pub fn add_node<T>(&mut self, node: T)
-    where
-        T: Into<Box<dyn svg::Node>>,
-    {
-        // Bad API means we need to do memory tricks...
-        let mut working = Svg::new();
-        mem::swap(&mut self.svg, &mut working);
-        working = working.add(node);
-        mem::swap(&mut self.svg, &mut working);
+    pub fn add_node<T: Into<Element>>(&mut self, node: T) {
+        match self.groups.last_mut() {
+            Some(group) => group.push(node),
+            None => self.svg.push(node),
+        }
     }
 
     /// Translate from (0, 0) in bottom left to (0, 0) in top left. Makes sure
@@ -68,7 +117,12 @@ impl SvgCtx {
     }
 
     /// Get the svg
-    pub fn svg(self) -> Svg {
+    pub fn svg(mut self) -> Svg {
+        while !self.groups.is_empty() {
+            warn!("unterminated SVG group");
+            self.end_group();
+        }
+
         let mut svg = self.svg;
         let (min_x, max_x) = self.x_range.unwrap_or((0, 0));
         let (min_y, max_y) = self.y_range.unwrap_or((0, 0));
@@ -76,17 +130,23 @@ impl SvgCtx {
         // Add a 5% border on all sides
         let side_extra = (max_x - min_x) / 20;
         let vert_extra = (max_y - min_y) / 20;
+        let (x, y) = (min_x - side_extra, min_y - vert_extra);
+        let width = (max_x - min_x) + side_extra * 2;
+        let height = (max_y - min_y) + vert_extra * 2;
 
-        svg = svg.set(
-            "viewBox",
-            format!(
-                "{} {} {} {}",
-                min_x - side_extra,
-                min_y - vert_extra,
-                (max_x - min_x) + side_extra * 2,
-                (max_y - min_y) + vert_extra * 2,
-            ),
-        );
+        svg = svg.view_box(ViewBox::new(x, y, width, height));
+
+        if let Some(scale) = self.scale {
+            let nm_scale = f64::from(scale) * f64::from(self.unit_scale);
+            let to_mm = |v: i32| Length::Mm(Number::F(f64::from(v) * nm_scale / 1e6));
+            svg = svg.width(to_mm(width)).height(to_mm(height));
+        }
+
+        if let Some(background) = self.background {
+            // Needs to be the first node so it is drawn below everything else
+            let rect = el::Rect::new(x, y, width, height).fill(background.to_hex());
+            svg.insert(0, rect);
+        }
 
         if self.has_embedded_images {
             svg = svg.set("xmlns:xlink", "http://www.w3.org/1999/xlink");
@@ -99,6 +159,38 @@ impl SvgCtx {
     pub fn enable_inline_images(&mut self) {
         self.has_embedded_images = true;
     }
+
+    /// Polygons with cutouts need a path with one subpath per outline
+    fn draw_polygon_with_holes(&mut self, item: &canvas::DrawPolygon) {
+        let mut data = PathData::new();
+        for outline in std::iter::once(item.locations).chain(item.holes.iter().map(Vec::as_slice)) {
+            let points = self.points(outline);
+            let Some((&first, rest)) = points.split_first() else {
+                continue;
+            };
+            data = data.move_to(first.0, first.1);
+            for &(x, y) in rest {
+                data = data.line_to(x, y);
+            }
+            data = data.close();
+        }
+
+        let node = el::Path::new(&data)
+            .fill(item.fill_color.to_hex())
+            .set("fill-rule", "evenodd")
+            .stroke(item.stroke_color.to_hex())
+            .stroke_width(item.stroke_width)
+            .set("stroke-linejoin", join_str(item.line_join));
+        self.add_node(node);
+    }
+
+    /// Convert locations to SVG points, updating the view box as needed
+    fn points(&mut self, locations: &[Location]) -> Vec<(i32, i32)> {
+        locations
+            .iter()
+            .map(|loc| (self.x_coord(loc.x, 0), self.y_coord(loc.y, 0)))
+            .collect()
+    }
 }
 
 impl crate::sealed::Sealed for SvgCtx {}
@@ -109,9 +201,6 @@ impl Canvas for SvgCtx {
         use PosVert::{Bottom, Middle, Top};
         use Rotation90::{R0, R180, R270, R90};
 
-        let cmt = svg::node::Comment::new(format!("{item:#?}"));
-        self.add_node(cmt);
-
         let (x, y) = (item.x, item.y);
         let (width, height) = text_dims(item.text, item.font.size);
         let halfwidth = width / 2;
@@ -146,71 +235,232 @@ impl Canvas for SvgCtx {
         self.x_coord(xmin, xmax - xmin);
         self.x_coord(ymin, ymax - ymin);
 
-        let txtnode = Text::new(item.text);
-        let mut node = el::Text::new("foo")
-            .set("x", self.x_coord(x, width))
-            .set("y", self.y_coord(y, height))
+        let node = el::Text::new(self.x_coord(x, width), self.y_coord(y, height), item.text)
             .set("text-anchor", anchor)
             .set("font-size", format!("{}px", item.font.size * 7 / 10))
             .set("font-family", format!("{}, sans-serif", item.font.name))
-            .set("transform", format!("rotate({})", item.rotation.as_int()));
-        node.append(txtnode);
+            .fill(item.color.to_hex())
+            .transform(el::Transform::Rotate(item.rotation.as_int().into()));
         self.add_node(node);
-
-        // Add a circle to the text anchor
-        let node2 = el::Circle::new()
-            .set("cx", self.x_coord(x, width))
-            .set("cy", self.y_coord(y, height))
-            .set("r", 0.5)
-            .set("fill", "red");
-        self.add_node(node2);
     }
 
     fn draw_line(&mut self, item: canvas::DrawLine) {
-        // let dx = item.start.x - item.end.x;
-        let dy = item.start.y - item.end.y;
+        let x1 = self.x_coord(item.start.x, 0);
+        let x2 = self.x_coord(item.end.x, 0);
+        let y1 = self.y_coord(item.start.y, 0);
+        let y2 = self.y_coord(item.end.y, 0);
 
-        let mut node = el::Line::new()
-            .set("x1", self.x_coord(item.start.x, 0))
-            .set("x2", self.x_coord(item.end.x, 0))
-            .set("y1", self.y_coord(item.start.y, dy))
-            .set("y2", self.y_coord(item.end.y, dy))
-            .set("stroke", item.color.to_hex());
-
-        // if let Some(w) = item.width {
-        //     node = node.set("stroke-width", w);
-        // }
-        node = node.set("stroke-width", format!("{}px", item.width));
+        let node = el::Line::new(x1, y1, x2, y2)
+            .stroke(item.color.to_hex())
+            .stroke_width(item.width)
+            .set("stroke-linecap", cap_str(item.start_cap));
 
         self.add_node(node);
     }
 
     fn draw_rectangle(&mut self, item: canvas::DrawRectangle) {
-        let node = el::Rectangle::new()
-            .set("width", item.width)
-            .set("height", item.height)
-            // Need top left corner to set location
-            .set("x", self.x_coord(item.x, item.width))
-            .set("y", self.y_coord(item.y, item.height))
-            .set("fill", item.fill_color.to_hex())
-            .set("stroke", item.stroke_color.to_hex())
-            .set("stroke-width", item.stroke_width);
+        // Need top left corner to set location
+        let x = self.x_coord(item.x, item.width);
+        let y = self.y_coord(item.y, item.height);
+        let node = el::Rect::new(x, y, item.width, item.height)
+            .fill(item.fill_color.to_hex())
+            .stroke(item.stroke_color.to_hex())
+            .stroke_width(item.stroke_width);
         self.add_node(node);
     }
 
-    fn draw_polygon(&mut self, _item: canvas::DrawPolygon) {
-        // todo!()
+    fn draw_polygon(&mut self, item: canvas::DrawPolygon) {
+        if !item.holes.is_empty() {
+            self.draw_polygon_with_holes(&item);
+            return;
+        }
+
+        let points = self.points(item.locations);
+        let node = el::Polygon::new(points)
+            .fill(item.fill_color.to_hex())
+            .stroke(item.stroke_color.to_hex())
+            .stroke_width(item.stroke_width)
+            .set("stroke-linejoin", join_str(item.line_join));
+        self.add_node(node);
+    }
+
+    fn draw_polyline(&mut self, item: canvas::DrawPolyLine) {
+        let points = self.points(item.locations);
+        let node = el::Polyline::new(points)
+            .fill("none")
+            .stroke(item.color.to_hex())
+            .stroke_width(item.width)
+            .set("stroke-linecap", cap_str(item.start_cap))
+            .set("stroke-linejoin", join_str(item.line_join));
+        self.add_node(node);
     }
 
-    fn draw_image(&mut self, _item: canvas::DrawImage) {}
+    fn draw_image(&mut self, item: canvas::DrawImage) {
+        let href = match &item.data {
+            Some(data)
+                if base64::encoded_len(data.len(), true)
+                    .is_some_and(|len| len <= self.max_embed_size) =>
+            {
+                let mut b64_str = format!("data:{};base64,", image_mime_type(data));
+                STANDARD.encode_string(data, &mut b64_str);
+                b64_str
+            }
+            Some(data) => {
+                warn!(
+                    "image '{}' ({} bytes) exceeds the embed limit of {} bytes; linking instead",
+                    item.file_name,
+                    data.len(),
+                    self.max_embed_size
+                );
+                file_href(item.file_name)
+            }
+            None => file_href(item.file_name),
+        };
+
+        if href.is_empty() {
+            warn!("image has neither data nor a file name, skipping");
+            return;
+        }
+
+        // Normalize so that (x, y) is the bottom left corner
+        let (x, width) = if item.width < 0 {
+            (item.x + item.width, -item.width)
+        } else {
+            (item.x, item.width)
+        };
+        let (y, height) = if item.height < 0 {
+            (item.y + item.height, -item.height)
+        } else {
+            (item.y, item.height)
+        };
+
+        let aspect = if item.keep_aspect {
+            "xMidYMid meet"
+        } else {
+            "none"
+        };
+
+        // Need top left corner to set location
+        let x = self.x_coord(x, width);
+        let y = self.y_coord(y, height);
+        let node = el::Image::new(x, y, width, height, href).set("preserveAspectRatio", aspect);
+        self.add_node(node);
+
+        self.enable_inline_images();
+    }
 
     fn add_comment<S: Into<String>>(&mut self, comment: S) {
-        let cmt = svg::node::Comment::new(comment);
-        self.add_node(cmt);
+        self.add_node(el::Comment::new(comment));
     }
 
-    fn draw_arc(&mut self, _item: super::DrawArc) {
-        // todo!()
+    fn start_group(&mut self, group: canvas::DrawGroup) {
+        let mut node = el::Group::new().set("data-record-type", group.kind);
+        if let Some(unique_id) = group.unique_id {
+            node = node.set("data-unique-id", unique_id);
+        }
+        if let Some(name) = group.name {
+            node = node.set("data-name", name);
+        }
+        if let Some(designator) = group.designator {
+            node = node.set("data-designator", designator);
+        }
+        if let Some(net) = group.net {
+            node = node.set("data-net", net);
+        }
+        self.groups.push(node);
+    }
+
+    fn end_group(&mut self) {
+        let Some(group) = self.groups.pop() else {
+            warn!("end_group called without a matching start_group");
+            return;
+        };
+
+        // Records that don't draw anything don't need a group
+        if !group.is_empty() {
+            self.add_node(group);
+        }
+    }
+
+    fn draw_arc(&mut self, item: super::DrawArc) {
+        let Location { x: cx, y: cy } = item.center;
+        let rx = i32::try_from(item.x_radius).unwrap_or(i32::MAX);
+        let ry = i32::try_from(item.y_radius).unwrap_or(i32::MAX);
+
+        // Include the entire ellipse in the view box, which is simpler than
+        // figuring out the arc's extents
+        self.x_coord(cx - rx, rx * 2);
+        self.y_coord(cy - ry, ry * 2);
+
+        let mut sweep = (item.end_angle - item.start_angle).rem_euclid(TAU);
+        if sweep == 0.0 {
+            // Altium uses equal start and end angles for a full ellipse
+            sweep = TAU;
+        }
+
+        let stroke = item.color.to_hex();
+        if (sweep - TAU).abs() < f32::EPSILON {
+            let node = el::Ellipse::new(cx, -cy, rx, ry)
+                .fill("none")
+                .stroke(stroke)
+                .stroke_width(item.width);
+            self.add_node(node);
+            return;
+        }
+
+        // Altium angles are counterclockwise with y up. Flipping y for SVG
+        // means these are also counterclockwise on screen, i.e. sweep flag 0.
+        let point = |angle: f32| {
+            let x = f64::from(cx) + f64::from(rx) * f64::from(angle.cos());
+            let y = f64::from(cy) + f64::from(ry) * f64::from(angle.sin());
+            (Number::F(x), Number::F(-y))
+        };
+        let start = point(item.start_angle);
+        let end = point(item.start_angle + sweep);
+        let data = PathData::new().move_to(start.0, start.1).arc_to(
+            (Number::from(rx), Number::from(ry)),
+            sweep > PI,
+            false,
+            end,
+        );
+
+        let node = el::Path::new(&data)
+            .fill("none")
+            .stroke(stroke)
+            .stroke_width(item.width)
+            .set("stroke-linecap", cap_str(item.start_cap));
+        self.add_node(node);
+    }
+}
+
+fn cap_str(cap: LineCap) -> &'static str {
+    match cap {
+        LineCap::Butt => "butt",
+        LineCap::Square => "square",
+        LineCap::Round => "round",
+    }
+}
+
+fn join_str(join: LineJoin) -> &'static str {
+    match join {
+        LineJoin::Miter => "miter",
+        LineJoin::MiterClip => "miter-clip",
+        LineJoin::Round => "round",
+        LineJoin::Bevel => "bevel",
+    }
+}
+
+/// Turn a path as stored by Altium (usually Windows-style) into something
+/// usable as a link
+fn file_href(path: &str) -> String {
+    let path = path.replace('\\', "/").replace(' ', "%20");
+    let bytes = path.as_bytes();
+
+    // `C:/foo` style absolute paths
+    if bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        format!("file:///{path}")
+    } else {
+        path
     }
 }
 
@@ -223,6 +473,11 @@ fn text_dims(text: &str, font_size: u16) -> (i32, i32) {
     (width, height)
 }
 
+/// MIME type of embedded image data, assuming PNG if the format isn't recognized
+fn image_mime_type(data: &[u8]) -> &'static str {
+    image::guess_format(data).map_or("image/png", |fmt| fmt.to_mime_type())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -234,4 +489,62 @@ mod tests {
         assert_eq!(svg.x_range, Some((10, 30)));
         assert_eq!(svg.y_range, Some((-30, -10)));
     }
+
+    #[test]
+    fn test_image_embed_limit() {
+        let image = canvas::DrawImage {
+            width: 100,
+            height: 100,
+            data: Some(vec![0u8; 64].into()),
+            file_name: r"C:\Users\me\my image.png",
+            ..Default::default()
+        };
+
+        let mut svg = SvgCtx::new();
+        svg.draw_image(image.clone());
+        let out = svg.svg().to_string();
+        assert!(out.contains("data:image/png;base64,AAAA"), "{out}");
+
+        let mut svg = SvgCtx::new();
+        svg.set_max_embed_size(10);
+        svg.draw_image(image);
+        let out = svg.svg().to_string();
+        assert!(out.contains("file:///C:/Users/me/my%20image.png"), "{out}");
+        assert!(!out.contains("base64"), "{out}");
+    }
+
+    #[test]
+    fn test_image_mime_type() {
+        // Start of a JPEG and a BMP file
+        let jpeg = canvas::DrawImage {
+            data: Some(vec![0xff, 0xd8, 0xff, 0xe0, 0, 0x10, b'J', b'F', b'I', b'F'].into()),
+            ..Default::default()
+        };
+        let bmp = canvas::DrawImage {
+            data: Some(b"BM\0\0\0\0".to_vec().into()),
+            ..Default::default()
+        };
+
+        let mut svg = SvgCtx::new();
+        svg.draw_image(jpeg);
+        svg.draw_image(bmp);
+        let out = svg.svg().to_string();
+        assert!(out.contains("data:image/jpeg;base64,"), "{out}");
+        assert!(out.contains("data:image/bmp;base64,"), "{out}");
+        assert!(!out.contains("data:image/png"), "{out}");
+    }
+
+    #[test]
+    fn test_text_without_anchor_marker() {
+        let font = crate::font::Font::default();
+        let mut svg = SvgCtx::new();
+        svg.draw_text(canvas::DrawText {
+            text: "R1",
+            font: &font,
+            ..Default::default()
+        });
+        let out = svg.svg().to_string();
+        assert!(out.contains(">R1</text>"), "{out}");
+        assert!(!out.contains("<circle"), "{out}");
+    }
 }
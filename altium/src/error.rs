@@ -91,6 +91,7 @@ impl std::error::Error for Error {}
 #[non_exhaustive]
 pub enum ErrorKind {
     BufferTooShort(usize, TruncBuf<u8>),
+    Database(Box<str>),
     ElectricalType(u8),
     ExpectedBool(String),
     ExpectedColor(TruncBuf<u8>),
@@ -113,8 +114,10 @@ pub enum ErrorKind {
     Pin(PinError),
     ReadOnlyState(u8),
     RequiredSplit(String),
+    SExpr(Box<str>, usize),
     SheetStyle(u8),
     Utf8(Utf8Error, String),
+    Xml(Box<str>),
 }
 
 impl fmt::Display for ErrorKind {
@@ -151,6 +154,7 @@ impl fmt::Display for ErrorKind {
             ErrorKind::ExpectedBool(s) => write!(f, "error parsing bool from `{s}`"),
             ErrorKind::ExpectedColor(v) => write!(f, "error parsing color from `{v:x}`"),
             ErrorKind::SheetStyle(v) => write!(f, "invalid sheet style {v}"),
+            ErrorKind::SExpr(e, pos) => write!(f, "invalid S-expression: {e} at byte {pos}"),
             ErrorKind::ReadOnlyState(v) => write!(f, "invalid readonly state {v}"),
             ErrorKind::Justification(v) => write!(f, "invalid justification state {v}"),
             ErrorKind::Pin(v) => write!(f, "error parsing pin: {v}"),
@@ -161,6 +165,8 @@ impl fmt::Display for ErrorKind {
             ),
             ErrorKind::Image(e) => write!(f, "image error: {e}"),
             ErrorKind::ExpectedNul(e) => write!(f, "expected nul near {e}"),
+            ErrorKind::Database(e) => write!(f, "database error: {e}"),
+            ErrorKind::Xml(e) => write!(f, "error parsing XML: {e}"),
             ErrorKind::Overflow(a, b, op) => write!(f, "overflow at {a} {op} {b}"),
         }
     }
@@ -213,6 +219,12 @@ impl From<io::Error> for ErrorKind {
     }
 }
 
+impl From<quick_xml::Error> for ErrorKind {
+    fn from(value: quick_xml::Error) -> Self {
+        Self::Xml(value.to_string().into())
+    }
+}
+
 impl From<PinError> for ErrorKind {
     fn from(value: PinError) -> Self {
         Self::Pin(value)
@@ -0,0 +1,285 @@
+//! Integrated libraries (`.IntLib`)
+//!
+//! An integrated library is a compound file that bundles compiled copies of
+//! its source libraries. Each kind of source gets a storage (`SchLib`,
+//! `PCBLib`, ...) with one stream per library, named `0`, `1`, etc. Streams are
+//! zlib compressed, usually after a one byte prefix.
+//!
+//! This layout is inferred. There is no integrated library saved by Altium in
+//! `tests/samples` yet, so reading is only tested against files we build
+//! ourselves. The cross-reference data that Altium stores in the file isn't
+//! read either; [`IntLib::cross_refs`] is derived from the schematic libraries.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+
+use cfb::CompoundFile;
+use flate2::read::ZlibDecoder;
+
+use crate::error::AddContext;
+use crate::pcb::Footprint;
+use crate::sch::Component;
+use crate::{Error, ErrorKind, PcbLib, SchLib};
+
+/// A named library extracted from an integrated library
+type Inner<T> = (Box<str>, T);
+
+/// An integrated library
+///
+/// Source libraries are decompressed when the file is opened and are available
+/// through the usual [`SchLib`] and [`PcbLib`] APIs.
+pub struct IntLib {
+    schlibs: Vec<Inner<SchLib<Cursor<Vec<u8>>>>>,
+    pcblibs: Vec<Inner<PcbLib<Cursor<Vec<u8>>>>>,
+}
+
+/// Impls that are specific to a file
+impl IntLib {
+    /// Open a file from disk
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let cfile: CompoundFile<File> = cfb::open(&path)?;
+        Self::from_cfile(cfile)
+            .context("parsing IntLib")
+            .or_context(|| format!("with file {}", path.as_ref().display()))
+    }
+
+    /// Open an in-memory file from a buffer
+    pub fn from_buffer(buf: &[u8]) -> Result<Self, Error> {
+        let cfile = CompoundFile::open(Cursor::new(buf))?;
+        Self::from_cfile(cfile).context("parsing IntLib from Cursor")
+    }
+
+    /// Schematic libraries in this package. Names are the stream paths, e.g.
+    /// `SchLib/0`.
+    pub fn schlibs(&self) -> impl Iterator<Item = (&str, &SchLib<Cursor<Vec<u8>>>)> {
+        self.schlibs.iter().map(|(name, lib)| (&**name, lib))
+    }
+
+    /// PCB libraries in this package. Names are the stream paths, e.g.
+    /// `PCBLib/0`.
+    pub fn pcblibs(&self) -> impl Iterator<Item = (&str, &PcbLib<Cursor<Vec<u8>>>)> {
+        self.pcblibs.iter().map(|(name, lib)| (&**name, lib))
+    }
+
+    /// Lookup a component by libref in any of the schematic libraries
+    pub fn try_get_component(&self, libref: &str) -> Result<Option<Component>, Error> {
+        for (_, schlib) in &self.schlibs {
+            if let Some(comp) = schlib.try_get_component(libref)? {
+                return Ok(Some(comp));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Lookup a footprint by name in any of the PCB libraries, ignoring case
+    pub fn try_get_footprint(&self, name: &str) -> Result<Option<Footprint>, Error> {
+        self.find_footprint(name).map_or(Ok(None), |(idx, name)| {
+            self.pcblibs[idx].1.try_get_footprint(name)
+        })
+    }
+
+    /// Every component with its parameters and the footprints it links to, in
+    /// library order
+    ///
+    /// This comes from the components' models in the schematic libraries, not
+    /// from the cross-references stored in the file.
+    pub fn cross_refs(&self) -> Result<Vec<CrossRef>, Error> {
+        let mut ret = Vec::new();
+
+        for (schlib_idx, (_, schlib)) in self.schlibs.iter().enumerate() {
+            for meta in schlib.component_meta() {
+                let Some(comp) = schlib.try_get_component(meta.libref())? else {
+                    continue;
+                };
+
+                let current = comp.current_footprint().map(|fp| fp.name().to_owned());
+                let footprints = comp
+                    .footprints()
+                    .iter()
+                    .map(|fp| FootprintRef {
+                        name: fp.name().into(),
+                        is_current: current.as_deref() == Some(fp.name()),
+                        pcblib: self.find_footprint(fp.name()).map(|(idx, _)| idx),
+                    })
+                    .collect();
+
+                ret.push(CrossRef {
+                    libref: comp.name().into(),
+                    description: comp.description().into(),
+                    schlib: schlib_idx,
+                    parameters: comp
+                        .parameters()
+                        .into_iter()
+                        .map(|(name, val)| (name.into(), val.into()))
+                        .collect(),
+                    footprints,
+                });
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Find which PCB library holds a footprint, and its name as stored
+    fn find_footprint(&self, name: &str) -> Option<(usize, &str)> {
+        self.pcblibs.iter().enumerate().find_map(|(idx, (_, lib))| {
+            lib.footprint_meta()
+                .iter()
+                .find(|meta| meta.name().eq_ignore_ascii_case(name))
+                .map(|meta| (idx, meta.name()))
+        })
+    }
+
+    fn from_cfile<F: Read + Seek>(mut cfile: CompoundFile<F>) -> Result<Self, Error> {
+        let mut ret = Self {
+            schlibs: Vec::new(),
+            pcblibs: Vec::new(),
+        };
+
+        for (name, buf) in read_sources(&mut cfile, "SchLib")? {
+            let lib = CompoundFile::open(Cursor::new(buf))
+                .map_err(Error::from)
+                .and_then(SchLib::from_cfile)
+                .or_context(|| format!("parsing `{name}`"))?;
+            ret.schlibs.push((name, lib));
+        }
+
+        for (name, buf) in read_sources(&mut cfile, "PCBLib")? {
+            let lib = CompoundFile::open(Cursor::new(buf))
+                .map_err(Error::from)
+                .and_then(PcbLib::from_cfile)
+                .or_context(|| format!("parsing `{name}`"))?;
+            ret.pcblibs.push((name, lib));
+        }
+
+        Ok(ret)
+    }
+}
+
+impl fmt::Debug for IntLib {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntLib")
+            .field("schlibs", &self.schlibs)
+            .field("pcblibs", &self.pcblibs)
+            .finish()
+    }
+}
+
+/// A component in an integrated library and what it refers to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrossRef {
+    libref: Box<str>,
+    description: Box<str>,
+    schlib: usize,
+    parameters: Vec<(Box<str>, Box<str>)>,
+    footprints: Vec<FootprintRef>,
+}
+
+impl CrossRef {
+    /// The component's libref
+    pub fn libref(&self) -> &str {
+        &self.libref
+    }
+
+    /// The component's description
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Index of the schematic library that holds the component, in the order
+    /// of [`IntLib::schlibs`]
+    pub fn schlib(&self) -> usize {
+        self.schlib
+    }
+
+    /// The component's parameters as `(name, value)`
+    pub fn parameters(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.parameters.iter().map(|(name, val)| (&**name, &**val))
+    }
+
+    /// Value of a parameter by name, ignoring case
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, val)| val)
+    }
+
+    /// Footprints linked to the component
+    pub fn footprints(&self) -> &[FootprintRef] {
+        &self.footprints
+    }
+}
+
+/// A link from a component to a footprint
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FootprintRef {
+    name: Box<str>,
+    is_current: bool,
+    pcblib: Option<usize>,
+}
+
+impl FootprintRef {
+    /// Name of the footprint
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether this is the component's selected footprint
+    pub fn is_current(&self) -> bool {
+        self.is_current
+    }
+
+    /// Index of the PCB library that has this footprint, in the order of
+    /// [`IntLib::pcblibs`]. `None` if the footprint isn't in the package.
+    pub fn pcblib(&self) -> Option<usize> {
+        self.pcblib
+    }
+}
+
+/// Decompress every stream in a source storage, in numeric order. The storage
+/// name is matched ignoring case, and a missing storage has no sources.
+fn read_sources<F: Read + Seek>(
+    cfile: &mut CompoundFile<F>,
+    storage: &str,
+) -> Result<Vec<Inner<Vec<u8>>>, Error> {
+    let Some(storage) = cfile
+        .read_root_storage()
+        .find(|entry| entry.is_storage() && entry.name().eq_ignore_ascii_case(storage))
+        .map(|entry| entry.name().to_owned())
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut streams: Vec<(u32, String)> = cfile
+        .read_storage(&storage)?
+        .filter(cfb::Entry::is_stream)
+        .filter_map(|entry| Some((entry.name().parse().ok()?, entry.path().to_str()?.into())))
+        .collect();
+    streams.sort_unstable();
+
+    let mut ret = Vec::new();
+    for (idx, path) in streams {
+        let mut buf = Vec::new();
+        cfile.open_stream(&path)?.read_to_end(&mut buf)?;
+        let data = inflate(&buf).or_context(|| format!("decompressing `{path}`"))?;
+        ret.push((format!("{storage}/{idx}").into(), data));
+    }
+
+    Ok(ret)
+}
+
+/// Decompress a source stream, skipping the prefix byte if there is one
+fn inflate(buf: &[u8]) -> Result<Vec<u8>, ErrorKind> {
+    // A zlib header is `0x78` for the default window size
+    let start = match buf {
+        [0x78, ..] => 0,
+        [_, 0x78, ..] => 1,
+        _ => return Err(ErrorKind::new_invalid_stream("compressed source", 0)),
+    };
+
+    let mut ret = Vec::new();
+    ZlibDecoder::new(&buf[start..]).read_to_end(&mut ret)?;
+    Ok(ret)
+}
@@ -0,0 +1,402 @@
+//! Conversion between Altium and KiCad formats
+//!
+//! KiCad files are S-expressions with lengths in millimeters. Output targets
+//! the KiCad 6 file versions, which newer versions of KiCad can still open.
+
+mod sexpr;
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+pub(crate) use sexpr::SExpr;
+
+use crate::error::AddContext;
+use crate::font::{Font, FontCollection};
+use crate::pcb::record::kicad_footprint;
+use crate::pcb::{BoardLayers, Footprint, Layer, MechanicalKind};
+use crate::sch::record::{kicad_symbol, symbol_records};
+use crate::sch::Component;
+use crate::{Error, ErrorKind};
+
+/// Version written to generated symbol libraries
+const SYMBOL_LIB_VERSION: u32 = 20211014;
+/// Version written to generated footprints
+pub(crate) const FOOTPRINT_VERSION: u32 = 20211014;
+/// Name we write as the generating program
+pub(crate) const GENERATOR: &str = "altium_rs";
+
+/// A KiCad symbol library (`.kicad_sym`), either built from Altium components
+/// or read from a file
+///
+/// Each component becomes one symbol. Parts of a multipart component become
+/// KiCad units, and the first alternate display mode becomes the De Morgan
+/// body style (KiCad has no equivalent to further display modes).
+///
+/// ```no_run
+/// use altium::kicad::SymbolLib;
+/// use altium::SchLib;
+///
+/// let schlib = SchLib::open("example.SchLib").unwrap();
+/// let mut lib = SymbolLib::new();
+/// for component in schlib.components() {
+///     lib.add_component(&component);
+/// }
+/// lib.save("example.kicad_sym").unwrap();
+/// ```
+///
+/// Going the other way, symbols can be converted to components and written to
+/// a `SchLib`. Units and body styles map back to parts and display modes, and
+/// derived (`extends`) symbols get their parent's graphics.
+///
+/// ```no_run
+/// use altium::kicad::SymbolLib;
+/// use altium::sch::SchLibWriter;
+///
+/// let lib = SymbolLib::open("vendor.kicad_sym").unwrap();
+/// let mut writer = SchLibWriter::new();
+/// for component in lib.components().unwrap() {
+///     writer.add_component(&component);
+/// }
+/// writer.save("vendor.SchLib").unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SymbolLib {
+    symbols: Vec<SExpr>,
+}
+
+impl SymbolLib {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a `.kicad_sym` file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        Self::from_kicad_sym(&text).or_context(|| format!("reading {}", path.display()))
+    }
+
+    /// Parse the contents of a `.kicad_sym` file
+    pub fn from_kicad_sym(text: &str) -> Result<Self, Error> {
+        let lib = SExpr::parse(text).context("parsing symbol library")?;
+        if lib.name() != Some("kicad_symbol_lib") {
+            return Err(ErrorKind::FileType(
+                lib.name().unwrap_or_default().to_owned(),
+                "KiCad symbol library",
+            )
+            .into());
+        }
+
+        Ok(Self {
+            symbols: lib.find_all("symbol").cloned().collect(),
+        })
+    }
+
+    /// Convert a component and add it to this library
+    pub fn add_component(&mut self, component: &Component) {
+        self.symbols
+            .push(kicad_symbol(component.name(), component.records()));
+    }
+
+    /// Number of symbols in this library
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Names of the symbols in this library
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.symbols.iter().filter_map(|sym| sym.arg(0))
+    }
+
+    /// Convert a single symbol to a component, if it exists
+    pub fn component(&self, name: &str) -> Result<Option<Component>, Error> {
+        self.symbols
+            .iter()
+            .find(|sym| sym.arg(0) == Some(name))
+            .map(|sym| self.to_component(sym))
+            .transpose()
+    }
+
+    /// Convert all symbols to components
+    pub fn components(&self) -> Result<Vec<Component>, Error> {
+        self.symbols
+            .iter()
+            .map(|sym| self.to_component(sym))
+            .collect()
+    }
+
+    /// Get the library file contents
+    pub fn kicad_sym(&self) -> String {
+        let mut lib = SExpr::list(
+            "kicad_symbol_lib",
+            [
+                SExpr::pair("version", SYMBOL_LIB_VERSION),
+                SExpr::pair("generator", GENERATOR),
+            ],
+        );
+        for symbol in &self.symbols {
+            lib.push(symbol.clone());
+        }
+
+        let mut out = lib.to_string();
+        out.push('\n');
+        out
+    }
+
+    /// Write the library file to a writer
+    pub fn write_to<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.kicad_sym().as_bytes())
+    }
+
+    /// Write the library to a file, creating it if needed
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    fn to_component(&self, symbol: &SExpr) -> Result<Component, Error> {
+        let name = symbol.arg(0).unwrap_or_default();
+        let records = symbol_records(symbol, &self.symbols)?;
+
+        // Imported text all uses a single font
+        let font = Font {
+            name: "Times New Roman".into(),
+            size: 10,
+        };
+
+        Ok(Component {
+            name: name.into(),
+            records,
+            fonts: Arc::new(FontCollection::from(vec![font])),
+            storage: Arc::default(),
+        })
+    }
+}
+
+/// A KiCad footprint library (a `.pretty` directory of `.kicad_mod` files)
+/// built from Altium footprints
+///
+/// Pads keep their shapes (including rounded rectangle corner radii) and
+/// drills. Graphics are placed according to a [`LayerMap`], keepout regions
+/// become keepout zones and copper regions under a pad become part of that pad.
+/// 3D bodies become model references.
+///
+/// ```no_run
+/// use altium::kicad::{FootprintLib, LayerMap};
+/// use altium::PcbLib;
+///
+/// let pcblib = PcbLib::open("example.PcbLib").unwrap();
+/// let layers = LayerMap::from_layers(&pcblib.layers().unwrap());
+/// let mut lib = FootprintLib::with_layer_map(layers);
+/// lib.set_model_dir("${KIPRJMOD}/3dmodels");
+/// for footprint in pcblib.footprints() {
+///     lib.add_footprint(&footprint);
+/// }
+/// lib.save("example.pretty").unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FootprintLib {
+    layers: LayerMap,
+    model_dir: String,
+    /// Footprint names and their contents
+    footprints: Vec<(String, SExpr)>,
+}
+
+impl FootprintLib {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use a custom layer mapping for footprints added after this call
+    pub fn with_layer_map(layers: LayerMap) -> Self {
+        Self {
+            layers,
+            ..Self::default()
+        }
+    }
+
+    /// Set the directory that 3D model paths are relative to. By default just
+    /// the model's file name is used.
+    pub fn set_model_dir(&mut self, dir: &str) {
+        dir.clone_into(&mut self.model_dir);
+    }
+
+    /// Convert a footprint and add it to this library
+    pub fn add_footprint(&mut self, footprint: &Footprint) {
+        let expr = kicad_footprint(footprint, &self.layers, &self.model_dir);
+        self.footprints.push((footprint.name().to_owned(), expr));
+    }
+
+    /// Number of footprints in this library
+    pub fn len(&self) -> usize {
+        self.footprints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.footprints.is_empty()
+    }
+
+    /// Names of the footprints in this library
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.footprints.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Get the `.kicad_mod` file contents for a single footprint
+    pub fn kicad_mod(&self, name: &str) -> Option<String> {
+        let (_, expr) = self.footprints.iter().find(|(n, _)| n == name)?;
+        let mut out = expr.to_string();
+        out.push('\n');
+        Some(out)
+    }
+
+    /// Write the library to a directory, creating it if needed. Each footprint
+    /// is written to its own file.
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> io::Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        for (name, expr) in &self.footprints {
+            let path = dir.join(format!("{}.kicad_mod", file_stem(name)));
+            let mut writer = BufWriter::new(File::create(path)?);
+            writeln!(writer, "{expr}")?;
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Assignment of Altium layers to KiCad layer names
+///
+/// Copper, overlay, paste and solder mask layers have direct equivalents.
+/// Mechanical layers have no fixed purpose in Altium; by default, mechanical 13
+/// and 15 are treated as the front fab and courtyard layers (as the IPC
+/// footprint wizard uses them), 14 and 16 as their back side counterparts, and
+/// everything else is placed on `Dwgs.User`. [`LayerMap::from_layers`] uses the
+/// kinds assigned to mechanical layers instead.
+#[derive(Clone, Debug)]
+pub struct LayerMap {
+    mechanical: BTreeMap<u8, String>,
+    mechanical_default: String,
+}
+
+impl Default for LayerMap {
+    fn default() -> Self {
+        let mechanical = [
+            (13, "F.Fab"),
+            (14, "B.Fab"),
+            (15, "F.CrtYd"),
+            (16, "B.CrtYd"),
+        ]
+        .into_iter()
+        .map(|(num, name)| (num, name.to_owned()))
+        .collect();
+
+        Self {
+            mechanical,
+            mechanical_default: "Dwgs.User".to_owned(),
+        }
+    }
+}
+
+impl LayerMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map mechanical layers by their kind, e.g. courtyard layers to
+    /// `F.CrtYd` and `B.CrtYd`. Layers without a kind keep the default
+    /// mapping, layers with a kind that KiCad has no equivalent for go to the
+    /// default layer.
+    pub fn from_layers(layers: &BoardLayers) -> Self {
+        let mut ret = Self::default();
+
+        for info in layers.layers() {
+            let (Layer::Mechanical(num), Some(kind)) = (info.layer(), info.mechanical_kind())
+            else {
+                continue;
+            };
+            let kicad_layer = match kind {
+                MechanicalKind::AssemblyTop
+                | MechanicalKind::ComponentOutlineTop
+                | MechanicalKind::DesignatorTop
+                | MechanicalKind::ValueTop => "F.Fab",
+                MechanicalKind::AssemblyBottom
+                | MechanicalKind::ComponentOutlineBottom
+                | MechanicalKind::DesignatorBottom
+                | MechanicalKind::ValueBottom => "B.Fab",
+                MechanicalKind::CourtyardTop => "F.CrtYd",
+                MechanicalKind::CourtyardBottom => "B.CrtYd",
+                MechanicalKind::AssemblyNotes
+                | MechanicalKind::FabricationNotes
+                | MechanicalKind::Dimensions
+                | MechanicalKind::DimensionsTop
+                | MechanicalKind::DimensionsBottom => "Cmts.User",
+                _ => {
+                    ret.mechanical.remove(&num);
+                    continue;
+                }
+            };
+            ret.set_mechanical(num, kicad_layer);
+        }
+
+        ret
+    }
+
+    /// Place items from mechanical layer `num` on KiCad layer `kicad_layer`
+    pub fn set_mechanical(&mut self, num: u8, kicad_layer: &str) {
+        self.mechanical.insert(num, kicad_layer.to_owned());
+    }
+
+    /// Set the KiCad layer for mechanical layers without an explicit mapping
+    pub fn set_mechanical_default(&mut self, kicad_layer: &str) {
+        kicad_layer.clone_into(&mut self.mechanical_default);
+    }
+
+    /// The KiCad layer that items on `layer` are placed on, if there is one
+    pub fn kicad_layer(&self, layer: Layer) -> Option<&str> {
+        let ret = match layer {
+            Layer::Top => "F.Cu",
+            Layer::Bottom => "B.Cu",
+            Layer::Mid(n) => return MID_LAYERS.get(usize::from(n).checked_sub(1)?).copied(),
+            Layer::TopOverlay => "F.SilkS",
+            Layer::BottomOverlay => "B.SilkS",
+            Layer::TopPaste => "F.Paste",
+            Layer::BottomPaste => "B.Paste",
+            Layer::TopSolder => "F.Mask",
+            Layer::BottomSolder => "B.Mask",
+            Layer::Mechanical(n) => {
+                return Some(self.mechanical.get(&n).unwrap_or(&self.mechanical_default));
+            }
+            Layer::KeepOut | Layer::DrillGuide | Layer::DrillDrawing => "Dwgs.User",
+            _ => return None,
+        };
+        Some(ret)
+    }
+}
+
+/// KiCad names for Altium's mid layers
+const MID_LAYERS: [&str; 30] = [
+    "In1.Cu", "In2.Cu", "In3.Cu", "In4.Cu", "In5.Cu", "In6.Cu", "In7.Cu", "In8.Cu", "In9.Cu",
+    "In10.Cu", "In11.Cu", "In12.Cu", "In13.Cu", "In14.Cu", "In15.Cu", "In16.Cu", "In17.Cu",
+    "In18.Cu", "In19.Cu", "In20.Cu", "In21.Cu", "In22.Cu", "In23.Cu", "In24.Cu", "In25.Cu",
+    "In26.Cu", "In27.Cu", "In28.Cu", "In29.Cu", "In30.Cu",
+];
+
+/// Replace characters that can't be used in file names
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|ch| match ch {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            ch => ch,
+        })
+        .collect()
+}
@@ -0,0 +1,335 @@
+//! A minimal S-expression tree, as used by all of KiCad's file formats
+
+use core::fmt::{self, Write};
+
+use crate::ErrorKind;
+
+/// Rendered lists longer than this get broken across lines
+const MAX_INLINE: usize = 80;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum SExpr {
+    /// A bare token such as a keyword or number
+    Atom(Box<str>),
+    /// A quoted string
+    Str(Box<str>),
+    List(Vec<SExpr>),
+}
+
+impl SExpr {
+    /// A list headed by keyword `name`
+    pub fn list<I: IntoIterator<Item = SExpr>>(name: &str, items: I) -> Self {
+        let mut list = vec![Self::atom(name)];
+        list.extend(items);
+        Self::List(list)
+    }
+
+    pub fn atom<T: fmt::Display>(val: T) -> Self {
+        Self::Atom(val.to_string().into())
+    }
+
+    pub fn string(val: &str) -> Self {
+        Self::Str(val.into())
+    }
+
+    /// Shorthand for `(name value)` with a bare value
+    pub fn pair<T: fmt::Display>(name: &str, val: T) -> Self {
+        Self::list(name, [Self::atom(val)])
+    }
+
+    /// A length in nanometers, written as millimeters
+    pub fn mm(nm: i64) -> Self {
+        let sign = if nm < 0 { "-" } else { "" };
+        let abs = nm.unsigned_abs();
+        let frac = abs % 1_000_000;
+
+        if frac == 0 {
+            return Self::atom(format!("{sign}{}", abs / 1_000_000));
+        }
+
+        let frac = format!("{frac:06}");
+        Self::atom(format!(
+            "{sign}{}.{}",
+            abs / 1_000_000,
+            frac.trim_end_matches('0')
+        ))
+    }
+
+    /// A unitless number such as an angle or ratio, rounded to 6 decimal
+    /// places
+    pub fn float(val: f64) -> Self {
+        let s = format!("{val:.6}");
+        let s = s.trim_end_matches('0').trim_end_matches('.');
+        match s {
+            "-0" => Self::atom("0"),
+            _ => Self::atom(s),
+        }
+    }
+
+    /// Parse a single expression from text, such as a whole KiCad file
+    pub fn parse(text: &str) -> Result<Self, ErrorKind> {
+        let err = |msg: &str, pos: usize| ErrorKind::SExpr(msg.into(), pos);
+        // Lists that are still open, innermost last
+        let mut stack: Vec<Vec<SExpr>> = Vec::new();
+        let mut ret = None;
+        let mut chars = text.char_indices().peekable();
+
+        while let Some((pos, ch)) = chars.next() {
+            let item = match ch {
+                _ if ch.is_whitespace() => continue,
+                '(' => {
+                    stack.push(Vec::new());
+                    continue;
+                }
+                ')' => Self::List(stack.pop().ok_or_else(|| err("unmatched `)`", pos))?),
+                '"' => {
+                    let mut val = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '"')) => break,
+                            Some((_, '\\')) => match chars.next() {
+                                Some((_, 'n')) => val.push('\n'),
+                                Some((_, 't')) => val.push('\t'),
+                                Some((_, ch)) => val.push(ch),
+                                None => return Err(err("unterminated string", pos)),
+                            },
+                            Some((_, ch)) => val.push(ch),
+                            None => return Err(err("unterminated string", pos)),
+                        }
+                    }
+                    Self::Str(val.into())
+                }
+                _ => {
+                    let mut end = text.len();
+                    while let Some((next_pos, next)) = chars.peek() {
+                        if next.is_whitespace() || matches!(next, '(' | ')' | '"') {
+                            end = *next_pos;
+                            break;
+                        }
+                        chars.next();
+                    }
+                    Self::Atom(text[pos..end].into())
+                }
+            };
+
+            match stack.last_mut() {
+                Some(list) => list.push(item),
+                None if ret.is_none() => ret = Some(item),
+                None => return Err(err("unexpected data after expression", pos)),
+            }
+        }
+
+        if !stack.is_empty() {
+            return Err(err("unclosed `(`", text.len()));
+        }
+        ret.ok_or_else(|| err("no expression", 0))
+    }
+
+    /// The keyword heading a list, e.g. `symbol` for `(symbol ...)`
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::List(items) => match items.first() {
+                Some(Self::Atom(v)) => Some(v),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Items of a list after the keyword. Empty for atoms and strings.
+    pub fn items(&self) -> &[SExpr] {
+        match self {
+            Self::List(items) if self.name().is_some() => &items[1..],
+            Self::List(items) => items,
+            _ => &[],
+        }
+    }
+
+    /// The first child list with the given keyword
+    pub fn find(&self, name: &str) -> Option<&SExpr> {
+        self.items().iter().find(|item| item.name() == Some(name))
+    }
+
+    /// All child lists with the given keyword
+    pub fn find_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a SExpr> {
+        self.items()
+            .iter()
+            .filter(move |item| item.name() == Some(name))
+    }
+
+    /// The text of an atom or string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Atom(v) | Self::Str(v) => Some(v),
+            Self::List(_) => None,
+        }
+    }
+
+    /// The text of the item at `idx` (after the keyword)
+    pub fn arg(&self, idx: usize) -> Option<&str> {
+        self.items().get(idx).and_then(Self::as_str)
+    }
+
+    /// Whether this list contains a bare atom, e.g. `hide` in
+    /// `(pin_names hide)`
+    pub fn has_atom(&self, atom: &str) -> bool {
+        self.items()
+            .iter()
+            .any(|item| matches!(item, Self::Atom(v) if &**v == atom))
+    }
+
+    /// Append an item to a list. Does nothing for atoms or strings.
+    pub fn push(&mut self, item: SExpr) {
+        if let Self::List(items) = self {
+            items.push(item);
+        }
+    }
+
+    /// Length of this expression if written on a single line, stopping early
+    /// once it exceeds `limit`
+    fn inline_len(&self, limit: usize) -> usize {
+        match self {
+            Self::Atom(v) => v.len(),
+            // Rough, escapes are rare
+            Self::Str(v) => v.len() + 2,
+            Self::List(items) => {
+                let mut len = 1;
+                for item in items {
+                    len += item.inline_len(limit) + 1;
+                    if len > limit {
+                        break;
+                    }
+                }
+                len
+            }
+        }
+    }
+
+    fn write_indented<W: Write>(&self, f: &mut W, indent: usize) -> fmt::Result {
+        let items = match self {
+            Self::Atom(v) => return f.write_str(v),
+            Self::Str(v) => return write_quoted(f, v),
+            Self::List(items) => items,
+        };
+
+        f.write_char('(')?;
+
+        if self.inline_len(MAX_INLINE) <= MAX_INLINE.saturating_sub(indent * 2) {
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(' ')?;
+                }
+                item.write_indented(f, indent)?;
+            }
+            return f.write_char(')');
+        }
+
+        // Leading atoms (the keyword and any values) stay on the first line, the
+        // rest go on their own lines
+        let head_len = items
+            .iter()
+            .position(|item| matches!(item, Self::List(_)))
+            .unwrap_or(items.len());
+        let (head, tail) = items.split_at(head_len);
+
+        for (i, item) in head.iter().enumerate() {
+            if i > 0 {
+                f.write_char(' ')?;
+            }
+            item.write_indented(f, indent)?;
+        }
+
+        for item in tail {
+            f.write_char('\n')?;
+            write_indent(f, indent + 1)?;
+            item.write_indented(f, indent + 1)?;
+        }
+
+        f.write_char('\n')?;
+        write_indent(f, indent)?;
+        f.write_char(')')
+    }
+}
+
+impl fmt::Display for SExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+fn write_indent<W: Write>(f: &mut W, indent: usize) -> fmt::Result {
+    (0..indent).try_for_each(|_| f.write_str("  "))
+}
+
+fn write_quoted<W: Write>(f: &mut W, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for ch in s.chars() {
+        match ch {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            _ => f.write_char(ch)?,
+        }
+    }
+    f.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mm() {
+        assert_eq!(SExpr::mm(2_540_000).to_string(), "2.54");
+        assert_eq!(SExpr::mm(-1_270_000).to_string(), "-1.27");
+        assert_eq!(SExpr::mm(5_000_000).to_string(), "5");
+        assert_eq!(SExpr::mm(-1).to_string(), "-0.000001");
+    }
+
+    #[test]
+    fn test_float() {
+        assert_eq!(SExpr::float(90.0).to_string(), "90");
+        assert_eq!(SExpr::float(0.125).to_string(), "0.125");
+        assert_eq!(SExpr::float(-0.0000001).to_string(), "0");
+        assert_eq!(SExpr::float(1.0 / 3.0).to_string(), "0.333333");
+    }
+
+    #[test]
+    fn test_parse() {
+        let text =
+            r#"(symbol "R" (pin_names hide) (at 1.27 -2) (property "Value" "say \"hi\"\n"))"#;
+        let expr = SExpr::parse(text).unwrap();
+
+        assert_eq!(expr.name(), Some("symbol"));
+        assert_eq!(expr.arg(0), Some("R"));
+        assert!(expr.find("pin_names").unwrap().has_atom("hide"));
+        let at = expr.find("at").unwrap();
+        assert_eq!((at.arg(0), at.arg(1)), (Some("1.27"), Some("-2")));
+        assert_eq!(expr.find("property").unwrap().arg(1), Some("say \"hi\"\n"));
+        assert!(expr.find("missing").is_none());
+
+        // Parsing what we write gives the same tree
+        assert_eq!(SExpr::parse(&expr.to_string()).unwrap(), expr);
+
+        assert!(SExpr::parse("(a (b)").is_err());
+        assert!(SExpr::parse("(a))").is_err());
+        assert!(SExpr::parse("(a) (b)").is_err());
+        assert!(SExpr::parse("(a \"b)").is_err());
+    }
+
+    #[test]
+    fn test_write() {
+        let expr = SExpr::list(
+            "property",
+            [
+                SExpr::string("Value"),
+                SExpr::string("say \"hi\""),
+                SExpr::list("at", [SExpr::mm(0), SExpr::mm(1_270_000)]),
+            ],
+        );
+        assert_eq!(
+            expr.to_string(),
+            r#"(property "Value" "say \"hi\"" (at 0 1.27))"#
+        );
+    }
+}
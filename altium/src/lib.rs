@@ -29,22 +29,31 @@
 mod common;
 mod logging;
 mod parse;
+mod write;
 
 #[doc(hidden)]
 pub mod __private;
+pub mod dblib;
 pub mod draw;
 pub mod dwf;
 pub mod error;
 pub mod font;
+pub mod intlib;
+pub mod kicad;
+pub mod matlib;
+pub mod netlist;
 pub mod pcb;
 pub mod prj;
 pub mod sch;
+pub mod validate;
 
 #[doc(inline)]
 pub use common::{Location, Rgb, Rotation90, UniqueId, Visibility};
 #[doc(inline)]
 pub use error::{Error, ErrorKind, Result};
 #[doc(inline)]
+pub use intlib::IntLib;
+#[doc(inline)]
 pub use pcb::{PcbDoc, PcbLib};
 #[doc(inline)]
 pub use prj::PrjPcb;
@@ -0,0 +1,522 @@
+//! Material libraries (`.MatLib`), used for layer stackups
+//!
+//! These are XML files with a list of entities, each holding typed properties.
+//! The kind of material is only given by the entity's `TypeId`.
+
+use std::fs;
+use std::path::Path;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::common::{length_factor, number_len};
+use crate::error::AddContext;
+use crate::parse::{decode_xml_text, parse_hex_color, resolve_xml_ref, XmlAttrs};
+use crate::{Error, ErrorKind, Rgb};
+
+/// A material library
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MatLib {
+    library_id: Box<str>,
+    version: Box<str>,
+    materials: Vec<Material>,
+}
+
+impl MatLib {
+    /// Open a `.MatLib` file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let text = fs::read_to_string(&path)?;
+        Self::from_xml(&text).or_context(|| format!("with file {}", path.as_ref().display()))
+    }
+
+    /// Parse a material library from its XML
+    pub fn from_xml(xml: &str) -> Result<Self, Error> {
+        let mut reader = Reader::from_str(xml);
+        reader.config_mut().trim_text(true);
+
+        let mut ret = Self::default();
+        // The entity and property being read
+        let mut entity: Option<Material> = None;
+        let mut property: Option<(XmlAttrs, String)> = None;
+
+        loop {
+            match reader.read_event().map_err(ErrorKind::from)? {
+                Event::Start(e) | Event::Empty(e)
+                    if e.local_name().as_ref() == b"ExtensibleLibrary" =>
+                {
+                    let attrs = XmlAttrs::read(&e)?;
+                    ret.library_id = attrs.get("LibraryId").into();
+                    ret.version = attrs.get("Version").into();
+                }
+                Event::Start(e) if e.local_name().as_ref() == b"Entity" => {
+                    entity = Some(Material::new(&XmlAttrs::read(&e)?));
+                }
+                Event::Empty(e) if e.local_name().as_ref() == b"Entity" => {
+                    ret.materials.push(Material::new(&XmlAttrs::read(&e)?));
+                }
+                Event::Start(e) if e.local_name().as_ref() == b"Property" => {
+                    property = Some((XmlAttrs::read(&e)?, String::new()));
+                }
+                Event::Empty(e) if e.local_name().as_ref() == b"Property" => {
+                    if let Some(entity) = &mut entity {
+                        entity
+                            .properties
+                            .push(Property::new(&XmlAttrs::read(&e)?, ""));
+                    }
+                }
+                Event::Text(t) => {
+                    if let Some((_, text)) = &mut property {
+                        text.push_str(&decode_xml_text(&t)?);
+                    }
+                }
+                Event::GeneralRef(r) => {
+                    if let Some((_, text)) = &mut property {
+                        text.push_str(&resolve_xml_ref(&r)?);
+                    }
+                }
+                Event::End(e) if e.local_name().as_ref() == b"Property" => {
+                    if let (Some((attrs, text)), Some(entity)) = (property.take(), &mut entity) {
+                        entity.properties.push(Property::new(&attrs, &text));
+                    }
+                }
+                Event::End(e) if e.local_name().as_ref() == b"Entity" => {
+                    ret.materials.extend(entity.take());
+                }
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// ID of this library
+    pub fn library_id(&self) -> &str {
+        &self.library_id
+    }
+
+    /// Format version of the file
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Every material in the library, in file order
+    pub fn materials(&self) -> &[Material] {
+        &self.materials
+    }
+
+    /// Materials of a single kind
+    pub fn materials_of<'a>(
+        &'a self,
+        kind: &'a MaterialKind,
+    ) -> impl Iterator<Item = &'a Material> {
+        self.materials.iter().filter(move |mat| mat.kind == *kind)
+    }
+
+    /// Find a material by its ID, ignoring case
+    pub fn get(&self, id: &str) -> Option<&Material> {
+        self.materials
+            .iter()
+            .find(|mat| mat.id.eq_ignore_ascii_case(id))
+    }
+}
+
+/// A single material definition
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Material {
+    id: Box<str>,
+    type_id: Box<str>,
+    revision_id: Box<str>,
+    revision_date: Box<str>,
+    kind: MaterialKind,
+    properties: Vec<Property>,
+}
+
+impl Material {
+    fn new(attrs: &XmlAttrs) -> Self {
+        let type_id = attrs.get("TypeId");
+        Self {
+            id: attrs.get("Id").into(),
+            type_id: type_id.into(),
+            revision_id: attrs.get("RevisionId").into(),
+            revision_date: attrs.get("RevisionDate").into(),
+            kind: MaterialKind::from_type_id(type_id),
+            properties: Vec::new(),
+        }
+    }
+
+    /// Unique ID of the material
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// ID of the material's type, which determines [`Material::kind`]
+    pub fn type_id(&self) -> &str {
+        &self.type_id
+    }
+
+    /// ID of this revision of the material
+    pub fn revision_id(&self) -> &str {
+        &self.revision_id
+    }
+
+    /// Date of this revision, as an ISO 8601 string
+    pub fn revision_date(&self) -> &str {
+        &self.revision_date
+    }
+
+    /// What kind of material this is
+    pub fn kind(&self) -> &MaterialKind {
+        &self.kind
+    }
+
+    /// All properties in file order
+    pub fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+
+    /// Find a property by name, ignoring case
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|prop| prop.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Product name. Surface finishes only have a material name, which is used
+    /// instead.
+    pub fn name(&self) -> Option<&str> {
+        self.string("Name").or_else(|| self.string("Material"))
+    }
+
+    pub fn manufacturer(&self) -> Option<&str> {
+        self.string("Manufacturer")
+    }
+
+    /// Manufacturing process, e.g. for copper foil or a surface finish
+    pub fn process(&self) -> Option<&str> {
+        self.string("Process")
+    }
+
+    /// Glass weave styles of a core or prepreg, e.g. `1080`
+    pub fn constructions(&self) -> Option<&str> {
+        self.string("Constructions")
+    }
+
+    pub fn thickness(&self) -> Option<&DimValue> {
+        self.dim("Thickness")
+    }
+
+    /// Relative permittivity (Dk)
+    pub fn dielectric_constant(&self) -> Option<f64> {
+        self.dim("DielectricConstant").map(DimValue::value)
+    }
+
+    /// Dissipation factor (Df)
+    pub fn loss_tangent(&self) -> Option<f64> {
+        self.dim("LossTangent").map(DimValue::value)
+    }
+
+    /// Frequency that the dielectric constant and loss tangent are given at
+    pub fn frequency(&self) -> Option<&DimValue> {
+        self.dim("Frequency")
+    }
+
+    /// Resin content of a core or prepreg
+    pub fn resin_content(&self) -> Option<&DimValue> {
+        self.dim("Resin")
+    }
+
+    /// Glass transition temperature (Tg)
+    pub fn glass_transition_temp(&self) -> Option<&DimValue> {
+        self.dim("GlassTransTemp")
+    }
+
+    /// Copper weight
+    pub fn weight(&self) -> Option<&DimValue> {
+        self.dim("Weight")
+    }
+
+    /// Display color and its alpha
+    pub fn color(&self) -> Option<(Rgb, u8)> {
+        match self.property("Color")?.value {
+            PropertyValue::Color(rgb, alpha) => Some((rgb, alpha)),
+            _ => None,
+        }
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        match &self.property(name)?.value {
+            PropertyValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn dim(&self, name: &str) -> Option<&DimValue> {
+        match &self.property(name)?.value {
+            PropertyValue::Dim(dim) => Some(dim),
+            _ => None,
+        }
+    }
+}
+
+/// Kinds of material, identified by their type ID
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum MaterialKind {
+    SolderMask,
+    /// Flex coverlay
+    Coverlay,
+    /// Copper foil
+    Copper,
+    Core,
+    Prepreg,
+    SurfaceFinish(SurfaceFinish),
+    /// A type we don't know. See [`Material::type_id`].
+    #[default]
+    Unknown,
+}
+
+impl MaterialKind {
+    fn from_type_id(id: &str) -> Self {
+        const KINDS: [(&str, MaterialKind); 10] = [
+            (
+                "968469a9-c799-46e2-bc61-c05b2553ab48",
+                MaterialKind::SolderMask,
+            ),
+            (
+                "cd632416-6fe1-4ea1-bb89-01d4b2eae217",
+                MaterialKind::Coverlay,
+            ),
+            ("4be0915d-5b0d-4c59-8fae-d57f8650d474", MaterialKind::Copper),
+            ("27d70fdc-4c4e-4774-bfac-7efbb48cde47", MaterialKind::Core),
+            (
+                "e04a4e7f-10f0-42df-add7-587710efd89e",
+                MaterialKind::Prepreg,
+            ),
+            (
+                "d782f951-a176-457d-bef0-463bd4d45ad7",
+                MaterialKind::SurfaceFinish(SurfaceFinish::Osp),
+            ),
+            (
+                "4dcb0c85-3a3d-4462-9e84-a89dc57f4b84",
+                MaterialKind::SurfaceFinish(SurfaceFinish::ImmersionTin),
+            ),
+            (
+                "0800b1d6-17ee-40e9-adba-334c59a1066e",
+                MaterialKind::SurfaceFinish(SurfaceFinish::ImmersionGold),
+            ),
+            (
+                "e8b99bb8-b51f-4a6e-a0fc-7439b27f8c76",
+                MaterialKind::SurfaceFinish(SurfaceFinish::Hasl),
+            ),
+            (
+                "b6b5d288-d4b3-4b60-857f-b949da02a37a",
+                MaterialKind::SurfaceFinish(SurfaceFinish::Enig),
+            ),
+        ];
+
+        KINDS
+            .into_iter()
+            .find(|(type_id, _)| type_id.eq_ignore_ascii_case(id))
+            .map_or(Self::Unknown, |(_, kind)| kind)
+    }
+}
+
+/// Surface finish processes
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurfaceFinish {
+    /// Organic solderability preservative
+    Osp,
+    ImmersionTin,
+    ImmersionGold,
+    /// Hot air solder leveling
+    Hasl,
+    /// Electroless nickel immersion gold
+    Enig,
+}
+
+/// A named property of a material
+#[derive(Clone, Debug, PartialEq)]
+pub struct Property {
+    pub name: Box<str>,
+    pub value: PropertyValue,
+}
+
+impl Property {
+    fn new(attrs: &XmlAttrs, text: &str) -> Self {
+        let ty = attrs.get("Type");
+        let text = text.trim();
+        let value = if ty == "DimValue" {
+            PropertyValue::Dim(DimValue::parse(
+                text,
+                Dimension::from_name(attrs.get("Dimension")),
+            ))
+        } else if ty == "String" || ty == "System.String" {
+            PropertyValue::String(text.into())
+        } else if ty.starts_with("System.Windows.Media.Color") {
+            parse_hex_color(text).map_or_else(
+                || PropertyValue::Other {
+                    ty: ty.into(),
+                    text: text.into(),
+                },
+                |(rgb, alpha)| PropertyValue::Color(rgb, alpha),
+            )
+        } else {
+            PropertyValue::Other {
+                ty: ty.into(),
+                text: text.into(),
+            }
+        };
+
+        Self {
+            name: attrs.get("Name").into(),
+            value,
+        }
+    }
+}
+
+/// The value of a property
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    /// A quantity with units
+    Dim(DimValue),
+    String(Box<str>),
+    /// A color with its alpha
+    Color(Rgb, u8),
+    /// A type we don't interpret, with its text as written
+    Other {
+        ty: Box<str>,
+        text: Box<str>,
+    },
+}
+
+/// A number with units, e.g. `0.05mm` or `1GHz`
+#[derive(Clone, Debug, PartialEq)]
+pub struct DimValue {
+    value: f64,
+    unit: Box<str>,
+    dimension: Dimension,
+}
+
+impl DimValue {
+    /// Split text into its number and unit. Text that isn't a number has a
+    /// value of 0.
+    fn parse(text: &str, dimension: Dimension) -> Self {
+        let split = number_len(text);
+        Self {
+            value: text[..split].parse().unwrap_or_default(),
+            unit: text[split..].trim().into(),
+            dimension,
+        }
+    }
+
+    /// The number as written, in [`DimValue::unit`]
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Unit as written, e.g. `mm`. Empty for dimensionless values.
+    pub fn unit(&self) -> &str {
+        &self.unit
+    }
+
+    /// What this value measures
+    pub fn dimension(&self) -> &Dimension {
+        &self.dimension
+    }
+
+    /// The value in SI base units: meters, hertz, kelvin, kilograms, ohm
+    /// meters and volts per meter. Relative values are a fraction rather than
+    /// a percentage. `None` if the unit isn't known.
+    pub fn si_value(&self) -> Option<f64> {
+        let unit = self.unit.as_ref();
+        let factor = match (&self.dimension, unit) {
+            (Dimension::Dimensionless | Dimension::Relative, "")
+            | (Dimension::Temperature, "K") => 1.0,
+            (Dimension::Relative, "%") => 0.01,
+            (Dimension::Length, _) => length_factor(unit)?,
+            (Dimension::Frequency, _) => match unit {
+                "Hz" => 1.0,
+                "kHz" => 1e3,
+                "MHz" => 1e6,
+                "GHz" => 1e9,
+                _ => return None,
+            },
+            (Dimension::Temperature, "C" | "°C") => return Some(self.value + 273.15),
+            (Dimension::Temperature, "F" | "°F") => {
+                return Some((self.value - 32.0) * 5.0 / 9.0 + 273.15)
+            }
+            (Dimension::Mass, _) => match unit {
+                "kg" => 1.0,
+                "g" => 1e-3,
+                "mg" => 1e-6,
+                "oz" => 0.028_349_523_125,
+                _ => return None,
+            },
+            (Dimension::ElectricalResistivity, _) => {
+                let (ohm, len) = unit.split_once('*')?;
+                if !ohm.eq_ignore_ascii_case("ohm") {
+                    return None;
+                }
+                length_factor(len)?
+            }
+            (Dimension::DielectricStrength, _) => {
+                let (volts, len) = unit.split_once('/')?;
+                let volts = match volts {
+                    "V" => 1.0,
+                    "kV" => 1e3,
+                    "MV" => 1e6,
+                    _ => return None,
+                };
+                volts / length_factor(len)?
+            }
+            _ => return None,
+        };
+
+        Some(self.value * factor)
+    }
+
+    /// A length in nm, as used for other lengths in this crate
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn to_nm(&self) -> Option<i32> {
+        if self.dimension != Dimension::Length {
+            return None;
+        }
+        Some((self.si_value()? * 1e9).round() as i32)
+    }
+}
+
+/// What a [`DimValue`] measures
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Dimension {
+    Length,
+    Frequency,
+    /// A ratio, usually a percentage
+    Relative,
+    Dimensionless,
+    Temperature,
+    Mass,
+    DielectricStrength,
+    ElectricalResistivity,
+    /// A dimension we don't know, as written
+    Other(Box<str>),
+}
+
+impl Dimension {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "Length" => Self::Length,
+            "Frequency" => Self::Frequency,
+            "Relative" => Self::Relative,
+            "Dimensionless" => Self::Dimensionless,
+            "Temperature" => Self::Temperature,
+            "Mass" => Self::Mass,
+            "DielectricStrength" => Self::DielectricStrength,
+            "ElectricalResistivity" => Self::ElectricalResistivity,
+            _ => Self::Other(name.into()),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Netlists and writers for common netlist formats
+//!
+//! A [`Netlist`] is a flat list of components and the nets connecting their
+//! pins. It can be extracted from a schematic with [`SchDoc::netlist`] or built
+//! by hand, then written with any [`NetlistWriter`].
+//!
+//! [`SchDoc::netlist`]: crate::SchDoc::netlist
+
+mod edif;
+mod kicad;
+mod protel;
+mod spice;
+
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+pub use edif::Edif;
+pub use kicad::KicadNetlist;
+pub use protel::Protel2;
+pub use spice::Spice;
+
+use crate::sch::ElectricalType;
+
+/// A format that netlists can be written in
+///
+/// ```no_run
+/// use altium::netlist::{NetlistWriter, Protel2};
+/// use altium::SchDoc;
+///
+/// let schdoc = SchDoc::open("example.SchDoc").unwrap();
+/// Protel2.save(&schdoc.netlist(), "example.NET").unwrap();
+/// ```
+pub trait NetlistWriter {
+    /// The usual file extension for this format, without the dot
+    fn extension(&self) -> &'static str;
+
+    /// Write a netlist to a writer
+    fn write(&self, netlist: &Netlist, writer: &mut dyn io::Write) -> io::Result<()>;
+
+    /// Write a netlist to a string
+    fn write_string(&self, netlist: &Netlist) -> String {
+        let mut buf = Vec::new();
+        self.write(netlist, &mut buf)
+            .expect("writing to a `Vec` can't fail");
+        String::from_utf8(buf).expect("netlist writers only write UTF-8")
+    }
+
+    /// Write a netlist to a file, creating it if needed
+    fn save<P: AsRef<Path>>(&self, netlist: &Netlist, path: P) -> io::Result<()>
+    where
+        Self: Sized,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(netlist, &mut writer)?;
+        writer.flush()
+    }
+}
+
+/// Components and the nets that connect them
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Netlist {
+    title: Box<str>,
+    components: Vec<NetComponent>,
+    nets: Vec<Net>,
+}
+
+impl Netlist {
+    /// Create an empty netlist. The title is used as the design name in
+    /// formats that have one.
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn components(&self) -> &[NetComponent] {
+        &self.components
+    }
+
+    pub fn nets(&self) -> &[Net] {
+        &self.nets
+    }
+
+    pub fn add_component(&mut self, component: NetComponent) {
+        self.components.push(component);
+    }
+
+    pub fn add_net(&mut self, net: Net) {
+        self.nets.push(net);
+    }
+
+    /// Find a component by its designator
+    pub fn component(&self, designator: &str) -> Option<&NetComponent> {
+        self.components
+            .iter()
+            .find(|comp| &*comp.designator == designator)
+    }
+
+    /// Find a net by name
+    pub fn net(&self, name: &str) -> Option<&Net> {
+        self.nets.iter().find(|net| &*net.name == name)
+    }
+
+    /// The net that a component's pin is connected to, if any
+    pub fn net_of(&self, designator: &str, pin: &str) -> Option<&Net> {
+        self.nets.iter().find(|net| {
+            net.nodes
+                .iter()
+                .any(|node| &*node.designator == designator && &*node.pin == pin)
+        })
+    }
+
+    /// Pin information for a node, if the component and pin exist
+    pub(crate) fn node_pin(&self, node: &Node) -> Option<&NetPin> {
+        self.component(&node.designator)?
+            .pins
+            .iter()
+            .find(|pin| pin.designator == node.pin)
+    }
+}
+
+/// A placed component
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetComponent {
+    /// Reference designator, e.g. `R1`
+    pub designator: Box<str>,
+    /// Name of the library component
+    pub libref: Box<str>,
+    /// The component's value (Altium's comment)
+    pub value: Box<str>,
+    pub description: Box<str>,
+    /// Name of the current footprint model
+    pub footprint: Option<Box<str>>,
+    /// Name of the current simulation model
+    pub sim_model: Option<Box<str>>,
+    pub pins: Vec<NetPin>,
+}
+
+/// A pin of a component
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetPin {
+    /// Pin number, e.g. `1` or `A3`
+    pub designator: Box<str>,
+    pub name: Box<str>,
+    pub electrical: ElectricalType,
+}
+
+/// A set of connected pins
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Net {
+    pub name: Box<str>,
+    pub nodes: Vec<Node>,
+}
+
+/// A single pin on a net
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Node {
+    /// Designator of the component
+    pub designator: Box<str>,
+    /// Designator of the pin
+    pub pin: Box<str>,
+}
+
+/// Compare designators so that numbers sort by value, e.g. `R2` before `R10`
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_rest = a;
+    let mut b_rest = b;
+
+    loop {
+        let (Some(ac), Some(bc)) = (a_rest.chars().next(), b_rest.chars().next()) else {
+            return a_rest.len().cmp(&b_rest.len());
+        };
+
+        if ac.is_ascii_digit() && bc.is_ascii_digit() {
+            let a_len = a_rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(a_rest.len());
+            let b_len = b_rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(b_rest.len());
+            let a_num = a_rest[..a_len].trim_start_matches('0');
+            let b_num = b_rest[..b_len].trim_start_matches('0');
+            let ord = a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num));
+            if ord.is_ne() {
+                return ord;
+            }
+            a_rest = &a_rest[a_len..];
+            b_rest = &b_rest[b_len..];
+        } else {
+            let ord = ac.cmp(&bc);
+            if ord.is_ne() {
+                return ord;
+            }
+            a_rest = &a_rest[ac.len_utf8()..];
+            b_rest = &b_rest[bc.len_utf8()..];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = ["R10", "R2", "C1", "R1", "U1A", "U1", "R02"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["C1", "R1", "R2", "R02", "R10", "U1", "U1A"]);
+    }
+}
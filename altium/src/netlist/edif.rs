@@ -0,0 +1,213 @@
+//! EDIF 2.0.0 netlists
+
+use std::collections::BTreeMap;
+use std::io;
+
+use super::{natural_cmp, NetComponent, NetPin, Netlist, NetlistWriter};
+use crate::kicad::{SExpr, GENERATOR};
+
+/// Library holding one cell per library component
+const COMPONENT_LIB: &str = "COMPONENT_LIB";
+/// Library holding the top level design
+const SHEET_LIB: &str = "SHEET_LIB";
+const VIEW: &str = "netListView";
+
+/// EDIF 2.0.0 netlist (`.EDF`) writer
+///
+/// Every library component becomes a cell with one port per pin, and every
+/// placed component an instance of that cell. Names that aren't valid EDIF
+/// identifiers are written with `rename`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Edif;
+
+impl NetlistWriter for Edif {
+    fn extension(&self) -> &'static str {
+        "EDF"
+    }
+
+    fn write(&self, netlist: &Netlist, w: &mut dyn io::Write) -> io::Result<()> {
+        let design = if netlist.title().is_empty() {
+            "design"
+        } else {
+            netlist.title()
+        };
+
+        // Components with the same libref share a cell, which needs the pins
+        // of all of them
+        let mut cells: BTreeMap<&str, Vec<&NetPin>> = BTreeMap::new();
+        for comp in netlist.components() {
+            let pins = cells.entry(&comp.libref).or_default();
+            for pin in &comp.pins {
+                if !pins.iter().any(|p| p.designator == pin.designator) {
+                    pins.push(pin);
+                }
+            }
+        }
+
+        let mut component_lib = library(COMPONENT_LIB);
+        for (libref, pins) in &mut cells {
+            pins.sort_by(|a, b| natural_cmp(&a.designator, &b.designator));
+            let ports = pins.iter().map(|pin| {
+                SExpr::list(
+                    "port",
+                    [
+                        ident("P", &pin.designator),
+                        SExpr::pair("direction", "INOUT"),
+                    ],
+                )
+            });
+            component_lib.push(cell(libref, SExpr::list("interface", ports), None));
+        }
+
+        let instances = netlist.components().iter().map(instance);
+
+        let nets = netlist.nets().iter().map(|net| {
+            let refs = net.nodes.iter().map(|node| {
+                SExpr::list(
+                    "portRef",
+                    [
+                        ident_ref("P", &node.pin),
+                        SExpr::list("instanceRef", [ident_ref("I", &node.designator)]),
+                    ],
+                )
+            });
+            SExpr::list("net", [ident("N", &net.name), SExpr::list("joined", refs)])
+        });
+
+        let contents = SExpr::list("contents", instances.chain(nets));
+        let mut sheet_lib = library(SHEET_LIB);
+        sheet_lib.push(cell(design, SExpr::list("interface", []), Some(contents)));
+
+        let edif = SExpr::list(
+            "edif",
+            [
+                ident("D", design),
+                SExpr::list("edifVersion", ["2", "0", "0"].map(SExpr::atom)),
+                SExpr::pair("edifLevel", 0),
+                SExpr::list("keywordMap", [SExpr::pair("keywordLevel", 0)]),
+                SExpr::list(
+                    "status",
+                    [SExpr::list(
+                        "written",
+                        [SExpr::list("program", [SExpr::string(GENERATOR)])],
+                    )],
+                ),
+                component_lib,
+                sheet_lib,
+                SExpr::list(
+                    "design",
+                    [
+                        ident("D", design),
+                        SExpr::list(
+                            "cellRef",
+                            [ident_ref("C", design), SExpr::pair("libraryRef", SHEET_LIB)],
+                        ),
+                    ],
+                ),
+            ],
+        );
+
+        writeln!(w, "{edif}")
+    }
+}
+
+/// An instance of a component's library cell
+fn instance(comp: &NetComponent) -> SExpr {
+    let mut ret = SExpr::list(
+        "instance",
+        [
+            ident("I", &comp.designator),
+            SExpr::list(
+                "viewRef",
+                [
+                    SExpr::atom(VIEW),
+                    SExpr::list(
+                        "cellRef",
+                        [
+                            ident_ref("C", &comp.libref),
+                            SExpr::pair("libraryRef", COMPONENT_LIB),
+                        ],
+                    ),
+                ],
+            ),
+        ],
+    );
+    for (name, val) in [
+        ("Designator", Some(&comp.designator)),
+        ("Comment", Some(&comp.value)),
+        ("Footprint", comp.footprint.as_ref()),
+    ] {
+        if let Some(val) = val.filter(|v| !v.is_empty()) {
+            ret.push(property(name, val));
+        }
+    }
+    ret
+}
+
+fn library(name: &str) -> SExpr {
+    SExpr::list(
+        "library",
+        [
+            SExpr::atom(name),
+            SExpr::pair("edifLevel", 0),
+            SExpr::list("technology", [SExpr::list("numberDefinition", [])]),
+        ],
+    )
+}
+
+fn cell(name: &str, interface: SExpr, contents: Option<SExpr>) -> SExpr {
+    let mut view = SExpr::list(
+        "view",
+        [
+            SExpr::atom(VIEW),
+            SExpr::pair("viewType", "NETLIST"),
+            interface,
+        ],
+    );
+    if let Some(contents) = contents {
+        view.push(contents);
+    }
+
+    SExpr::list(
+        "cell",
+        [ident("C", name), SExpr::pair("cellType", "GENERIC"), view],
+    )
+}
+
+fn property(name: &str, val: &str) -> SExpr {
+    SExpr::list(
+        "property",
+        [
+            SExpr::atom(to_ident("", name)),
+            SExpr::list("string", [SExpr::string(val)]),
+        ],
+    )
+}
+
+/// A name definition, using `rename` if `name` isn't a valid identifier
+fn ident(prefix: &str, name: &str) -> SExpr {
+    let id = to_ident(prefix, name);
+    if *id == *name {
+        SExpr::atom(id)
+    } else {
+        SExpr::list("rename", [SExpr::atom(id), SExpr::string(name)])
+    }
+}
+
+/// A reference to a name defined with [`ident`]
+fn ident_ref(prefix: &str, name: &str) -> SExpr {
+    SExpr::atom(to_ident(prefix, name))
+}
+
+/// EDIF identifiers start with a letter and contain only letters, digits and
+/// underscores. Anything else gets replaced, and `prefix` is added if needed.
+fn to_ident(prefix: &str, name: &str) -> String {
+    let mut ret: String = name
+        .chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+        .collect();
+    if !ret.starts_with(|ch: char| ch.is_ascii_alphabetic()) {
+        ret.insert_str(0, if prefix.is_empty() { "X" } else { prefix });
+    }
+    ret
+}
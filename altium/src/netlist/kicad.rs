@@ -0,0 +1,103 @@
+//! KiCad's S-expression netlist format
+
+use std::io;
+
+use super::{Netlist, NetlistWriter};
+use crate::kicad::{SExpr, GENERATOR};
+use crate::sch::record::kicad_pin_type;
+
+/// KiCad netlist (`.net`) writer
+///
+/// This is the format that KiCad's schematic editor exports and its PCB
+/// editor imports (version `E`). Footprints are written without a library
+/// prefix, so they need to be assigned a library before use.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KicadNetlist;
+
+impl NetlistWriter for KicadNetlist {
+    fn extension(&self) -> &'static str {
+        "net"
+    }
+
+    fn write(&self, netlist: &Netlist, w: &mut dyn io::Write) -> io::Result<()> {
+        let design = SExpr::list(
+            "design",
+            [
+                SExpr::list("source", [SExpr::string(netlist.title())]),
+                SExpr::list("tool", [SExpr::string(GENERATOR)]),
+            ],
+        );
+
+        let components = netlist.components().iter().map(|comp| {
+            let mut ret = SExpr::list(
+                "comp",
+                [
+                    SExpr::list("ref", [SExpr::string(&comp.designator)]),
+                    SExpr::list("value", [SExpr::string(&comp.value)]),
+                ],
+            );
+            if let Some(footprint) = &comp.footprint {
+                ret.push(SExpr::list("footprint", [SExpr::string(footprint)]));
+            }
+            if !comp.description.is_empty() {
+                ret.push(SExpr::list(
+                    "description",
+                    [SExpr::string(&comp.description)],
+                ));
+            }
+            ret.push(SExpr::list(
+                "libsource",
+                [
+                    SExpr::list("lib", [SExpr::string("")]),
+                    SExpr::list("part", [SExpr::string(&comp.libref)]),
+                ],
+            ));
+            ret
+        });
+
+        let nets = netlist.nets().iter().enumerate().map(|(idx, net)| {
+            let nodes = net.nodes.iter().map(|node| {
+                let mut ret = SExpr::list(
+                    "node",
+                    [
+                        SExpr::list("ref", [SExpr::string(&node.designator)]),
+                        SExpr::list("pin", [SExpr::string(&node.pin)]),
+                    ],
+                );
+                if let Some(pin) = netlist.node_pin(node) {
+                    if !pin.name.is_empty() {
+                        ret.push(SExpr::list("pinfunction", [SExpr::string(&pin.name)]));
+                    }
+                    ret.push(SExpr::list(
+                        "pintype",
+                        [SExpr::string(kicad_pin_type(pin.electrical))],
+                    ));
+                }
+                ret
+            });
+
+            // Codes start at 1
+            SExpr::list(
+                "net",
+                [
+                    SExpr::list("code", [SExpr::string(&(idx + 1).to_string())]),
+                    SExpr::list("name", [SExpr::string(&net.name)]),
+                ]
+                .into_iter()
+                .chain(nodes),
+            )
+        });
+
+        let export = SExpr::list(
+            "export",
+            [
+                SExpr::list("version", [SExpr::string("E")]),
+                design,
+                SExpr::list("components", components),
+                SExpr::list("nets", nets),
+            ],
+        );
+
+        writeln!(w, "{export}")
+    }
+}
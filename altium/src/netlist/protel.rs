@@ -0,0 +1,84 @@
+//! The Protel 2.0 netlist format, Altium's native netlist
+
+use std::io;
+
+use super::{Netlist, NetlistWriter};
+use crate::sch::ElectricalType;
+
+/// Number of user part fields in each component block
+const PART_FIELDS: usize = 16;
+/// Number of library fields in each component block
+const LIBRARY_FIELDS: usize = 8;
+
+/// Protel 2.0 netlist (`.NET`) writer
+///
+/// Each component gets a `[DSC ... ]` block and each net a `( ... )` block
+/// listing its pins with their names and electrical types.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Protel2;
+
+impl NetlistWriter for Protel2 {
+    fn extension(&self) -> &'static str {
+        "NET"
+    }
+
+    fn write(&self, netlist: &Netlist, w: &mut dyn io::Write) -> io::Result<()> {
+        writeln!(w, "PROTEL NETLIST 2.0")?;
+
+        for comp in netlist.components() {
+            writeln!(w, "[DSC")?;
+            writeln!(w, "DESIGNATOR\n{}", field(&comp.designator))?;
+            writeln!(
+                w,
+                "FOOTPRINT\n{}",
+                field(comp.footprint.as_deref().unwrap_or_default())
+            )?;
+            writeln!(w, "PARTTYPE\n{}", field(&comp.value))?;
+            writeln!(w, "DESCRIPTION\n{}", field(&comp.description))?;
+            for idx in 1..=PART_FIELDS {
+                writeln!(w, "Part Field {idx}\n*")?;
+            }
+            for idx in 1..=LIBRARY_FIELDS {
+                writeln!(w, "LIBRARYFIELD{idx}\n*")?;
+            }
+            writeln!(w, "]")?;
+        }
+
+        for net in netlist.nets() {
+            writeln!(w, "(")?;
+            writeln!(w, "NETNAME\n{}", field(&net.name))?;
+            for node in &net.nodes {
+                let (name, ty) = netlist.node_pin(node).map_or(("", "PASSIVE"), |pin| {
+                    (&*pin.name, pin_type(pin.electrical))
+                });
+                writeln!(w, "{}-{} {} {ty}", node.designator, node.pin, field(name))?;
+            }
+            writeln!(w, ")")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Values are one per line, `*` means empty
+fn field(val: &str) -> &str {
+    let val = val.lines().next().unwrap_or_default();
+    if val.is_empty() {
+        "*"
+    } else {
+        val
+    }
+}
+
+fn pin_type(ty: ElectricalType) -> &'static str {
+    match ty {
+        ElectricalType::Input => "INPUT",
+        ElectricalType::Id => "BIDIRECTIONAL",
+        ElectricalType::Output => "OUTPUT",
+        ElectricalType::OpenCollector => "OPENCOLLECTOR",
+        ElectricalType::Passive => "PASSIVE",
+        ElectricalType::HighZ => "HIZ",
+        ElectricalType::OpenEmitter => "OPENEMITTER",
+        ElectricalType::Power => "POWER",
+    }
+}
@@ -0,0 +1,110 @@
+//! SPICE decks for simulation
+
+use std::io;
+
+use super::{natural_cmp, NetComponent, Netlist, NetlistWriter};
+
+/// Primitives where the component value follows the nodes, e.g. `R1 1 2 10k`
+/// or `V1 1 0 5`
+const VALUE_PRIMITIVES: &[char] = &['C', 'I', 'L', 'R', 'V'];
+/// Primitives where a `.model` name follows the nodes, e.g. `D1 1 2 1N4148`
+const MODEL_PRIMITIVES: &[char] = &['D', 'J', 'M', 'Q'];
+
+/// SPICE netlist (`.cir`) writer
+///
+/// Only components with a simulation model (an implementation with model type
+/// `SIM`) are included; others are listed in a comment. Pins are connected in
+/// order of their designators, and nets named `GND` or `GROUND` become node
+/// `0`.
+///
+/// Passives and independent sources are written with their value, and diodes
+/// and transistors with their model name. Every other element is written as a
+/// subcircuit instance (`X<designator> <nodes> <model>`), including controlled
+/// and behavioral sources since their gains or expressions aren't known. The
+/// matching `.subckt` and `.model` definitions are not included.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Spice;
+
+impl NetlistWriter for Spice {
+    fn extension(&self) -> &'static str {
+        "cir"
+    }
+
+    fn write(&self, netlist: &Netlist, w: &mut dyn io::Write) -> io::Result<()> {
+        // The first line is always the title
+        writeln!(w, "* {}", netlist.title())?;
+
+        for comp in netlist.components() {
+            let Some(model) = &comp.sim_model else {
+                writeln!(w, "* {} has no simulation model", comp.designator)?;
+                continue;
+            };
+
+            let mut line = element_name(&comp.designator);
+            for node in nodes(netlist, comp) {
+                line.push(' ');
+                line.push_str(&node);
+            }
+            line.push(' ');
+
+            let letter = line.chars().next().unwrap_or_default();
+            if VALUE_PRIMITIVES.contains(&letter) && !comp.value.is_empty() {
+                line.push_str(&node_name(&comp.value));
+            } else {
+                line.push_str(&node_name(model));
+            }
+
+            writeln!(w, "{line}")?;
+        }
+
+        writeln!(w, ".end")
+    }
+}
+
+/// Element names need to start with the letter for their type. Designators
+/// usually do, but anything that isn't a supported primitive becomes a
+/// subcircuit.
+fn element_name(designator: &str) -> String {
+    let designator = node_name(designator);
+    let first = designator
+        .chars()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+
+    if VALUE_PRIMITIVES.contains(&first) || MODEL_PRIMITIVES.contains(&first) {
+        designator
+    } else {
+        format!("X{designator}")
+    }
+}
+
+/// The node names that a component's pins connect to, in pin order.
+/// Unconnected pins get their own node.
+fn nodes(netlist: &Netlist, comp: &NetComponent) -> Vec<String> {
+    let mut pins: Vec<_> = comp.pins.iter().collect();
+    pins.sort_by(|a, b| natural_cmp(&a.designator, &b.designator));
+
+    pins.iter()
+        .map(
+            |pin| match netlist.net_of(&comp.designator, &pin.designator) {
+                Some(net) if is_ground(&net.name) => "0".to_owned(),
+                Some(net) => node_name(&net.name),
+                None => node_name(&format!("NC_{}_{}", comp.designator, pin.designator)),
+            },
+        )
+        .collect()
+}
+
+fn is_ground(name: &str) -> bool {
+    ["0", "GND", "GROUND"]
+        .iter()
+        .any(|gnd| name.eq_ignore_ascii_case(gnd))
+}
+
+/// Whitespace separates fields so it can't be in names
+fn node_name(name: &str) -> String {
+    name.chars()
+        .map(|ch| if ch.is_whitespace() { '_' } else { ch })
+        .collect()
+}
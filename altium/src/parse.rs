@@ -2,7 +2,9 @@
 mod bin;
 mod from_record;
 mod utf8;
+mod xml;
 
-pub use bin::{extract_sized_buf, extract_sized_utf8_buf, BufLenMatch};
+pub use bin::{extract_sized_buf, extract_sized_utf8_buf, split_chunk, BufLenMatch};
 pub use from_record::FromRecord;
 pub use utf8::{FromUtf8, ParseUtf8};
+pub use xml::{decode_xml_text, parse_hex_color, resolve_xml_ref, XmlAttrs};
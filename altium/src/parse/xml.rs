@@ -0,0 +1,67 @@
+//! Helpers for reading XML with `quick_xml`
+
+use std::borrow::Cow;
+
+use quick_xml::events::{BytesRef, BytesStart, BytesText};
+
+use crate::common::Rgb;
+use crate::ErrorKind;
+
+/// Attributes of an element, unescaped and keyed by local name
+#[derive(Clone, Debug, Default)]
+pub struct XmlAttrs(Vec<(Box<str>, Box<str>)>);
+
+impl XmlAttrs {
+    pub fn read(e: &BytesStart) -> Result<Self, ErrorKind> {
+        let mut ret = Vec::new();
+        for attr in e.attributes() {
+            let attr = attr.map_err(quick_xml::Error::from)?;
+            let key = String::from_utf8_lossy(attr.key.local_name().as_ref()).into();
+            let value = attr.unescape_value()?;
+            ret.push((key, value.into()));
+        }
+        Ok(Self(ret))
+    }
+
+    /// Get an attribute or an empty string
+    pub fn get(&self, key: &str) -> &str {
+        self.try_get(key).unwrap_or_default()
+    }
+
+    /// Get an attribute if it exists, ignoring case
+    pub fn try_get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| &**v)
+    }
+}
+
+/// Decode a text event. Entities come as separate events, see
+/// [`resolve_xml_ref`].
+pub fn decode_xml_text<'a>(text: &'a BytesText) -> Result<Cow<'a, str>, ErrorKind> {
+    Ok(text.decode().map_err(quick_xml::Error::from)?)
+}
+
+/// Resolve an entity or character reference in text
+pub fn resolve_xml_ref(r: &BytesRef) -> Result<Cow<'static, str>, ErrorKind> {
+    if let Some(ch) = r.resolve_char_ref()? {
+        return Ok(ch.to_string().into());
+    }
+    let name = r.decode().map_err(quick_xml::Error::from)?;
+    quick_xml::escape::resolve_predefined_entity(&name)
+        .map(Cow::Borrowed)
+        .ok_or_else(|| ErrorKind::Xml(format!("unknown entity `&{name};`").into()))
+}
+
+/// Parse `#RRGGBB` or `#AARRGGBB` into a color and its alpha
+pub fn parse_hex_color(text: &str) -> Option<(Rgb, u8)> {
+    let hex = text.strip_prefix('#')?;
+    let val = u32::from_str_radix(hex, 16).ok()?;
+    let [alpha, r, g, b] = match hex.len() {
+        6 => (val | 0xff00_0000).to_be_bytes(),
+        8 => val.to_be_bytes(),
+        _ => return None,
+    };
+    Some((Rgb { r, g, b }, alpha))
+}
@@ -1,8 +1,33 @@
 //! Everything related to PCB documents (`.PcbDoc`) and schematic
 //! libraries (`.PcbLib`)
 
+mod component;
+mod draw;
+pub mod drc;
+pub mod fab;
+mod footprint;
+mod layer;
+mod model;
 mod pcbdoc;
 mod pcblib;
+mod polygon;
+pub mod rule;
+mod stackup;
+mod stats;
 
+pub mod record;
+
+pub use component::Component;
+pub use draw::PcbDrawCtx;
+pub use footprint::Footprint;
+#[doc(inline)]
+pub use layer::{BoardLayers, Layer, LayerInfo, MechanicalKind};
+pub use model::{EmbeddedModel, EmbeddedModels};
 pub use pcbdoc::PcbDoc;
-pub use pcblib::PcbLib;
+pub use pcblib::{FootprintMeta, FootprintsIter, PcbLib};
+pub use polygon::{HatchStyle, Polygon, PolygonKind, PourOver};
+#[doc(inline)]
+pub use record::PcbRecord;
+pub use rule::{DesignRules, Rule, RuleKind};
+pub use stackup::{StackLayer, StackLayerKind};
+pub use stats::{BoardStatistics, CopperArea, DrillCount};
@@ -0,0 +1,116 @@
+//! Components placed on a board
+
+use super::record::Properties;
+use super::Layer;
+use crate::common::{Location, UniqueId};
+use crate::error::AddContext;
+use crate::parse::FromUtf8;
+use crate::Error;
+
+/// A footprint placed on a board, as stored in `Components6`
+///
+/// A primitive belongs to a component if its `component` field is this
+/// component's index in [`PcbDoc::components`].
+///
+/// [`PcbDoc::components`]: super::PcbDoc::components
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Component {
+    designator: Box<str>,
+    footprint: Box<str>,
+    description: Box<str>,
+    location: Location,
+    rotation: f64,
+    layer: Layer,
+    height: i32,
+    locked: bool,
+    unique_id: Option<UniqueId>,
+}
+
+impl Component {
+    pub(crate) fn from_props(props: &Properties) -> Result<Self, Error> {
+        let layer = props
+            .get("LAYER")
+            .and_then(Layer::from_ident)
+            .unwrap_or(Layer::Top);
+        let unique_id = props
+            .get("UNIQUEID")
+            .and_then(|id| UniqueId::from_utf8(id.as_bytes()).ok());
+
+        let ret = Self {
+            designator: props.get_str("SOURCEDESIGNATOR"),
+            footprint: props.get_str("PATTERN"),
+            description: props.get_str("FOOTPRINTDESCRIPTION"),
+            location: Location::new(props.get_len("X")?, props.get_len("Y")?),
+            rotation: props.get_f64("ROTATION")?,
+            layer,
+            height: props.get_len("HEIGHT")?,
+            locked: props.get_bool("LOCKED"),
+            unique_id,
+        };
+        Ok(ret)
+    }
+
+    /// Set the designator if it wasn't stored with the component
+    pub(crate) fn set_designator_fallback(&mut self, designator: &str) {
+        if self.designator.is_empty() {
+            self.designator = designator.into();
+        }
+    }
+
+    /// The reference designator, e.g. `R1`. This is empty if the design has
+    /// not been annotated.
+    pub fn designator(&self) -> &str {
+        &self.designator
+    }
+
+    /// Name of the footprint in its library
+    pub fn footprint(&self) -> &str {
+        &self.footprint
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The footprint's reference point
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    /// Rotation in degrees counterclockwise
+    pub fn rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    /// `Top` or `Bottom`
+    pub fn layer(&self) -> Layer {
+        self.layer
+    }
+
+    pub fn is_bottom(&self) -> bool {
+        self.layer == Layer::Bottom
+    }
+
+    /// Component height in nm
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn unique_id(&self) -> Option<UniqueId> {
+        self.unique_id
+    }
+}
+
+/// Parse every component in a `Components6` stream
+pub(crate) fn parse_components(list: &[Properties]) -> Result<Vec<Component>, Error> {
+    list.iter()
+        .enumerate()
+        .map(|(idx, props)| {
+            Component::from_props(props).or_context(|| format!("parsing component {idx}"))
+        })
+        .collect()
+}
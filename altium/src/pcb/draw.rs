@@ -0,0 +1,658 @@
+//! Drawing PCB primitives, footprints and boards
+//!
+//! Unlike schematic records, PCB primitives are drawn one layer at a time so
+//! that layers stack in a consistent order. Pads and vias exist on several
+//! layers and draw the shape that belongs to each one.
+
+use std::collections::BTreeMap;
+use std::f64::consts::FRAC_PI_2;
+
+use super::fab::{expand, fill_corners, pad_size, rotate};
+use super::layer::MECHANICAL_LAYER_COUNT;
+use super::record::{
+    Arc,
+    Fill,
+    HoleShape,
+    Pad,
+    PadShape,
+    PadSize,
+    PcbRecord,
+    Region,
+    RegionKind,
+    Text,
+    TextKind,
+    Track,
+    Via,
+};
+use super::{Footprint, Layer};
+use crate::common::{Location, PosHoriz, PosVert, Rgb, Rotation90};
+use crate::draw::{
+    Canvas,
+    Draw,
+    DrawArc,
+    DrawGroup,
+    DrawLine,
+    DrawPolygon,
+    DrawText,
+    LineCap,
+    RenderOptions,
+    Theme,
+};
+use crate::font::Font;
+
+/// Line segments used for each quarter circle of a rounded shape
+const ARC_SEGMENTS: u32 = 8;
+/// nm per point, for sizing text
+const NM_PER_PT: u32 = 352_778;
+/// Board outlines aren't on a layer, so they get a fixed color
+const BOARD_LINE: Rgb = Rgb::from_hex(0x80, 0x80, 0x80);
+
+/// Which layers to draw, in what order and with which colors
+///
+/// Layers are drawn in the order of `draw_order`, so later layers cover
+/// earlier ones. Layers that aren't listed are never drawn.
+#[derive(Clone, Debug)]
+pub struct PcbDrawCtx<'a> {
+    /// Theme and color remapping. Only [`Theme::Monochrome`] changes the
+    /// layer colors, since they are already meant for a dark background.
+    pub options: &'a RenderOptions,
+    /// Layers to draw, bottom-most first
+    pub draw_order: Vec<Layer>,
+    /// Layers in `draw_order` that are currently hidden
+    pub hidden: Vec<Layer>,
+    /// Colors that replace the defaults for a layer. [`Layer::Background`]
+    /// sets the background.
+    pub colors: BTreeMap<Layer, Rgb>,
+    /// Net names, used to annotate drawn groups. Primitives' `net` fields
+    /// index into this.
+    pub nets: Vec<Box<str>>,
+    /// Solder mask expansion in nm, for pads and vias that don't set their own
+    pub solder_mask_expansion: i32,
+    /// Paste mask expansion in nm, for pads that don't set their own
+    pub paste_mask_expansion: i32,
+}
+
+impl<'a> PcbDrawCtx<'a> {
+    /// A context for a board with the given copper layers, listed from top to
+    /// bottom. Paste layers start out hidden, like they would be covered by
+    /// the copper below them otherwise.
+    pub fn new(options: &'a RenderOptions, copper: &[Layer]) -> Self {
+        Self {
+            options,
+            draw_order: default_draw_order(copper),
+            hidden: vec![Layer::TopPaste, Layer::BottomPaste],
+            colors: BTreeMap::new(),
+            nets: Vec::new(),
+            // Altium's default of 4 mil
+            solder_mask_expansion: 101_600,
+            paste_mask_expansion: 0,
+        }
+    }
+
+    /// A context for a two layer board, which is enough for footprints
+    pub fn two_layer(options: &'a RenderOptions) -> Self {
+        Self::new(options, &[Layer::Top, Layer::Bottom])
+    }
+
+    /// Layers that will be drawn, in drawing order
+    pub fn visible_layers(&self) -> impl Iterator<Item = Layer> + '_ {
+        self.draw_order
+            .iter()
+            .copied()
+            .filter(|layer| !self.hidden.contains(layer))
+    }
+
+    /// Show or hide a layer
+    pub fn set_visible(&mut self, layer: Layer, visible: bool) {
+        self.hidden.retain(|l| *l != layer);
+        if !visible {
+            self.hidden.push(layer);
+        }
+    }
+
+    /// Color to draw a layer with, after applying the theme
+    pub fn layer_color(&self, layer: Layer) -> Rgb {
+        if self.options.theme == Theme::Monochrome {
+            return match layer {
+                Layer::Background | Layer::PadHoles | Layer::ViaHoles => Rgb::white(),
+                _ => Rgb::black(),
+            };
+        }
+        let color = self
+            .colors
+            .get(&layer)
+            .copied()
+            .unwrap_or_else(|| default_color(layer));
+        self.options.remap(color)
+    }
+
+    /// Color of the area behind the board
+    pub fn background(&self) -> Rgb {
+        self.layer_color(Layer::Background)
+    }
+
+    fn net(&self, idx: Option<u16>) -> Option<&str> {
+        idx.and_then(|idx| self.nets.get(usize::from(idx)))
+            .map(AsRef::as_ref)
+    }
+}
+
+/// Altium's default color for a layer
+fn default_color(layer: Layer) -> Rgb {
+    const MID: [Rgb; 6] = [
+        Rgb::from_hex(0xbc, 0x8e, 0x00),
+        Rgb::from_hex(0x70, 0xdb, 0xfa),
+        Rgb::from_hex(0x00, 0xcc, 0x66),
+        Rgb::from_hex(0x99, 0x33, 0x00),
+        Rgb::from_hex(0x00, 0xcc, 0xcc),
+        Rgb::from_hex(0x99, 0x66, 0xcc),
+    ];
+    const PLANE: [Rgb; 4] = [
+        Rgb::from_hex(0x00, 0x80, 0x00),
+        Rgb::from_hex(0x80, 0x00, 0x00),
+        Rgb::from_hex(0x80, 0x00, 0x80),
+        Rgb::from_hex(0x00, 0x80, 0x80),
+    ];
+    const MECHANICAL: [Rgb; 4] = [
+        Rgb::from_hex(0xff, 0x00, 0xff),
+        Rgb::from_hex(0x80, 0x00, 0x80),
+        Rgb::from_hex(0x00, 0x80, 0x80),
+        Rgb::from_hex(0x80, 0x80, 0x00),
+    ];
+    let cycle = |colors: &[Rgb], n: u8| colors[usize::from(n.saturating_sub(1)) % colors.len()];
+
+    match layer {
+        Layer::Top => Rgb::from_hex(0xff, 0x00, 0x00),
+        Layer::Mid(n) => cycle(&MID, n),
+        Layer::Bottom => Rgb::from_hex(0x00, 0x00, 0xff),
+        Layer::TopOverlay => Rgb::from_hex(0xff, 0xff, 0x00),
+        Layer::BottomOverlay => Rgb::from_hex(0x80, 0x80, 0x00),
+        Layer::TopPaste | Layer::Unknown(_) => Rgb::from_hex(0x80, 0x80, 0x80),
+        Layer::BottomPaste | Layer::DrillGuide => Rgb::from_hex(0x80, 0x00, 0x00),
+        Layer::TopSolder => Rgb::from_hex(0x80, 0x00, 0x80),
+        Layer::BottomSolder | Layer::KeepOut => Rgb::from_hex(0xff, 0x00, 0xff),
+        Layer::InternalPlane(n) => cycle(&PLANE, n),
+        Layer::Mechanical(n) => cycle(&MECHANICAL, n),
+        Layer::DrillDrawing => Rgb::from_hex(0xff, 0x00, 0x2a),
+        Layer::MultiLayer => Rgb::from_hex(0xc0, 0xc0, 0xc0),
+        Layer::Connections => Rgb::from_hex(0xa8, 0xcb, 0xec),
+        Layer::Background => Rgb::black(),
+        Layer::DrcErrors => Rgb::from_hex(0x00, 0xff, 0x00),
+        Layer::Highlight => Rgb::white(),
+        Layer::GridColor1 => Rgb::from_hex(0x60, 0x60, 0x60),
+        Layer::GridColor10 => Rgb::from_hex(0xa0, 0xa0, 0xa4),
+        Layer::PadHoles => Rgb::from_hex(0x00, 0xe3, 0xe3),
+        Layer::ViaHoles => Rgb::from_hex(0x80, 0x80, 0x40),
+    }
+}
+
+/// Mechanical layers at the bottom, then the bottom side, copper from bottom
+/// to top, the top side and finally holes
+fn default_draw_order(copper: &[Layer]) -> Vec<Layer> {
+    let mut ret: Vec<Layer> = (1..=MECHANICAL_LAYER_COUNT)
+        .map(Layer::Mechanical)
+        .collect();
+    ret.extend([
+        Layer::BottomOverlay,
+        Layer::BottomPaste,
+        Layer::BottomSolder,
+    ]);
+    ret.extend(copper.iter().rev().filter(|layer| layer.is_copper()));
+    ret.extend([
+        Layer::TopSolder,
+        Layer::TopPaste,
+        Layer::TopOverlay,
+        Layer::KeepOut,
+        Layer::DrillGuide,
+        Layer::DrillDrawing,
+        Layer::PadHoles,
+        Layer::ViaHoles,
+    ]);
+    ret
+}
+
+/// Draw `records` layer by layer, grouping each primitive with its net
+pub(crate) fn draw_records<C: Canvas>(records: &[PcbRecord], canvas: &mut C, ctx: &PcbDrawCtx<'_>) {
+    for layer in ctx.visible_layers() {
+        for record in records {
+            canvas.start_group(record_group(record, ctx));
+            record.draw_layer(canvas, ctx, layer);
+            canvas.end_group();
+        }
+    }
+}
+
+/// Draw a board outline, filled with the background color
+pub(crate) fn draw_board_outline<C: Canvas>(
+    outline: &[Location],
+    canvas: &mut C,
+    ctx: &PcbDrawCtx<'_>,
+) {
+    if outline.is_empty() {
+        return;
+    }
+    canvas.start_group(DrawGroup {
+        kind: "BoardOutline",
+        ..Default::default()
+    });
+    canvas.draw_polygon(DrawPolygon {
+        locations: outline,
+        fill_color: ctx.background(),
+        stroke_color: ctx.options.stroke(BOARD_LINE),
+        stroke_width: 100_000,
+        ..Default::default()
+    });
+    canvas.end_group();
+}
+
+fn record_group<'a>(record: &'a PcbRecord, ctx: &'a PcbDrawCtx<'_>) -> DrawGroup<'a> {
+    let (kind, net) = match record {
+        PcbRecord::Arc(v) => ("Arc", v.net),
+        PcbRecord::Pad(v) => ("Pad", v.net),
+        PcbRecord::Via(v) => ("Via", v.net),
+        PcbRecord::Track(v) => ("Track", v.net),
+        PcbRecord::Text(_) => ("Text", None),
+        PcbRecord::Fill(v) => ("Fill", v.net),
+        PcbRecord::Region(v) => ("Region", v.net),
+        PcbRecord::ComponentBody(_) => ("ComponentBody", None),
+    };
+    DrawGroup {
+        kind,
+        designator: match record {
+            PcbRecord::Pad(pad) => Some(&pad.designator),
+            _ => None,
+        },
+        net: ctx.net(net),
+        ..Default::default()
+    }
+}
+
+/// Drawing a primitive's contents on a single layer
+trait DrawLayer {
+    fn draw_layer<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>, layer: Layer);
+}
+
+/// Implement `Draw` for primitives by drawing every visible layer in order
+macro_rules! impl_draw {
+    ($($ty:ty),*) => {$(
+        impl Draw for $ty {
+            type Context<'a> = PcbDrawCtx<'a>;
+
+            fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>) {
+                for layer in ctx.visible_layers() {
+                    self.draw_layer(canvas, ctx, layer);
+                }
+            }
+        }
+    )*};
+}
+
+impl_draw!(Arc, Pad, Via, Track, Text, Fill, Region);
+
+impl Draw for PcbRecord {
+    type Context<'a> = PcbDrawCtx<'a>;
+
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>) {
+        canvas.start_group(record_group(self, ctx));
+        for layer in ctx.visible_layers() {
+            self.draw_layer(canvas, ctx, layer);
+        }
+        canvas.end_group();
+    }
+}
+
+impl Draw for &[PcbRecord] {
+    type Context<'a> = PcbDrawCtx<'a>;
+
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>) {
+        draw_records(self, canvas, ctx);
+    }
+}
+
+impl Draw for Footprint {
+    type Context<'a> = PcbDrawCtx<'a>;
+
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>) {
+        canvas.start_group(DrawGroup {
+            kind: "Footprint",
+            name: Some(self.name()),
+            ..Default::default()
+        });
+        draw_records(self.records(), canvas, ctx);
+        canvas.end_group();
+    }
+}
+
+impl DrawLayer for PcbRecord {
+    fn draw_layer<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>, layer: Layer) {
+        match self {
+            PcbRecord::Arc(v) => v.draw_layer(canvas, ctx, layer),
+            PcbRecord::Pad(v) => v.draw_layer(canvas, ctx, layer),
+            PcbRecord::Via(v) => v.draw_layer(canvas, ctx, layer),
+            PcbRecord::Track(v) => v.draw_layer(canvas, ctx, layer),
+            PcbRecord::Text(v) => v.draw_layer(canvas, ctx, layer),
+            PcbRecord::Fill(v) => v.draw_layer(canvas, ctx, layer),
+            PcbRecord::Region(v) => v.draw_layer(canvas, ctx, layer),
+            // Bodies are only meaningful in 3D
+            PcbRecord::ComponentBody(_) => (),
+        }
+    }
+}
+
+impl DrawLayer for Arc {
+    fn draw_layer<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>, layer: Layer) {
+        if layer != self.layer {
+            return;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        canvas.draw_arc(DrawArc {
+            center: self.center,
+            x_radius: self.radius,
+            y_radius: self.radius,
+            start_angle: self.start_angle.to_radians() as f32,
+            end_angle: self.end_angle.to_radians() as f32,
+            width: self.width,
+            color: ctx.layer_color(layer),
+            start_cap: LineCap::Round,
+            end_cap: LineCap::Round,
+            ..Default::default()
+        });
+    }
+}
+
+impl DrawLayer for Track {
+    fn draw_layer<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>, layer: Layer) {
+        if layer != self.layer {
+            return;
+        }
+        canvas.draw_line(DrawLine {
+            start: self.start,
+            end: self.end,
+            color: ctx.layer_color(layer),
+            width: self.width,
+            start_cap: LineCap::Round,
+            end_cap: LineCap::Round,
+            ..Default::default()
+        });
+    }
+}
+
+impl DrawLayer for Fill {
+    fn draw_layer<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>, layer: Layer) {
+        if layer != self.layer {
+            return;
+        }
+        fill_shape(canvas, &fill_corners(self), ctx.layer_color(layer));
+    }
+}
+
+impl DrawLayer for Region {
+    fn draw_layer<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>, layer: Layer) {
+        // Cutouts only shape polygon pours, which are already stored poured
+        if layer != self.layer || self.kind == RegionKind::PolygonCutout {
+            return;
+        }
+        let color = ctx.layer_color(layer);
+        canvas.draw_polygon(DrawPolygon {
+            locations: &self.outline,
+            holes: &self.holes,
+            fill_color: color,
+            stroke_color: color,
+            ..Default::default()
+        });
+    }
+}
+
+impl DrawLayer for Text {
+    fn draw_layer<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>, layer: Layer) {
+        if layer != self.layer || self.text.is_empty() {
+            return;
+        }
+        let font = Font {
+            name: match self.kind {
+                TextKind::TrueType if !self.font_name.is_empty() => self.font_name.clone(),
+                _ => "Arial".into(),
+            },
+            size: u16::try_from((self.height / NM_PER_PT).max(1)).unwrap_or(u16::MAX),
+        };
+        canvas.draw_text(DrawText {
+            x: self.location.x(),
+            y: self.location.y(),
+            text: &self.text,
+            font: &font,
+            anchor_h: PosHoriz::Left,
+            anchor_v: PosVert::Bottom,
+            color: ctx.layer_color(layer),
+            rotation: nearest_rotation(self.rotation),
+        });
+    }
+}
+
+impl DrawLayer for Pad {
+    fn draw_layer<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>, layer: Layer) {
+        let through = self.layer == Layer::MultiLayer;
+        // The copper layer on the side that a mask layer belongs to
+        let copper = match layer {
+            Layer::TopSolder | Layer::TopPaste => Layer::Top,
+            Layer::BottomSolder | Layer::BottomPaste => Layer::Bottom,
+            _ => layer,
+        };
+        let on_side = through || self.layer == copper;
+
+        let (size, expansion) = match layer {
+            Layer::PadHoles if self.hole_size > 0 => {
+                fill_shape(canvas, &self.hole_outline(), ctx.layer_color(layer));
+                return;
+            }
+            Layer::Top | Layer::Mid(_) | Layer::Bottom if through => (pad_size(self, layer), 0),
+            Layer::TopSolder | Layer::BottomSolder if on_side => {
+                let tented = if copper == Layer::Top {
+                    self.tented_top
+                } else {
+                    self.tented_bottom
+                };
+                if tented {
+                    return;
+                }
+                let expansion = self
+                    .solder_mask_expansion
+                    .unwrap_or(ctx.solder_mask_expansion);
+                (pad_size(self, copper), expansion)
+            }
+            Layer::TopPaste | Layer::BottomPaste if on_side && self.hole_size == 0 => {
+                let expansion = self
+                    .paste_mask_expansion
+                    .unwrap_or(ctx.paste_mask_expansion);
+                (pad_size(self, copper), expansion)
+            }
+            _ if layer == self.layer => (pad_size(self, layer), 0),
+            _ => return,
+        };
+
+        let outline = pad_outline(size, expansion);
+        let outline = place(&outline, self.location, self.rotation);
+        fill_shape(canvas, &outline, ctx.layer_color(layer));
+    }
+}
+
+impl Pad {
+    /// The hole's outline, with slots rounded at their ends
+    fn hole_outline(&self) -> Vec<Location> {
+        let center = self
+            .location
+            .add_x(self.hole_offset.x)
+            .add_y(self.hole_offset.y);
+        let center = rotate(center, self.location, self.rotation);
+        let (size, rotation) = match self.hole_shape {
+            HoleShape::Round => (round_size(self.hole_size), 0.0),
+            HoleShape::Square => (
+                PadSize {
+                    x: self.hole_size,
+                    y: self.hole_size,
+                    shape: PadShape::Rect,
+                },
+                self.rotation + self.slot_rotation,
+            ),
+            HoleShape::Slot => (
+                PadSize {
+                    x: self.slot_size.max(self.hole_size),
+                    y: self.hole_size,
+                    shape: PadShape::Round,
+                },
+                self.rotation + self.slot_rotation,
+            ),
+        };
+        place(&pad_outline(size, 0), center, rotation)
+    }
+}
+
+impl DrawLayer for Via {
+    fn draw_layer<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>, layer: Layer) {
+        // `Layer`'s ordering puts mid layers between top and bottom
+        let (start, end) = if self.start_layer <= self.end_layer {
+            (self.start_layer, self.end_layer)
+        } else {
+            (self.end_layer, self.start_layer)
+        };
+        let diameter = match layer {
+            Layer::ViaHoles => self.hole_size,
+            Layer::Top | Layer::Mid(_) | Layer::Bottom if (start..=end).contains(&layer) => {
+                self.diameter
+            }
+            Layer::TopSolder if start == Layer::Top && !self.tented_top => {
+                expand(self.diameter, ctx.solder_mask_expansion)
+            }
+            Layer::BottomSolder if end == Layer::Bottom && !self.tented_bottom => {
+                expand(self.diameter, ctx.solder_mask_expansion)
+            }
+            _ => return,
+        };
+        if diameter == 0 {
+            return;
+        }
+        let outline = place(&pad_outline(round_size(diameter), 0), self.location, 0.0);
+        fill_shape(canvas, &outline, ctx.layer_color(layer));
+    }
+}
+
+/// Draw a filled outline with no stroke
+fn fill_shape<C: Canvas>(canvas: &mut C, outline: &[Location], color: Rgb) {
+    canvas.draw_polygon(DrawPolygon {
+        locations: outline,
+        fill_color: color,
+        stroke_color: color,
+        ..Default::default()
+    });
+}
+
+fn round_size(diameter: u32) -> PadSize {
+    PadSize {
+        x: diameter,
+        y: diameter,
+        shape: PadShape::Round,
+    }
+}
+
+/// Outline of a pad shape grown by `expansion` on each side, centered on
+/// the origin. Points are counterclockwise.
+#[allow(clippy::cast_possible_truncation)]
+pub(super) fn pad_outline(size: PadSize, expansion: i32) -> Vec<Location> {
+    let grow = |v: u32| ((f64::from(v) / 2.0) + f64::from(expansion)).max(0.0);
+    let (hw, hh) = (grow(size.x), grow(size.y));
+    let short = hw.min(hh);
+    let radius = match size.shape {
+        PadShape::Rect => 0.0,
+        PadShape::Round => short,
+        PadShape::RoundRect { corner_radius } => short * f64::from(corner_radius.min(100)) / 100.0,
+        PadShape::Octagonal => {
+            let c = short / 2.0;
+            let points = [
+                (hw, hh - c),
+                (hw - c, hh),
+                (c - hw, hh),
+                (-hw, hh - c),
+                (-hw, c - hh),
+                (c - hw, -hh),
+                (hw - c, -hh),
+                (hw, c - hh),
+            ];
+            return points
+                .iter()
+                .map(|&(x, y)| Location::new(x.round() as i32, y.round() as i32))
+                .collect();
+        }
+    };
+
+    // Corner centers, counterclockwise from the top right
+    let corners = [
+        (hw - radius, hh - radius),
+        (radius - hw, hh - radius),
+        (radius - hw, radius - hh),
+        (hw - radius, radius - hh),
+    ];
+    if radius <= 0.0 {
+        return corners
+            .iter()
+            .map(|&(x, y)| Location::new(x.round() as i32, y.round() as i32))
+            .collect();
+    }
+
+    let mut ret = Vec::new();
+    for (quarter, (cx, cy)) in (0..4u32).zip(corners) {
+        for step in 0..=ARC_SEGMENTS {
+            let angle =
+                FRAC_PI_2 * (f64::from(quarter) + f64::from(step) / f64::from(ARC_SEGMENTS));
+            let (sin, cos) = angle.sin_cos();
+            ret.push(Location::new(
+                (cx + radius * cos).round() as i32,
+                (cy + radius * sin).round() as i32,
+            ));
+        }
+    }
+    ret
+}
+
+/// Rotate an outline about the origin then move it to `center`
+pub(super) fn place(outline: &[Location], center: Location, rotation: f64) -> Vec<Location> {
+    outline
+        .iter()
+        .map(|loc| rotate(center.add_x(loc.x).add_y(loc.y), center, rotation))
+        .collect()
+}
+
+/// Texts can have any rotation, but canvases only support multiples of 90
+#[allow(clippy::cast_possible_truncation)]
+fn nearest_rotation(degrees: f64) -> Rotation90 {
+    match ((degrees / 90.0).round() as i64).rem_euclid(4) {
+        0 => Rotation90::R0,
+        1 => Rotation90::R90,
+        2 => Rotation90::R180,
+        _ => Rotation90::R270,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotated_slot_outline() {
+        // A slot rotated by 90 degrees in total is taller than it is wide
+        let pad = Pad {
+            rotation: 30.0,
+            hole_size: 500_000,
+            hole_shape: HoleShape::Slot,
+            hole_rotation: 45.0,
+            slot_size: 1_500_000,
+            slot_rotation: 60.0,
+            ..Default::default()
+        };
+        let outline = pad.hole_outline();
+        let xs = outline.iter().map(|loc| loc.x);
+        let ys = outline.iter().map(|loc| loc.y);
+        let width = xs.clone().max().unwrap() - xs.min().unwrap();
+        let height = ys.clone().max().unwrap() - ys.min().unwrap();
+        assert!((width - 500_000).abs() <= 2, "{width}");
+        assert!((height - 1_500_000).abs() <= 2, "{height}");
+    }
+}
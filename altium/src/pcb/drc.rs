@@ -0,0 +1,876 @@
+//! A basic design rule check on parsed board geometry
+//!
+//! This is meant as a fast guardrail for scripted edits, not a replacement for
+//! Altium's DRC. The checks are:
+//!
+//! - Copper clearance between objects on different nets
+//! - Minimum track and arc width
+//! - Minimum annular ring of plated pads and vias
+//! - Clearance between holes
+//! - Un-routed connections, i.e. pads on the same net that aren't connected
+//!   through copper
+//!
+//! Limits come from the board's `Clearance`, `Width`, `HoleToHoleClearance`
+//! and `MinimumAnnularRing` rules, in priority order. Only the `All` and
+//! `InNet('...')` scope queries are understood, rules with other scopes are
+//! skipped. [`DrcOptions`] gives the limits used when no rule applies.
+//!
+//! Round shapes are approximated by polygons, so reported distances can be off
+//! by a fraction of a percent of a pad's size.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::draw::{pad_outline, place};
+use super::fab::{fill_corners, pad_size, rotate};
+use super::record::{Arc, Pad, PadMode, PcbRecord, RegionKind};
+use super::rule::{NetScope, Rule, RuleKind};
+use super::{DesignRules, Layer};
+use crate::common::{Location, UniqueId};
+
+/// Limits used when the board has no rule that applies. Lengths are in nm.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DrcOptions {
+    pub clearance: i32,
+    pub min_track_width: i32,
+    pub min_annular_ring: i32,
+    pub hole_to_hole_clearance: i32,
+    /// Report pads on the same net that aren't connected
+    pub check_unrouted: bool,
+}
+
+impl Default for DrcOptions {
+    fn default() -> Self {
+        Self {
+            clearance: 254_000,
+            min_track_width: 254_000,
+            min_annular_ring: 50_800,
+            hole_to_hole_clearance: 254_000,
+            check_unrouted: true,
+        }
+    }
+}
+
+/// What a check runs on
+#[derive(Clone, Copy, Debug)]
+pub struct DrcInput<'a> {
+    /// Every primitive on the board. Poured primitives should have the net of
+    /// their polygon.
+    pub records: &'a [PcbRecord],
+    /// Net names, indexed by a primitive's `net`
+    pub nets: &'a [Box<str>],
+    /// Copper layers from top to bottom
+    pub copper_layers: &'a [Layer],
+    pub rules: &'a DesignRules,
+}
+
+/// The kind of a violation. Values are in nm.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// Copper objects on different nets are too close. Overlapping objects
+    /// have a gap of 0.
+    Clearance {
+        gap: i32,
+        required: i32,
+    },
+    TrackWidth {
+        width: i32,
+        required: i32,
+    },
+    AnnularRing {
+        ring: i32,
+        required: i32,
+    },
+    /// Holes are too close, a negative gap means they overlap
+    HoleToHole {
+        gap: i32,
+        required: i32,
+    },
+    /// Two pads on the same net aren't connected
+    Unrouted,
+}
+
+/// A primitive involved in a violation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViolationObject {
+    /// Index into the checked records
+    pub record: usize,
+    pub unique_id: Option<UniqueId>,
+}
+
+/// A single rule violation
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    /// Name of the rule that set the limit, `None` if it came from
+    /// [`DrcOptions`]
+    pub rule: Option<Box<str>>,
+    /// The copper layer, if the violation is on a single layer
+    pub layer: Option<Layer>,
+    pub location: Location,
+    /// The net of the first object
+    pub net: Option<Box<str>>,
+    pub objects: Vec<ViolationObject>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mm = |nm: i32| f64::from(nm) / 1e6;
+        match self.kind {
+            ViolationKind::Clearance { gap, required } => {
+                write!(f, "clearance {:.3}mm < {:.3}mm", mm(gap), mm(required))?;
+            }
+            ViolationKind::TrackWidth { width, required } => {
+                write!(f, "width {:.3}mm < {:.3}mm", mm(width), mm(required))?;
+            }
+            ViolationKind::AnnularRing { ring, required } => {
+                write!(f, "annular ring {:.3}mm < {:.3}mm", mm(ring), mm(required))?;
+            }
+            ViolationKind::HoleToHole { gap, required } => {
+                write!(f, "hole to hole {:.3}mm < {:.3}mm", mm(gap), mm(required))?;
+            }
+            ViolationKind::Unrouted => f.write_str("un-routed connection")?,
+        }
+        if let Some(net) = &self.net {
+            write!(f, " on net {net}")?;
+        }
+        if let Some(layer) = self.layer {
+            write!(f, " on {layer}")?;
+        }
+        write!(
+            f,
+            " at ({:.3}mm, {:.3}mm)",
+            mm(self.location.x),
+            mm(self.location.y)
+        )?;
+        if let Some(rule) = &self.rule {
+            write!(f, " (rule {rule})")?;
+        }
+        for obj in &self.objects {
+            match obj.unique_id {
+                Some(id) => write!(f, " [{id}]")?,
+                None => write!(f, " [#{}]", obj.record)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run every check. Violations are grouped by check, then in record order.
+pub fn check(input: &DrcInput<'_>, options: &DrcOptions) -> Vec<Violation> {
+    let ctx = Ctx { input, options };
+    let items = copper_items(input);
+
+    let mut ret = Vec::new();
+    let connections = ctx.check_clearance(&items, &mut ret);
+    ctx.check_widths(&mut ret);
+    ctx.check_annular_rings(&mut ret);
+    ctx.check_holes(&mut ret);
+    if options.check_unrouted {
+        ctx.check_unrouted(&connections, &mut ret);
+    }
+    ret
+}
+
+/// A straight or round ended polyline, or a filled polygon, in nm
+#[derive(Clone, Debug)]
+struct Shape {
+    points: Vec<(f64, f64)>,
+    /// Cutouts of filled polygons
+    holes: Vec<Vec<(f64, f64)>>,
+    /// Half the width of polylines
+    radius: f64,
+    /// Filled polygons have an implicit closing edge
+    closed: bool,
+}
+
+impl Shape {
+    fn line(points: Vec<(f64, f64)>, width: u32) -> Self {
+        Self {
+            points,
+            holes: Vec::new(),
+            radius: f64::from(width) / 2.0,
+            closed: false,
+        }
+    }
+
+    fn polygon(outline: &[Location]) -> Self {
+        Self {
+            points: outline.iter().map(|loc| to_f64(*loc)).collect(),
+            holes: Vec::new(),
+            radius: 0.0,
+            closed: true,
+        }
+    }
+
+    fn with_holes(mut self, holes: &[Vec<Location>]) -> Self {
+        self.holes = holes
+            .iter()
+            .map(|hole| hole.iter().map(|loc| to_f64(*loc)).collect())
+            .collect();
+        self
+    }
+
+    /// min x, min y, max x, max y
+    fn bbox(&self) -> [f64; 4] {
+        let mut ret = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+        for &(x, y) in &self.points {
+            ret = [ret[0].min(x), ret[1].min(y), ret[2].max(x), ret[3].max(y)];
+        }
+        let r = self.radius;
+        [ret[0] - r, ret[1] - r, ret[2] + r, ret[3] + r]
+    }
+
+    /// Edges of the outline and every hole
+    fn segments(&self) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+        let closed = self.closed;
+        [&self.points]
+            .into_iter()
+            .chain(&self.holes)
+            .flat_map(move |ring| {
+                let n = ring.len();
+                let count = match (closed, n) {
+                    (_, 0) => 0,
+                    (false, 1) => 1,
+                    (false, _) => n - 1,
+                    (true, _) => n,
+                };
+                (0..count).map(move |i| (ring[i], ring[(i + 1) % n]))
+            })
+    }
+
+    /// Whether a point is in the filled area, using the even-odd rule so
+    /// that holes are excluded
+    fn contains(&self, (px, py): (f64, f64)) -> bool {
+        if !self.closed {
+            return false;
+        }
+        let mut inside = false;
+        for ((x1, y1), (x2, y2)) in self.segments() {
+            if (y1 > py) != (y2 > py) && px < x1 + (py - y1) * (x2 - x1) / (y2 - y1) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// Gap between the edges of two shapes, 0 if they overlap, and the
+    /// point between them where it is smallest
+    fn gap(&self, other: &Self) -> (f64, (f64, f64)) {
+        let overlap = self
+            .points
+            .first()
+            .filter(|p| other.contains(**p))
+            .or_else(|| other.points.first().filter(|p| self.contains(**p)));
+        if let Some(&p) = overlap {
+            return (0.0, p);
+        }
+
+        let (dist, point) = self.edge_gap(other);
+        (dist.max(0.0), point)
+    }
+
+    /// Gap between the edges of two shapes ignoring filled interiors,
+    /// negative if lines overlap, and the point between them where it is
+    /// smallest
+    fn edge_gap(&self, other: &Self) -> (f64, (f64, f64)) {
+        let mut best = (f64::MAX, (0.0, 0.0));
+        for a in self.segments() {
+            for b in other.segments() {
+                let (dist, pa, pb) = segment_distance(a, b);
+                if dist < best.0 {
+                    let mid = (f64::midpoint(pa.0, pb.0), f64::midpoint(pa.1, pb.1));
+                    best = (dist, mid);
+                }
+            }
+        }
+        (best.0 - self.radius - other.radius, best.1)
+    }
+}
+
+/// One primitive's copper on one layer
+struct Item {
+    record: usize,
+    layer: Layer,
+    net: Option<u16>,
+    shape: Shape,
+    bbox: [f64; 4],
+}
+
+struct Ctx<'a> {
+    input: &'a DrcInput<'a>,
+    options: &'a DrcOptions,
+}
+
+impl Ctx<'_> {
+    fn net_name(&self, net: Option<u16>) -> Option<&str> {
+        net.and_then(|idx| self.input.nets.get(usize::from(idx)))
+            .map(|name| &**name)
+    }
+
+    fn object(&self, record: usize) -> ViolationObject {
+        ViolationObject {
+            record,
+            unique_id: self.input.records[record].unique_id(),
+        }
+    }
+
+    /// Enabled rules of a kind in priority order
+    fn rules(&self, kind: &'static str) -> impl Iterator<Item = &Rule> {
+        self.input.rules.of_kind(kind)
+    }
+
+    /// The clearance required between two nets and the rule it came from
+    fn clearance(&self, a: Option<u16>, b: Option<u16>) -> (i32, Option<&str>) {
+        let (a, b) = (self.net_name(a), self.net_name(b));
+        for rule in self.rules("Clearance") {
+            let RuleKind::Clearance { gap } = *rule.kind() else {
+                continue;
+            };
+            if rule.net_scope() == NetScope::SameNet {
+                continue;
+            }
+            let matches = |x, y| {
+                scope_matches(rule.scope1(), x).zip(scope_matches(rule.scope2(), y))
+                    == Some((true, true))
+            };
+            if matches(a, b) || matches(b, a) {
+                return (gap, Some(rule.name()));
+            }
+        }
+        (self.options.clearance, None)
+    }
+
+    /// A limit from the first rule of `kind` whose first scope matches `net`
+    fn single_scope_limit(
+        &self,
+        kind: &'static str,
+        net: Option<u16>,
+        limit: impl Fn(&Rule) -> Option<i32>,
+        default: i32,
+    ) -> (i32, Option<&str>) {
+        let net = self.net_name(net);
+        self.rules(kind)
+            .filter(|rule| scope_matches(rule.scope1(), net) == Some(true))
+            .find_map(|rule| Some((limit(rule)?, Some(rule.name()))))
+            .unwrap_or((default, None))
+    }
+
+    fn violation(
+        &self,
+        kind: ViolationKind,
+        rule: Option<&str>,
+        layer: Option<Layer>,
+        location: Location,
+        net: Option<u16>,
+        records: &[usize],
+    ) -> Violation {
+        Violation {
+            kind,
+            rule: rule.map(Into::into),
+            layer,
+            location,
+            net: self.net_name(net).map(Into::into),
+            objects: records.iter().map(|&rec| self.object(rec)).collect(),
+        }
+    }
+
+    /// Check clearance between copper on different nets and return pairs of
+    /// records on the same net that touch
+    fn check_clearance(&self, items: &[Item], out: &mut Vec<Violation>) -> Vec<(usize, usize)> {
+        let max_clearance = self
+            .rules("Clearance")
+            .filter_map(|rule| match *rule.kind() {
+                RuleKind::Clearance { gap } => Some(gap),
+                _ => None,
+            })
+            .chain([self.options.clearance])
+            .max()
+            .unwrap_or_default();
+
+        let mut order: Vec<usize> = (0..items.len()).collect();
+        order.sort_by(|&a, &b| {
+            (items[a].layer, items[a].bbox[0])
+                .partial_cmp(&(items[b].layer, items[b].bbox[0]))
+                .unwrap()
+        });
+
+        let mut reported = BTreeSet::new();
+        let mut connections = Vec::new();
+        for (pos, &i) in order.iter().enumerate() {
+            let a = &items[i];
+            for &j in &order[pos + 1..] {
+                let b = &items[j];
+                if b.layer != a.layer || b.bbox[0] > a.bbox[2] + f64::from(max_clearance) {
+                    break;
+                }
+                if a.record == b.record
+                    || b.bbox[1] > a.bbox[3] + f64::from(max_clearance)
+                    || a.bbox[1] > b.bbox[3] + f64::from(max_clearance)
+                {
+                    continue;
+                }
+
+                let same_net = a.net.is_some() && a.net == b.net;
+                if same_net {
+                    if a.shape.gap(&b.shape).0 <= 0.0 {
+                        connections.push((a.record, b.record));
+                    }
+                    continue;
+                }
+                if a.net.is_none() && b.net.is_none() {
+                    continue;
+                }
+
+                let (required, rule) = self.clearance(a.net, b.net);
+                let (gap, point) = a.shape.gap(&b.shape);
+                let gap = round_nm(gap);
+                let pair = (a.record.min(b.record), a.record.max(b.record));
+                if gap < required && reported.insert(pair) {
+                    out.push(self.violation(
+                        ViolationKind::Clearance { gap, required },
+                        rule,
+                        Some(a.layer),
+                        to_location(point),
+                        a.net,
+                        &[pair.0, pair.1],
+                    ));
+                }
+            }
+        }
+
+        connections
+    }
+
+    fn check_widths(&self, out: &mut Vec<Violation>) {
+        let width_limit = |rule: &Rule| match rule.kind() {
+            RuleKind::Width(limits) => Some(limits.min),
+            _ => None,
+        };
+
+        for (idx, record) in self.input.records.iter().enumerate() {
+            let (layer, net, width, location) = match record {
+                PcbRecord::Track(t) if !t.keepout && t.polygon.is_none() => {
+                    (t.layer, t.net, t.width, t.start)
+                }
+                PcbRecord::Arc(a) if !a.keepout && a.polygon.is_none() => {
+                    (a.layer, a.net, a.width, arc_start(a))
+                }
+                _ => continue,
+            };
+            if !is_signal(layer) {
+                continue;
+            }
+            let (required, rule) =
+                self.single_scope_limit("Width", net, width_limit, self.options.min_track_width);
+            let width = i32::try_from(width).unwrap_or(i32::MAX);
+            if width < required {
+                out.push(self.violation(
+                    ViolationKind::TrackWidth { width, required },
+                    rule,
+                    Some(layer),
+                    location,
+                    net,
+                    &[idx],
+                ));
+            }
+        }
+    }
+
+    fn check_annular_rings(&self, out: &mut Vec<Violation>) {
+        let ring_limit = |rule: &Rule| {
+            rule.param("MINIMUMRING")
+                .and_then(|v| super::record::parse_len(v).ok())
+        };
+
+        for (idx, record) in self.input.records.iter().enumerate() {
+            let (ring, net, location) = match record {
+                PcbRecord::Pad(pad) if pad.plated && pad.hole_size > 0 => {
+                    let Some(ring) = pad_ring(pad, self.input.copper_layers.len()) else {
+                        continue;
+                    };
+                    (ring, pad.net, pad.location)
+                }
+                PcbRecord::Via(via) if via.hole_size > 0 => {
+                    let ring = (i64::from(via.diameter) - i64::from(via.hole_size)) / 2;
+                    (ring, via.net, via.location)
+                }
+                _ => continue,
+            };
+            let (required, rule) = self.single_scope_limit(
+                "MinimumAnnularRing",
+                net,
+                ring_limit,
+                self.options.min_annular_ring,
+            );
+            let ring = i32::try_from(ring).unwrap_or(i32::MAX);
+            if ring < required {
+                out.push(self.violation(
+                    ViolationKind::AnnularRing { ring, required },
+                    rule,
+                    None,
+                    location,
+                    net,
+                    &[idx],
+                ));
+            }
+        }
+    }
+
+    fn check_holes(&self, out: &mut Vec<Violation>) {
+        let (required, rule) = self.single_scope_limit(
+            "HoleToHoleClearance",
+            None,
+            |rule| match *rule.kind() {
+                RuleKind::HoleToHoleClearance { gap } => Some(gap),
+                _ => None,
+            },
+            self.options.hole_to_hole_clearance,
+        );
+
+        let mut holes: Vec<(usize, Option<u16>, Shape)> = self
+            .input
+            .records
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, record)| match record {
+                PcbRecord::Pad(pad) if pad.hole_size > 0 => Some((idx, pad.net, pad_hole(pad))),
+                PcbRecord::Via(via) if via.hole_size > 0 => Some((
+                    idx,
+                    via.net,
+                    Shape::line(vec![to_f64(via.location)], via.hole_size),
+                )),
+                _ => None,
+            })
+            .collect();
+        holes.sort_by(|a, b| a.2.bbox()[0].partial_cmp(&b.2.bbox()[0]).unwrap());
+
+        for (pos, (rec_a, net, a)) in holes.iter().enumerate() {
+            let reach = a.bbox()[2] + f64::from(required);
+            for (rec_b, _, b) in &holes[pos + 1..] {
+                if b.bbox()[0] > reach {
+                    break;
+                }
+                let (dist, point) = a.edge_gap(b);
+                let gap = round_nm(dist);
+                if gap < required {
+                    out.push(self.violation(
+                        ViolationKind::HoleToHole { gap, required },
+                        rule,
+                        None,
+                        to_location(point),
+                        *net,
+                        &[*rec_a, *rec_b],
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Group pads by what they connect to through copper, then report a
+    /// connection for every group after the first on each net
+    fn check_unrouted(&self, connections: &[(usize, usize)], out: &mut Vec<Violation>) {
+        let records = self.input.records;
+        let mut sets = DisjointSets::new(records.len());
+        for &(a, b) in connections {
+            sets.union(a, b);
+        }
+
+        let mut pads: Vec<(u16, usize, Location)> = records
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, record)| match record {
+                PcbRecord::Pad(pad) => Some((pad.net?, idx, pad.location)),
+                _ => None,
+            })
+            .collect();
+        pads.sort_by_key(|&(net, idx, _)| (net, idx));
+
+        for net_pads in pads.chunk_by(|a, b| a.0 == b.0) {
+            let net = net_pads[0].0;
+            let mut connected: Vec<_> = net_pads
+                .iter()
+                .filter(|pad| sets.find(pad.1) == sets.find(net_pads[0].1))
+                .collect();
+            let mut rest: Vec<_> = net_pads
+                .iter()
+                .filter(|pad| sets.find(pad.1) != sets.find(net_pads[0].1))
+                .collect();
+
+            // Join the nearest disconnected group each time
+            while !rest.is_empty() {
+                let (from, to) = connected
+                    .iter()
+                    .flat_map(|a| rest.iter().map(move |b| (*a, *b)))
+                    .min_by_key(|(a, b)| distance_sq(a.2, b.2))
+                    .expect("both sides are non-empty");
+                let group = sets.find(to.1);
+                let location =
+                    Location::new(midpoint(from.2.x, to.2.x), midpoint(from.2.y, to.2.y));
+                out.push(self.violation(
+                    ViolationKind::Unrouted,
+                    None,
+                    None,
+                    location,
+                    Some(net),
+                    &[from.1, to.1],
+                ));
+                let (joined, remaining) =
+                    rest.into_iter().partition(|pad| sets.find(pad.1) == group);
+                rest = remaining;
+                connected.extend::<Vec<_>>(joined);
+            }
+        }
+    }
+}
+
+/// Interpret a scope query. `None` if the query isn't supported.
+pub(super) fn scope_matches(query: &str, net: Option<&str>) -> Option<bool> {
+    let query = query.trim();
+    if query.eq_ignore_ascii_case("All") {
+        return Some(true);
+    }
+    let name = query
+        .strip_prefix("InNet(")
+        .and_then(|rest| rest.strip_suffix(')'))?
+        .trim()
+        .trim_matches('\'');
+    Some(net == Some(name))
+}
+
+/// Every primitive's copper, split by layer
+fn copper_items(input: &DrcInput<'_>) -> Vec<Item> {
+    let stack = input.copper_layers;
+    let mut ret = Vec::new();
+    let mut push = |record: usize, layer: Layer, net: Option<u16>, shape: Shape| {
+        if shape.points.is_empty() {
+            return;
+        }
+        ret.push(Item {
+            record,
+            layer,
+            net,
+            bbox: shape.bbox(),
+            shape,
+        });
+    };
+
+    for (idx, record) in input.records.iter().enumerate() {
+        match record {
+            PcbRecord::Track(t) if !t.keepout && is_signal(t.layer) => {
+                let shape = Shape::line(vec![to_f64(t.start), to_f64(t.end)], t.width);
+                push(idx, t.layer, t.net, shape);
+            }
+            PcbRecord::Arc(a) if !a.keepout && is_signal(a.layer) => {
+                push(idx, a.layer, a.net, Shape::line(arc_points(a), a.width));
+            }
+            PcbRecord::Fill(f) if !f.keepout && is_signal(f.layer) => {
+                push(idx, f.layer, f.net, Shape::polygon(&fill_corners(f)));
+            }
+            PcbRecord::Region(r)
+                if !r.keepout && r.kind == RegionKind::Copper && is_signal(r.layer) =>
+            {
+                push(
+                    idx,
+                    r.layer,
+                    r.net,
+                    Shape::polygon(&r.outline).with_holes(&r.holes),
+                );
+            }
+            PcbRecord::Pad(pad) => {
+                let layers: &[Layer] = if pad.layer == Layer::MultiLayer {
+                    stack
+                } else {
+                    std::slice::from_ref(&pad.layer)
+                };
+                for &layer in layers.iter().filter(|l| is_signal(**l)) {
+                    let size = pad_size(pad, layer);
+                    if size.x == 0 || size.y == 0 {
+                        continue;
+                    }
+                    let outline = place(&pad_outline(size, 0), pad.location, pad.rotation);
+                    push(idx, layer, pad.net, Shape::polygon(&outline));
+                }
+            }
+            PcbRecord::Via(via) => {
+                let pos = |layer| stack.iter().position(|l| *l == layer);
+                let start = pos(via.start_layer).unwrap_or(0);
+                let end = pos(via.end_layer).unwrap_or(stack.len().saturating_sub(1));
+                for &layer in stack.iter().take(start.max(end) + 1).skip(start.min(end)) {
+                    let shape = Shape::line(vec![to_f64(via.location)], via.diameter);
+                    push(idx, layer, via.net, shape);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    ret
+}
+
+/// Copper layers that primitives are drawn on
+pub(super) fn is_signal(layer: Layer) -> bool {
+    matches!(layer, Layer::Top | Layer::Mid(_) | Layer::Bottom)
+}
+
+/// The smallest annular ring over the layers a pad has copper on
+fn pad_ring(pad: &Pad, copper_count: usize) -> Option<i64> {
+    let sizes = match pad.mode {
+        PadMode::Simple => vec![pad.top],
+        _ if copper_count > 2 => vec![pad.top, pad.middle, pad.bottom],
+        _ => vec![pad.top, pad.bottom],
+    };
+    let offset = f64::from(pad.hole_offset.x).hypot(f64::from(pad.hole_offset.y));
+    #[allow(clippy::cast_possible_truncation)]
+    let offset = offset.round() as i64;
+
+    sizes
+        .iter()
+        .filter(|size| size.x > 0 && size.y > 0)
+        .map(|size| {
+            let short = i64::from(size.x.min(size.y));
+            (short - i64::from(pad.hole_size)) / 2 - offset
+        })
+        .min()
+}
+
+/// A pad's hole as a line, round holes are a single point
+fn pad_hole(pad: &Pad) -> Shape {
+    let center = rotate(
+        pad.location
+            .add_x(pad.hole_offset.x)
+            .add_y(pad.hole_offset.y),
+        pad.location,
+        pad.rotation,
+    );
+    let half = i32::try_from(pad.slot_size.saturating_sub(pad.hole_size) / 2).unwrap_or(0);
+    if pad.slot_size <= pad.hole_size || half == 0 {
+        return Shape::line(vec![to_f64(center)], pad.hole_size);
+    }
+    let angle = pad.rotation + pad.slot_rotation;
+    let ends = [
+        rotate(center.add_x(-half), center, angle),
+        rotate(center.add_x(half), center, angle),
+    ];
+    Shape::line(ends.map(to_f64).to_vec(), pad.hole_size)
+}
+
+pub(super) fn arc_points(arc: &Arc) -> Vec<(f64, f64)> {
+    const MAX_STEP: f64 = 5.0;
+
+    let mut sweep = (arc.end_angle - arc.start_angle).rem_euclid(360.0);
+    if sweep == 0.0 {
+        sweep = 360.0;
+    }
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let steps = (sweep / MAX_STEP).ceil().max(1.0) as u32;
+    let (cx, cy) = to_f64(arc.center);
+    let radius = f64::from(arc.radius);
+    (0..=steps)
+        .map(|i| {
+            let angle = arc.start_angle + sweep * f64::from(i) / f64::from(steps);
+            let (sin, cos) = angle.to_radians().sin_cos();
+            (cx + radius * cos, cy + radius * sin)
+        })
+        .collect()
+}
+
+fn arc_start(arc: &Arc) -> Location {
+    to_location(arc_points(arc)[0])
+}
+
+/// Closest distance between two segments, and the closest point on each
+fn segment_distance(
+    (p1, q1): ((f64, f64), (f64, f64)),
+    (p2, q2): ((f64, f64), (f64, f64)),
+) -> (f64, (f64, f64), (f64, f64)) {
+    let sub = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0, a.1 - b.1);
+    let dot = |a: (f64, f64), b: (f64, f64)| a.0 * b.0 + a.1 * b.1;
+    let cross = |a: (f64, f64), b: (f64, f64)| a.0 * b.1 - a.1 * b.0;
+    let at = |p: (f64, f64), d: (f64, f64), t: f64| (p.0 + d.0 * t, p.1 + d.1 * t);
+
+    let (d1, d2) = (sub(q1, p1), sub(q2, p2));
+
+    // Proper intersections have a distance of 0
+    let denom = cross(d1, d2);
+    if denom != 0.0 {
+        let r = sub(p2, p1);
+        let t = cross(r, d2) / denom;
+        let u = cross(r, d1) / denom;
+        if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+            let point = at(p1, d1, t);
+            return (0.0, point, point);
+        }
+    }
+
+    // Otherwise the closest point involves an endpoint
+    let closest = |p: (f64, f64), a: (f64, f64), d: (f64, f64)| {
+        let len_sq = dot(d, d);
+        let t = if len_sq == 0.0 {
+            0.0
+        } else {
+            (dot(sub(p, a), d) / len_sq).clamp(0.0, 1.0)
+        };
+        at(a, d, t)
+    };
+    [
+        (p1, closest(p1, p2, d2)),
+        (q1, closest(q1, p2, d2)),
+        (closest(p2, p1, d1), p2),
+        (closest(q2, p1, d1), q2),
+    ]
+    .into_iter()
+    .map(|(a, b)| {
+        let d = sub(a, b);
+        (dot(d, d).sqrt(), a, b)
+    })
+    .min_by(|a, b| a.0.total_cmp(&b.0))
+    .expect("array is non-empty")
+}
+
+/// Union find over record indices
+struct DisjointSets(Vec<usize>);
+
+impl DisjointSets {
+    fn new(len: usize) -> Self {
+        Self((0..len).collect())
+    }
+
+    fn find(&mut self, mut idx: usize) -> usize {
+        while self.0[idx] != idx {
+            self.0[idx] = self.0[self.0[idx]];
+            idx = self.0[idx];
+        }
+        idx
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.0[a.max(b)] = a.min(b);
+    }
+}
+
+fn to_f64(loc: Location) -> (f64, f64) {
+    (f64::from(loc.x), f64::from(loc.y))
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn to_location((x, y): (f64, f64)) -> Location {
+    Location::new(x.round() as i32, y.round() as i32)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn round_nm(val: f64) -> i32 {
+    val.round() as i32
+}
+
+fn midpoint(a: i32, b: i32) -> i32 {
+    i32::try_from((i64::from(a) + i64::from(b)) / 2).unwrap_or_default()
+}
+
+fn distance_sq(a: Location, b: Location) -> i64 {
+    let dx = i64::from(a.x) - i64::from(b.x);
+    let dy = i64::from(a.y) - i64::from(b.y);
+    dx * dx + dy * dy
+}
@@ -0,0 +1,896 @@
+//! Fabrication and assembly outputs: Gerber X2 layer images, Excellon drill
+//! files, a Gerber job file, pick and place files, IPC-D-356A test netlists
+//! and IPC-2581 design data
+//!
+//! Outputs are generated from the primitives stored in a [`PcbDoc`], without
+//! Altium's output job settings. Copper layers get pads, vias, tracks, arcs,
+//! fills and regions, including the regions and tracks that make up poured
+//! polygons. Pour geometry is used as stored, nothing is repoured.
+//!
+//! Some things are not generated yet:
+//!
+//! - Texts are skipped, since they need Altium's stroke and TrueType fonts.
+//! - Internal planes are negative images of the primitives placed on them,
+//!   plus clearances from the `PlaneClearance` rule around holes on other
+//!   nets. Pads and vias on the plane's net connect directly, without thermal
+//!   reliefs.
+//! - Mask and paste expansions come from the pad or from [`FabOptions`], not
+//!   from design rules.
+//! - Keepouts, polygon cutouts and board cutouts are not drawn.
+//!
+//! [`PcbDoc`]: super::PcbDoc
+
+mod excellon;
+mod gerber;
+mod ipc2581;
+mod ipc356;
+mod job;
+mod placement;
+
+use std::fs;
+use std::path::Path;
+
+use excellon::Hole;
+use gerber::{Aperture, GerberWriter};
+pub(super) use ipc2581::ipc2581;
+pub use ipc2581::{BomPart, Ipc2581Options};
+pub(super) use ipc356::ipc356_netlist;
+use job::JobEntry;
+pub(super) use placement::pick_and_place;
+pub use placement::{PlacementOptions, PlacementOrigin, Units};
+
+use super::drc::scope_matches;
+use super::record::{Fill, HoleShape, Pad, PadMode, PadShape, PadSize, PcbRecord, RegionKind, Via};
+use super::{Component, DesignRules, Layer, RuleKind, StackLayer};
+use crate::common::Location;
+use crate::error::AddContext;
+use crate::Error;
+
+/// Altium's default plane clearance of 20 mil, in nm
+const DEFAULT_PLANE_CLEARANCE: i32 = 508_000;
+
+/// Vendor, application and version for `TF.GenerationSoftware`
+const GENERATOR: (&str, &str, &str) = ("altium-rs", "altium", env!("CARGO_PKG_VERSION"));
+
+/// Settings for fabrication outputs
+#[derive(Clone, Debug, PartialEq)]
+pub struct FabOptions {
+    /// Prefix for file names, e.g. `board` gives `board-top_layer.gbr`
+    pub name: String,
+    /// Solder mask expansion in nm, for pads that don't set their own
+    pub solder_mask_expansion: i32,
+    /// Paste mask expansion in nm, for pads that don't set their own
+    pub paste_mask_expansion: i32,
+    /// Line width for the board profile in nm
+    pub outline_width: u32,
+}
+
+impl Default for FabOptions {
+    fn default() -> Self {
+        Self {
+            name: "board".to_owned(),
+            // Altium's default of 4 mil
+            solder_mask_expansion: 101_600,
+            paste_mask_expansion: 0,
+            outline_width: 100_000,
+        }
+    }
+}
+
+/// What a generated file contains
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FabFileKind {
+    /// Gerber image of a single layer
+    Gerber(Layer),
+    /// Gerber board outline
+    Profile,
+    /// Excellon drill file for holes between two copper layers, numbered
+    /// from 1 at the top
+    Drill {
+        plated: bool,
+        from: usize,
+        to: usize,
+    },
+    /// Gerber job file listing the other files
+    Job,
+}
+
+/// A single generated file
+#[derive(Clone, Debug, PartialEq)]
+pub struct FabFile {
+    name: Box<str>,
+    kind: FabFileKind,
+    function: Box<str>,
+    polarity: Option<&'static str>,
+    contents: String,
+}
+
+impl FabFile {
+    /// File name, including the extension
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> FabFileKind {
+        self.kind
+    }
+
+    /// The `TF.FileFunction` attribute, e.g. `Copper,L1,Top,Signal`
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+}
+
+/// A set of files for a board fabricator
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FabOutput {
+    files: Vec<FabFile>,
+}
+
+impl FabOutput {
+    pub fn files(&self) -> &[FabFile] {
+        &self.files
+    }
+
+    /// Find a file by name
+    pub fn get(&self, name: &str) -> Option<&FabFile> {
+        self.files.iter().find(|file| &*file.name == name)
+    }
+
+    /// Write every file to a directory, creating it if needed
+    pub fn write_to_dir<P: AsRef<Path>>(&self, dir: P) -> Result<(), Error> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)
+            .map_err(Error::from)
+            .or_context(|| format!("creating {}", dir.display()))?;
+        for file in &self.files {
+            let path = dir.join(&*file.name);
+            fs::write(&path, &file.contents)
+                .map_err(Error::from)
+                .or_context(|| format!("writing {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    fn push_gerber(
+        &mut self,
+        prefix: &str,
+        kind: FabFileKind,
+        function: &str,
+        polarity: &'static str,
+        writer: GerberWriter,
+    ) {
+        let stem = match kind {
+            FabFileKind::Gerber(layer) => layer.to_string().to_lowercase().replace([' ', '-'], "_"),
+            _ => "profile".to_owned(),
+        };
+        let contents = writer.finish(&[("FileFunction", function), ("FilePolarity", polarity)]);
+        self.files.push(FabFile {
+            name: format!("{prefix}-{stem}.gbr").into(),
+            kind,
+            function: function.into(),
+            polarity: Some(polarity),
+            contents,
+        });
+    }
+}
+
+/// Everything needed from a document to generate outputs
+#[derive(Debug)]
+pub(super) struct BoardData {
+    pub records: Vec<PcbRecord>,
+    pub components: Vec<Component>,
+    pub nets: Vec<Box<str>>,
+    /// Net index of each polygon pour
+    pub polygon_nets: Vec<Option<u16>>,
+    /// Net name of each internal plane, from plane 1
+    pub plane_nets: Vec<Option<Box<str>>>,
+    pub rules: DesignRules,
+    pub outline: Vec<Location>,
+    pub origin: Location,
+    /// Copper layers from top to bottom
+    pub stack: Vec<Layer>,
+    /// Every physical layer from top to bottom
+    pub stackup: Vec<StackLayer>,
+}
+
+impl BoardData {
+    fn net(&self, idx: Option<u16>) -> Option<&str> {
+        idx.and_then(|idx| self.nets.get(usize::from(idx)))
+            .map(|name| &**name)
+    }
+
+    /// The net of a primitive, or of the polygon it was poured from
+    fn poured_net(&self, net: Option<u16>, polygon: Option<u16>) -> Option<&str> {
+        let polygon_net = || {
+            polygon
+                .and_then(|idx| self.polygon_nets.get(usize::from(idx)))
+                .copied()
+                .flatten()
+        };
+        self.net(net.or_else(polygon_net))
+    }
+
+    pub(super) fn pads(&self) -> impl Iterator<Item = &Pad> {
+        self.records.iter().filter_map(|rec| match rec {
+            PcbRecord::Pad(pad) => Some(pad),
+            _ => None,
+        })
+    }
+
+    pub(super) fn vias(&self) -> impl Iterator<Item = &Via> {
+        self.records.iter().filter_map(|rec| match rec {
+            PcbRecord::Via(via) => Some(via),
+            _ => None,
+        })
+    }
+
+    /// The clearance between a plane and holes on other nets
+    fn plane_clearance(&self, net: Option<&str>) -> i32 {
+        self.rules
+            .of_kind("PlaneClearance")
+            .filter(|rule| scope_matches(rule.scope1(), net) == Some(true))
+            .find_map(|rule| match *rule.kind() {
+                RuleKind::PlaneClearance { clearance } => Some(clearance),
+                _ => None,
+            })
+            .unwrap_or(DEFAULT_PLANE_CLEARANCE)
+    }
+
+    /// 0-based stack positions of the first and last layer a via connects
+    pub(super) fn via_span(&self, via: &Via) -> (usize, usize) {
+        let last = self.stack.len().saturating_sub(1);
+        let pos = |layer| self.stack.iter().position(|l| *l == layer);
+        let start = pos(via.start_layer).unwrap_or(0);
+        let end = pos(via.end_layer).unwrap_or(last);
+        (start.min(end), start.max(end))
+    }
+}
+
+/// Generate every output for a board
+pub(super) fn generate(board: &BoardData, options: &FabOptions) -> FabOutput {
+    let mut out = FabOutput::default();
+    let name = &options.name;
+    let layer_count = board.stack.len();
+
+    for (idx, &layer) in board.stack.iter().enumerate() {
+        let side = if idx == 0 {
+            "Top"
+        } else if idx + 1 == layer_count {
+            "Bot"
+        } else {
+            "Inr"
+        };
+        let plane = matches!(layer, Layer::InternalPlane(_));
+        let ty = if plane { "Plane" } else { "Signal" };
+        let polarity = if plane { "Negative" } else { "Positive" };
+        let function = format!("Copper,L{},{side},{ty}", idx + 1);
+        let writer = copper_image(board, layer, idx);
+        out.push_gerber(
+            name,
+            FabFileKind::Gerber(layer),
+            &function,
+            polarity,
+            writer,
+        );
+    }
+
+    for (copper, side) in [(Layer::Top, "Top"), (Layer::Bottom, "Bot")] {
+        let bottom = copper == Layer::Bottom;
+        let (mask, paste, overlay) = if bottom {
+            (
+                Layer::BottomSolder,
+                Layer::BottomPaste,
+                Layer::BottomOverlay,
+            )
+        } else {
+            (Layer::TopSolder, Layer::TopPaste, Layer::TopOverlay)
+        };
+
+        // Mask images are the openings, so they are negative
+        let writer = mask_image(board, copper, mask, options);
+        let function = format!("Soldermask,{side}");
+        out.push_gerber(
+            name,
+            FabFileKind::Gerber(mask),
+            &function,
+            "Negative",
+            writer,
+        );
+
+        let writer = paste_image(board, copper, paste, options);
+        let function = format!("Paste,{side}");
+        out.push_gerber(
+            name,
+            FabFileKind::Gerber(paste),
+            &function,
+            "Positive",
+            writer,
+        );
+
+        let mut writer = GerberWriter::new(board.origin);
+        draw_primitives(&mut writer, board, overlay, false);
+        let function = format!("Legend,{side}");
+        out.push_gerber(
+            name,
+            FabFileKind::Gerber(overlay),
+            &function,
+            "Positive",
+            writer,
+        );
+    }
+
+    for layer in mechanical_layers(board) {
+        let mut writer = GerberWriter::new(board.origin);
+        draw_primitives(&mut writer, board, layer, false);
+        let Layer::Mechanical(num) = layer else {
+            unreachable!()
+        };
+        let function = format!("Other,Mechanical{num}");
+        out.push_gerber(
+            name,
+            FabFileKind::Gerber(layer),
+            &function,
+            "Positive",
+            writer,
+        );
+    }
+
+    if board.outline.len() >= 3 {
+        let writer = profile_image(board, options);
+        out.push_gerber(name, FabFileKind::Profile, "Profile,NP", "Positive", writer);
+    }
+
+    push_drills(&mut out, board, name);
+
+    push_job(&mut out, board, name);
+    out
+}
+
+/// The job file, listing every file generated so far
+fn push_job(out: &mut FabOutput, board: &BoardData, name: &str) {
+    let entries: Vec<_> = out
+        .files
+        .iter()
+        .map(|file| JobEntry {
+            path: &file.name,
+            function: &file.function,
+            polarity: file.polarity,
+        })
+        .collect();
+    let contents = job::write_job(
+        name,
+        outline_size(&board.outline),
+        board.stack.len(),
+        &entries,
+    );
+    out.files.push(FabFile {
+        name: format!("{name}.gbrjob").into(),
+        kind: FabFileKind::Job,
+        function: "Job".into(),
+        polarity: None,
+        contents,
+    });
+}
+
+/// Pads, vias and other primitives on a copper layer. `idx` is the layer's
+/// position in the stack.
+fn copper_image(board: &BoardData, layer: Layer, idx: usize) -> GerberWriter {
+    let mut writer = GerberWriter::new(board.origin);
+    draw_primitives(&mut writer, board, layer, true);
+
+    if let Layer::InternalPlane(plane) = layer {
+        plane_clearances(&mut writer, board, plane, idx);
+        return writer;
+    }
+
+    for pad in board.pads() {
+        if pad.layer != layer && pad.layer != Layer::MultiLayer {
+            continue;
+        }
+        let Some(aperture) = pad_aperture(pad_size(pad, layer), pad.rotation, 0) else {
+            continue;
+        };
+        let function = if pad.hole_size == 0 {
+            "SMDPad,CuDef"
+        } else {
+            "ComponentPad"
+        };
+        writer.set_net(board.net(pad.net));
+        writer.select(aperture, Some(function));
+        writer.flash(pad.location);
+    }
+
+    for via in board.vias() {
+        let (start, end) = board.via_span(via);
+        if !(start..=end).contains(&idx) || via.diameter == 0 {
+            continue;
+        }
+        writer.set_net(board.net(via.net));
+        writer.select(Aperture::Circle(via.diameter), Some("ViaPad"));
+        writer.flash(via.location);
+    }
+
+    writer
+}
+
+/// Clearances on a negative plane image around holes that pass through it
+/// but aren't on its net. Holes on the plane's net are left in the plane.
+fn plane_clearances(writer: &mut GerberWriter, board: &BoardData, plane: u8, idx: usize) {
+    let plane_net = board
+        .plane_nets
+        .get(usize::from(plane).saturating_sub(1))
+        .and_then(Option::as_deref);
+    let connected = |net: Option<&str>| net.is_some() && net == plane_net;
+    writer.set_net(None);
+
+    for pad in board.pads() {
+        let net = board.net(pad.net);
+        if pad.hole_size == 0 || pad.layer != Layer::MultiLayer || connected(net) {
+            continue;
+        }
+        let hole = pad_hole(pad)("AntiPad");
+        let diameter = expand(hole.diameter, board.plane_clearance(net));
+        writer.select(Aperture::Circle(diameter), Some("AntiPad"));
+        match hole.slot_end {
+            Some(end) => writer.line(hole.location, end),
+            None => writer.flash(hole.location),
+        }
+    }
+
+    for via in board.vias() {
+        let net = board.net(via.net);
+        let (start, end) = board.via_span(via);
+        if !(start..=end).contains(&idx) || via.hole_size == 0 || connected(net) {
+            continue;
+        }
+        let diameter = expand(via.hole_size, board.plane_clearance(net));
+        writer.select(Aperture::Circle(diameter), Some("AntiPad"));
+        writer.flash(via.location);
+    }
+}
+
+/// Openings for pads and vias that aren't tented, plus primitives on the mask
+/// layer
+fn mask_image(board: &BoardData, copper: Layer, mask: Layer, options: &FabOptions) -> GerberWriter {
+    let mut writer = GerberWriter::new(board.origin);
+    draw_primitives(&mut writer, board, mask, false);
+    let bottom = copper == Layer::Bottom;
+
+    for pad in board.pads() {
+        let tented = if bottom {
+            pad.tented_bottom
+        } else {
+            pad.tented_top
+        };
+        if tented || (pad.layer != copper && pad.layer != Layer::MultiLayer) {
+            continue;
+        }
+        let expansion = pad
+            .solder_mask_expansion
+            .unwrap_or(options.solder_mask_expansion);
+        if let Some(aperture) = pad_aperture(pad_size(pad, copper), pad.rotation, expansion) {
+            writer.select(aperture, None);
+            writer.flash(pad.location);
+        }
+    }
+
+    let last = board.stack.len().saturating_sub(1);
+    for via in board.vias() {
+        let tented = if bottom {
+            via.tented_bottom
+        } else {
+            via.tented_top
+        };
+        let (start, end) = board.via_span(via);
+        let reaches = if bottom { end == last } else { start == 0 };
+        let diameter = expand(via.diameter, options.solder_mask_expansion);
+        if tented || !reaches || diameter == 0 {
+            continue;
+        }
+        writer.select(Aperture::Circle(diameter), None);
+        writer.flash(via.location);
+    }
+
+    writer
+}
+
+/// Surface mount pads on one side, plus primitives on the paste layer
+fn paste_image(
+    board: &BoardData,
+    copper: Layer,
+    paste: Layer,
+    options: &FabOptions,
+) -> GerberWriter {
+    let mut writer = GerberWriter::new(board.origin);
+    draw_primitives(&mut writer, board, paste, false);
+
+    for pad in board.pads() {
+        if pad.hole_size != 0 || pad.layer != copper {
+            continue;
+        }
+        let expansion = pad
+            .paste_mask_expansion
+            .unwrap_or(options.paste_mask_expansion);
+        if let Some(aperture) = pad_aperture(pad_size(pad, copper), pad.rotation, expansion) {
+            writer.select(aperture, None);
+            writer.flash(pad.location);
+        }
+    }
+
+    writer
+}
+
+/// The board outline as a closed line
+fn profile_image(board: &BoardData, options: &FabOptions) -> GerberWriter {
+    let mut writer = GerberWriter::new(board.origin);
+    writer.select(Aperture::Circle(options.outline_width), Some("Profile"));
+    let outline = &board.outline;
+    for (start, end) in outline.iter().zip(outline.iter().cycle().skip(1)) {
+        if start != end {
+            writer.line(*start, *end);
+        }
+    }
+    writer
+}
+
+/// Draw regions, fills, tracks and arcs on `layer`. Regions come first since
+/// their holes clear anything already drawn.
+fn draw_primitives(writer: &mut GerberWriter, board: &BoardData, layer: Layer, copper: bool) {
+    let conductor = copper.then_some("Conductor");
+    let set_net = |writer: &mut GerberWriter, net, polygon| {
+        if copper {
+            writer.set_net(board.poured_net(net, polygon));
+        }
+    };
+
+    for rec in &board.records {
+        match rec {
+            PcbRecord::Region(region)
+                if region.layer == layer
+                    && !region.keepout
+                    && region.kind == RegionKind::Copper =>
+            {
+                set_net(writer, region.net, region.polygon);
+                writer.region(&region.outline, &region.holes);
+            }
+            _ => (),
+        }
+    }
+
+    for rec in &board.records {
+        match rec {
+            PcbRecord::Fill(fill) if fill.layer == layer && !fill.keepout => {
+                let corners = fill_corners(fill);
+                set_net(writer, fill.net, None);
+                writer.region(&corners, &[]);
+            }
+            PcbRecord::Track(track) if track.layer == layer && !track.keepout => {
+                if track.width == 0 {
+                    continue;
+                }
+                set_net(writer, track.net, track.polygon);
+                writer.select(Aperture::Circle(track.width), conductor);
+                writer.line(track.start, track.end);
+            }
+            PcbRecord::Arc(arc) if arc.layer == layer && !arc.keepout => {
+                if arc.width == 0 || arc.radius == 0 {
+                    continue;
+                }
+                let point = |angle: f64| {
+                    let radius = i32::try_from(arc.radius).unwrap_or(i32::MAX);
+                    rotate(arc.center.add_x(radius), arc.center, angle)
+                };
+                let sweep = (arc.end_angle - arc.start_angle).rem_euclid(360.0);
+                let start = point(arc.start_angle);
+                // A sweep of zero is a full circle, which Gerber writes with
+                // the same start and end
+                let end = if sweep == 0.0 {
+                    start
+                } else {
+                    point(arc.end_angle)
+                };
+                set_net(writer, arc.net, arc.polygon);
+                writer.select(Aperture::Circle(arc.width), conductor);
+                writer.arc(start, end, arc.center);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Mechanical layers that have anything on them
+fn mechanical_layers(board: &BoardData) -> Vec<Layer> {
+    let mut ret: Vec<_> = board
+        .records
+        .iter()
+        .map(PcbRecord::layer)
+        .filter(|layer| matches!(layer, Layer::Mechanical(_)))
+        .collect();
+    ret.sort_unstable();
+    ret.dedup();
+    ret
+}
+
+/// Drill files for plated and non-plated through holes, plus one per
+/// blind or buried via span
+fn push_drills(out: &mut FabOutput, board: &BoardData, name: &str) {
+    let last = board.stack.len().saturating_sub(1);
+    let mut plated = Vec::new();
+    let mut non_plated = Vec::new();
+    // Keyed by 0-based stack positions
+    let mut spans: Vec<((usize, usize), Vec<Hole>)> = Vec::new();
+
+    for pad in board.pads().filter(|pad| pad.hole_size > 0) {
+        let hole = pad_hole(pad);
+        if pad.plated {
+            plated.push(hole("Plated,PTH,ComponentDrill"));
+        } else {
+            non_plated.push(hole("NonPlated,NPTH,ComponentDrill"));
+        }
+    }
+
+    for via in board.vias().filter(|via| via.hole_size > 0) {
+        let span = board.via_span(via);
+        let blind = span.0 == 0 || span.1 == last;
+        let function = match (span == (0, last), blind) {
+            (true, _) => "Plated,PTH,ViaDrill",
+            (false, true) => "Plated,Blind,ViaDrill",
+            (false, false) => "Plated,Buried,ViaDrill",
+        };
+        let hole = Hole {
+            location: via.location,
+            diameter: via.hole_size,
+            slot_end: None,
+            function,
+        };
+        if span == (0, last) {
+            plated.push(hole);
+        } else if let Some((_, holes)) = spans.iter_mut().find(|(s, _)| *s == span) {
+            holes.push(hole);
+        } else {
+            spans.push((span, vec![hole]));
+        }
+    }
+
+    let count = last + 1;
+    let mut push = |holes: &[Hole], file: String, function: String, plated, from, to| {
+        if holes.is_empty() {
+            return;
+        }
+        out.files.push(FabFile {
+            name: file.into(),
+            kind: FabFileKind::Drill { plated, from, to },
+            contents: excellon::write_drill(holes, board.origin, &function),
+            function: function.into(),
+            polarity: None,
+        });
+    };
+
+    push(
+        &plated,
+        format!("{name}-PTH.drl"),
+        format!("Plated,1,{count},PTH"),
+        true,
+        1,
+        count,
+    );
+    push(
+        &non_plated,
+        format!("{name}-NPTH.drl"),
+        format!("NonPlated,1,{count},NPTH"),
+        false,
+        1,
+        count,
+    );
+
+    spans.sort_by_key(|(span, _)| *span);
+    for ((start, end), holes) in spans {
+        let (from, to) = (start + 1, end + 1);
+        let ty = if start == 0 || end == last {
+            "Blind"
+        } else {
+            "Buried"
+        };
+        push(
+            &holes,
+            format!("{name}-L{from}-L{to}.drl"),
+            format!("Plated,{from},{to},{ty}"),
+            true,
+            from,
+            to,
+        );
+    }
+}
+
+/// A pad's hole, given its `TA.AperFunction`. Square holes are drilled round.
+fn pad_hole(pad: &Pad) -> impl Fn(&'static str) -> Hole + '_ {
+    let location = pad
+        .location
+        .add_x(pad.hole_offset.x)
+        .add_y(pad.hole_offset.y);
+    let location = rotate(location, pad.location, pad.rotation);
+
+    let slot_end =
+        (pad.hole_shape == HoleShape::Slot && pad.slot_size > pad.hole_size).then(|| {
+            // The slot runs between the centers of its rounded ends
+            let half = i32::try_from((pad.slot_size - pad.hole_size) / 2).unwrap_or(i32::MAX);
+            let angle = pad.rotation + pad.slot_rotation;
+            (
+                rotate(location.add_x(-half), location, angle),
+                rotate(location.add_x(half), location, angle),
+            )
+        });
+
+    move |function| match slot_end {
+        Some((start, end)) => Hole {
+            location: start,
+            diameter: pad.hole_size,
+            slot_end: Some(end),
+            function,
+        },
+        None => Hole {
+            location,
+            diameter: pad.hole_size,
+            slot_end: None,
+            function,
+        },
+    }
+}
+
+/// The shape of a pad on a copper layer
+pub(super) fn pad_size(pad: &Pad, layer: Layer) -> PadSize {
+    match (pad.mode, layer) {
+        (PadMode::Simple, _) | (_, Layer::Top) => pad.top,
+        (_, Layer::Bottom) => pad.bottom,
+        _ => pad.middle,
+    }
+}
+
+/// Find the aperture for a pad shape grown by `expansion` on each side
+fn pad_aperture(size: PadSize, rotation: f64, expansion: i32) -> Option<Aperture> {
+    let (w, h) = (expand(size.x, expansion), expand(size.y, expansion));
+    if w == 0 || h == 0 {
+        return None;
+    }
+    let rot = millidegrees(rotation);
+    let square = rot % 90_000 == 0;
+    // Sizes for a rotation that is a multiple of 90 degrees
+    let (sw, sh) = if (rot / 90_000) % 2 == 1 {
+        (h, w)
+    } else {
+        (w, h)
+    };
+
+    let obround = || {
+        if square {
+            Aperture::Obround { w: sw, h: sh }
+        } else if w >= h {
+            Aperture::RotObround { w, h, rot }
+        } else {
+            Aperture::RotObround {
+                w: h,
+                h: w,
+                rot: (rot + 90_000) % 360_000,
+            }
+        }
+    };
+    let rect = || {
+        if square {
+            Aperture::Rect { w: sw, h: sh }
+        } else {
+            Aperture::RotRect { w, h, rot }
+        }
+    };
+
+    let aperture = match size.shape {
+        PadShape::Round if w == h => Aperture::Circle(w),
+        PadShape::Round => obround(),
+        PadShape::Rect => rect(),
+        PadShape::Octagonal => Aperture::Octagon { w, h, rot },
+        PadShape::RoundRect { corner_radius } if corner_radius >= 100 => {
+            if w == h {
+                Aperture::Circle(w)
+            } else {
+                obround()
+            }
+        }
+        PadShape::RoundRect { corner_radius } => {
+            let r = u64::from(w.min(h)) * u64::from(corner_radius) / 200;
+            match u32::try_from(r) {
+                Ok(0) | Err(_) => rect(),
+                Ok(r) => Aperture::RoundRect { w, h, r, rot },
+            }
+        }
+    };
+    Some(aperture)
+}
+
+/// Grow a size by `expansion` on each side
+pub(super) fn expand(size: u32, expansion: i32) -> u32 {
+    let ret = (i64::from(size) + 2 * i64::from(expansion)).max(0);
+    u32::try_from(ret).unwrap_or(u32::MAX)
+}
+
+/// An angle in degrees as millidegrees in `0..360_000`
+#[allow(clippy::cast_possible_truncation)]
+fn millidegrees(angle: f64) -> i32 {
+    ((angle.rem_euclid(360.0) * 1000.0).round() as i32) % 360_000
+}
+
+fn midpoint(a: i32, b: i32) -> i32 {
+    i32::try_from((i64::from(a) + i64::from(b)) / 2).unwrap_or_default()
+}
+
+/// Corners of a fill after rotating it about its center
+pub(super) fn fill_corners(fill: &Fill) -> [Location; 4] {
+    let center = Location::new(
+        midpoint(fill.corner1.x, fill.corner2.x),
+        midpoint(fill.corner1.y, fill.corner2.y),
+    );
+    [
+        fill.corner1,
+        Location::new(fill.corner2.x, fill.corner1.y),
+        fill.corner2,
+        Location::new(fill.corner1.x, fill.corner2.y),
+    ]
+    .map(|corner| rotate(corner, center, fill.rotation))
+}
+
+/// Rotate a point counterclockwise about `center`
+#[allow(clippy::cast_possible_truncation)]
+pub(super) fn rotate(point: Location, center: Location, angle: f64) -> Location {
+    if angle.rem_euclid(360.0) == 0.0 {
+        return point;
+    }
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let dx = f64::from(point.x) - f64::from(center.x);
+    let dy = f64::from(point.y) - f64::from(center.y);
+    Location::new(
+        center.x + (dx * cos - dy * sin).round() as i32,
+        center.y + (dx * sin + dy * cos).round() as i32,
+    )
+}
+
+/// Width and height of the outline's bounding box
+fn outline_size(outline: &[Location]) -> Option<(i64, i64)> {
+    let xs = outline.iter().map(|loc| i64::from(loc.x));
+    let ys = outline.iter().map(|loc| i64::from(loc.y));
+    Some((xs.clone().max()? - xs.min()?, ys.clone().max()? - ys.min()?))
+}
+
+/// Format nm as mm with full precision
+fn fmt_mm(nm: i64) -> String {
+    let sign = if nm < 0 { "-" } else { "" };
+    let nm = nm.unsigned_abs();
+    format!("{sign}{}.{:06}", nm / 1_000_000, nm % 1_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotated_slot() {
+        // The pad is rotated by 30 degrees and the slot by 60 more, so it
+        // runs vertically. The main record's hole rotation doesn't matter.
+        let pad = Pad {
+            location: Location::new(1_000_000, 2_000_000),
+            rotation: 30.0,
+            hole_size: 500_000,
+            hole_shape: HoleShape::Slot,
+            hole_rotation: 45.0,
+            slot_size: 1_500_000,
+            slot_rotation: 60.0,
+            ..Default::default()
+        };
+        let hole = pad_hole(&pad)("Plated,PTH,ComponentDrill");
+        assert_eq!(hole.location, Location::new(1_000_000, 1_500_000));
+        assert_eq!(hole.slot_end, Some(Location::new(1_000_000, 2_500_000)));
+    }
+}
@@ -0,0 +1,80 @@
+//! Excellon drill file writer
+//!
+//! Files are metric with decimal coordinates. Gerber X2 style attributes are
+//! written as `; #@!` comments, which is how KiCad and other tools annotate
+//! drill files.
+
+use std::fmt::Write;
+
+use super::GENERATOR;
+use crate::common::Location;
+
+/// A single drilled hole or routed slot
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct Hole {
+    pub location: Location,
+    pub diameter: u32,
+    /// The other end of a slot
+    pub slot_end: Option<Location>,
+    /// `TA.AperFunction` of the tool, e.g. `Plated,PTH,ViaDrill`
+    pub function: &'static str,
+}
+
+/// Write a drill file. `file_function` is the `TF.FileFunction` value, e.g.
+/// `Plated,1,4,PTH`.
+pub(super) fn write_drill(holes: &[Hole], origin: Location, file_function: &str) -> String {
+    let mut tools: Vec<(u32, &str)> = Vec::new();
+    for hole in holes {
+        if !tools.contains(&(hole.diameter, hole.function)) {
+            tools.push((hole.diameter, hole.function));
+        }
+    }
+    tools.sort_unstable();
+
+    let mut ret = String::new();
+    let (vendor, app, version) = GENERATOR;
+    ret.push_str("M48\n");
+    writeln!(ret, "; #@! TF.GenerationSoftware,{vendor},{app},{version}").unwrap();
+    writeln!(ret, "; #@! TF.FileFunction,{file_function}").unwrap();
+    ret.push_str("METRIC\n");
+    for (idx, (diameter, function)) in tools.iter().enumerate() {
+        writeln!(ret, "; #@! TA.AperFunction,{function}").unwrap();
+        writeln!(ret, "T{}C{}", idx + 1, fmt_drill(i64::from(*diameter))).unwrap();
+    }
+    ret.push_str("%\nG90\nG05\n");
+
+    let coord = |loc: Location| {
+        format!(
+            "X{}Y{}",
+            fmt_drill(i64::from(loc.x) - i64::from(origin.x)),
+            fmt_drill(i64::from(loc.y) - i64::from(origin.y))
+        )
+    };
+
+    for (idx, tool) in tools.iter().enumerate() {
+        writeln!(ret, "T{}", idx + 1).unwrap();
+        for hole in holes
+            .iter()
+            .filter(|hole| (hole.diameter, hole.function) == *tool)
+        {
+            match hole.slot_end {
+                Some(end) => {
+                    writeln!(ret, "{}G85{}", coord(hole.location), coord(end)).unwrap();
+                }
+                None => writeln!(ret, "{}", coord(hole.location)).unwrap(),
+            }
+        }
+    }
+
+    ret.push_str("M30\n");
+    ret
+}
+
+/// Format nm as mm with micrometer resolution, the usual precision for
+/// metric drill files
+fn fmt_drill(nm: i64) -> String {
+    let um = (nm + nm.signum() * 500) / 1000;
+    let sign = if um < 0 { "-" } else { "" };
+    let um = um.unsigned_abs();
+    format!("{sign}{}.{:03}", um / 1000, um % 1000)
+}
@@ -0,0 +1,407 @@
+//! Gerber X2 (RS-274X with attributes) writer
+//!
+//! Coordinates use `%FSLAX46Y46*%` with millimeters, so one coordinate unit is
+//! exactly one nanometer.
+
+use std::fmt::Write;
+
+use log::warn;
+
+use super::{fmt_mm, GENERATOR};
+use crate::common::Location;
+
+/// Aperture macros, defined in the header only if they are used
+const MACROS: [(&str, &str); 4] = [
+    ("RotRect", "21,1,$1,$2,0,0,$3*"),
+    // Center rectangle plus a circle at each end
+    (
+        "RotObround",
+        "21,1,$1,$2,0,0,$5*\n1,1,$2,$3,0,$5*\n1,1,$2,$4,0,$5*",
+    ),
+    // Two overlapping rectangles plus a circle in each corner
+    (
+        "RoundRect",
+        "21,1,$1,$2,0,0,$10*\n21,1,$3,$4,0,0,$10*\n1,1,$5,$6,$7,$10*\n\
+         1,1,$5,$8,$7,$10*\n1,1,$5,$8,$9,$10*\n1,1,$5,$6,$9,$10*",
+    ),
+    (
+        "Octagon",
+        "4,1,8,$1,$4,$3,$2,$7,$2,$5,$4,$5,$8,$7,$6,$3,$6,$1,$8,$1,$4,$9*",
+    ),
+];
+
+/// A flashable shape. Sizes are in nm and rotations in millidegrees so that
+/// apertures can be deduplicated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(super) enum Aperture {
+    Circle(u32),
+    Rect {
+        w: u32,
+        h: u32,
+    },
+    Obround {
+        w: u32,
+        h: u32,
+    },
+    RotRect {
+        w: u32,
+        h: u32,
+        rot: i32,
+    },
+    /// Obround with `w >= h`
+    RotObround {
+        w: u32,
+        h: u32,
+        rot: i32,
+    },
+    RoundRect {
+        w: u32,
+        h: u32,
+        r: u32,
+        rot: i32,
+    },
+    Octagon {
+        w: u32,
+        h: u32,
+        rot: i32,
+    },
+}
+
+impl Aperture {
+    fn macro_name(self) -> Option<&'static str> {
+        match self {
+            Self::Circle(_) | Self::Rect { .. } | Self::Obround { .. } => None,
+            Self::RotRect { .. } => Some("RotRect"),
+            Self::RotObround { .. } => Some("RotObround"),
+            Self::RoundRect { .. } => Some("RoundRect"),
+            Self::Octagon { .. } => Some("Octagon"),
+        }
+    }
+
+    /// The template and parameters of an `AD` command
+    fn definition(self) -> String {
+        let join = |vals: &[i64]| {
+            vals.iter()
+                .map(|v| fmt_mm(*v))
+                .collect::<Vec<_>>()
+                .join("X")
+        };
+        let deg = |rot: i32| format!("{}.{:03}", rot / 1000, rot % 1000);
+
+        match self {
+            Self::Circle(d) => format!("C,{}", fmt_mm(d.into())),
+            Self::Rect { w, h } => format!("R,{}", join(&[w.into(), h.into()])),
+            Self::Obround { w, h } => format!("O,{}", join(&[w.into(), h.into()])),
+            Self::RotRect { w, h, rot } => {
+                format!("RotRect,{}X{}", join(&[w.into(), h.into()]), deg(rot))
+            }
+            Self::RotObround { w, h, rot } => {
+                let (w, h) = (i64::from(w), i64::from(h));
+                let half = (w - h) / 2;
+                format!("RotObround,{}X{}", join(&[w - h, h, half, -half]), deg(rot))
+            }
+            Self::RoundRect { w, h, r, rot } => {
+                let (w, h, r) = (i64::from(w), i64::from(h), i64::from(r));
+                let (cx, cy) = (w / 2 - r, h / 2 - r);
+                let params = [w, h - 2 * r, w - 2 * r, h, 2 * r, cx, cy, -cx, -cy];
+                format!("RoundRect,{}X{}", join(&params), deg(rot))
+            }
+            Self::Octagon { w, h, rot } => {
+                let (hw, hh) = (i64::from(w) / 2, i64::from(h) / 2);
+                let chamfer = hw.min(hh) / 2;
+                let params = [
+                    hw,
+                    hh,
+                    hw - chamfer,
+                    hh - chamfer,
+                    -hw,
+                    -hh,
+                    chamfer - hw,
+                    chamfer - hh,
+                ];
+                format!("Octagon,{}X{}", join(&params), deg(rot))
+            }
+        }
+    }
+}
+
+/// Builds the body of a single Gerber file
+#[derive(Debug)]
+pub(super) struct GerberWriter {
+    origin: Location,
+    /// Apertures with their `TA.AperFunction`. D codes start at 10.
+    apertures: Vec<(Aperture, Option<&'static str>)>,
+    body: String,
+    current: Option<usize>,
+    net: Option<Box<str>>,
+}
+
+impl GerberWriter {
+    /// Create a writer with coordinates relative to `origin`
+    pub fn new(origin: Location) -> Self {
+        Self {
+            origin,
+            apertures: Vec::new(),
+            body: String::new(),
+            current: None,
+            net: None,
+        }
+    }
+
+    /// Select an aperture for the following objects, defining it if needed
+    pub fn select(&mut self, aperture: Aperture, function: Option<&'static str>) {
+        let idx = self
+            .apertures
+            .iter()
+            .position(|ap| *ap == (aperture, function))
+            .unwrap_or_else(|| {
+                self.apertures.push((aperture, function));
+                self.apertures.len() - 1
+            });
+
+        if self.current != Some(idx) {
+            self.current = Some(idx);
+            writeln!(self.body, "D{}*", idx + 10).unwrap();
+        }
+    }
+
+    /// Set the net of the following objects. Only useful on copper layers.
+    pub fn set_net(&mut self, net: Option<&str>) {
+        if self.net.as_deref() == net {
+            return;
+        }
+        match net {
+            Some(name) => writeln!(self.body, "%TO.N,{}*%", escape_attr(name)).unwrap(),
+            None => self.body.push_str("%TD.N*%\n"),
+        }
+        self.net = net.map(Into::into);
+    }
+
+    pub fn flash(&mut self, at: Location) {
+        let at = self.coord(at);
+        writeln!(self.body, "{at}D03*").unwrap();
+    }
+
+    pub fn line(&mut self, start: Location, end: Location) {
+        let (start, end) = (self.coord(start), self.coord(end));
+        writeln!(self.body, "{start}D02*\n{end}D01*").unwrap();
+    }
+
+    /// A counterclockwise arc. If `start` and `end` are the same this is a
+    /// full circle.
+    pub fn arc(&mut self, start: Location, end: Location, center: Location) {
+        let i = i64::from(center.x) - i64::from(start.x);
+        let j = i64::from(center.y) - i64::from(start.y);
+        let (start, end) = (self.coord(start), self.coord(end));
+        writeln!(self.body, "{start}D02*\nG03{end}I{i}J{j}D01*\nG01*").unwrap();
+    }
+
+    /// A filled region. Holes are joined to the outline with cut-ins so the
+    /// region stays a single contour, rather than clearing them (which would
+    /// also clear any copper already drawn below).
+    pub fn region(&mut self, outline: &[Location], holes: &[Vec<Location>]) {
+        if outline.len() < 3 {
+            return;
+        }
+        if holes.is_empty() {
+            self.contour(outline);
+        } else {
+            self.contour(&cut_in_holes(outline, holes));
+        }
+    }
+
+    fn contour(&mut self, points: &[Location]) {
+        self.body.push_str("G36*\n");
+        writeln!(self.body, "{}D02*", self.coord(points[0])).unwrap();
+        for point in &points[1..] {
+            writeln!(self.body, "{}D01*", self.coord(*point)).unwrap();
+        }
+        if points.first() != points.last() {
+            writeln!(self.body, "{}D01*", self.coord(points[0])).unwrap();
+        }
+        self.body.push_str("G37*\n");
+    }
+
+    fn coord(&self, loc: Location) -> String {
+        let x = i64::from(loc.x) - i64::from(self.origin.x);
+        let y = i64::from(loc.y) - i64::from(self.origin.y);
+        format!("X{x}Y{y}")
+    }
+
+    /// Produce the complete file with the given `TF` file attributes, e.g.
+    /// `("FileFunction", "Copper,L1,Top")`
+    pub fn finish(self, file_attrs: &[(&str, &str)]) -> String {
+        let mut ret = String::new();
+        let (vendor, app, version) = GENERATOR;
+        writeln!(ret, "%TF.GenerationSoftware,{vendor},{app},{version}*%").unwrap();
+        ret.push_str("%TF.SameCoordinates,Original*%\n");
+        for (name, value) in file_attrs {
+            writeln!(ret, "%TF.{name},{value}*%").unwrap();
+        }
+        ret.push_str("%FSLAX46Y46*%\n%MOMM*%\n%LPD*%\nG01*\nG75*\n");
+
+        for (name, body) in MACROS {
+            if self
+                .apertures
+                .iter()
+                .any(|(ap, _)| ap.macro_name() == Some(name))
+            {
+                writeln!(ret, "%AM{name}*\n{body}%").unwrap();
+            }
+        }
+
+        for (idx, (aperture, function)) in self.apertures.iter().enumerate() {
+            if let Some(function) = function {
+                writeln!(ret, "%TA.AperFunction,{function}*%").unwrap();
+            }
+            writeln!(ret, "%ADD{}{}*%", idx + 10, aperture.definition()).unwrap();
+            if function.is_some() {
+                ret.push_str("%TD.AperFunction*%\n");
+            }
+        }
+
+        ret.push_str(&self.body);
+        if self.net.is_some() {
+            ret.push_str("%TD*%\n");
+        }
+        ret.push_str("M02*\n");
+        ret
+    }
+}
+
+/// Join holes to their outline with cut-ins, so that the region can be drawn as
+/// a single contour. Each hole is joined by a horizontal cut from its leftmost
+/// point to the nearest edge on its left. Holes are merged from left to right,
+/// so a cut can't cross a hole that hasn't been merged yet.
+fn cut_in_holes(outline: &[Location], holes: &[Vec<Location>]) -> Vec<Location> {
+    let mut ret = open_ring(outline);
+    let outline_area = signed_area(&ret);
+
+    let mut holes: Vec<_> = holes
+        .iter()
+        .filter(|hole| hole.len() >= 3)
+        .map(|hole| open_ring(hole))
+        .collect();
+    // Holes need to wind the other way so they stay outside of the region
+    for hole in &mut holes {
+        if signed_area(hole).signum() == outline_area.signum() {
+            hole.reverse();
+        }
+    }
+    holes.sort_by_key(|hole| hole.iter().map(|p| p.x).min());
+
+    for hole in holes {
+        let (start, p) = hole
+            .iter()
+            .copied()
+            .enumerate()
+            .min_by_key(|(_, p)| (p.x, p.y))
+            .expect("holes have points");
+
+        // Closest edge that crosses the horizontal line left of `p`
+        let mut best: Option<(usize, f64)> = None;
+        for (i, &a) in ret.iter().enumerate() {
+            let b = ret[(i + 1) % ret.len()];
+            if (a.y <= p.y) == (b.y <= p.y) {
+                continue;
+            }
+            let t = (f64::from(p.y) - f64::from(a.y)) / (f64::from(b.y) - f64::from(a.y));
+            let x = f64::from(a.x) + t * (f64::from(b.x) - f64::from(a.x));
+            if x <= f64::from(p.x) && best.is_none_or(|(_, best_x)| x > best_x) {
+                best = Some((i, x));
+            }
+        }
+        let Some((edge, x)) = best else {
+            warn!("region hole at {p:?} is outside of its outline, skipping it");
+            continue;
+        };
+
+        // Cut in, go around the hole, and come back out the same way
+        #[allow(clippy::cast_possible_truncation)]
+        let bridge = Location::new(x.round() as i32, p.y);
+        let mut cut = Vec::with_capacity(hole.len() + 3);
+        cut.push(bridge);
+        cut.extend(hole[start..].iter().chain(&hole[..start]));
+        cut.push(p);
+        cut.push(bridge);
+        let at = edge + 1;
+        ret.splice(at..at, cut);
+    }
+
+    ret.dedup();
+    ret
+}
+
+/// Points of a ring without the closing point, if it is repeated
+fn open_ring(points: &[Location]) -> Vec<Location> {
+    let mut ret = points.to_vec();
+    if ret.len() > 1 && ret.first() == ret.last() {
+        ret.pop();
+    }
+    ret
+}
+
+/// Twice the signed area of a ring, positive if counterclockwise
+fn signed_area(points: &[Location]) -> f64 {
+    let mut area = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        area += f64::from(a.x) * f64::from(b.y) - f64::from(b.x) * f64::from(a.y);
+    }
+    area
+}
+
+/// Escape characters that are not allowed in attribute fields
+fn escape_attr(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for ch in s.chars() {
+        if matches!(ch, ',' | '*' | '%' | '\\') || ch.is_control() {
+            for unit in ch.encode_utf16(&mut [0; 2]) {
+                write!(ret, "\\u{unit:04X}").unwrap();
+            }
+        } else {
+            ret.push(ch);
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: i32, y: i32, size: i32) -> Vec<Location> {
+        [(0, 0), (size, 0), (size, size), (0, size)]
+            .map(|(dx, dy)| Location::new(x + dx, y + dy))
+            .into()
+    }
+
+    #[test]
+    fn test_cut_in_holes() {
+        let ret = cut_in_holes(&square(0, 0, 10), &[square(1, 1, 2)]);
+        let expected = [
+            (0, 0),
+            (10, 0),
+            (10, 10),
+            (0, 10),
+            // Cut in from the left edge and go around the hole clockwise
+            (0, 1),
+            (1, 1),
+            (1, 3),
+            (3, 3),
+            (3, 1),
+            (1, 1),
+            (0, 1),
+        ]
+        .map(|(x, y)| Location::new(x, y));
+        assert_eq!(ret, expected);
+
+        // Holes are drawn in the same contour rather than cleared
+        let mut writer = GerberWriter::new(Location::default());
+        writer.region(&square(0, 0, 10), &[square(5, 5, 2), square(1, 1, 2)]);
+        let out = writer.finish(&[]);
+        assert_eq!(out.matches("G36*").count(), 1, "{out}");
+        assert!(!out.contains("%LPC*%"), "{out}");
+        assert!(out.contains("X0Y5D01*\nX5Y5D01*\nX5Y7D01*"), "{out}");
+    }
+}
@@ -0,0 +1,1395 @@
+//! IPC-2581 (revision C) design data
+//!
+//! A single XML file holds the layer stack, the features on every layer,
+//! padstacks, packages, placed components, the logical netlist and a bill of
+//! materials. Units are millimeters and coordinates are relative to the board
+//! origin.
+//!
+//! Packages are built from the first component placed with each footprint,
+//! and pins are named after pad designators. As with the Gerber output, texts
+//! are skipped. Slotted holes are written as round holes and no
+//! `HistoryRecord` is written, so that output is reproducible.
+
+use std::fmt::{self, Write};
+
+use quick_xml::escape::escape;
+
+use super::{expand, fill_corners, fmt_mm, pad_size, rotate, BoardData, FabOptions, GENERATOR};
+use crate::common::Location;
+use crate::pcb::record::{Arc, Pad, PadShape, PadSize, PcbRecord, RegionKind, Via};
+use crate::pcb::{Layer, StackLayerKind};
+
+/// Settings for IPC-2581 files
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ipc2581Options {
+    /// The design name and mask expansions
+    pub fab: FabOptions,
+    /// Project parameters as name and value, e.g. from the `.PrjPcb`
+    pub parameters: Vec<(String, String)>,
+    /// Bill of materials data. Components without an entry use their
+    /// footprint as the part number.
+    pub parts: Vec<BomPart>,
+}
+
+/// A line of the bill of materials for a single designator
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BomPart {
+    pub designator: String,
+    pub part_number: String,
+    pub description: String,
+    pub manufacturer: String,
+}
+
+/// Shapes in the standard primitive dictionary. Sizes are in nm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Primitive {
+    Circle(u32),
+    Rect { w: u32, h: u32 },
+    Oval { w: u32, h: u32 },
+    RoundRect { w: u32, h: u32, r: u32 },
+    Octagon { w: u32, h: u32 },
+}
+
+impl Primitive {
+    /// The unrotated shape of a pad grown by `expansion` on each side
+    fn from_pad(size: PadSize, expansion: i32) -> Option<Self> {
+        let (w, h) = (expand(size.x, expansion), expand(size.y, expansion));
+        if w == 0 || h == 0 {
+            return None;
+        }
+        let round = if w == h {
+            Self::Circle(w)
+        } else {
+            Self::Oval { w, h }
+        };
+
+        let ret = match size.shape {
+            PadShape::Round => round,
+            PadShape::Rect => Self::Rect { w, h },
+            PadShape::Octagonal => Self::Octagon { w, h },
+            PadShape::RoundRect { corner_radius } if corner_radius >= 100 => round,
+            PadShape::RoundRect { corner_radius } => {
+                let r = u64::from(w.min(h)) * u64::from(corner_radius) / 200;
+                match u32::try_from(r) {
+                    Ok(0) | Err(_) => Self::Rect { w, h },
+                    Ok(r) => Self::RoundRect { w, h, r },
+                }
+            }
+        };
+        Some(ret)
+    }
+
+    fn id(self) -> String {
+        let mm = |v: u32| Mm(v.into());
+        match self {
+            Self::Circle(d) => format!("CIRCLE_{}", mm(d)),
+            Self::Rect { w, h } => format!("RECT_{}X{}", mm(w), mm(h)),
+            Self::Oval { w, h } => format!("OVAL_{}X{}", mm(w), mm(h)),
+            Self::RoundRect { w, h, r } => format!("RECTROUND_{}X{}_R{}", mm(w), mm(h), mm(r)),
+            Self::Octagon { w, h } => format!("OCTAGON_{}X{}", mm(w), mm(h)),
+        }
+    }
+
+    fn write(self, xml: &mut Xml) {
+        let mm = |v: u32| Mm(v.into());
+        let corners: [(&str, &dyn fmt::Display); 4] = [
+            ("upperRight", &true),
+            ("upperLeft", &true),
+            ("lowerLeft", &true),
+            ("lowerRight", &true),
+        ];
+        match self {
+            Self::Circle(d) => xml.empty("Circle", &[("diameter", &mm(d))]),
+            Self::Rect { w, h } => {
+                xml.empty("RectCenter", &[("width", &mm(w)), ("height", &mm(h))]);
+            }
+            Self::Oval { w, h } => xml.empty("Oval", &[("width", &mm(w)), ("height", &mm(h))]),
+            Self::RoundRect { w, h, r } => {
+                let sizes: [(&str, &dyn fmt::Display); 3] =
+                    [("width", &mm(w)), ("height", &mm(h)), ("radius", &mm(r))];
+                xml.empty("RectRound", &[&sizes[..], &corners[..]].concat());
+            }
+            Self::Octagon { w, h } => {
+                // Matches the Gerber octagon, cut by a quarter of the short side
+                let chamfer = w.min(h) / 4;
+                let sizes: [(&str, &dyn fmt::Display); 3] = [
+                    ("width", &mm(w)),
+                    ("height", &mm(h)),
+                    ("chamfer", &mm(chamfer)),
+                ];
+                xml.empty("RectCham", &[&sizes[..], &corners[..]].concat());
+            }
+        }
+    }
+}
+
+/// A length in nm, displayed as mm without trailing zeros
+#[derive(Clone, Copy, Debug)]
+struct Mm(i64);
+
+impl fmt::Display for Mm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = fmt_mm(self.0);
+        f.write_str(s.trim_end_matches('0').trim_end_matches('.'))
+    }
+}
+
+/// An angle in degrees, displayed without trailing zeros
+#[derive(Clone, Copy, Debug)]
+struct Deg(f64);
+
+impl fmt::Display for Deg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = format!("{:.3}", self.0.rem_euclid(360.0));
+        f.write_str(s.trim_end_matches('0').trim_end_matches('.'))
+    }
+}
+
+type Attrs<'a> = &'a [(&'a str, &'a dyn fmt::Display)];
+
+/// Indented XML output
+#[derive(Debug, Default)]
+struct Xml {
+    out: String,
+    depth: usize,
+}
+
+impl Xml {
+    fn new(depth: usize) -> Self {
+        Self {
+            out: String::new(),
+            depth,
+        }
+    }
+
+    fn start(&mut self, name: &str, attrs: Attrs) {
+        self.tag(name, attrs, ">");
+        self.depth += 1;
+    }
+
+    fn end(&mut self, name: &str) {
+        self.depth -= 1;
+        self.indent();
+        writeln!(self.out, "</{name}>").unwrap();
+    }
+
+    fn empty(&mut self, name: &str, attrs: Attrs) {
+        self.tag(name, attrs, "/>");
+    }
+
+    fn tag(&mut self, name: &str, attrs: Attrs, close: &str) {
+        self.indent();
+        write!(self.out, "<{name}").unwrap();
+        for (key, val) in attrs {
+            write!(self.out, " {key}=\"{}\"", escape(val.to_string())).unwrap();
+        }
+        self.out.push_str(close);
+        self.out.push('\n');
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn location(&mut self, x: Mm, y: Mm) {
+        self.empty("Location", &[("x", &x), ("y", &y)]);
+    }
+
+    /// A closed polygon from points relative to the board origin
+    fn polygon(&mut self, name: &str, points: &[Location], origin: Location) {
+        let Some(first) = points.first() else {
+            return;
+        };
+        let (x, y) = relative(*first, origin);
+        self.start(name, &[]);
+        self.empty("PolyBegin", &[("x", &x), ("y", &y)]);
+        for point in points.iter().skip(1).chain(Some(first)) {
+            let (x, y) = relative(*point, origin);
+            self.empty("PolyStepSegment", &[("x", &x), ("y", &y)]);
+        }
+        self.end(name);
+    }
+
+    /// A rotation and mirroring, omitted if there is neither
+    fn xform(&mut self, rotation: f64, mirror: bool) {
+        let rotation = Deg(rotation);
+        match (rotation.0.rem_euclid(360.0) == 0.0, mirror) {
+            (true, false) => (),
+            (_, false) => self.empty("Xform", &[("rotation", &rotation)]),
+            (_, true) => self.empty("Xform", &[("rotation", &rotation), ("mirror", &true)]),
+        }
+    }
+
+    /// Start a set holding a single feature
+    fn start_features(&mut self, net: Option<&str>) {
+        match net {
+            Some(net) => self.start("Set", &[("net", &net)]),
+            None => self.start("Set", &[]),
+        }
+        self.start("Features", &[]);
+        self.location(Mm(0), Mm(0));
+    }
+
+    fn end_features(&mut self) {
+        self.end("Features");
+        self.end("Set");
+    }
+
+    fn line_desc(&mut self, width: u32) {
+        self.empty(
+            "LineDesc",
+            &[("lineEnd", &"ROUND"), ("lineWidth", &Mm(width.into()))],
+        );
+    }
+}
+
+/// A layer in the `CadData` section
+#[derive(Clone, Debug)]
+struct LayerDef {
+    name: String,
+    function: &'static str,
+    side: &'static str,
+    kind: LayerDefKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LayerDefKind {
+    /// A layer that primitives are placed on
+    Placed(Layer),
+    /// A dielectric in the stack
+    Dielectric {
+        thickness: u32,
+        dielectric_constant: Option<f64>,
+    },
+    /// Holes between two copper layers, as stack positions
+    Drill {
+        plated: bool,
+        from: usize,
+        to: usize,
+    },
+}
+
+/// Everything shared while writing the file
+struct Ctx<'a> {
+    board: &'a BoardData,
+    options: &'a Ipc2581Options,
+    name: String,
+    layers: Vec<LayerDef>,
+    /// Standard primitives by ID
+    dictionary: Vec<(String, Primitive)>,
+    /// The contents of each padstack definition, named `PADSTACK_n`
+    padstacks: Vec<String>,
+    /// Unique reference designator of each component
+    refdes: Vec<String>,
+    /// Pin number of each pad, in the order of `BoardData::pads`
+    pins: Vec<Option<String>>,
+    /// Part number of each component
+    part_numbers: Vec<String>,
+}
+
+/// Write a complete IPC-2581 file
+pub(crate) fn ipc2581(board: &BoardData, options: &Ipc2581Options) -> String {
+    let mut ctx = Ctx::new(board, options);
+    let pad_stacks: Vec<_> = board.pads().map(|pad| ctx.pad_stack(pad)).collect();
+    let via_stacks: Vec<_> = board.vias().map(|via| ctx.via_stack(via)).collect();
+
+    let mut ecad = Xml::new(1);
+    ctx.write_ecad(&mut ecad, &pad_stacks, &via_stacks);
+
+    let mut xml = Xml::new(0);
+    xml.out
+        .push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.start(
+        "IPC-2581",
+        &[
+            ("revision", &"C"),
+            ("xmlns", &"http://webstds.ipc.org/2581"),
+            ("xmlns:xsi", &"http://www.w3.org/2001/XMLSchema-instance"),
+        ],
+    );
+    ctx.write_content(&mut xml);
+    write_logistic_header(&mut xml);
+    ctx.write_bom(&mut xml);
+    xml.out.push_str(&ecad.out);
+    xml.end("IPC-2581");
+    xml.out
+}
+
+impl<'a> Ctx<'a> {
+    fn new(board: &'a BoardData, options: &'a Ipc2581Options) -> Self {
+        let refdes = unique_refdes(board);
+        let part_numbers = board
+            .components
+            .iter()
+            .zip(&refdes)
+            .map(|(component, refdes)| {
+                options
+                    .parts
+                    .iter()
+                    .find(|part| part.designator == *refdes && !part.part_number.is_empty())
+                    .map_or_else(
+                        || package_name(component.footprint()),
+                        |part| part.part_number.clone(),
+                    )
+            })
+            .collect();
+
+        Self {
+            board,
+            options,
+            name: options.fab.name.clone(),
+            layers: layer_defs(board),
+            dictionary: Vec::new(),
+            padstacks: Vec::new(),
+            refdes,
+            pins: pin_numbers(board),
+            part_numbers,
+        }
+    }
+
+    fn bom_name(&self) -> String {
+        format!("{}_BOM", self.name)
+    }
+
+    /// The name of a layer that primitives are placed on
+    fn layer_name(&self, layer: Layer) -> String {
+        self.layers
+            .iter()
+            .find(|def| def.kind == LayerDefKind::Placed(layer))
+            .map_or_else(|| ident(&layer.to_string()), |def| def.name.clone())
+    }
+
+    /// Add a primitive to the dictionary and return its ID
+    fn primitive(&mut self, primitive: Primitive) -> String {
+        let id = primitive.id();
+        if !self.dictionary.iter().any(|(existing, _)| *existing == id) {
+            self.dictionary.push((id.clone(), primitive));
+        }
+        id
+    }
+
+    /// Add a padstack definition and return its name
+    fn padstack(&mut self, contents: String) -> String {
+        let idx = self
+            .padstacks
+            .iter()
+            .position(|existing| *existing == contents)
+            .unwrap_or_else(|| {
+                self.padstacks.push(contents);
+                self.padstacks.len() - 1
+            });
+        format!("PADSTACK_{}", idx + 1)
+    }
+
+    /// The padstack of a pad, with the hole and the pad shape on each copper
+    /// layer. Rotation is applied where the pad is placed.
+    fn pad_stack(&mut self, pad: &Pad) -> String {
+        let mut xml = Xml::new(5);
+        if pad.hole_size > 0 {
+            let plating = if pad.plated { "PLATED" } else { "NONPLATED" };
+            let diameter = Mm(pad.hole_size.into());
+            xml.empty(
+                "PadstackHoleDef",
+                &[
+                    ("name", &format_args!("HOLE_{diameter}")),
+                    ("diameter", &diameter),
+                    ("platingStatus", &plating),
+                    ("plusTol", &0),
+                    ("minusTol", &0),
+                    ("x", &Mm(pad.hole_offset.x.into())),
+                    ("y", &Mm(pad.hole_offset.y.into())),
+                ],
+            );
+        }
+
+        let layers = if pad.layer == Layer::MultiLayer || pad.hole_size > 0 {
+            self.board.stack.clone()
+        } else {
+            vec![pad.layer]
+        };
+        for layer in layers.into_iter().filter(|layer| layer.is_copper()) {
+            if let Some(primitive) = Primitive::from_pad(pad_size(pad, layer), 0) {
+                self.padstack_pad(&mut xml, layer, primitive);
+            }
+        }
+
+        self.padstack(xml.out)
+    }
+
+    fn via_stack(&mut self, via: &Via) -> String {
+        let mut xml = Xml::new(5);
+        let diameter = Mm(via.hole_size.into());
+        xml.empty(
+            "PadstackHoleDef",
+            &[
+                ("name", &format_args!("VIA_{diameter}")),
+                ("diameter", &diameter),
+                ("platingStatus", &"VIA"),
+                ("plusTol", &0),
+                ("minusTol", &0),
+                ("x", &0),
+                ("y", &0),
+            ],
+        );
+
+        let (start, end) = self.board.via_span(via);
+        if via.diameter > 0 {
+            for pos in start..=end {
+                let layer = self.board.stack[pos];
+                self.padstack_pad(&mut xml, layer, Primitive::Circle(via.diameter));
+            }
+        }
+
+        self.padstack(xml.out)
+    }
+
+    fn padstack_pad(&mut self, xml: &mut Xml, layer: Layer, primitive: Primitive) {
+        let id = self.primitive(primitive);
+        xml.start(
+            "PadstackPadDef",
+            &[
+                ("layerRef", &self.layer_name(layer)),
+                ("padUse", &"REGULAR"),
+            ],
+        );
+        xml.location(Mm(0), Mm(0));
+        xml.empty("StandardPrimitiveRef", &[("id", &id)]);
+        xml.end("PadstackPadDef");
+    }
+
+    fn write_content(&self, xml: &mut Xml) {
+        xml.start("Content", &[("roleRef", &"Owner")]);
+        xml.empty("FunctionMode", &[("mode", &"ASSEMBLY")]);
+        xml.empty("StepRef", &[("name", &self.name)]);
+        for layer in &self.layers {
+            xml.empty("LayerRef", &[("name", &layer.name)]);
+        }
+        xml.empty("BomRef", &[("name", &self.bom_name())]);
+
+        xml.start("DictionaryStandard", &[("units", &"MILLIMETER")]);
+        for (id, primitive) in &self.dictionary {
+            xml.start("EntryStandard", &[("id", id)]);
+            primitive.write(xml);
+            xml.end("EntryStandard");
+        }
+        xml.end("DictionaryStandard");
+        xml.end("Content");
+    }
+
+    /// Components grouped into items by part number
+    fn write_bom(&self, xml: &mut Xml) {
+        let board = self.board;
+        let mut items: Vec<(&str, Vec<usize>)> = Vec::new();
+        for (idx, part_number) in self.part_numbers.iter().enumerate() {
+            match items.iter_mut().find(|(pn, _)| *pn == part_number) {
+                Some((_, components)) => components.push(idx),
+                None => items.push((part_number, vec![idx])),
+            }
+        }
+
+        xml.start("Bom", &[("name", &self.bom_name())]);
+        xml.start("BomHeader", &[("assembly", &self.name), ("revision", &"1")]);
+        xml.empty("StepRef", &[("name", &self.name)]);
+        xml.end("BomHeader");
+
+        for (part_number, components) in items {
+            let first = components[0];
+            let pin_count = board
+                .pads()
+                .filter(|pad| pad.component.map(usize::from) == Some(first))
+                .count();
+            xml.start(
+                "BomItem",
+                &[
+                    ("OEMDesignNumberRef", &part_number),
+                    ("quantity", &components.len()),
+                    ("pinCount", &pin_count),
+                    ("category", &"ELECTRICAL"),
+                ],
+            );
+            for &idx in &components {
+                let component = &board.components[idx];
+                let side = if component.is_bottom() {
+                    Layer::Bottom
+                } else {
+                    Layer::Top
+                };
+                xml.empty(
+                    "RefDes",
+                    &[
+                        ("name", &self.refdes[idx]),
+                        ("packageRef", &package_name(component.footprint())),
+                        ("populate", &true),
+                        ("layerRef", &self.layer_name(side)),
+                    ],
+                );
+            }
+            self.write_characteristics(xml, first);
+            xml.end("BomItem");
+        }
+
+        xml.end("Bom");
+    }
+
+    /// Description, manufacturer and footprint of the part used by a
+    /// component
+    fn write_characteristics(&self, xml: &mut Xml, component: usize) {
+        let part = self
+            .options
+            .parts
+            .iter()
+            .find(|part| part.designator == self.refdes[component]);
+        let description = part
+            .map(|part| part.description.as_str())
+            .filter(|desc| !desc.is_empty())
+            .unwrap_or(self.board.components[component].description());
+        let manufacturer = part.map_or("", |part| part.manufacturer.as_str());
+        let footprint = self.board.components[component].footprint();
+        let values = [
+            ("Description", description),
+            ("Manufacturer", manufacturer),
+            ("Footprint", footprint),
+        ];
+
+        xml.start("Characteristics", &[("category", &"ELECTRICAL")]);
+        for (name, value) in values.into_iter().filter(|(_, val)| !val.is_empty()) {
+            xml.empty(
+                "Textual",
+                &[
+                    ("definitionSource", &GENERATOR.1),
+                    ("textualCharacteristicName", &name),
+                    ("textualCharacteristicValue", &value),
+                ],
+            );
+        }
+        xml.end("Characteristics");
+    }
+
+    fn write_ecad(&mut self, xml: &mut Xml, pad_stacks: &[String], via_stacks: &[String]) {
+        xml.start("Ecad", &[("name", &self.name)]);
+        xml.start("CadHeader", &[("units", &"MILLIMETER")]);
+        for layer in &self.layers {
+            if let LayerDefKind::Dielectric {
+                dielectric_constant: Some(value),
+                ..
+            } = layer.kind
+            {
+                xml.start("Spec", &[("name", &format_args!("SPEC_{}", layer.name))]);
+                xml.start("Dielectric", &[("type", &"DIELECTRIC_CONSTANT")]);
+                xml.empty("Property", &[("value", &value)]);
+                xml.end("Dielectric");
+                xml.end("Spec");
+            }
+        }
+        xml.end("CadHeader");
+
+        xml.start("CadData", &[]);
+        for layer in &self.layers {
+            self.write_layer(xml, layer);
+        }
+        self.write_stackup(xml);
+        self.write_step(xml, pad_stacks, via_stacks);
+        xml.end("CadData");
+        xml.end("Ecad");
+    }
+
+    fn write_layer(&self, xml: &mut Xml, layer: &LayerDef) {
+        let attrs: [(&str, &dyn fmt::Display); 4] = [
+            ("name", &layer.name),
+            ("layerFunction", &layer.function),
+            ("side", &layer.side),
+            ("polarity", &"POSITIVE"),
+        ];
+        let LayerDefKind::Drill { from, to, .. } = layer.kind else {
+            xml.empty("Layer", &attrs);
+            return;
+        };
+        xml.start("Layer", &attrs);
+        xml.empty(
+            "Span",
+            &[
+                ("fromLayer", &self.layer_name(self.board.stack[from])),
+                ("toLayer", &self.layer_name(self.board.stack[to])),
+            ],
+        );
+        xml.end("Layer");
+    }
+
+    /// Every stack layer with a thickness, from top to bottom
+    fn write_stackup(&self, xml: &mut Xml) {
+        let layers: Vec<_> = self
+            .board
+            .stackup
+            .iter()
+            .filter(|layer| {
+                !matches!(
+                    layer.kind(),
+                    StackLayerKind::Overlay | StackLayerKind::Paste
+                )
+            })
+            .collect();
+        let total: i64 = layers
+            .iter()
+            .map(|layer| i64::from(layer.thickness()))
+            .sum();
+        let tolerances: [(&str, &dyn fmt::Display); 2] = [("tolPlus", &0), ("tolMinus", &0)];
+
+        let attrs: [(&str, &dyn fmt::Display); 3] = [
+            ("name", &"PRIMARY"),
+            ("overallThickness", &Mm(total)),
+            ("whereMeasured", &"METAL"),
+        ];
+        xml.start("Stackup", &[&attrs[..], &tolerances[..]].concat());
+        let attrs: [(&str, &dyn fmt::Display); 2] =
+            [("name", &"PRIMARY_GROUP"), ("thickness", &Mm(total))];
+        xml.start("StackupGroup", &[&attrs[..], &tolerances[..]].concat());
+
+        for (idx, stack_layer) in layers.into_iter().enumerate() {
+            let name = match stack_layer.layer() {
+                Some(layer) => self.layer_name(layer),
+                None => ident(stack_layer.name()),
+            };
+            let attrs: [(&str, &dyn fmt::Display); 3] = [
+                ("layerOrGroupRef", &name),
+                ("thickness", &Mm(stack_layer.thickness().into())),
+                ("sequence", &(idx + 1)),
+            ];
+            let attrs = [&attrs[..], &tolerances[..]].concat();
+            let has_spec =
+                stack_layer.layer().is_none() && stack_layer.dielectric_constant().is_some();
+            if has_spec {
+                xml.start("StackupLayer", &attrs);
+                xml.empty("SpecRef", &[("id", &format_args!("SPEC_{name}"))]);
+                xml.end("StackupLayer");
+            } else {
+                xml.empty("StackupLayer", &attrs);
+            }
+        }
+
+        xml.end("StackupGroup");
+        xml.end("Stackup");
+    }
+
+    fn write_step(&mut self, xml: &mut Xml, pad_stacks: &[String], via_stacks: &[String]) {
+        let board = self.board;
+        xml.start("Step", &[("name", &self.name)]);
+        for (name, value) in &self.options.parameters {
+            xml.empty(
+                "NonstandardAttribute",
+                &[("name", name), ("value", value), ("type", &"STRING")],
+            );
+        }
+        for (idx, contents) in self.padstacks.iter().enumerate() {
+            xml.start(
+                "PadStackDef",
+                &[("name", &format_args!("PADSTACK_{}", idx + 1))],
+            );
+            xml.out.push_str(contents);
+            xml.end("PadStackDef");
+        }
+        xml.empty("Datum", &[("x", &0), ("y", &0)]);
+
+        xml.start("Profile", &[]);
+        xml.polygon("Polygon", &board.outline, board.origin);
+        xml.end("Profile");
+
+        self.write_packages(xml);
+        self.write_components(xml);
+        self.write_nets(xml);
+
+        let layers = self.layers.clone();
+        for layer in &layers {
+            let mut features = Xml::new(xml.depth + 1);
+            self.write_features(&mut features, layer, pad_stacks, via_stacks);
+            if !features.out.is_empty() {
+                xml.start("LayerFeature", &[("layerRef", &layer.name)]);
+                xml.out.push_str(&features.out);
+                xml.end("LayerFeature");
+            }
+        }
+
+        xml.end("Step");
+    }
+
+    /// A package for each footprint, from the first component that uses it
+    fn write_packages(&mut self, xml: &mut Xml) {
+        let board = self.board;
+        let mut written: Vec<String> = Vec::new();
+
+        for (idx, component) in board.components.iter().enumerate() {
+            let name = package_name(component.footprint());
+            if written.contains(&name) {
+                continue;
+            }
+            let pins = self.package_pins(idx);
+
+            // The outline is the bounding box of the pads
+            let bounds = |f: fn(&PackagePin) -> i64| pins.iter().map(f).collect::<Vec<_>>();
+            let min_x = bounds(|pin| pin.x - pin.half_size).into_iter().min();
+            let max_x = bounds(|pin| pin.x + pin.half_size).into_iter().max();
+            let min_y = bounds(|pin| pin.y - pin.half_size).into_iter().min();
+            let max_y = bounds(|pin| pin.y + pin.half_size).into_iter().max();
+            let (min_x, min_y) = (min_x.unwrap_or(-500_000), min_y.unwrap_or(-500_000));
+            let (max_x, max_y) = (max_x.unwrap_or(500_000), max_y.unwrap_or(500_000));
+
+            let pin_one = pins
+                .iter()
+                .find(|pin| pin.number == "1")
+                .or(pins.first())
+                .map_or("", |pin| pin.number.as_str());
+            xml.start(
+                "Package",
+                &[
+                    ("name", &name),
+                    ("type", &"OTHER"),
+                    ("pinOne", &pin_one),
+                    ("height", &Mm(component.height().into())),
+                ],
+            );
+            xml.start("Outline", &[]);
+            xml.start("Polygon", &[]);
+            xml.empty("PolyBegin", &[("x", &Mm(min_x)), ("y", &Mm(min_y))]);
+            for (x, y) in [
+                (max_x, min_y),
+                (max_x, max_y),
+                (min_x, max_y),
+                (min_x, min_y),
+            ] {
+                xml.empty("PolyStepSegment", &[("x", &Mm(x)), ("y", &Mm(y))]);
+            }
+            xml.end("Polygon");
+            xml.line_desc(0);
+            xml.end("Outline");
+
+            for pin in pins {
+                xml.start(
+                    "Pin",
+                    &[
+                        ("number", &pin.number),
+                        ("type", &pin.kind),
+                        ("electricalType", &"ELECTRICAL"),
+                    ],
+                );
+                xml.xform(pin.rotation, false);
+                xml.location(Mm(pin.x), Mm(pin.y));
+                if let Some(id) = &pin.primitive {
+                    xml.empty("StandardPrimitiveRef", &[("id", id)]);
+                }
+                xml.end("Pin");
+            }
+
+            xml.end("Package");
+            written.push(name);
+        }
+    }
+
+    /// Pads of a component relative to its reference point, as placed on the
+    /// top side without rotation
+    fn package_pins(&mut self, component_idx: usize) -> Vec<PackagePin> {
+        let board = self.board;
+        let component = &board.components[component_idx];
+        let center = component.location();
+        let copper = if component.is_bottom() {
+            Layer::Bottom
+        } else {
+            Layer::Top
+        };
+        let mut ret = Vec::new();
+
+        for (pad_idx, pad) in board.pads().enumerate() {
+            if pad.component.map(usize::from) != Some(component_idx) {
+                continue;
+            }
+            let Some(number) = self.pins[pad_idx].clone() else {
+                continue;
+            };
+            let local = rotate(pad.location, center, -component.rotation());
+            let mut x = i64::from(local.x) - i64::from(center.x);
+            let mut rotation = pad.rotation - component.rotation();
+            if component.is_bottom() {
+                x = -x;
+                rotation = -rotation;
+            }
+            let size = pad_size(pad, copper);
+
+            ret.push(PackagePin {
+                number,
+                kind: if pad.hole_size > 0 { "THRU" } else { "SURFACE" },
+                x,
+                y: i64::from(local.y) - i64::from(center.y),
+                rotation,
+                half_size: i64::from(size.x.max(size.y) / 2),
+                primitive: Primitive::from_pad(size, 0).map(|shape| self.primitive(shape)),
+            });
+        }
+
+        ret
+    }
+
+    fn write_components(&self, xml: &mut Xml) {
+        let board = self.board;
+        for (idx, component) in board.components.iter().enumerate() {
+            let side = if component.is_bottom() {
+                Layer::Bottom
+            } else {
+                Layer::Top
+            };
+            let through = board
+                .pads()
+                .any(|pad| pad.component.map(usize::from) == Some(idx) && pad.hole_size > 0);
+            let mount = if through { "THMT" } else { "SMT" };
+
+            xml.start(
+                "Component",
+                &[
+                    ("refDes", &self.refdes[idx]),
+                    ("packageRef", &package_name(component.footprint())),
+                    ("layerRef", &self.layer_name(side)),
+                    ("mountType", &mount),
+                    ("part", &self.part_numbers[idx]),
+                ],
+            );
+            xml.xform(component.rotation(), component.is_bottom());
+            let (x, y) = relative(component.location(), board.origin);
+            xml.location(x, y);
+            xml.end("Component");
+        }
+    }
+
+    /// Every net with the component pins on it
+    fn write_nets(&self, xml: &mut Xml) {
+        let board = self.board;
+        for (idx, net) in board.nets.iter().enumerate() {
+            let pins: Vec<_> = board
+                .pads()
+                .zip(&self.pins)
+                .filter(|(pad, _)| pad.net.map(usize::from) == Some(idx))
+                .filter_map(|(pad, pin)| Some((pad.component?, pin.as_ref()?)))
+                .collect();
+            if pins.is_empty() {
+                xml.empty("LogicalNet", &[("name", net)]);
+                continue;
+            }
+            xml.start("LogicalNet", &[("name", net)]);
+            for (component, pin) in pins {
+                xml.empty(
+                    "PinRef",
+                    &[
+                        ("componentRef", &self.refdes[usize::from(component)]),
+                        ("pin", pin),
+                    ],
+                );
+            }
+            xml.end("LogicalNet");
+        }
+    }
+
+    fn write_features(
+        &mut self,
+        xml: &mut Xml,
+        layer: &LayerDef,
+        pad_stacks: &[String],
+        via_stacks: &[String],
+    ) {
+        match layer.kind {
+            LayerDefKind::Placed(placed) if self.board.stack.contains(&placed) => {
+                self.write_primitives(xml, placed, true);
+                self.write_copper_pads(xml, placed, pad_stacks, via_stacks);
+            }
+            LayerDefKind::Placed(placed @ (Layer::TopSolder | Layer::BottomSolder)) => {
+                self.write_primitives(xml, placed, false);
+                self.write_mask_openings(xml, placed == Layer::BottomSolder);
+            }
+            LayerDefKind::Placed(placed @ (Layer::TopPaste | Layer::BottomPaste)) => {
+                self.write_primitives(xml, placed, false);
+                self.write_paste_openings(xml, placed == Layer::BottomPaste);
+            }
+            LayerDefKind::Placed(placed) => self.write_primitives(xml, placed, false),
+            LayerDefKind::Drill { plated, from, to } => self.write_holes(xml, plated, (from, to)),
+            LayerDefKind::Dielectric { .. } => (),
+        }
+    }
+
+    /// Regions, fills, tracks and arcs on a layer
+    fn write_primitives(&self, xml: &mut Xml, layer: Layer, copper: bool) {
+        let board = self.board;
+        let origin = board.origin;
+        let net = |net: Option<&'a str>| net.filter(|_| copper);
+
+        for rec in &board.records {
+            match rec {
+                PcbRecord::Region(region)
+                    if region.layer == layer
+                        && !region.keepout
+                        && region.kind == RegionKind::Copper =>
+                {
+                    xml.start_features(net(board.poured_net(region.net, region.polygon)));
+                    xml.start("Contour", &[]);
+                    xml.polygon("Polygon", &region.outline, origin);
+                    for hole in &region.holes {
+                        xml.polygon("Cutout", hole, origin);
+                    }
+                    xml.end("Contour");
+                    xml.end_features();
+                }
+                PcbRecord::Fill(fill) if fill.layer == layer && !fill.keepout => {
+                    let corners = fill_corners(fill);
+                    xml.start_features(net(board.net(fill.net)));
+                    xml.start("Contour", &[]);
+                    xml.polygon("Polygon", &corners, origin);
+                    xml.end("Contour");
+                    xml.end_features();
+                }
+                PcbRecord::Track(track)
+                    if track.layer == layer && !track.keepout && track.width > 0 =>
+                {
+                    let (sx, sy) = relative(track.start, origin);
+                    let (ex, ey) = relative(track.end, origin);
+                    xml.start_features(net(board.poured_net(track.net, track.polygon)));
+                    xml.start(
+                        "Line",
+                        &[
+                            ("startX", &sx),
+                            ("startY", &sy),
+                            ("endX", &ex),
+                            ("endY", &ey),
+                        ],
+                    );
+                    xml.line_desc(track.width);
+                    xml.end("Line");
+                    xml.end_features();
+                }
+                PcbRecord::Arc(arc)
+                    if arc.layer == layer && !arc.keepout && arc.width > 0 && arc.radius > 0 =>
+                {
+                    write_arc(
+                        xml,
+                        arc,
+                        net(board.poured_net(arc.net, arc.polygon)),
+                        origin,
+                    );
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Pads and vias on a copper layer, referring to their padstacks
+    fn write_copper_pads(
+        &mut self,
+        xml: &mut Xml,
+        layer: Layer,
+        pad_stacks: &[String],
+        via_stacks: &[String],
+    ) {
+        let board = self.board;
+        let pos = board.stack.iter().position(|l| *l == layer);
+
+        for ((pad_idx, pad), padstack) in board.pads().enumerate().zip(pad_stacks) {
+            if pad.layer != layer && pad.layer != Layer::MultiLayer && pad.hole_size == 0 {
+                continue;
+            }
+            let Some(primitive) = Primitive::from_pad(pad_size(pad, layer), 0) else {
+                continue;
+            };
+            let id = self.primitive(primitive);
+            let pin_ref = pad
+                .component
+                .zip(self.pins[pad_idx].as_ref())
+                .map(|(component, pin)| (&*self.refdes[usize::from(component)], pin.as_str()));
+            let placed = PlacedPad {
+                padstack: Some(padstack),
+                location: pad.location,
+                rotation: pad.rotation,
+                primitive: &id,
+                pin: pin_ref,
+            };
+            placed.write(xml, board, board.net(pad.net), "TERMINATION");
+        }
+
+        for (via, padstack) in board.vias().zip(via_stacks) {
+            let (start, end) = board.via_span(via);
+            if via.diameter == 0 || !pos.is_some_and(|pos| (start..=end).contains(&pos)) {
+                continue;
+            }
+            let id = self.primitive(Primitive::Circle(via.diameter));
+            let placed = PlacedPad {
+                padstack: Some(padstack),
+                location: via.location,
+                rotation: 0.0,
+                primitive: &id,
+                pin: None,
+            };
+            placed.write(xml, board, board.net(via.net), "VIA");
+        }
+    }
+
+    /// Openings for pads and vias that aren't tented
+    fn write_mask_openings(&mut self, xml: &mut Xml, bottom: bool) {
+        let board = self.board;
+        let copper = if bottom { Layer::Bottom } else { Layer::Top };
+        let default = self.options.fab.solder_mask_expansion;
+
+        for pad in board.pads() {
+            let tented = if bottom {
+                pad.tented_bottom
+            } else {
+                pad.tented_top
+            };
+            if tented || (pad.layer != copper && pad.layer != Layer::MultiLayer) {
+                continue;
+            }
+            let expansion = pad.solder_mask_expansion.unwrap_or(default);
+            if let Some(primitive) = Primitive::from_pad(pad_size(pad, copper), expansion) {
+                self.write_opening(xml, pad.location, pad.rotation, primitive);
+            }
+        }
+
+        let last = board.stack.len().saturating_sub(1);
+        for via in board.vias() {
+            let tented = if bottom {
+                via.tented_bottom
+            } else {
+                via.tented_top
+            };
+            let (start, end) = board.via_span(via);
+            let reaches = if bottom { end == last } else { start == 0 };
+            let diameter = expand(via.diameter, default);
+            if !tented && reaches && diameter > 0 {
+                self.write_opening(xml, via.location, 0.0, Primitive::Circle(diameter));
+            }
+        }
+    }
+
+    /// Surface mount pads on one side
+    fn write_paste_openings(&mut self, xml: &mut Xml, bottom: bool) {
+        let copper = if bottom { Layer::Bottom } else { Layer::Top };
+        for pad in self.board.pads() {
+            if pad.hole_size != 0 || pad.layer != copper {
+                continue;
+            }
+            let expansion = pad
+                .paste_mask_expansion
+                .unwrap_or(self.options.fab.paste_mask_expansion);
+            if let Some(primitive) = Primitive::from_pad(pad_size(pad, copper), expansion) {
+                self.write_opening(xml, pad.location, pad.rotation, primitive);
+            }
+        }
+    }
+
+    fn write_opening(
+        &mut self,
+        xml: &mut Xml,
+        location: Location,
+        rotation: f64,
+        shape: Primitive,
+    ) {
+        let id = self.primitive(shape);
+        let placed = PlacedPad {
+            padstack: None,
+            location,
+            rotation,
+            primitive: &id,
+            pin: None,
+        };
+        placed.write(xml, self.board, None, "MASK");
+    }
+
+    /// Holes between two copper layers
+    fn write_holes(&self, xml: &mut Xml, plated: bool, span: (usize, usize)) {
+        let board = self.board;
+        let last = board.stack.len().saturating_sub(1);
+        let mut holes = Vec::new();
+
+        if span == (0, last) {
+            for pad in board
+                .pads()
+                .filter(|pad| pad.hole_size > 0 && pad.plated == plated)
+            {
+                let offset = pad
+                    .location
+                    .add_x(pad.hole_offset.x)
+                    .add_y(pad.hole_offset.y);
+                let location = rotate(offset, pad.location, pad.rotation);
+                let plating = if plated { "PLATED" } else { "NONPLATED" };
+                holes.push((board.net(pad.net), location, pad.hole_size, plating));
+            }
+        }
+        if plated {
+            for via in board.vias() {
+                if via.hole_size > 0 && board.via_span(via) == span {
+                    holes.push((board.net(via.net), via.location, via.hole_size, "VIA"));
+                }
+            }
+        }
+
+        for (idx, (net, location, diameter, plating)) in holes.into_iter().enumerate() {
+            match net {
+                Some(net) => xml.start("Set", &[("net", &net)]),
+                None => xml.start("Set", &[]),
+            }
+            let (x, y) = relative(location, board.origin);
+            xml.empty(
+                "Hole",
+                &[
+                    ("name", &format_args!("H{}", idx + 1)),
+                    ("diameter", &Mm(diameter.into())),
+                    ("platingStatus", &plating),
+                    ("plusTol", &0),
+                    ("minusTol", &0),
+                    ("x", &x),
+                    ("y", &y),
+                ],
+            );
+            xml.end("Set");
+        }
+    }
+}
+
+/// A pin in a package, with coordinates in nm
+struct PackagePin {
+    number: String,
+    /// `THRU` or `SURFACE`
+    kind: &'static str,
+    x: i64,
+    y: i64,
+    rotation: f64,
+    /// Half of the larger side of the pad
+    half_size: i64,
+    primitive: Option<String>,
+}
+
+/// A pad instance in a layer feature
+struct PlacedPad<'a> {
+    padstack: Option<&'a str>,
+    location: Location,
+    rotation: f64,
+    primitive: &'a str,
+    /// Component and pin
+    pin: Option<(&'a str, &'a str)>,
+}
+
+impl PlacedPad<'_> {
+    fn write(&self, xml: &mut Xml, board: &BoardData, net: Option<&str>, usage: &str) {
+        match net {
+            Some(net) => xml.start("Set", &[("net", &net), ("padUsage", &usage)]),
+            None => xml.start("Set", &[("padUsage", &usage)]),
+        }
+        match self.padstack {
+            Some(padstack) => xml.start("Pad", &[("padstackDefRef", &padstack)]),
+            None => xml.start("Pad", &[]),
+        }
+        xml.xform(self.rotation, false);
+        let (x, y) = relative(self.location, board.origin);
+        xml.location(x, y);
+        xml.empty("StandardPrimitiveRef", &[("id", &self.primitive)]);
+        if let Some((component, pin)) = self.pin {
+            xml.empty("PinRef", &[("componentRef", &component), ("pin", &pin)]);
+        }
+        xml.end("Pad");
+        xml.end("Set");
+    }
+}
+
+/// An arc as a set of its own. A full circle is written as two halves, each
+/// in its own set.
+fn write_arc(xml: &mut Xml, arc: &Arc, net: Option<&str>, origin: Location) {
+    let radius = i32::try_from(arc.radius).unwrap_or(i32::MAX);
+    let point = |angle: f64| rotate(arc.center.add_x(radius), arc.center, angle);
+    let sweep = (arc.end_angle - arc.start_angle).rem_euclid(360.0);
+    let segments = if sweep == 0.0 {
+        let mid = arc.start_angle + 180.0;
+        vec![(arc.start_angle, mid), (mid, arc.start_angle)]
+    } else {
+        vec![(arc.start_angle, arc.end_angle)]
+    };
+
+    for (start, end) in segments {
+        xml.start_features(net);
+        let (sx, sy) = relative(point(start), origin);
+        let (ex, ey) = relative(point(end), origin);
+        let (cx, cy) = relative(arc.center, origin);
+        xml.start(
+            "Arc",
+            &[
+                ("startX", &sx),
+                ("startY", &sy),
+                ("endX", &ex),
+                ("endY", &ey),
+                ("centerX", &cx),
+                ("centerY", &cy),
+                ("clockwise", &false),
+            ],
+        );
+        xml.line_desc(arc.width);
+        xml.end("Arc");
+        xml.end_features();
+    }
+}
+
+fn write_logistic_header(xml: &mut Xml) {
+    let (vendor, ..) = GENERATOR;
+    xml.start("LogisticHeader", &[]);
+    xml.empty("Role", &[("id", &"Owner"), ("roleFunction", &"SENDER")]);
+    xml.empty("Enterprise", &[("id", &vendor), ("code", &"NONE")]);
+    xml.empty(
+        "Person",
+        &[
+            ("name", &vendor),
+            ("enterpriseRef", &vendor),
+            ("roleRef", &"Owner"),
+        ],
+    );
+    xml.end("LogisticHeader");
+}
+
+/// Layers in stack order, followed by one drill layer per hole span
+fn layer_defs(board: &BoardData) -> Vec<LayerDef> {
+    let last = board.stack.len().saturating_sub(1);
+    let mut ret = Vec::new();
+
+    for stack_layer in &board.stackup {
+        let side = |layer: Layer| match layer {
+            Layer::Top => "TOP",
+            Layer::Bottom => "BOTTOM",
+            _ if layer.is_copper() => "INTERNAL",
+            _ if layer.is_bottom() => "BOTTOM",
+            _ => "TOP",
+        };
+        let (function, side, kind) = match (stack_layer.kind(), stack_layer.layer()) {
+            (_, Some(layer @ Layer::InternalPlane(_))) => ("PLANE", "INTERNAL", layer),
+            (StackLayerKind::Copper, Some(layer)) => ("SIGNAL", side(layer), layer),
+            (StackLayerKind::SolderMask, Some(layer)) => ("SOLDERMASK", side(layer), layer),
+            (StackLayerKind::Overlay, Some(layer)) => ("SILKSCREEN", side(layer), layer),
+            (StackLayerKind::Paste, Some(layer)) => ("SOLDERPASTE", side(layer), layer),
+            (_, Some(layer)) => ("DOCUMENT", side(layer), layer),
+            (kind, None) => {
+                let function = if kind == StackLayerKind::Prepreg {
+                    "DIELPREG"
+                } else {
+                    "DIELCORE"
+                };
+                ret.push(LayerDef {
+                    name: ident(stack_layer.name()),
+                    function,
+                    side: "INTERNAL",
+                    kind: LayerDefKind::Dielectric {
+                        thickness: stack_layer.thickness(),
+                        dielectric_constant: stack_layer.dielectric_constant(),
+                    },
+                });
+                continue;
+            }
+        };
+        ret.push(LayerDef {
+            name: ident(stack_layer.name()),
+            function,
+            side,
+            kind: LayerDefKind::Placed(kind),
+        });
+    }
+
+    let mut spans = Vec::new();
+    for pad in board.pads().filter(|pad| pad.hole_size > 0) {
+        spans.push((pad.plated, 0, last));
+    }
+    for via in board.vias().filter(|via| via.hole_size > 0) {
+        let (from, to) = board.via_span(via);
+        spans.push((true, from, to));
+    }
+    // Plated through holes first, then non-plated, then other spans
+    spans.sort_by_key(|&(plated, from, to)| ((from, to) != (0, last), !plated, from, to));
+    spans.dedup();
+
+    for (plated, from, to) in spans {
+        let name = match (plated, (from, to) == (0, last)) {
+            (true, true) => format!("DRILL_1-{}", last + 1),
+            (false, _) => format!("DRILL_1-{}_NPTH", last + 1),
+            (true, false) => format!("DRILL_{}-{}", from + 1, to + 1),
+        };
+        let side = match (from == 0, to == last) {
+            (true, true) => "ALL",
+            (true, false) => "TOP",
+            (false, true) => "BOTTOM",
+            (false, false) => "INTERNAL",
+        };
+        ret.push(LayerDef {
+            name,
+            function: "DRILL",
+            side,
+            kind: LayerDefKind::Drill { plated, from, to },
+        });
+    }
+
+    ret
+}
+
+/// Designators that are unique and not empty, falling back to `COMPn`
+fn unique_refdes(board: &BoardData) -> Vec<String> {
+    let mut ret: Vec<String> = Vec::new();
+    for (idx, component) in board.components.iter().enumerate() {
+        let designator = component.designator();
+        if designator.is_empty() || ret.iter().any(|existing| existing == designator) {
+            ret.push(format!("COMP{}", idx + 1));
+        } else {
+            ret.push(designator.to_owned());
+        }
+    }
+    ret
+}
+
+/// Pin numbers for pads that belong to a component. Pads without a
+/// designator are numbered and duplicates get a suffix.
+fn pin_numbers(board: &BoardData) -> Vec<Option<String>> {
+    let mut used: Vec<Vec<String>> = vec![Vec::new(); board.components.len()];
+    board
+        .pads()
+        .map(|pad| {
+            let names = used.get_mut(usize::from(pad.component?))?;
+            let base = if pad.designator.is_empty() {
+                (names.len() + 1).to_string()
+            } else {
+                pad.designator.to_string()
+            };
+            let mut name = base.clone();
+            let mut suffix = 1;
+            while names.contains(&name) {
+                suffix += 1;
+                name = format!("{base}_{suffix}");
+            }
+            names.push(name.clone());
+            Some(name)
+        })
+        .collect()
+}
+
+/// The package for a footprint name
+fn package_name(footprint: &str) -> String {
+    if footprint.is_empty() {
+        "PACKAGE".to_owned()
+    } else {
+        footprint.to_owned()
+    }
+}
+
+/// A name made of uppercase letters, digits and underscores
+fn ident(name: &str) -> String {
+    name.chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() {
+                ch.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn relative(location: Location, origin: Location) -> (Mm, Mm) {
+    (
+        Mm(i64::from(location.x) - i64::from(origin.x)),
+        Mm(i64::from(location.y) - i64::from(origin.y)),
+    )
+}
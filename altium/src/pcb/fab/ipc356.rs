@@ -0,0 +1,182 @@
+//! IPC-D-356A bare board test netlists
+//!
+//! Every pad and via is a test point. Records use fixed columns, metric units
+//! (`UNITS CUST 1`, so lengths are in µm) and coordinates relative to the
+//! board origin. Net names longer than the 14 character field are written as
+//! `NNAME` aliases.
+
+use std::fmt::Write;
+
+use super::{millidegrees, pad_size, BoardData, FabOptions, GENERATOR};
+use crate::common::Location;
+use crate::pcb::record::{Pad, Via};
+use crate::pcb::Layer;
+
+const NET_LEN: usize = 14;
+const REFDES_LEN: usize = 6;
+const PIN_LEN: usize = 4;
+/// Net name for pads without a net
+const NO_NET: &str = "N/C";
+
+/// A single test record
+struct TestPoint<'a> {
+    /// 317 for through holes, 327 for surface mount and 367 for non-plated
+    /// holes
+    code: u16,
+    net: &'a str,
+    refdes: &'a str,
+    pin: &'a str,
+    /// Hole diameter and plating
+    hole: Option<(u32, bool)>,
+    /// 0 for both sides, otherwise the copper layer number
+    access: usize,
+    location: Location,
+    size: (u32, u32),
+    rotation: f64,
+    /// 0 for none, 1 for primary side, 2 for secondary and 3 for both
+    mask: u8,
+}
+
+pub(crate) fn ipc356_netlist(board: &BoardData, options: &FabOptions) -> String {
+    let (_, app, version) = GENERATOR;
+    let mut ret = String::new();
+    writeln!(ret, "C  IPC-D-356A netlist generated by {app} {version}").unwrap();
+    writeln!(ret, "P  JOB   {}", options.name).unwrap();
+    ret.push_str("P  CODE  00\nP  UNITS CUST 1\nP  DIM   N\n");
+
+    // Long names are replaced with an alias, declared before first use
+    let mut aliases: Vec<&str> = Vec::new();
+    let mut net_name = |ret: &mut String, net: Option<u16>| -> String {
+        let Some(name) = board.net(net) else {
+            return NO_NET.to_owned();
+        };
+        if name.chars().count() <= NET_LEN && !name.contains(' ') {
+            return name.to_owned();
+        }
+        let idx = aliases.iter().position(|n| *n == name).unwrap_or_else(|| {
+            aliases.push(name);
+            writeln!(ret, "P  NNAME{:<5} {name}", aliases.len()).unwrap();
+            aliases.len() - 1
+        });
+        format!("NNAME{}", idx + 1)
+    };
+
+    for pad in board.pads() {
+        let net = net_name(&mut ret, pad.net);
+        let refdes = pad
+            .component
+            .and_then(|idx| board.components.get(usize::from(idx)))
+            .map_or("", |component| component.designator());
+        write_record(&mut ret, &pad_point(board, pad, &net, refdes), board.origin);
+    }
+
+    for via in board.vias() {
+        let net = net_name(&mut ret, via.net);
+        write_record(&mut ret, &via_point(board, via, &net), board.origin);
+    }
+
+    ret.push_str("999\n");
+    ret
+}
+
+fn pad_point<'a>(board: &BoardData, pad: &'a Pad, net: &'a str, refdes: &'a str) -> TestPoint<'a> {
+    let layer_count = board.stack.len();
+    let through = pad.hole_size > 0;
+    let bottom = pad.layer == Layer::Bottom;
+    let size = pad_size(pad, if bottom { Layer::Bottom } else { Layer::Top });
+    let mask = match (through, bottom) {
+        (true, _) => mask_sides(!pad.tented_top, !pad.tented_bottom),
+        (false, false) => mask_sides(!pad.tented_top, false),
+        (false, true) => mask_sides(false, !pad.tented_bottom),
+    };
+
+    TestPoint {
+        code: match (through, pad.plated) {
+            (false, _) => 327,
+            (true, true) => 317,
+            (true, false) => 367,
+        },
+        net,
+        refdes,
+        pin: &pad.designator,
+        hole: through.then_some((pad.hole_size, pad.plated)),
+        access: match (through, bottom) {
+            (true, _) => 0,
+            (false, false) => 1,
+            (false, true) => layer_count,
+        },
+        location: pad.location,
+        size: (size.x, size.y),
+        rotation: pad.rotation,
+        mask,
+    }
+}
+
+fn via_point<'a>(board: &BoardData, via: &Via, net: &'a str) -> TestPoint<'a> {
+    let last = board.stack.len().saturating_sub(1);
+    let (start, end) = board.via_span(via);
+    let access = match (start == 0, end == last) {
+        (true, true) => 0,
+        (true, false) => 1,
+        (false, _) => end + 1,
+    };
+
+    TestPoint {
+        code: 317,
+        net,
+        refdes: "VIA",
+        pin: "",
+        hole: Some((via.hole_size, true)),
+        access,
+        location: via.location,
+        size: (via.diameter, via.diameter),
+        rotation: 0.0,
+        mask: mask_sides(
+            start == 0 && !via.tented_top,
+            end == last && !via.tented_bottom,
+        ),
+    }
+}
+
+fn mask_sides(top: bool, bottom: bool) -> u8 {
+    u8::from(top) | (u8::from(bottom) << 1)
+}
+
+/// Write a record in the fixed column layout
+fn write_record(out: &mut String, point: &TestPoint, origin: Location) {
+    let (drill, plated) = match point.hole {
+        Some((size, plated)) => (
+            format!("D{:04}", to_um(size).min(9999)),
+            if plated { "P" } else { "U" },
+        ),
+        None => (String::new(), ""),
+    };
+    let x = i64::from(point.location.x) - i64::from(origin.x);
+    let y = i64::from(point.location.y) - i64::from(origin.y);
+    let rotation = millidegrees(point.rotation) / 1000;
+
+    writeln!(
+        out,
+        "{code}{net:<NET_LEN$}   {refdes:<REFDES_LEN$}-{pin:<PIN_LEN$} {drill:<5}{plated:<1}\
+         A{access:02}X{x:+07}Y{y:+07}X{w:04}Y{h:04}R{rotation:03} S{mask}",
+        code = point.code,
+        net = truncate(point.net, NET_LEN),
+        refdes = truncate(point.refdes, REFDES_LEN),
+        pin = truncate(point.pin, PIN_LEN),
+        access = point.access.min(99),
+        x = (x + x.signum() * 500) / 1000,
+        y = (y + y.signum() * 500) / 1000,
+        w = to_um(point.size.0).min(9999),
+        h = to_um(point.size.1).min(9999),
+        mask = point.mask,
+    )
+    .unwrap();
+}
+
+fn to_um(nm: u32) -> u32 {
+    nm.saturating_add(500) / 1000
+}
+
+fn truncate(s: &str, len: usize) -> &str {
+    s.char_indices().nth(len).map_or(s, |(idx, _)| &s[..idx])
+}
@@ -0,0 +1,81 @@
+//! Gerber job file (`.gbrjob`) writer
+//!
+//! The job file is JSON that lists every fabrication file with its function
+//! and polarity, along with general board properties. `CreationDate` is
+//! omitted so that output is reproducible.
+
+use std::fmt::Write;
+
+use super::GENERATOR;
+
+/// A file to list in the job
+#[derive(Clone, Debug)]
+pub(super) struct JobEntry<'a> {
+    pub path: &'a str,
+    pub function: &'a str,
+    pub polarity: Option<&'a str>,
+}
+
+/// Write the job file. `size` is the board's bounding box in nm.
+pub(super) fn write_job(
+    name: &str,
+    size: Option<(i64, i64)>,
+    layer_count: usize,
+    entries: &[JobEntry],
+) -> String {
+    let (vendor, app, version) = GENERATOR;
+    let mut ret = String::new();
+    ret.push_str("{\n  \"Header\": {\n    \"GenerationSoftware\": {\n");
+    writeln!(ret, "      \"Vendor\": {},", json_str(vendor)).unwrap();
+    writeln!(ret, "      \"Application\": {},", json_str(app)).unwrap();
+    writeln!(ret, "      \"Version\": {}", json_str(version)).unwrap();
+    ret.push_str("    }\n  },\n  \"GeneralSpecs\": {\n");
+    writeln!(
+        ret,
+        "    \"ProjectId\": {{\n      \"Name\": {}\n    }},",
+        json_str(name)
+    )
+    .unwrap();
+    if let Some((x, y)) = size {
+        writeln!(
+            ret,
+            "    \"Size\": {{\n      \"X\": {},\n      \"Y\": {}\n    }},",
+            super::fmt_mm(x),
+            super::fmt_mm(y)
+        )
+        .unwrap();
+    }
+    writeln!(ret, "    \"LayerNumber\": {layer_count}").unwrap();
+    ret.push_str("  },\n  \"FilesAttributes\": [");
+
+    for (idx, entry) in entries.iter().enumerate() {
+        ret.push_str(if idx == 0 { "\n" } else { ",\n" });
+        ret.push_str("    {\n");
+        writeln!(ret, "      \"Path\": {},", json_str(entry.path)).unwrap();
+        write!(ret, "      \"FileFunction\": {}", json_str(entry.function)).unwrap();
+        if let Some(polarity) = entry.polarity {
+            write!(ret, ",\n      \"FilePolarity\": {}", json_str(polarity)).unwrap();
+        }
+        ret.push_str("\n    }");
+    }
+
+    ret.push_str("\n  ]\n}\n");
+    ret
+}
+
+/// Quote and escape a JSON string
+fn json_str(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len() + 2);
+    ret.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            ch if ch.is_control() => write!(ret, "\\u{:04x}", u32::from(ch)).unwrap(),
+            ch => ret.push(ch),
+        }
+    }
+    ret.push('"');
+    ret
+}
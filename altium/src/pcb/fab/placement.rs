@@ -0,0 +1,135 @@
+//! Pick and place (centroid) files
+
+use std::fmt::Write;
+
+use super::BoardData;
+use crate::common::Location;
+use crate::pcb::record::PcbRecord;
+
+/// Settings for pick and place files
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlacementOptions {
+    /// Where coordinates are measured from
+    pub origin: PlacementOrigin,
+    pub units: Units,
+}
+
+/// The zero point for pick and place coordinates
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlacementOrigin {
+    /// The board's relative origin, as set in Altium
+    #[default]
+    Board,
+    /// Altium's absolute origin
+    Absolute,
+    /// A point relative to the absolute origin, in nm
+    Custom { x: i32, y: i32 },
+}
+
+/// Length units for text outputs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Units {
+    #[default]
+    Mm,
+    Mil,
+}
+
+impl Units {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Mm => "mm",
+            Self::Mil => "mil",
+        }
+    }
+
+    /// Format a length in nm
+    #[allow(clippy::cast_precision_loss)]
+    fn format(self, nm: i64) -> String {
+        match self {
+            Self::Mm => format!("{:.4}", nm as f64 / 1_000_000.0),
+            Self::Mil => format!("{:.2}", nm as f64 / 25_400.0),
+        }
+    }
+}
+
+/// Write a CSV with a row per component. The center is the middle of the
+/// component's pads, or its reference point if it has no pads.
+pub(crate) fn pick_and_place(board: &BoardData, options: &PlacementOptions) -> String {
+    let origin = match options.origin {
+        PlacementOrigin::Board => board.origin,
+        PlacementOrigin::Absolute => Location::default(),
+        PlacementOrigin::Custom { x, y } => Location::new(x, y),
+    };
+    let unit = options.units.name();
+
+    let mut ret = format!(
+        "Designator,Footprint,Description,Center-X({unit}),Center-Y({unit}),\
+         Ref-X({unit}),Ref-Y({unit}),Rotation,Side\n"
+    );
+
+    for (idx, component) in board.components.iter().enumerate() {
+        let center = pad_center(board, idx).unwrap_or(component.location());
+        let coord = |loc: Location| {
+            let x = i64::from(loc.x) - i64::from(origin.x);
+            let y = i64::from(loc.y) - i64::from(origin.y);
+            (options.units.format(x), options.units.format(y))
+        };
+        let (cx, cy) = coord(center);
+        let (rx, ry) = coord(component.location());
+        let rotation = component.rotation().rem_euclid(360.0);
+        let side = if component.is_bottom() {
+            "Bottom"
+        } else {
+            "Top"
+        };
+
+        writeln!(
+            ret,
+            "{},{},{},{cx},{cy},{rx},{ry},{},{side}",
+            csv_field(component.designator()),
+            csv_field(component.footprint()),
+            csv_field(component.description()),
+            format_angle(rotation),
+        )
+        .unwrap();
+    }
+
+    ret
+}
+
+/// Middle of the bounding box of a component's pad centers
+fn pad_center(board: &BoardData, component: usize) -> Option<Location> {
+    let mut pads = board.records.iter().filter_map(|rec| match rec {
+        PcbRecord::Pad(pad) if pad.component.map(usize::from) == Some(component) => {
+            Some(pad.location)
+        }
+        _ => None,
+    });
+
+    let first = pads.next()?;
+    let (min, max) = pads.fold((first, first), |(min, max), loc| {
+        (
+            Location::new(min.x.min(loc.x), min.y.min(loc.y)),
+            Location::new(max.x.max(loc.x), max.y.max(loc.y)),
+        )
+    });
+    Some(Location::new(
+        super::midpoint(min.x, max.x),
+        super::midpoint(min.y, max.y),
+    ))
+}
+
+/// Angles without trailing zeros, e.g. `90` or `22.5`
+fn format_angle(angle: f64) -> String {
+    let ret = format!("{angle:.2}");
+    ret.trim_end_matches('0').trim_end_matches('.').to_owned()
+}
+
+/// Quote a field if it contains a separator, quote or newline
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
@@ -0,0 +1,150 @@
+//! A single footprint in a PCB library
+
+use super::record::{parse_all_records, parse_len, ComponentBody, Pad, PcbRecord, Properties};
+use super::PcbDrawCtx;
+use crate::draw::{Draw, RenderOptions, Svg, SvgCtx};
+use crate::error::AddContext;
+use crate::parse::{extract_sized_buf, BufLenMatch};
+use crate::{Error, ErrorKind};
+
+/// Representation of a footprint
+#[derive(Clone, Debug)]
+pub struct Footprint {
+    pub(crate) name: Box<str>,
+    pub(crate) description: Box<str>,
+    pub(crate) height: i32,
+    pub(crate) records: Vec<PcbRecord>,
+}
+
+impl Footprint {
+    /// Create a footprint from its `Data`, `Parameters` and `WideStrings`
+    /// streams
+    pub(crate) fn from_streams(
+        sec_key: &str,
+        data: &[u8],
+        params: &[u8],
+        wide_strings: &[u8],
+    ) -> Result<Self, Error> {
+        let params = parse_sized_properties(params).context("parsing footprint parameters")?;
+        let wide_strings =
+            parse_wide_strings(wide_strings).context("parsing footprint wide strings")?;
+
+        // The data stream starts with the footprint's (storage) name
+        let (_, data) = extract_sized_buf(data, BufLenMatch::U32, false)
+            .or_context(|| format!("reading name of `{sec_key}`"))?;
+        let mut records = parse_all_records(data, sec_key)?;
+
+        // Text stored in the record is narrow, use the UTF-16 version if there
+        // is one
+        for record in &mut records {
+            if let PcbRecord::Text(text) = record {
+                if let Some(s) = text
+                    .widestring_index
+                    .and_then(|idx| wide_strings.iter().find(|(i, _)| *i == idx))
+                {
+                    text.text = s.1.clone();
+                }
+            }
+        }
+
+        let name = params.get("PATTERN").unwrap_or(sec_key);
+
+        Ok(Self {
+            name: name.into(),
+            description: params.get_str("DESCRIPTION"),
+            height: params
+                .get("HEIGHT")
+                .map_or(Ok(0), parse_len)
+                .context("parsing footprint height")?,
+            records,
+        })
+    }
+
+    /// The name of this footprint
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// This footprint's description
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Height of the footprint's component, in nm
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// All primitives in this footprint
+    pub fn records(&self) -> &[PcbRecord] {
+        &self.records
+    }
+
+    /// Draw this footprint to a SVG
+    pub fn svg(&self) -> Svg {
+        self.svg_with_options(&RenderOptions::default())
+    }
+
+    /// Draw this footprint to a SVG using the given theme and scale
+    pub fn svg_with_options(&self, options: &RenderOptions) -> Svg {
+        self.svg_with_ctx(&PcbDrawCtx::two_layer(options))
+    }
+
+    /// Draw this footprint to a SVG with the given layer visibility, colors
+    /// and order
+    pub fn svg_with_ctx(&self, ctx: &PcbDrawCtx<'_>) -> Svg {
+        let mut draw = SvgCtx::with_options(ctx.options);
+        draw.set_background(ctx.background());
+        self.draw(&mut draw, ctx);
+        draw.svg()
+    }
+
+    /// Iterate over this footprint's pads
+    pub fn pads(&self) -> impl Iterator<Item = &Pad> {
+        self.records.iter().filter_map(|record| match record {
+            PcbRecord::Pad(pad) => Some(pad),
+            _ => None,
+        })
+    }
+
+    /// Iterate over this footprint's 3D bodies
+    pub fn bodies(&self) -> impl Iterator<Item = &ComponentBody> {
+        self.records.iter().filter_map(|record| match record {
+            PcbRecord::ComponentBody(body) => Some(body),
+            _ => None,
+        })
+    }
+}
+
+/// Parse a property stream with a 4-byte length header
+pub(crate) fn parse_sized_properties(buf: &[u8]) -> Result<Properties, Error> {
+    let (buf, _) = extract_sized_buf(buf, BufLenMatch::U32, false)?;
+    Ok(Properties::parse(buf)?)
+}
+
+/// Wide strings are stored as `ENCODEDTEXT<n>=<u16>,<u16>,...`
+fn parse_wide_strings(buf: &[u8]) -> Result<Vec<(u32, Box<str>)>, Error> {
+    const PFX: &str = "ENCODEDTEXT";
+
+    if buf.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let props = parse_sized_properties(buf)?;
+    let mut ret = Vec::new();
+
+    for (key, val) in props.iter() {
+        let Some(idx) = key.strip_prefix(PFX).and_then(|v| v.parse().ok()) else {
+            continue;
+        };
+        let units = val
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<u16>, _>>()
+            .map_err(|e| ErrorKind::ExpectedInt(val.into(), e))?;
+        ret.push((idx, String::from_utf16_lossy(&units).into()));
+    }
+
+    Ok(ret)
+}
@@ -0,0 +1,596 @@
+//! PCB layers
+
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::record::{Properties, Reader};
+use crate::error::{AddContext, ErrorKind};
+use crate::Error;
+
+/// Number of signal layers between top and bottom
+pub const MID_LAYER_COUNT: u8 = 30;
+/// Number of internal plane layers
+pub const PLANE_LAYER_COUNT: u8 = 16;
+/// Number of mechanical layers addressable with a V6 layer ID
+pub const MECHANICAL_LAYER_COUNT: u8 = 16;
+/// Number of mechanical layers addressable with a V7 layer ID
+pub const V7_MECHANICAL_LAYER_COUNT: u8 = 32;
+
+/// A layer that a PCB primitive can be placed on
+///
+/// Numbered variants are 1-indexed, e.g. `Mid(1)` is "Mid-Layer 1". Mechanical
+/// layers above 16 only have a V7 ID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Layer {
+    Top,
+    Mid(u8),
+    Bottom,
+    TopOverlay,
+    BottomOverlay,
+    TopPaste,
+    BottomPaste,
+    TopSolder,
+    BottomSolder,
+    InternalPlane(u8),
+    DrillGuide,
+    KeepOut,
+    Mechanical(u8),
+    DrillDrawing,
+    /// Primitives that exist on all copper layers, e.g. through hole pads
+    MultiLayer,
+    Connections,
+    Background,
+    DrcErrors,
+    Highlight,
+    GridColor1,
+    GridColor10,
+    PadHoles,
+    ViaHoles,
+    /// An ID we don't know about
+    Unknown(u8),
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Self::Unknown(0)
+    }
+}
+
+impl Layer {
+    /// Interpret a layer ID as used by binary records
+    pub fn from_v6_id(id: u8) -> Self {
+        const MID_END: u8 = 1 + MID_LAYER_COUNT;
+        const PLANE_START: u8 = 39;
+        const PLANE_END: u8 = PLANE_START + PLANE_LAYER_COUNT - 1;
+        const MECH_START: u8 = 57;
+        const MECH_END: u8 = MECH_START + MECHANICAL_LAYER_COUNT - 1;
+
+        match id {
+            1 => Self::Top,
+            2..=MID_END => Self::Mid(id - 1),
+            32 => Self::Bottom,
+            33 => Self::TopOverlay,
+            34 => Self::BottomOverlay,
+            35 => Self::TopPaste,
+            36 => Self::BottomPaste,
+            37 => Self::TopSolder,
+            38 => Self::BottomSolder,
+            PLANE_START..=PLANE_END => Self::InternalPlane(id - PLANE_START + 1),
+            55 => Self::DrillGuide,
+            56 => Self::KeepOut,
+            MECH_START..=MECH_END => Self::Mechanical(id - MECH_START + 1),
+            73 => Self::DrillDrawing,
+            74 => Self::MultiLayer,
+            75 => Self::Connections,
+            76 => Self::Background,
+            77 => Self::DrcErrors,
+            78 => Self::Highlight,
+            79 => Self::GridColor1,
+            80 => Self::GridColor10,
+            81 => Self::PadHoles,
+            82 => Self::ViaHoles,
+            _ => Self::Unknown(id),
+        }
+    }
+
+    /// Interpret a layer ID as used by the layer stack, e.g. `16777217` for
+    /// the top layer. The high 16 bits are the layer group and the low 16
+    /// bits a number within it. Dielectrics and unknown groups give `None`.
+    pub fn from_v7_id(id: u32) -> Option<Self> {
+        let group = id >> 16;
+        let num = id & 0xffff;
+        let small = u8::try_from(num).ok();
+
+        let ret = match (group, num) {
+            (0x0100, 1) => Self::Top,
+            (0x0100, 0xffff) => Self::Bottom,
+            (0x0100, _) => Self::Mid(small?.checked_sub(1)?),
+            (0x0101, _) => Self::InternalPlane(small?),
+            (0x0102, _) => {
+                Self::Mechanical(small.filter(|n| (1..=V7_MECHANICAL_LAYER_COUNT).contains(n))?)
+            }
+            (0x0103, 6) => Self::TopOverlay,
+            (0x0103, 7) => Self::BottomOverlay,
+            (0x0103, 8) => Self::TopPaste,
+            (0x0103, 9) => Self::BottomPaste,
+            (0x0103, 10) => Self::TopSolder,
+            (0x0103, 11) => Self::BottomSolder,
+            (0x0103, 12) => Self::DrillGuide,
+            (0x0103, 13) => Self::KeepOut,
+            (0x0103, 14) => Self::DrillDrawing,
+            (0x0103, 15) => Self::MultiLayer,
+            (0x0103, 16) => Self::Connections,
+            (0x0103, 17) => Self::Background,
+            (0x0103, 18) => Self::DrcErrors,
+            (0x0103, 19) => Self::Highlight,
+            (0x0103, 20) => Self::GridColor1,
+            (0x0103, 21) => Self::GridColor10,
+            (0x0103, 22) => Self::PadHoles,
+            (0x0103, 23) => Self::ViaHoles,
+            _ => return None,
+        };
+        Some(ret)
+    }
+
+    /// The ID used by binary records, `None` for mechanical layers that only
+    /// exist in V7
+    pub fn to_v6_id(self) -> Option<u8> {
+        let ret = match self {
+            Self::Top => 1,
+            Self::Mid(n) if (1..=MID_LAYER_COUNT).contains(&n) => n + 1,
+            Self::Bottom => 32,
+            Self::TopOverlay => 33,
+            Self::BottomOverlay => 34,
+            Self::TopPaste => 35,
+            Self::BottomPaste => 36,
+            Self::TopSolder => 37,
+            Self::BottomSolder => 38,
+            Self::InternalPlane(n) if (1..=PLANE_LAYER_COUNT).contains(&n) => n + 38,
+            Self::DrillGuide => 55,
+            Self::KeepOut => 56,
+            Self::Mechanical(n) if (1..=MECHANICAL_LAYER_COUNT).contains(&n) => n + 56,
+            Self::DrillDrawing => 73,
+            Self::MultiLayer => 74,
+            Self::Connections => 75,
+            Self::Background => 76,
+            Self::DrcErrors => 77,
+            Self::Highlight => 78,
+            Self::GridColor1 => 79,
+            Self::GridColor10 => 80,
+            Self::PadHoles => 81,
+            Self::ViaHoles => 82,
+            Self::Unknown(id) => id,
+            Self::Mid(_) | Self::InternalPlane(_) | Self::Mechanical(_) => return None,
+        };
+        Some(ret)
+    }
+
+    /// The ID used by the layer stack, the inverse of [`Layer::from_v7_id`]
+    pub fn to_v7_id(self) -> Option<u32> {
+        let (group, num) = match self {
+            Self::Top => (0x0100, 1),
+            Self::Mid(n) => (0x0100, u32::from(n) + 1),
+            Self::Bottom => (0x0100, 0xffff),
+            Self::InternalPlane(n) => (0x0101, u32::from(n)),
+            Self::Mechanical(n) => (0x0102, u32::from(n)),
+            Self::TopOverlay => (0x0103, 6),
+            Self::BottomOverlay => (0x0103, 7),
+            Self::TopPaste => (0x0103, 8),
+            Self::BottomPaste => (0x0103, 9),
+            Self::TopSolder => (0x0103, 10),
+            Self::BottomSolder => (0x0103, 11),
+            Self::DrillGuide => (0x0103, 12),
+            Self::KeepOut => (0x0103, 13),
+            Self::DrillDrawing => (0x0103, 14),
+            Self::MultiLayer => (0x0103, 15),
+            Self::Connections => (0x0103, 16),
+            Self::Background => (0x0103, 17),
+            Self::DrcErrors => (0x0103, 18),
+            Self::Highlight => (0x0103, 19),
+            Self::GridColor1 => (0x0103, 20),
+            Self::GridColor10 => (0x0103, 21),
+            Self::PadHoles => (0x0103, 22),
+            Self::ViaHoles => (0x0103, 23),
+            Self::Unknown(_) => return None,
+        };
+        Some((group << 16) | num)
+    }
+
+    /// Parse a layer identifier as used in properties, e.g. `TOP`, `MID3` or
+    /// `MECHANICAL13`
+    pub fn from_ident(ident: &str) -> Option<Self> {
+        let numbered = |pfx: &str| ident.strip_prefix(pfx).and_then(|n| n.parse::<u8>().ok());
+
+        let ret = match ident {
+            "TOP" => Self::Top,
+            "BOTTOM" => Self::Bottom,
+            "TOPOVERLAY" => Self::TopOverlay,
+            "BOTTOMOVERLAY" => Self::BottomOverlay,
+            "TOPPASTE" => Self::TopPaste,
+            "BOTTOMPASTE" => Self::BottomPaste,
+            "TOPSOLDER" => Self::TopSolder,
+            "BOTTOMSOLDER" => Self::BottomSolder,
+            "DRILLGUIDE" => Self::DrillGuide,
+            "KEEPOUT" => Self::KeepOut,
+            "DRILLDRAWING" => Self::DrillDrawing,
+            "MULTILAYER" => Self::MultiLayer,
+            _ => {
+                if let Some(n) = numbered("MID") {
+                    Self::Mid(n)
+                } else if let Some(n) = numbered("PLANE") {
+                    Self::InternalPlane(n)
+                } else if let Some(n) = numbered("MECHANICAL") {
+                    Self::Mechanical(n)
+                } else {
+                    return None;
+                }
+            }
+        };
+
+        Some(ret)
+    }
+
+    /// True for signal and plane layers
+    pub fn is_copper(self) -> bool {
+        matches!(
+            self,
+            Self::Top | Self::Mid(_) | Self::Bottom | Self::InternalPlane(_) | Self::MultiLayer
+        )
+    }
+
+    pub fn is_mechanical(self) -> bool {
+        matches!(self, Self::Mechanical(_))
+    }
+
+    /// True for layers that belong to the bottom side of the board
+    pub fn is_bottom(self) -> bool {
+        matches!(
+            self,
+            Self::Bottom | Self::BottomOverlay | Self::BottomPaste | Self::BottomSolder
+        )
+    }
+}
+
+impl fmt::Display for Layer {
+    /// The layer's default name, as shown in Altium
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Top => f.write_str("Top Layer"),
+            Self::Mid(n) => write!(f, "Mid-Layer {n}"),
+            Self::Bottom => f.write_str("Bottom Layer"),
+            Self::TopOverlay => f.write_str("Top Overlay"),
+            Self::BottomOverlay => f.write_str("Bottom Overlay"),
+            Self::TopPaste => f.write_str("Top Paste"),
+            Self::BottomPaste => f.write_str("Bottom Paste"),
+            Self::TopSolder => f.write_str("Top Solder"),
+            Self::BottomSolder => f.write_str("Bottom Solder"),
+            Self::InternalPlane(n) => write!(f, "Internal Plane {n}"),
+            Self::DrillGuide => f.write_str("Drill Guide"),
+            Self::KeepOut => f.write_str("Keep-Out Layer"),
+            Self::Mechanical(n) => write!(f, "Mechanical {n}"),
+            Self::DrillDrawing => f.write_str("Drill Drawing"),
+            Self::MultiLayer => f.write_str("Multi-Layer"),
+            Self::Connections => f.write_str("Connections"),
+            Self::Background => f.write_str("Background"),
+            Self::DrcErrors => f.write_str("DRC Error Markers"),
+            Self::Highlight => f.write_str("Highlight"),
+            Self::GridColor1 => f.write_str("Visible Grid 1"),
+            Self::GridColor10 => f.write_str("Visible Grid 10"),
+            Self::PadHoles => f.write_str("Pad Holes"),
+            Self::ViaHoles => f.write_str("Via Holes"),
+            Self::Unknown(id) => write!(f, "Unknown Layer {id}"),
+        }
+    }
+}
+
+/// What a mechanical layer is used for, as set in the layer stack manager
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MechanicalKind {
+    AssemblyTop,
+    AssemblyBottom,
+    AssemblyNotes,
+    Board,
+    CoatingTop,
+    CoatingBottom,
+    ComponentCenterTop,
+    ComponentCenterBottom,
+    ComponentOutlineTop,
+    ComponentOutlineBottom,
+    CourtyardTop,
+    CourtyardBottom,
+    DesignatorTop,
+    DesignatorBottom,
+    Dimensions,
+    DimensionsTop,
+    DimensionsBottom,
+    FabricationNotes,
+    GluePointsTop,
+    GluePointsBottom,
+    GoldPlatingTop,
+    GoldPlatingBottom,
+    ValueTop,
+    ValueBottom,
+    VCut,
+    Body3DTop,
+    Body3DBottom,
+}
+
+impl MechanicalKind {
+    /// Every kind in order of its numeric code, starting at 1
+    const ALL: [(Self, &'static str); 27] = [
+        (Self::AssemblyTop, "AssemblyTop"),
+        (Self::AssemblyBottom, "AssemblyBottom"),
+        (Self::AssemblyNotes, "AssemblyNotes"),
+        (Self::Board, "Board"),
+        (Self::CoatingTop, "CoatingTop"),
+        (Self::CoatingBottom, "CoatingBottom"),
+        (Self::ComponentCenterTop, "ComponentCenterTop"),
+        (Self::ComponentCenterBottom, "ComponentCenterBottom"),
+        (Self::ComponentOutlineTop, "ComponentOutlineTop"),
+        (Self::ComponentOutlineBottom, "ComponentOutlineBottom"),
+        (Self::CourtyardTop, "CourtyardTop"),
+        (Self::CourtyardBottom, "CourtyardBottom"),
+        (Self::DesignatorTop, "DesignatorTop"),
+        (Self::DesignatorBottom, "DesignatorBottom"),
+        (Self::Dimensions, "Dimensions"),
+        (Self::DimensionsTop, "DimensionsTop"),
+        (Self::DimensionsBottom, "DimensionsBottom"),
+        (Self::FabricationNotes, "FabNotes"),
+        (Self::GluePointsTop, "GluePointsTop"),
+        (Self::GluePointsBottom, "GluePointsBottom"),
+        (Self::GoldPlatingTop, "GoldPlatingTop"),
+        (Self::GoldPlatingBottom, "GoldPlatingBottom"),
+        (Self::ValueTop, "ValueTop"),
+        (Self::ValueBottom, "ValueBottom"),
+        (Self::VCut, "VCut"),
+        (Self::Body3DTop, "3DBodyTop"),
+        (Self::Body3DBottom, "3DBodyBottom"),
+    ];
+
+    /// Parse a kind as written in `MECHKIND` properties, e.g. `CourtyardTop`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name.trim()))
+            .map(|(kind, _)| *kind)
+    }
+
+    /// Interpret the numeric code used by the `LayerKindMapping` stream
+    pub fn from_code(code: u32) -> Option<Self> {
+        let idx = usize::try_from(code.checked_sub(1)?).ok()?;
+        Self::ALL.get(idx).map(|(kind, _)| *kind)
+    }
+
+    /// The name used in `MECHKIND` properties
+    pub fn name(self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(kind, _)| *kind == self)
+            .map_or("", |(_, name)| name)
+    }
+
+    /// True for kinds that belong to the bottom side of the board
+    pub fn is_bottom(self) -> bool {
+        self.name().ends_with("Bottom")
+    }
+}
+
+impl fmt::Display for MechanicalKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A layer as set up in a document, with its user-visible name
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerInfo {
+    layer: Layer,
+    name: Box<str>,
+    enabled: bool,
+    used: bool,
+    mechanical_kind: Option<MechanicalKind>,
+}
+
+impl LayerInfo {
+    pub fn layer(&self) -> Layer {
+        self.layer
+    }
+
+    /// The name set by the user, e.g. `Top Courtyard`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the layer is turned on. Only mechanical layers can be
+    /// disabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether any primitive is placed on this layer
+    pub fn is_used(&self) -> bool {
+        self.used
+    }
+
+    /// What a mechanical layer is used for, if it has been assigned a kind
+    pub fn mechanical_kind(&self) -> Option<MechanicalKind> {
+        self.mechanical_kind
+    }
+}
+
+/// The layers of a document along with their names, mechanical layer kinds and
+/// layer pairs
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BoardLayers {
+    layers: Vec<LayerInfo>,
+    mechanical_pairs: Vec<(Layer, Layer)>,
+    drill_pairs: Vec<(Layer, Layer)>,
+}
+
+impl BoardLayers {
+    /// Every layer the document defines
+    pub fn layers(&self) -> &[LayerInfo] {
+        &self.layers
+    }
+
+    pub fn get(&self, layer: Layer) -> Option<&LayerInfo> {
+        self.layers.iter().find(|info| info.layer == layer)
+    }
+
+    /// The user-visible name of a layer, or its default name if the document
+    /// doesn't define it
+    pub fn name(&self, layer: Layer) -> String {
+        self.get(layer)
+            .map_or_else(|| layer.to_string(), |info| info.name.to_string())
+    }
+
+    pub fn mechanical_kind(&self, layer: Layer) -> Option<MechanicalKind> {
+        self.get(layer).and_then(LayerInfo::mechanical_kind)
+    }
+
+    /// The first mechanical layer with the given kind
+    pub fn find_kind(&self, kind: MechanicalKind) -> Option<Layer> {
+        self.layers
+            .iter()
+            .find(|info| info.mechanical_kind == Some(kind))
+            .map(|info| info.layer)
+    }
+
+    /// Mechanical layers that are paired, so that primitives on one move to
+    /// the other when a component is flipped to the other side of the board
+    pub fn mechanical_pairs(&self) -> &[(Layer, Layer)] {
+        &self.mechanical_pairs
+    }
+
+    /// The layer that `layer` is paired with, if it is in a mechanical pair
+    pub fn paired_layer(&self, layer: Layer) -> Option<Layer> {
+        self.mechanical_pairs.iter().find_map(|&(a, b)| {
+            if a == layer {
+                Some(b)
+            } else if b == layer {
+                Some(a)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Copper layers that drills run between, as (start, end)
+    pub fn drill_pairs(&self) -> &[(Layer, Layer)] {
+        &self.drill_pairs
+    }
+}
+
+/// Read the layer definitions in a `Board6` or library header, then fill in
+/// mechanical kinds from a `LayerKindMapping` stream if there is one
+///
+/// Layers are listed by `V9_CACHE_LAYERn_*` properties in newer files, older
+/// files only have `LAYERn*` properties keyed by V6 ID.
+pub(crate) fn parse_board_layers(
+    props: &Properties,
+    kind_mapping: Option<&[u8]>,
+) -> Result<BoardLayers, Error> {
+    let mut layers = Vec::new();
+
+    for idx in 0.. {
+        let key = |name: &str| format!("V9_CACHE_LAYER{idx}_{name}");
+        let Some(id) = props.get(&key("LAYERID")) else {
+            break;
+        };
+        let id: u32 = id
+            .trim()
+            .parse()
+            .map_err(|e| ErrorKind::ExpectedInt(id.into(), e))
+            .or_context(|| format!("reading layer {idx}"))?;
+        // Dielectrics are part of the stack, not something to place on
+        let Some(layer) = Layer::from_v7_id(id) else {
+            continue;
+        };
+        layers.push(layer_info(props, layer, &key));
+    }
+
+    if layers.is_empty() {
+        for id in 1..=82 {
+            let key = |name: &str| format!("LAYER{id}{name}");
+            if props.get(&key("NAME")).is_some() {
+                layers.push(layer_info(props, Layer::from_v6_id(id), &key));
+            }
+        }
+    }
+
+    if let Some(buf) = kind_mapping {
+        let mapping = parse_kind_mapping(buf).context("reading layer kind mapping")?;
+        for (layer, kind) in mapping {
+            if let Some(info) = layers.iter_mut().find(|info| info.layer == layer) {
+                info.mechanical_kind.get_or_insert(kind);
+            }
+        }
+    }
+
+    let pairs = |prefix: &str, first: &str, second: &str| {
+        (0..)
+            .map_while(|idx| {
+                let a = props.get(&format!("{prefix}{idx}{first}"))?;
+                let b = props.get(&format!("{prefix}{idx}{second}"))?;
+                Some((Layer::from_ident(a.trim()), Layer::from_ident(b.trim())))
+            })
+            .filter_map(|pair| match pair {
+                (Some(a), Some(b)) => Some((a, b)),
+                _ => None,
+            })
+            .collect()
+    };
+
+    Ok(BoardLayers {
+        layers,
+        mechanical_pairs: pairs("MECHPAIR", "L1", "L2"),
+        drill_pairs: pairs("LAYERPAIR", "LOW", "HIGH"),
+    })
+}
+
+/// Read one layer's definition, `key` gives the property name of a field
+fn layer_info(props: &Properties, layer: Layer, key: &dyn Fn(&str) -> String) -> LayerInfo {
+    let name = props.get_str(&key("NAME"));
+    let is_mech = layer.is_mechanical();
+
+    LayerInfo {
+        name: if name.is_empty() {
+            layer.to_string().into()
+        } else {
+            name
+        },
+        layer,
+        enabled: !is_mech || props.get_bool(&key("MECHENABLED")),
+        used: props.get_bool(&key("USEDBYPRIMS")),
+        mechanical_kind: props
+            .get(&key("MECHKIND"))
+            .filter(|_| is_mech)
+            .and_then(MechanicalKind::from_name),
+    }
+}
+
+/// `LayerKindMapping` is a UTF-16 version string, a `u32` we don't know the
+/// meaning of, then a count of `(layer ID, kind code)` pairs, all `u32`
+fn parse_kind_mapping(buf: &[u8]) -> Result<Vec<(Layer, MechanicalKind)>, ErrorKind> {
+    let mut rd = Reader::new(buf);
+    let version_len = rd.u32()?;
+    rd.skip(usize::try_from(version_len).unwrap_or(usize::MAX))?;
+    rd.u32()?;
+
+    let mut ret = Vec::new();
+    for _ in 0..rd.u32()? {
+        let id = rd.u32()?;
+        let code = rd.u32()?;
+        let layer = match u8::try_from(id) {
+            Ok(id) => Some(Layer::from_v6_id(id)),
+            Err(_) => Layer::from_v7_id(id),
+        };
+        if let (Some(layer), Some(kind)) = (layer, MechanicalKind::from_code(code)) {
+            ret.push((layer, kind));
+        }
+    }
+
+    Ok(ret)
+}
@@ -0,0 +1,294 @@
+//! 3D models embedded in PCB libraries and documents
+//!
+//! Models live in a `Models` storage (`Library/Models` in a `PcbLib`). Its
+//! `Data` stream lists each model's properties, and stream `n` holds the
+//! zlib-compressed STEP file of the `n`th model.
+
+use std::fs;
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+use cfb::CompoundFile;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use uuid::Uuid;
+
+use super::record::{to_nm, Properties};
+use crate::error::AddContext;
+use crate::parse::{extract_sized_buf, BufLenMatch};
+use crate::{Error, ErrorKind};
+
+/// A STEP model stored in a PCB file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EmbeddedModel {
+    id: Box<str>,
+    name: Box<str>,
+    source: Box<str>,
+    checksum: i32,
+    rotation: [f64; 3],
+    dz: i32,
+    /// zlib compressed STEP data
+    compressed: Box<[u8]>,
+}
+
+impl EmbeddedModel {
+    /// Create a model from the contents of a STEP file, with a new random ID
+    ///
+    /// Altium's checksum algorithm isn't known, so new models have a checksum
+    /// of 0.
+    pub fn new(name: &str, step: &[u8]) -> Result<Self, Error> {
+        let mut ret = Self {
+            id: new_id(),
+            name: name.into(),
+            source: "Undefined".into(),
+            ..Default::default()
+        };
+        ret.set_step_data(step)?;
+        Ok(ret)
+    }
+
+    /// Model ID that component bodies refer to as their `model_id`
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// File name of the model, e.g. `CAPC1608X09L.step`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Checksum that Altium uses to detect changes to the model
+    pub fn checksum(&self) -> i32 {
+        self.checksum
+    }
+
+    /// Rotation of the model about its X, Y and Z axes in degrees
+    pub fn rotation(&self) -> [f64; 3] {
+        self.rotation
+    }
+
+    /// Offset of the model along Z in nm. Stored in internal units.
+    pub fn dz(&self) -> i32 {
+        self.dz
+    }
+
+    /// Decompress the model's STEP data
+    pub fn step_data(&self) -> Result<Vec<u8>, Error> {
+        let mut ret = Vec::new();
+        ZlibDecoder::new(&*self.compressed)
+            .read_to_end(&mut ret)
+            .map_err(Error::from)
+            .or_context(|| format!("decompressing model `{}`", self.name))?;
+        Ok(ret)
+    }
+
+    /// Replace the model's STEP data, keeping its ID so that bodies still
+    /// refer to it
+    pub fn set_step_data(&mut self, step: &[u8]) -> Result<(), Error> {
+        let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(step)?;
+        self.compressed = enc.finish()?.into();
+        self.checksum = 0;
+        Ok(())
+    }
+
+    /// Write the model as a `.step` file. Will create the file if it does not
+    /// exist.
+    pub fn save_step<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, self.step_data()?)?;
+        Ok(())
+    }
+
+    fn from_props(props: &Properties, compressed: Vec<u8>) -> Result<Self, ErrorKind> {
+        Ok(Self {
+            id: props.get_str("ID"),
+            name: props.get_str("NAME"),
+            source: props.get_str("MODELSOURCE"),
+            checksum: props.get_int("CHECKSUM")?,
+            rotation: [
+                props.get_f64("ROTX")?,
+                props.get_f64("ROTY")?,
+                props.get_f64("ROTZ")?,
+            ],
+            dz: to_nm(props.get_int("DZ")?),
+            compressed: compressed.into(),
+        })
+    }
+
+    /// Properties for the `Data` stream, nul terminated
+    fn props_buf(&self) -> Vec<u8> {
+        let [rot_x, rot_y, rot_z] = self.rotation;
+        #[allow(clippy::cast_possible_truncation)]
+        let dz = (f64::from(self.dz) / 2.54).round() as i32;
+        let mut ret = format!(
+            "EMBED=TRUE|MODELSOURCE={}|ID={}|ROTX={rot_x:.3}|ROTY={rot_y:.3}|ROTZ={rot_z:.3}\
+             |DZ={dz}|CHECKSUM={}|NAME={}",
+            self.source, self.id, self.checksum, self.name
+        )
+        .into_bytes();
+        ret.push(0);
+        ret
+    }
+}
+
+/// All models embedded in a PCB file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EmbeddedModels(Vec<EmbeddedModel>);
+
+impl EmbeddedModels {
+    /// Iterate over the models
+    pub fn iter(&self) -> impl Iterator<Item = &EmbeddedModel> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Find a model by its ID
+    pub fn get(&self, id: &str) -> Option<&EmbeddedModel> {
+        self.0
+            .iter()
+            .find(|model| model.id.eq_ignore_ascii_case(id))
+    }
+
+    /// Find a model by its ID to modify it
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut EmbeddedModel> {
+        self.0
+            .iter_mut()
+            .find(|model| model.id.eq_ignore_ascii_case(id))
+    }
+
+    /// Find a model by file name, ignoring case
+    pub fn by_name(&self, name: &str) -> Option<&EmbeddedModel> {
+        self.0
+            .iter()
+            .find(|model| model.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Add a model, replacing any with the same ID
+    pub fn insert(&mut self, model: EmbeddedModel) {
+        match self.0.iter_mut().find(|m| m.id == model.id) {
+            Some(existing) => *existing = model,
+            None => self.0.push(model),
+        }
+    }
+
+    /// Remove a model by ID, returning it if it existed
+    pub fn remove(&mut self, id: &str) -> Option<EmbeddedModel> {
+        let idx = self
+            .0
+            .iter()
+            .position(|model| model.id.eq_ignore_ascii_case(id))?;
+        Some(self.0.remove(idx))
+    }
+
+    /// Write these models into an existing `PcbLib` or `PcbDoc`, replacing the
+    /// models it has. Bodies in the file are not changed, so replacing a model
+    /// should keep its ID.
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let mut cfile = cfb::open_rw(path)?;
+        let storage = if cfile.is_storage("Library") {
+            "Library/Models"
+        } else {
+            "Models"
+        };
+
+        self.write_storage(&mut cfile, storage)
+            .or_context(|| format!("writing models to {}", path.display()))?;
+        cfile.flush()?;
+        Ok(())
+    }
+
+    /// Read the models in a `Models` storage. Files without one have no
+    /// models.
+    pub(crate) fn read_storage<F: Read + Seek>(
+        cfile: &mut CompoundFile<F>,
+        storage: &str,
+    ) -> Result<Self, Error> {
+        let data_path = format!("{storage}/Data");
+        if !cfile.is_stream(&data_path) {
+            return Ok(Self::default());
+        }
+
+        let data = read_stream(cfile, &data_path)?;
+        let mut rest = data.as_slice();
+        let mut models = Vec::new();
+
+        while !rest.is_empty() {
+            let (props, next) = extract_sized_buf(rest, BufLenMatch::U32, false)
+                .or_context(|| format!("reading properties of model {}", models.len()))?;
+            let props = Properties::parse(props)?;
+            let compressed = read_stream(cfile, &format!("{storage}/{}", models.len()))?;
+            models.push(EmbeddedModel::from_props(&props, compressed)?);
+            rest = next;
+        }
+
+        Ok(Self(models))
+    }
+
+    fn write_storage<F: Read + Write + Seek>(
+        &self,
+        cfile: &mut CompoundFile<F>,
+        storage: &str,
+    ) -> Result<(), Error> {
+        // Drop the old models so that none are left over
+        if cfile.is_storage(storage) {
+            cfile.remove_storage_all(storage)?;
+        }
+        cfile.create_storage_all(storage)?;
+
+        let count = u32::try_from(self.0.len()).expect("too many models");
+        cfile
+            .create_stream(format!("{storage}/Header"))?
+            .write_all(&count.to_le_bytes())?;
+
+        let mut data = Vec::new();
+        for (idx, model) in self.0.iter().enumerate() {
+            let props = model.props_buf();
+            let len = u32::try_from(props.len()).expect("model properties too long");
+            data.extend_from_slice(&len.to_le_bytes());
+            data.extend_from_slice(&props);
+
+            cfile
+                .create_stream(format!("{storage}/{idx}"))?
+                .write_all(&model.compressed)?;
+        }
+        cfile
+            .create_stream(format!("{storage}/Data"))?
+            .write_all(&data)?;
+
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a EmbeddedModels {
+    type Item = &'a EmbeddedModel;
+    type IntoIter = std::slice::Iter<'a, EmbeddedModel>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+fn read_stream<F: Read + Seek>(cfile: &mut CompoundFile<F>, path: &str) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    cfile
+        .open_stream(path)
+        .map_err(|e| Error::from(e).context(format!("reading required stream `{path}`")))?
+        .read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Model IDs are braced uppercase GUIDs
+fn new_id() -> Box<str> {
+    format!("{{{}}}", Uuid::new_v4().hyphenated())
+        .to_uppercase()
+        .into()
+}
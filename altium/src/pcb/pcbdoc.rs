@@ -1,2 +1,443 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+
+use cfb::CompoundFile;
+
+use super::component::{parse_components, Component};
+use super::draw::{draw_board_outline, draw_records};
+use super::drc::{self, DrcInput, DrcOptions, Violation};
+use super::fab::{self, BoardData, FabOptions, FabOutput, Ipc2581Options, PlacementOptions};
+use super::layer::{parse_board_layers, PLANE_LAYER_COUNT};
+use super::polygon::parse_polygons;
+use super::record::{
+    assign_unique_ids,
+    parse_all_records,
+    parse_prop_outline,
+    parse_shape_based_regions,
+    ComponentBody,
+    PcbRecord,
+    Properties,
+    Reader,
+};
+use super::stackup::{parse_stackup, StackLayer};
+use super::stats;
+use super::{
+    BoardLayers,
+    BoardStatistics,
+    DesignRules,
+    EmbeddedModels,
+    Layer,
+    PcbDrawCtx,
+    Polygon,
+};
+use crate::common::Location;
+use crate::draw::{Canvas, RenderOptions, Svg, SvgCtx};
+use crate::error::AddContext;
+use crate::Error;
+
 /// A PCB Document
-pub struct PcbDoc {}
+///
+/// Primitives, nets, the board shape, embedded 3D models and component bodies
+/// can be read.
+pub struct PcbDoc<F> {
+    /// Our open compoundfile buffer
+    cfile: RefCell<CompoundFile<F>>,
+}
+
+/// Impls that are specific to a file
+impl PcbDoc<File> {
+    /// Open a file from disk
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let cfile = cfb::open(&path)
+            .map_err(Error::from)
+            .context("parsing PcbDoc")
+            .or_context(|| format!("with file {}", path.as_ref().display()))?;
+        Ok(Self::from_cfile(cfile))
+    }
+}
+
+impl<'a> PcbDoc<Cursor<&'a [u8]>> {
+    /// Open an in-memory file from a buffer
+    pub fn from_buffer(buf: &'a [u8]) -> Result<Self, Error> {
+        let cfile = cfb::CompoundFile::open(Cursor::new(buf))?;
+        Ok(Self::from_cfile(cfile))
+    }
+}
+
+impl<F: Read + Seek> PcbDoc<F> {
+    const MODELS_STORAGE: &'static str = "Models";
+    const BODIES_STREAM: &'static str = "ComponentBodies6/Data";
+    const BOARD_STREAM: &'static str = "Board6/Data";
+    const LAYER_KIND_STREAM: &'static str = "LayerKindMapping/Data";
+    const RULES_STREAM: &'static str = "Rules6/Data";
+    const COMPONENTS_STREAM: &'static str = "Components6/Data";
+    const TEXTS_STREAM: &'static str = "Texts6/Data";
+    const NETS_STREAM: &'static str = "Nets6/Data";
+    const POLYGONS_STREAM: &'static str = "Polygons6/Data";
+    const REGIONS_STREAM: &'static str = "Regions6/Data";
+    const SHAPE_REGIONS_STREAM: &'static str = "ShapeBasedRegions6/Data";
+    const UNIQUE_IDS_STREAM: &'static str = "UniqueIDPrimitiveInformation/Data";
+    /// Streams holding primitives, other than regions
+    const PRIMITIVE_STREAMS: [&'static str; 6] = [
+        "Arcs6/Data",
+        "Pads6/Data",
+        "Vias6/Data",
+        "Tracks6/Data",
+        Self::TEXTS_STREAM,
+        "Fills6/Data",
+    ];
+
+    /// Every primitive placed on the board, including those inside
+    /// components
+    ///
+    /// `ShapeBasedRegions6` holds the same regions as `Regions6` but keeps
+    /// arcs in outlines, so it is used if it exists.
+    pub fn records(&self) -> Result<Vec<PcbRecord>, Error> {
+        let mut ret = Vec::new();
+        for name in Self::PRIMITIVE_STREAMS {
+            if let Some(buf) = self.read_stream(name)? {
+                ret.extend(parse_all_records(&buf, name)?);
+            }
+        }
+
+        if let Some(buf) = self.read_stream(Self::SHAPE_REGIONS_STREAM)? {
+            ret.extend(parse_shape_based_regions(&buf, Self::SHAPE_REGIONS_STREAM)?);
+        } else if let Some(buf) = self.read_stream(Self::REGIONS_STREAM)? {
+            ret.extend(parse_all_records(&buf, Self::REGIONS_STREAM)?);
+        }
+
+        if let Some(buf) = self.read_stream(Self::UNIQUE_IDS_STREAM)? {
+            let entries = read_property_list(&buf).context("reading primitive unique IDs")?;
+            assign_unique_ids(&mut ret, &entries);
+        }
+
+        Ok(ret)
+    }
+
+    /// Every component on the board
+    ///
+    /// If a component doesn't store its designator, the text of its
+    /// designator string is used.
+    pub fn components(&self) -> Result<Vec<Component>, Error> {
+        let Some(buf) = self.read_stream(Self::COMPONENTS_STREAM)? else {
+            return Ok(Vec::new());
+        };
+        let list = read_property_list(&buf).context("reading components")?;
+        let mut ret = parse_components(&list)?;
+
+        if let Some(buf) = self.read_stream(Self::TEXTS_STREAM)? {
+            for rec in parse_all_records(&buf, Self::TEXTS_STREAM)? {
+                let PcbRecord::Text(text) = rec else {
+                    continue;
+                };
+                let component = text.component.and_then(|idx| ret.get_mut(usize::from(idx)));
+                if let Some(component) = component.filter(|_| text.is_designator) {
+                    component.set_designator_fallback(&text.text);
+                }
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Names of all nets. A primitive's `net` is an index into this list.
+    pub fn nets(&self) -> Result<Vec<Box<str>>, Error> {
+        let Some(buf) = self.read_stream(Self::NETS_STREAM)? else {
+            return Ok(Vec::new());
+        };
+        let ret = read_property_list(&buf)
+            .context("reading nets")?
+            .iter()
+            .map(|props| props.get_str("NAME"))
+            .collect();
+        Ok(ret)
+    }
+
+    /// Every polygon pour's outline and settings. The poured copper is in
+    /// [`Self::records`], see [`Polygon::poured`].
+    pub fn polygons(&self) -> Result<Vec<Polygon>, Error> {
+        let Some(buf) = self.read_stream(Self::POLYGONS_STREAM)? else {
+            return Ok(Vec::new());
+        };
+        let list = read_property_list(&buf).context("reading polygons")?;
+        parse_polygons(&list)
+    }
+
+    /// The net index of each polygon pour. Primitives poured from a polygon
+    /// don't always store the net themselves.
+    fn polygon_nets(&self) -> Result<Vec<Option<u16>>, Error> {
+        Ok(self.polygons()?.iter().map(Polygon::net).collect())
+    }
+
+    /// The net name of each internal plane, starting with plane 1. Planes
+    /// without a net are `None`.
+    fn plane_nets(&self) -> Result<Vec<Option<Box<str>>>, Error> {
+        let props = self.board_properties()?;
+        let ret = (1..=PLANE_LAYER_COUNT)
+            .map(|n| {
+                props
+                    .get(&format!("PLANE{n}NETNAME"))
+                    .filter(|name| !name.is_empty() && *name != "(No Net)")
+                    .map(Into::into)
+            })
+            .collect();
+        Ok(ret)
+    }
+
+    /// The board shape as a closed outline. Arcs are converted to line
+    /// segments.
+    pub fn board_outline(&self) -> Result<Vec<Location>, Error> {
+        let props = self.board_properties()?;
+        parse_prop_outline(&props).context("reading board outline")
+    }
+
+    /// The board's relative origin, which outputs are usually placed relative
+    /// to
+    pub fn origin(&self) -> Result<Location, Error> {
+        let props = self.board_properties()?;
+        let origin = Location::new(props.get_len("ORIGINX")?, props.get_len("ORIGINY")?);
+        Ok(origin)
+    }
+
+    /// Copper layers in stack order, from top to bottom
+    pub fn copper_layers(&self) -> Result<Vec<Layer>, Error> {
+        let props = self.board_properties()?;
+        if props.get("LAYER1NEXT").is_none() {
+            return Ok(vec![Layer::Top, Layer::Bottom]);
+        }
+
+        // Each copper layer links to the next by V6 ID, ending with 0
+        let mut ret = Vec::new();
+        let mut id = 1;
+        while id != 0 {
+            let layer = Layer::from_v6_id(id);
+            if ret.contains(&layer) {
+                break;
+            }
+            ret.push(layer);
+            let next = props.get_int(&format!("LAYER{id}NEXT"))?;
+            id = u8::try_from(next).unwrap_or(0);
+        }
+        Ok(ret)
+    }
+
+    /// Names, mechanical layer kinds and layer pairs of every layer
+    pub fn layers(&self) -> Result<BoardLayers, Error> {
+        let kind_mapping = self.read_stream(Self::LAYER_KIND_STREAM)?;
+        parse_board_layers(&self.board_properties()?, kind_mapping.as_deref())
+    }
+
+    /// Every design rule, in the order they are stored. Use
+    /// [`DesignRules::save_to`] to write them to another board.
+    pub fn rules(&self) -> Result<DesignRules, Error> {
+        let Some(buf) = self.read_stream(Self::RULES_STREAM)? else {
+            return Ok(DesignRules::default());
+        };
+        DesignRules::from_buf(&buf).context("reading design rules")
+    }
+
+    /// The physical layer stack from top to bottom, including dielectrics,
+    /// solder mask, overlay and paste layers
+    ///
+    /// Documents without a stack in the layer stack manager give only their
+    /// copper layers.
+    pub fn stackup(&self) -> Result<Vec<StackLayer>, Error> {
+        let ret = parse_stackup(&self.board_properties()?)?;
+        if ret
+            .iter()
+            .any(|layer| layer.layer().is_some_and(Layer::is_copper))
+        {
+            return Ok(ret);
+        }
+        let ret = self
+            .copper_layers()?
+            .into_iter()
+            .map(StackLayer::copper)
+            .collect();
+        Ok(ret)
+    }
+
+    /// Generate Gerber X2, Excellon drill and Gerber job files. See
+    /// [`fab`] for what is included.
+    pub fn fab_outputs(&self, options: &FabOptions) -> Result<FabOutput, Error> {
+        Ok(fab::generate(&self.board_data()?, options))
+    }
+
+    /// A pick and place (centroid) CSV file with a row for each component
+    pub fn pick_and_place(&self, options: &PlacementOptions) -> Result<String, Error> {
+        Ok(fab::pick_and_place(&self.board_data()?, options))
+    }
+
+    /// An IPC-D-356A bare board test netlist with a test point for every pad
+    /// and via. `options` supplies the job name.
+    pub fn ipc356_netlist(&self, options: &FabOptions) -> Result<String, Error> {
+        Ok(fab::ipc356_netlist(&self.board_data()?, options))
+    }
+
+    /// IPC-2581 XML with the stackup, layer features, padstacks, components,
+    /// nets and a bill of materials. See [`fab`] for what is included.
+    pub fn ipc2581(&self, options: &Ipc2581Options) -> Result<String, Error> {
+        Ok(fab::ipc2581(&self.board_data()?, options))
+    }
+
+    /// Board size, layer count, pad, via and component counts, the smallest
+    /// track and drill, and copper area per layer
+    pub fn statistics(&self) -> Result<BoardStatistics, Error> {
+        Ok(stats::statistics(&self.board_data()?))
+    }
+
+    /// Check clearance, widths, annular rings, hole spacing and connectivity
+    /// against this board's rules. See [`drc`] for what is checked.
+    pub fn drc(&self, options: &DrcOptions) -> Result<Vec<Violation>, Error> {
+        let polygon_nets = self.polygon_nets()?;
+        let poured_net = |net: &mut Option<u16>, polygon: Option<u16>| {
+            if net.is_none() {
+                *net = polygon
+                    .and_then(|idx| polygon_nets.get(usize::from(idx)))
+                    .copied()
+                    .flatten();
+            }
+        };
+
+        let mut records = self.records()?;
+        for record in &mut records {
+            match record {
+                PcbRecord::Arc(a) => poured_net(&mut a.net, a.polygon),
+                PcbRecord::Track(t) => poured_net(&mut t.net, t.polygon),
+                PcbRecord::Region(r) => poured_net(&mut r.net, r.polygon),
+                _ => (),
+            }
+        }
+
+        let input = DrcInput {
+            records: &records,
+            nets: &self.nets()?,
+            copper_layers: &self.copper_layers()?,
+            rules: &self.rules()?,
+        };
+        Ok(drc::check(&input, options))
+    }
+
+    /// Drawing settings for this board's copper layers, with its nets for
+    /// annotating groups
+    pub fn draw_ctx<'o>(&self, options: &'o RenderOptions) -> Result<PcbDrawCtx<'o>, Error> {
+        let mut ctx = PcbDrawCtx::new(options, &self.copper_layers()?);
+        ctx.nets = self.nets()?;
+        Ok(ctx)
+    }
+
+    /// Draw the board shape and every primitive, layer by layer
+    pub fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &PcbDrawCtx<'_>) -> Result<(), Error> {
+        draw_board_outline(&self.board_outline()?, canvas, ctx);
+        draw_records(&self.records()?, canvas, ctx);
+        Ok(())
+    }
+
+    /// Draw this board to a SVG
+    pub fn svg(&self) -> Result<Svg, Error> {
+        self.svg_with_options(&RenderOptions::default())
+    }
+
+    /// Draw this board to a SVG using the given theme and scale
+    pub fn svg_with_options(&self, options: &RenderOptions) -> Result<Svg, Error> {
+        self.svg_with_ctx(&self.draw_ctx(options)?)
+    }
+
+    /// Draw this board to a SVG with the given layer visibility, colors and
+    /// order
+    pub fn svg_with_ctx(&self, ctx: &PcbDrawCtx<'_>) -> Result<Svg, Error> {
+        let mut draw = SvgCtx::with_options(ctx.options);
+        draw.set_background(ctx.background());
+        self.draw(&mut draw, ctx)?;
+        Ok(draw.svg())
+    }
+
+    /// Everything that outputs are generated from
+    fn board_data(&self) -> Result<BoardData, Error> {
+        Ok(BoardData {
+            records: self.records()?,
+            components: self.components()?,
+            nets: self.nets()?,
+            polygon_nets: self.polygon_nets()?,
+            plane_nets: self.plane_nets()?,
+            rules: self.rules()?,
+            outline: self.board_outline()?,
+            origin: self.origin()?,
+            stack: self.copper_layers()?,
+            stackup: self.stackup()?,
+        })
+    }
+
+    /// Properties in the `Board6` stream
+    fn board_properties(&self) -> Result<Properties, Error> {
+        let Some(buf) = self.read_stream(Self::BOARD_STREAM)? else {
+            return Ok(Properties::default());
+        };
+        Reader::new(&buf)
+            .properties()
+            .context("reading board properties")
+    }
+
+    /// Read a whole stream, or `None` if it doesn't exist
+    fn read_stream(&self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+        let mut cfile = self.cfile.borrow_mut();
+        if !cfile.is_stream(name) {
+            return Ok(None);
+        }
+        let mut buf = Vec::new();
+        cfile
+            .open_stream(name)
+            .map_err(Error::from)
+            .or_context(|| format!("opening {name}"))?
+            .read_to_end(&mut buf)?;
+        Ok(Some(buf))
+    }
+
+    /// The 3D models embedded in this document
+    pub fn models(&self) -> Result<EmbeddedModels, Error> {
+        EmbeddedModels::read_storage(&mut self.cfile.borrow_mut(), Self::MODELS_STORAGE)
+            .context("reading embedded models")
+    }
+
+    /// Every 3D body on the board. A body's `model_id` refers to a model in
+    /// [`PcbDoc::models`] if the model is embedded.
+    pub fn component_bodies(&self) -> Result<Vec<ComponentBody>, Error> {
+        let Some(buf) = self.read_stream(Self::BODIES_STREAM)? else {
+            return Ok(Vec::new());
+        };
+
+        let bodies = parse_all_records(&buf, Self::BODIES_STREAM)?
+            .into_iter()
+            .filter_map(|record| match record {
+                PcbRecord::ComponentBody(body) => Some(body),
+                _ => None,
+            })
+            .collect();
+        Ok(bodies)
+    }
+
+    fn from_cfile(cfile: CompoundFile<F>) -> Self {
+        Self {
+            cfile: RefCell::new(cfile),
+        }
+    }
+}
+
+/// Read a stream of property lists, each with a 4 byte length
+pub(crate) fn read_property_list(buf: &[u8]) -> Result<Vec<Properties>, Error> {
+    let mut rd = Reader::new(buf);
+    let mut ret = Vec::new();
+    while rd.remaining() > 0 {
+        ret.push(rd.properties()?);
+    }
+    Ok(ret)
+}
+
+impl<F> fmt::Debug for PcbDoc<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PcbDoc").finish_non_exhaustive()
+    }
+}
@@ -1,2 +1,255 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use cfb::CompoundFile;
+
+use super::footprint::parse_sized_properties;
+use super::layer::parse_board_layers;
+use super::{BoardLayers, EmbeddedModels, Footprint};
+use crate::error::{AddContext, ErrorKind};
+use crate::parse::{extract_sized_buf, extract_sized_utf8_buf, BufLenMatch};
+use crate::Error;
+
 /// A PCB Library
-pub struct PcbLib {}
+pub struct PcbLib<F> {
+    /// Our open compoundfile buffer
+    cfile: RefCell<CompoundFile<F>>,
+    /// Footprints listed in the library header
+    footprints: Vec<FootprintMeta>,
+}
+
+/// Impls that are specific to a file
+impl PcbLib<File> {
+    /// Open a file from disk
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let cfile = cfb::open(&path)?;
+        Self::from_cfile(cfile)
+            .context("parsing PcbLib")
+            .or_context(|| format!("with file {}", path.as_ref().display()))
+    }
+}
+
+impl<'a> PcbLib<Cursor<&'a [u8]>> {
+    /// Open an in-memory file from a buffer
+    pub fn from_buffer(buf: &'a [u8]) -> Result<Self, Error> {
+        let cfile = cfb::CompoundFile::open(Cursor::new(buf))?;
+        Self::from_cfile(cfile).context("parsing PcbLib from Cursor")
+    }
+}
+
+impl<F: Read + Seek> PcbLib<F> {
+    const DATA_STREAM: &'static str = "Library/Data";
+    const SEC_KEY_STREAM: &'static str = "SectionKeys";
+    const MODELS_STORAGE: &'static str = "Library/Models";
+    const LAYER_KIND_STREAM: &'static str = "Library/LayerKindMapping/Data";
+    const KIND: &'static str = "Protel_Advanced_PCB_Library";
+
+    /// Information about each footprint in this library
+    pub fn footprint_meta(&self) -> &[FootprintMeta] {
+        &self.footprints
+    }
+
+    /// Lookup a single footprint by name
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are any failures reading the footprint. This shouldn't
+    /// happen with files that Altium generates.
+    pub fn get_footprint(&self, name: &str) -> Option<Footprint> {
+        self.try_get_footprint(name).unwrap()
+    }
+
+    /// Lookup a single footprint by name, propagating errors if they arise
+    pub fn try_get_footprint(&self, name: &str) -> Result<Option<Footprint>, Error> {
+        let Some(meta) = self.footprints.iter().find(|meta| &*meta.name == name) else {
+            return Ok(None);
+        };
+
+        let key = &*meta.sec_key;
+        let data = self.read_stream(&[key, "Data"])?;
+        let params = self.read_stream(&[key, "Parameters"])?;
+        // Older files may not have wide strings
+        let wide_strings = self.read_stream(&[key, "WideStrings"]).unwrap_or_default();
+
+        Footprint::from_streams(key, &data, &params, &wide_strings).map(Some)
+    }
+
+    /// Create an iterator over all footprints in this library.
+    pub fn footprints(&self) -> FootprintsIter<'_, F> {
+        FootprintsIter {
+            pcblib: self,
+            current: 0,
+        }
+    }
+
+    /// The 3D models embedded in this library
+    pub fn models(&self) -> Result<EmbeddedModels, Error> {
+        EmbeddedModels::read_storage(&mut self.cfile.borrow_mut(), Self::MODELS_STORAGE)
+            .context("reading embedded models")
+    }
+
+    /// Names, mechanical layer kinds and layer pairs of every layer, as set up
+    /// in the library
+    pub fn layers(&self) -> Result<BoardLayers, Error> {
+        let header = parse_sized_properties(&self.read_stream(&[Self::DATA_STREAM])?)
+            .context("parsing library data")?;
+        let kind_mapping = if self.cfile.borrow().exists(Self::LAYER_KIND_STREAM) {
+            Some(self.read_stream(&[Self::LAYER_KIND_STREAM])?)
+        } else {
+            None
+        };
+        parse_board_layers(&header, kind_mapping.as_deref())
+    }
+
+    fn read_stream(&self, path: &[&str]) -> Result<Vec<u8>, Error> {
+        let path = PathBuf::from_iter(path);
+        let mut buf = Vec::new();
+        let mut cfile_ref = self.cfile.borrow_mut();
+        let mut stream = cfile_ref.open_stream(&path).map_err(|e| {
+            let path_disp = path.display();
+            Error::from(e).context(format!("reading required stream `{path_disp}`"))
+        })?;
+        stream.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Create a `PcbLib` representation from any `Read`able compound file.
+    pub(crate) fn from_cfile(cfile: CompoundFile<F>) -> Result<Self, Error> {
+        let mut ret = Self {
+            cfile: RefCell::new(cfile),
+            footprints: Vec::new(),
+        };
+
+        let data = ret.read_stream(&[Self::DATA_STREAM])?;
+        let names = Self::parse_data(&data).context("parsing library data")?;
+        let section_keys = if ret.cfile.borrow().exists(Self::SEC_KEY_STREAM) {
+            let buf = ret.read_stream(&[Self::SEC_KEY_STREAM])?;
+            Self::parse_section_keys(&buf).context("parsing section keys")?
+        } else {
+            Vec::new()
+        };
+
+        for name in names {
+            // Names that are too long to be a storage name get mapped in the
+            // section keys
+            let sec_key = section_keys
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map_or(&*name, |(_, key)| key.as_str())
+                .replace('/', "_");
+            let params = ret.read_stream(&[&sec_key, "Parameters"])?;
+            let params = parse_sized_properties(&params)
+                .or_context(|| format!("reading parameters for `{name}`"))?;
+
+            ret.footprints.push(FootprintMeta {
+                name: name.into(),
+                sec_key: sec_key.into(),
+                description: params.get_str("DESCRIPTION"),
+            });
+        }
+
+        Ok(ret)
+    }
+
+    /// Parse the map of `name -> storage name`, stored as a `u32` count then
+    /// pairs of length-prefixed strings
+    fn parse_section_keys(buf: &[u8]) -> Result<Vec<(String, String)>, Error> {
+        let (count, mut rest) = buf
+            .split_first_chunk::<4>()
+            .ok_or(ErrorKind::new_invalid_stream(Self::SEC_KEY_STREAM, 0))?;
+        let mut keys = Vec::new();
+
+        for _ in 0..u32::from_le_bytes(*count) {
+            let mut pair = [String::new(), String::new()];
+            for item in &mut pair {
+                let (entry, next) = extract_sized_buf(rest, BufLenMatch::U32, false)?;
+                let (s, _) = extract_sized_utf8_buf(entry, BufLenMatch::U8, false)?;
+                s.clone_into(item);
+                rest = next;
+            }
+            let [name, key] = pair;
+            keys.push((name, key));
+        }
+
+        Ok(keys)
+    }
+
+    /// Validate the library header and list the footprint names
+    fn parse_data(buf: &[u8]) -> Result<Vec<String>, Error> {
+        let header = parse_sized_properties(buf)?;
+        if header.get("KIND") != Some(Self::KIND) {
+            return Err(ErrorKind::new_invalid_stream(Self::DATA_STREAM, 0).into());
+        }
+
+        let (_, rest) = extract_sized_buf(buf, BufLenMatch::U32, false)?;
+        let (count, mut rest) = rest
+            .split_first_chunk::<4>()
+            .ok_or(ErrorKind::new_invalid_stream(Self::DATA_STREAM, buf.len()))?;
+        let count = u32::from_le_bytes(*count);
+
+        let mut names = Vec::new();
+        for _ in 0..count {
+            let (entry, next) = extract_sized_buf(rest, BufLenMatch::U32, false)?;
+            let (name, _) = extract_sized_utf8_buf(entry, BufLenMatch::U8, false)?;
+            names.push(name.to_owned());
+            rest = next;
+        }
+
+        Ok(names)
+    }
+}
+
+impl<F> fmt::Debug for PcbLib<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PcbLib")
+            .field("footprints", &self.footprints)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Iterator over footprints in a library
+pub struct FootprintsIter<'a, F> {
+    pcblib: &'a PcbLib<F>,
+    current: usize,
+}
+
+impl<F: Read + Seek> Iterator for FootprintsIter<'_, F> {
+    type Item = Footprint;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let meta = self.pcblib.footprint_meta().get(self.current)?;
+        self.current += 1;
+        // We assume that there are no errors
+        Some(
+            self.pcblib
+                .get_footprint(meta.name())
+                .expect("footprint should exist!"),
+        )
+    }
+}
+
+/// Information about a single footprint that is available without reading its
+/// primitives
+#[derive(Clone, Debug, Default)]
+pub struct FootprintMeta {
+    /// Name of the footprint in Altium
+    name: Box<str>,
+    /// Name of the footprint's storage in our OLE file
+    sec_key: Box<str>,
+    description: Box<str>,
+}
+
+impl FootprintMeta {
+    /// Name of this footprint
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// This footprint's description
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
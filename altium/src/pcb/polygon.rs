@@ -0,0 +1,282 @@
+//! Polygon pours, as stored in `Polygons6`
+//!
+//! A polygon holds the outline and pour settings. The poured copper is stored
+//! separately as regions (solid pours) or tracks and arcs (hatched pours) whose
+//! `polygon` field is the polygon's index.
+
+use super::record::{parse_prop_outline, PcbRecord, Properties};
+use super::Layer;
+use crate::common::Location;
+use crate::error::AddContext;
+use crate::Error;
+
+/// Internal units are 1/10000 mil, areas are stored in those units squared
+const MM2_PER_UNIT2: f64 = 2.54e-6 * 2.54e-6;
+
+/// What a polygon is used for
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum PolygonKind {
+    /// A copper pour on a signal layer
+    #[default]
+    Polygon,
+    /// A region of a split internal plane
+    SplitPlane,
+    Unknown(Box<str>),
+}
+
+impl PolygonKind {
+    fn from_name(name: &str) -> Self {
+        match name.trim() {
+            "" | "Polygon" => Self::Polygon,
+            "Split Plane" => Self::SplitPlane,
+            other => Self::Unknown(other.into()),
+        }
+    }
+}
+
+/// How a polygon is filled
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HatchStyle {
+    /// Filled with regions
+    #[default]
+    Solid,
+    /// Tracks at 0 and 90 degrees
+    Hatch90,
+    /// Tracks at 45 and 135 degrees
+    Hatch45,
+    Vertical,
+    Horizontal,
+    /// Only the outline is poured
+    None,
+}
+
+impl HatchStyle {
+    fn from_name(name: &str) -> Self {
+        match name.trim() {
+            "90Degree" => Self::Hatch90,
+            "45Degree" => Self::Hatch45,
+            "Vertical" => Self::Vertical,
+            "Horizontal" => Self::Horizontal,
+            "None" => Self::None,
+            _ => Self::Solid,
+        }
+    }
+}
+
+/// Which objects of the polygon's own net it pours over
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PourOver {
+    #[default]
+    Nothing,
+    AllSameNetObjects,
+    SameNetPolygons,
+}
+
+impl PourOver {
+    fn from_code(code: i32) -> Self {
+        match code {
+            1 => Self::AllSameNetObjects,
+            2 => Self::SameNetPolygons,
+            _ => Self::Nothing,
+        }
+    }
+}
+
+/// A polygon pour's outline and settings
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polygon {
+    index: u16,
+    name: Box<str>,
+    kind: PolygonKind,
+    layer: Layer,
+    net: Option<u16>,
+    outline: Vec<Location>,
+    hatch_style: HatchStyle,
+    track_width: i32,
+    grid_size: i32,
+    min_primitive_length: i32,
+    use_octagons: bool,
+    pour_over: PourOver,
+    remove_dead_copper: bool,
+    remove_islands_by_area: bool,
+    island_area_threshold: f64,
+    remove_necks: bool,
+    neck_width: i32,
+    arc_resolution: i32,
+    pour_index: i32,
+    shelved: bool,
+    locked: bool,
+}
+
+impl Polygon {
+    fn from_props(index: u16, props: &Properties) -> Result<Self, Error> {
+        let layer = props
+            .get("LAYER")
+            .and_then(|ident| Layer::from_ident(ident.trim()))
+            .unwrap_or(Layer::Top);
+        let area = props.get_f64("AREATHRESHOLD")?;
+
+        let ret = Self {
+            index,
+            name: decode_name(props.get("NAME").unwrap_or_default()),
+            kind: PolygonKind::from_name(props.get("POLYGONTYPE").unwrap_or_default()),
+            layer,
+            net: props.get("NET").and_then(|net| net.trim().parse().ok()),
+            outline: parse_prop_outline(props).context("reading outline")?,
+            hatch_style: HatchStyle::from_name(props.get("HATCHSTYLE").unwrap_or_default()),
+            track_width: props.get_len("TRACKWIDTH")?,
+            grid_size: props.get_len("GRIDSIZE")?,
+            min_primitive_length: props.get_len("MINPRIMLENGTH")?,
+            use_octagons: props.get_bool("USEOCTAGONS"),
+            pour_over: PourOver::from_code(props.get_int("POUROVERSTYLE")?),
+            remove_dead_copper: props.get_bool("REMOVEDEAD"),
+            remove_islands_by_area: props.get_bool("REMOVEISLANDSBYAREA"),
+            island_area_threshold: area * MM2_PER_UNIT2,
+            remove_necks: props.get_bool("REMOVENECKS"),
+            neck_width: props.get_len("NECKWIDTHTHRESHOLD")?,
+            arc_resolution: props.get_len("ARCRESOLUTION")?,
+            pour_index: props.get_int("POURINDEX")?,
+            shelved: props.get_bool("SHELVED"),
+            locked: props.get_bool("LOCKED"),
+        };
+        Ok(ret)
+    }
+
+    /// Index in [`PcbDoc::polygons`], which poured primitives refer to
+    ///
+    /// [`PcbDoc::polygons`]: super::PcbDoc::polygons
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &PolygonKind {
+        &self.kind
+    }
+
+    pub fn layer(&self) -> Layer {
+        self.layer
+    }
+
+    /// Index of the net this polygon is connected to
+    pub fn net(&self) -> Option<u16> {
+        self.net
+    }
+
+    /// The closed outline. Arcs are converted to line segments.
+    pub fn outline(&self) -> &[Location] {
+        &self.outline
+    }
+
+    pub fn hatch_style(&self) -> HatchStyle {
+        self.hatch_style
+    }
+
+    /// Width of the tracks of hatched pours, in nm
+    pub fn track_width(&self) -> i32 {
+        self.track_width
+    }
+
+    /// Spacing of the tracks of hatched pours, in nm
+    pub fn grid_size(&self) -> i32 {
+        self.grid_size
+    }
+
+    /// Primitives shorter than this are left out of the pour, in nm
+    pub fn min_primitive_length(&self) -> i32 {
+        self.min_primitive_length
+    }
+
+    /// Whether pads are surrounded by octagons rather than arcs
+    pub fn use_octagons(&self) -> bool {
+        self.use_octagons
+    }
+
+    pub fn pour_over(&self) -> PourOver {
+        self.pour_over
+    }
+
+    /// Whether copper not connected to the polygon's net is removed
+    pub fn remove_dead_copper(&self) -> bool {
+        self.remove_dead_copper
+    }
+
+    /// Whether islands smaller than [`Self::island_area_threshold`] are
+    /// removed
+    pub fn remove_islands_by_area(&self) -> bool {
+        self.remove_islands_by_area
+    }
+
+    /// Area below which islands are removed, in mm²
+    pub fn island_area_threshold(&self) -> f64 {
+        self.island_area_threshold
+    }
+
+    /// Whether necks narrower than [`Self::neck_width`] are removed
+    pub fn remove_necks(&self) -> bool {
+        self.remove_necks
+    }
+
+    /// In nm
+    pub fn neck_width(&self) -> i32 {
+        self.neck_width
+    }
+
+    /// Maximum deviation of arcs approximated by line segments, in nm
+    pub fn arc_resolution(&self) -> i32 {
+        self.arc_resolution
+    }
+
+    /// Order in which polygons are poured, lowest first
+    pub fn pour_index(&self) -> i32 {
+        self.pour_index
+    }
+
+    /// Shelved polygons are kept without any poured copper
+    pub fn shelved(&self) -> bool {
+        self.shelved
+    }
+
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    /// The stored primitives making up this polygon's pour
+    pub fn poured<'a>(&self, records: &'a [PcbRecord]) -> impl Iterator<Item = &'a PcbRecord> {
+        let index = self.index;
+        records
+            .iter()
+            .filter(move |record| record.polygon() == Some(index))
+    }
+}
+
+/// Names are stored as comma separated UTF-16 code units, but plain text is
+/// accepted too
+fn decode_name(name: &str) -> Box<str> {
+    let units: Result<Vec<u16>, _> = name
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(str::parse)
+        .collect();
+    match units {
+        Ok(units) if !units.is_empty() => String::from_utf16_lossy(&units).into(),
+        _ => name.into(),
+    }
+}
+
+/// Parse every polygon in a `Polygons6` stream
+pub(crate) fn parse_polygons(list: &[Properties]) -> Result<Vec<Polygon>, Error> {
+    list.iter()
+        .enumerate()
+        .map(|(idx, props)| {
+            let index = u16::try_from(idx).unwrap_or(u16::MAX);
+            Polygon::from_props(index, props).or_context(|| format!("parsing polygon {idx}"))
+        })
+        .collect()
+}
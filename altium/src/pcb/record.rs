@@ -0,0 +1,929 @@
+//! Primitives stored in PCB libraries and documents
+//!
+//! Unlike schematic records, PCB primitives are binary. Each starts with a
+//! single byte type identifier followed by a fixed number of subrecords, each
+//! of which is a `u32` length and that many bytes of data.
+//!
+//! Lengths are converted to nanometers. Coordinates use Altium's convention of
+//! the Y axis pointing up, and angles are in degrees counterclockwise.
+
+mod kicad;
+mod parse;
+
+use std::collections::BTreeMap;
+
+pub(crate) use kicad::kicad_footprint;
+use log::warn;
+pub(crate) use parse::{format_len, parse_len, to_nm, Properties, Reader};
+use serde::{Deserialize, Serialize};
+
+use super::Layer;
+use crate::common::{Location, UniqueId};
+use crate::error::AddContext;
+use crate::parse::{extract_sized_buf, BufLenMatch, FromUtf8};
+use crate::{Error, ErrorKind};
+
+/// A primitive that can be placed on a PCB
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PcbRecord {
+    Arc(Arc),
+    Pad(Pad),
+    Via(Via),
+    Track(Track),
+    Text(Text),
+    Fill(Fill),
+    Region(Region),
+    ComponentBody(ComponentBody),
+}
+
+impl PcbRecord {
+    /// The layer that this primitive is placed on
+    pub fn layer(&self) -> Layer {
+        match self {
+            PcbRecord::Arc(v) => v.layer,
+            PcbRecord::Pad(v) => v.layer,
+            PcbRecord::Via(_) => Layer::MultiLayer,
+            PcbRecord::Track(v) => v.layer,
+            PcbRecord::Text(v) => v.layer,
+            PcbRecord::Fill(v) => v.layer,
+            PcbRecord::Region(v) => v.layer,
+            PcbRecord::ComponentBody(v) => v.layer,
+        }
+    }
+
+    /// The primitive's unique ID, if the document stores one for it
+    pub fn unique_id(&self) -> Option<UniqueId> {
+        match self {
+            PcbRecord::Arc(v) => v.unique_id,
+            PcbRecord::Pad(v) => v.unique_id,
+            PcbRecord::Via(v) => v.unique_id,
+            PcbRecord::Track(v) => v.unique_id,
+            PcbRecord::Fill(v) => v.unique_id,
+            PcbRecord::Region(v) => v.unique_id,
+            PcbRecord::Text(_) | PcbRecord::ComponentBody(_) => None,
+        }
+    }
+
+    /// Index of the polygon pour this primitive was poured from
+    pub fn polygon(&self) -> Option<u16> {
+        match self {
+            PcbRecord::Arc(v) => v.polygon,
+            PcbRecord::Track(v) => v.polygon,
+            PcbRecord::Region(v) => v.polygon,
+            PcbRecord::Pad(_)
+            | PcbRecord::Via(_)
+            | PcbRecord::Text(_)
+            | PcbRecord::Fill(_)
+            | PcbRecord::ComponentBody(_) => None,
+        }
+    }
+
+    /// Mutable access to the unique ID of primitives that can have one
+    fn unique_id_mut(&mut self) -> Option<&mut Option<UniqueId>> {
+        match self {
+            PcbRecord::Arc(v) => Some(&mut v.unique_id),
+            PcbRecord::Pad(v) => Some(&mut v.unique_id),
+            PcbRecord::Via(v) => Some(&mut v.unique_id),
+            PcbRecord::Track(v) => Some(&mut v.unique_id),
+            PcbRecord::Fill(v) => Some(&mut v.unique_id),
+            PcbRecord::Region(v) => Some(&mut v.unique_id),
+            PcbRecord::Text(_) | PcbRecord::ComponentBody(_) => None,
+        }
+    }
+}
+
+/// An arc or full circle
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Arc {
+    pub layer: Layer,
+    pub locked: bool,
+    pub keepout: bool,
+    pub net: Option<u16>,
+    /// Polygon pour that this primitive was generated by
+    pub polygon: Option<u16>,
+    pub component: Option<u16>,
+    pub center: Location,
+    pub radius: u32,
+    pub start_angle: f64,
+    pub end_angle: f64,
+    pub width: u32,
+    /// Unique ID from the document's `UniqueIDPrimitiveInformation`, not
+    /// stored for every primitive
+    pub unique_id: Option<UniqueId>,
+}
+
+/// A straight line segment
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    pub layer: Layer,
+    pub locked: bool,
+    pub keepout: bool,
+    pub net: Option<u16>,
+    /// Polygon pour that this primitive was generated by
+    pub polygon: Option<u16>,
+    pub component: Option<u16>,
+    pub start: Location,
+    pub end: Location,
+    pub width: u32,
+    /// Unique ID from the document's `UniqueIDPrimitiveInformation`, not
+    /// stored for every primitive
+    pub unique_id: Option<UniqueId>,
+}
+
+/// Outline of a pad on a single layer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PadShape {
+    #[default]
+    Round,
+    Rect,
+    Octagonal,
+    /// Rectangle with rounded corners. The radius is a percentage of half the
+    /// shorter side, so 100 is the same as `Round`.
+    RoundRect {
+        corner_radius: u8,
+    },
+}
+
+/// Size and shape of a pad on a layer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PadSize {
+    pub x: u32,
+    pub y: u32,
+    pub shape: PadShape,
+}
+
+/// How a pad's shape varies through the layer stack
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PadMode {
+    /// The same on all layers
+    #[default]
+    Simple,
+    /// Separate top, inner and bottom shapes
+    TopMiddleBottom,
+    /// A separate shape on every layer
+    FullStack,
+}
+
+/// Shape of a pad's hole
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HoleShape {
+    #[default]
+    Round,
+    Square,
+    /// A slot. The pad's hole size is the width, `slot_size` is the length.
+    Slot,
+}
+
+/// A pad, either surface mount or through hole
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Pad {
+    pub designator: Box<str>,
+    pub layer: Layer,
+    pub locked: bool,
+    pub net: Option<u16>,
+    pub component: Option<u16>,
+    pub location: Location,
+    pub rotation: f64,
+    pub mode: PadMode,
+    pub top: PadSize,
+    pub middle: PadSize,
+    pub bottom: PadSize,
+    /// Hole diameter, zero for surface mount pads
+    pub hole_size: u32,
+    pub hole_shape: HoleShape,
+    /// Rotation stored in the main pad record. Slots and square holes are
+    /// oriented by `slot_rotation` instead.
+    pub hole_rotation: f64,
+    /// Offset of the hole from the pad center
+    pub hole_offset: Location,
+    pub slot_size: u32,
+    /// Rotation of a slot or square hole relative to the pad, in degrees
+    pub slot_rotation: f64,
+    pub plated: bool,
+    pub tented_top: bool,
+    pub tented_bottom: bool,
+    /// Solder mask expansion, if it is set manually rather than by rules
+    pub solder_mask_expansion: Option<i32>,
+    /// Paste mask expansion, if it is set manually rather than by rules
+    pub paste_mask_expansion: Option<i32>,
+    /// Unique ID from the document's `UniqueIDPrimitiveInformation`, not
+    /// stored for every primitive
+    pub unique_id: Option<UniqueId>,
+}
+
+/// A via. Vias in footprints are typically used for thermal pads.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Via {
+    pub locked: bool,
+    pub net: Option<u16>,
+    pub location: Location,
+    pub diameter: u32,
+    pub hole_size: u32,
+    pub start_layer: Layer,
+    pub end_layer: Layer,
+    pub tented_top: bool,
+    pub tented_bottom: bool,
+    /// Unique ID from the document's `UniqueIDPrimitiveInformation`, not
+    /// stored for every primitive
+    pub unique_id: Option<UniqueId>,
+}
+
+/// Font used to render a text
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextKind {
+    #[default]
+    Stroke,
+    TrueType,
+    BarCode,
+}
+
+/// A string
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Text {
+    pub layer: Layer,
+    pub component: Option<u16>,
+    /// Bottom left corner of the text before rotation
+    pub location: Location,
+    pub height: u32,
+    pub rotation: f64,
+    pub mirrored: bool,
+    pub stroke_width: u32,
+    pub kind: TextKind,
+    pub font_name: Box<str>,
+    pub bold: bool,
+    pub italic: bool,
+    pub is_comment: bool,
+    pub is_designator: bool,
+    /// The displayed string. Special strings such as `.Designator` are not
+    /// expanded.
+    pub text: Box<str>,
+    /// Index into the footprint's wide string table
+    pub(crate) widestring_index: Option<u32>,
+}
+
+/// A filled rectangle
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Fill {
+    pub layer: Layer,
+    pub locked: bool,
+    pub keepout: bool,
+    pub net: Option<u16>,
+    pub component: Option<u16>,
+    pub corner1: Location,
+    pub corner2: Location,
+    /// Rotation about the rectangle's center
+    pub rotation: f64,
+    /// Unique ID from the document's `UniqueIDPrimitiveInformation`, not
+    /// stored for every primitive
+    pub unique_id: Option<UniqueId>,
+}
+
+/// The purpose of a region
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegionKind {
+    #[default]
+    Copper,
+    PolygonCutout,
+    DashedOutline,
+    CavityDefinition,
+    BoardCutout,
+    Unknown(i32),
+}
+
+/// A filled polygon, possibly with holes
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    pub layer: Layer,
+    pub locked: bool,
+    pub keepout: bool,
+    pub net: Option<u16>,
+    /// Polygon pour that this primitive was generated by
+    pub polygon: Option<u16>,
+    pub component: Option<u16>,
+    pub kind: RegionKind,
+    pub outline: Vec<Location>,
+    pub holes: Vec<Vec<Location>>,
+    /// Unique ID from the document's `UniqueIDPrimitiveInformation`, not
+    /// stored for every primitive
+    pub unique_id: Option<UniqueId>,
+}
+
+/// A 3D body, usually referencing a STEP model
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ComponentBody {
+    pub layer: Layer,
+    pub component: Option<u16>,
+    pub identifier: Box<str>,
+    pub model_id: Box<str>,
+    /// File name of the model, e.g. `CAPC1608X09L.step`
+    pub model_name: Box<str>,
+    pub model_embedded: bool,
+    pub model_checksum: Box<str>,
+    /// Offset of the model in the footprint
+    pub model_offset: Location,
+    /// Offset of the model above the board
+    pub model_dz: i32,
+    /// Rotation of the model in the footprint's plane
+    pub model_rotation: f64,
+    /// Rotation of the model about its own X, Y and Z axes
+    pub model_rotation_3d: [f64; 3],
+    pub standoff_height: i32,
+    pub overall_height: i32,
+    pub opacity: f64,
+}
+
+/// Given a buffer of primitives, parse them all
+///
+/// Name is only used for diagnostics
+pub(crate) fn parse_all_records(buf: &[u8], err_name: &str) -> Result<Vec<PcbRecord>, Error> {
+    parse_records(buf, err_name, false)
+}
+
+/// Assign unique IDs from `UniqueIDPrimitiveInformation` entries, which give
+/// a primitive type (e.g. `Pad`) and an index among primitives of that type
+pub(crate) fn assign_unique_ids(records: &mut [PcbRecord], entries: &[Properties]) {
+    let mut ids = BTreeMap::new();
+    for entry in entries {
+        let (Some(kind), Some(idx), Some(id)) = (
+            entry.get("PRIMITIVEOBJECTID"),
+            entry
+                .get("PRIMITIVEINDEX")
+                .and_then(|idx| idx.trim().parse::<usize>().ok()),
+            entry
+                .get("UNIQUEID")
+                .and_then(|id| UniqueId::from_utf8(id.trim().as_bytes()).ok()),
+        ) else {
+            continue;
+        };
+        ids.insert((kind.trim().to_owned(), idx), id);
+    }
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for record in records {
+        let kind = match record {
+            PcbRecord::Arc(_) => "Arc",
+            PcbRecord::Pad(_) => "Pad",
+            PcbRecord::Via(_) => "Via",
+            PcbRecord::Track(_) => "Track",
+            PcbRecord::Fill(_) => "Fill",
+            PcbRecord::Region(_) => "Region",
+            PcbRecord::Text(_) | PcbRecord::ComponentBody(_) => continue,
+        };
+        let idx = counts.entry(kind).or_default();
+        if let Some(dst) = record.unique_id_mut() {
+            *dst = ids.get(&(kind.to_owned(), *idx)).copied();
+        }
+        *idx += 1;
+    }
+}
+
+/// Parse a document's `ShapeBasedRegions6` stream, where region outlines keep
+/// their arcs
+pub(crate) fn parse_shape_based_regions(
+    buf: &[u8],
+    err_name: &str,
+) -> Result<Vec<PcbRecord>, Error> {
+    parse_records(buf, err_name, true)
+}
+
+fn parse_records(buf: &[u8], err_name: &str, shape_based: bool) -> Result<Vec<PcbRecord>, Error> {
+    let mut working = buf;
+    let mut parsed = Vec::new();
+
+    while let Some((&ty, rest)) = working.split_first() {
+        let sub_count = match ty {
+            ARC_TY | VIA_TY | TRACK_TY | FILL_TY | REGION_TY | BODY_TY => 1,
+            TEXT_TY => 2,
+            PAD_TY => 6,
+            _ => {
+                // Records are length prefixed, so we can skip types that we
+                // don't know about. All known types except pads and text have a
+                // single subrecord.
+                warn!("skipping unknown PCB record type {ty} in `{err_name}`");
+                let (_, rest) = extract_sized_buf(rest, BufLenMatch::U32, false)
+                    .or_context(|| format!("reading record of type {ty} for `{err_name}`"))?;
+                working = rest;
+                continue;
+            }
+        };
+
+        let mut subs: [&[u8]; 6] = [&[]; 6];
+        working = rest;
+        for sub in subs.iter_mut().take(sub_count) {
+            let (data, rest) = extract_sized_buf(working, BufLenMatch::U32, false)
+                .or_context(|| format!("reading subrecord of type {ty} for `{err_name}`"))?;
+            *sub = data;
+            working = rest;
+        }
+
+        let record = match ty {
+            ARC_TY => Arc::parse(subs[0]).map(PcbRecord::Arc),
+            PAD_TY => Pad::parse(&subs).map(PcbRecord::Pad),
+            VIA_TY => Via::parse(subs[0]).map(PcbRecord::Via),
+            TRACK_TY => Track::parse(subs[0]).map(PcbRecord::Track),
+            TEXT_TY => Text::parse(subs[0], subs[1]).map(PcbRecord::Text),
+            FILL_TY => Fill::parse(subs[0]).map(PcbRecord::Fill),
+            REGION_TY => Region::parse(subs[0], shape_based).map(PcbRecord::Region),
+            BODY_TY => ComponentBody::parse(subs[0]).map(PcbRecord::ComponentBody),
+            _ => unreachable!("checked above"),
+        };
+
+        parsed.push(record.or_context(|| format!("parsing record type {ty} for `{err_name}`"))?);
+    }
+
+    Ok(parsed)
+}
+
+const ARC_TY: u8 = 1;
+const PAD_TY: u8 = 2;
+const VIA_TY: u8 = 3;
+const TRACK_TY: u8 = 4;
+const TEXT_TY: u8 = 5;
+const FILL_TY: u8 = 6;
+const REGION_TY: u8 = 11;
+const BODY_TY: u8 = 12;
+
+/// Locked unless this bit is set in the first flags byte
+const FLAG_UNLOCKED: u8 = 0x04;
+const FLAG_TENT_TOP: u8 = 0x20;
+const FLAG_TENT_BOTTOM: u8 = 0x40;
+/// Second flags byte value for keepouts
+const KEEPOUT: u8 = 2;
+
+impl Arc {
+    fn parse(buf: &[u8]) -> Result<Self, ErrorKind> {
+        let mut rd = Reader::new(buf);
+        let layer = Layer::from_v6_id(rd.u8()?);
+        let flags1 = rd.u8()?;
+        let flags2 = rd.u8()?;
+        let net = rd.index()?;
+        let polygon = rd.index()?;
+        let component = rd.index()?;
+        rd.skip(4)?;
+
+        Ok(Self {
+            layer,
+            locked: flags1 & FLAG_UNLOCKED == 0,
+            keepout: flags2 == KEEPOUT,
+            net,
+            polygon,
+            component,
+            center: rd.location()?,
+            radius: rd.ulen()?,
+            start_angle: rd.f64()?,
+            end_angle: rd.f64()?,
+            width: rd.ulen()?,
+            unique_id: None,
+        })
+    }
+}
+
+impl Track {
+    fn parse(buf: &[u8]) -> Result<Self, ErrorKind> {
+        let mut rd = Reader::new(buf);
+        let layer = Layer::from_v6_id(rd.u8()?);
+        let flags1 = rd.u8()?;
+        let flags2 = rd.u8()?;
+        let net = rd.index()?;
+        let polygon = rd.index()?;
+        let component = rd.index()?;
+        rd.skip(4)?;
+
+        Ok(Self {
+            layer,
+            locked: flags1 & FLAG_UNLOCKED == 0,
+            keepout: flags2 == KEEPOUT,
+            net,
+            polygon,
+            component,
+            start: rd.location()?,
+            end: rd.location()?,
+            width: rd.ulen()?,
+            unique_id: None,
+        })
+    }
+}
+
+impl PadShape {
+    fn from_u8(val: u8) -> Self {
+        match val {
+            2 => Self::Rect,
+            3 => Self::Octagonal,
+            // 1 is round, treat anything unknown the same
+            _ => Self::Round,
+        }
+    }
+}
+
+impl Pad {
+    /// Minimum length of the subrecord holding per-layer shapes
+    const SIZE_SHAPE_MIN_LEN: usize = 596;
+    const INNER_LAYERS: usize = 29;
+    const ALL_LAYERS: usize = 32;
+    const ALT_SHAPE_ROUNDRECT: u8 = 9;
+
+    fn parse(subs: &[&[u8]; 6]) -> Result<Self, ErrorKind> {
+        let designator = Reader::new(subs[0]).u8_str()?;
+        let mut rd = Reader::new(subs[4]);
+
+        let layer = Layer::from_v6_id(rd.u8()?);
+        let flags1 = rd.u8()?;
+        let _flags2 = rd.u8()?;
+        let net = rd.index()?;
+        rd.skip(2)?;
+        let component = rd.index()?;
+        rd.skip(4)?;
+        let location = rd.location()?;
+
+        let mut sizes = [PadSize::default(); 3];
+        for size in &mut sizes {
+            size.x = rd.ulen()?;
+            size.y = rd.ulen()?;
+        }
+        let hole_size = rd.ulen()?;
+        for size in &mut sizes {
+            size.shape = PadShape::from_u8(rd.u8()?);
+        }
+
+        let rotation = rd.f64()?;
+        let plated = rd.bool()?;
+        rd.skip(1)?;
+        let mode = match rd.u8()? {
+            1 => PadMode::TopMiddleBottom,
+            2 => PadMode::FullStack,
+            _ => PadMode::Simple,
+        };
+        rd.skip(23)?;
+        let paste_manual = rd.len()?;
+        let solder_manual = rd.len()?;
+        rd.skip(7)?;
+        // 0 is none, 1 is from rules, 2 is manual
+        let paste_mode = rd.u8()?;
+        let solder_mode = rd.u8()?;
+        rd.skip(3)?;
+        let hole_rotation = rd.f64()?;
+
+        let [top, middle, bottom] = sizes;
+        let mut ret = Self {
+            designator,
+            layer,
+            locked: flags1 & FLAG_UNLOCKED == 0,
+            net,
+            component,
+            location,
+            rotation,
+            mode,
+            top,
+            middle,
+            bottom,
+            hole_size,
+            hole_rotation,
+            plated,
+            tented_top: flags1 & FLAG_TENT_TOP != 0,
+            tented_bottom: flags1 & FLAG_TENT_BOTTOM != 0,
+            solder_mask_expansion: (solder_mode == 2).then_some(solder_manual),
+            paste_mask_expansion: (paste_mode == 2).then_some(paste_manual),
+            ..Default::default()
+        };
+
+        if subs[5].len() >= Self::SIZE_SHAPE_MIN_LEN {
+            ret.parse_size_and_shape(subs[5])?;
+        }
+
+        Ok(ret)
+    }
+
+    /// Parse the optional subrecord that has inner layer shapes, hole details
+    /// and rounded rectangle information
+    fn parse_size_and_shape(&mut self, buf: &[u8]) -> Result<(), ErrorKind> {
+        let mut rd = Reader::new(buf);
+        // Inner layer sizes (x then y) and shapes, we only keep one middle shape
+        rd.skip(Self::INNER_LAYERS * 4 * 2 + Self::INNER_LAYERS)?;
+        rd.skip(1)?;
+        self.hole_shape = match rd.u8()? {
+            1 => HoleShape::Square,
+            2 => HoleShape::Slot,
+            _ => HoleShape::Round,
+        };
+        self.slot_size = rd.ulen()?;
+        self.slot_rotation = rd.f64()?;
+
+        // Hole offsets for every layer, x then y. We only use the top layer.
+        let all_x = rd.bytes(Self::ALL_LAYERS * 4)?;
+        let all_y = rd.bytes(Self::ALL_LAYERS * 4)?;
+        self.hole_offset = Location::new(Reader::new(all_x).len()?, Reader::new(all_y).len()?);
+
+        rd.skip(1)?;
+        let alt_shapes = rd.bytes(Self::ALL_LAYERS)?;
+        let radii = rd.bytes(Self::ALL_LAYERS)?;
+
+        // Index 0 is the top layer, the last index is the bottom
+        let sizes = [
+            (&mut self.top, 0),
+            (&mut self.middle, 1),
+            (&mut self.bottom, Self::ALL_LAYERS - 1),
+        ];
+        for (size, idx) in sizes {
+            if alt_shapes[idx] == Self::ALT_SHAPE_ROUNDRECT {
+                size.shape = PadShape::RoundRect {
+                    corner_radius: radii[idx],
+                };
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Via {
+    fn parse(buf: &[u8]) -> Result<Self, ErrorKind> {
+        let mut rd = Reader::new(buf);
+        rd.skip(1)?;
+        let flags1 = rd.u8()?;
+        let _flags2 = rd.u8()?;
+        let net = rd.index()?;
+        rd.skip(8)?;
+
+        // TODO: longer subrecords have via stack modes
+        Ok(Self {
+            locked: flags1 & FLAG_UNLOCKED == 0,
+            net,
+            location: rd.location()?,
+            diameter: rd.ulen()?,
+            hole_size: rd.ulen()?,
+            start_layer: Layer::from_v6_id(rd.u8()?),
+            end_layer: Layer::from_v6_id(rd.u8()?),
+            tented_top: flags1 & FLAG_TENT_TOP != 0,
+            tented_bottom: flags1 & FLAG_TENT_BOTTOM != 0,
+            unique_id: None,
+        })
+    }
+}
+
+impl Text {
+    /// Subrecords at least this long have font information
+    const FONT_MIN_LEN: usize = 123;
+    const FONT_NAME_LEN: usize = 64;
+
+    fn parse(buf: &[u8], text_buf: &[u8]) -> Result<Self, ErrorKind> {
+        let mut rd = Reader::new(buf);
+        let layer = Layer::from_v6_id(rd.u8()?);
+        rd.skip(6)?;
+        let component = rd.index()?;
+        rd.skip(4)?;
+
+        let mut ret = Self {
+            layer,
+            component,
+            location: rd.location()?,
+            height: rd.ulen()?,
+            ..Default::default()
+        };
+        let _stroke_font = rd.u16()?;
+        ret.rotation = rd.f64()?;
+        ret.mirrored = rd.bool()?;
+        ret.stroke_width = rd.ulen()?;
+
+        if buf.len() >= Self::FONT_MIN_LEN {
+            rd.skip(1)?;
+            ret.is_comment = rd.bool()?;
+            ret.is_designator = rd.bool()?;
+            ret.kind = match rd.u8()? {
+                1 => TextKind::TrueType,
+                2 => TextKind::BarCode,
+                _ => TextKind::Stroke,
+            };
+            ret.bold = rd.bool()?;
+            ret.italic = rd.bool()?;
+            ret.font_name = rd.utf16(Self::FONT_NAME_LEN)?.into();
+            let _inverted = rd.bool()?;
+            let _border_width = rd.len()?;
+            ret.widestring_index = Some(rd.u32()?);
+        }
+
+        ret.text = Reader::new(text_buf).u8_str()?;
+        Ok(ret)
+    }
+}
+
+impl Fill {
+    fn parse(buf: &[u8]) -> Result<Self, ErrorKind> {
+        let mut rd = Reader::new(buf);
+        let layer = Layer::from_v6_id(rd.u8()?);
+        let flags1 = rd.u8()?;
+        let flags2 = rd.u8()?;
+        let net = rd.index()?;
+        rd.skip(2)?;
+        let component = rd.index()?;
+        rd.skip(4)?;
+
+        Ok(Self {
+            layer,
+            locked: flags1 & FLAG_UNLOCKED == 0,
+            keepout: flags2 == KEEPOUT,
+            net,
+            component,
+            corner1: rd.location()?,
+            corner2: rd.location()?,
+            rotation: rd.f64()?,
+            unique_id: None,
+        })
+    }
+}
+
+impl Region {
+    /// Parse a region. Shape based regions have outline vertices that may be
+    /// arcs, which are converted to line segments.
+    fn parse(buf: &[u8], shape_based: bool) -> Result<Self, ErrorKind> {
+        let mut rd = Reader::new(buf);
+        let mut layer = Layer::from_v6_id(rd.u8()?);
+        let flags1 = rd.u8()?;
+        let flags2 = rd.u8()?;
+        let net = rd.index()?;
+        let polygon = rd.index()?;
+        let component = rd.index()?;
+        rd.skip(5)?;
+        let hole_count = rd.u16()?;
+        rd.skip(2)?;
+
+        let props = rd.properties()?;
+        if let Some(v7) = props.get("V7_LAYER").and_then(Layer::from_ident) {
+            layer = v7;
+        }
+        let kind = match props.get_int("KIND")? {
+            0 if props.get_bool("ISBOARDCUTOUT") => RegionKind::BoardCutout,
+            0 => RegionKind::Copper,
+            1 => RegionKind::PolygonCutout,
+            2 => RegionKind::DashedOutline,
+            4 => RegionKind::CavityDefinition,
+            v => RegionKind::Unknown(v),
+        };
+
+        let outline = if shape_based {
+            read_shape_vertices(&mut rd)?
+        } else {
+            read_vertices(&mut rd)?
+        };
+        let holes = (0..hole_count)
+            .map(|_| read_vertices(&mut rd))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            layer,
+            locked: flags1 & FLAG_UNLOCKED == 0,
+            keepout: flags2 == KEEPOUT,
+            net,
+            polygon,
+            component,
+            kind,
+            outline,
+            holes,
+            unique_id: None,
+        })
+    }
+}
+
+/// Read vertices that may be arcs. The count doesn't include the closing
+/// vertex. Each is a flag for arcs, the vertex location, the arc's center and
+/// radius, then its start and end angles.
+fn read_shape_vertices(rd: &mut Reader) -> Result<Vec<Location>, ErrorKind> {
+    /// Length of each vertex
+    const VERTEX_LEN: usize = 37;
+
+    let count = rd.u32()?.saturating_add(1);
+    let mut ret = Vec::with_capacity((count as usize).min(rd.remaining() / VERTEX_LEN));
+    for _ in 0..count {
+        let is_arc = rd.bool()?;
+        let location = rd.location()?;
+        let center = rd.location()?;
+        let radius = rd.ulen()?;
+        let start_angle = rd.f64()?;
+        let end_angle = rd.f64()?;
+
+        if is_arc {
+            push_arc_vertices(&mut ret, location, center, radius, start_angle, end_angle);
+        } else {
+            ret.push(location);
+        }
+    }
+    Ok(ret)
+}
+
+/// Read an outline stored as `KIND<n>`, `VX<n>`, `VY<n>`, ... properties, as
+/// used for the board shape and polygon pours. Arcs become line segments.
+pub(crate) fn parse_prop_outline(props: &Properties) -> Result<Vec<Location>, ErrorKind> {
+    let mut ret = Vec::new();
+    for idx in 0.. {
+        let key = |pfx: &str| format!("{pfx}{idx}");
+        if props.get(&key("VX")).is_none() {
+            break;
+        }
+
+        let location = Location::new(props.get_len(&key("VX"))?, props.get_len(&key("VY"))?);
+        if props.get_int(&key("KIND"))? == 1 {
+            let center = Location::new(props.get_len(&key("CX"))?, props.get_len(&key("CY"))?);
+            push_arc_vertices(
+                &mut ret,
+                location,
+                center,
+                props.get_len(&key("R"))?.unsigned_abs(),
+                props.get_f64(&key("SA"))?,
+                props.get_f64(&key("EA"))?,
+            );
+        } else {
+            ret.push(location);
+        }
+    }
+    Ok(ret)
+}
+
+/// Approximate an outline arc with points at most 5 degrees apart. Arcs are
+/// counterclockwise, but the outline may run in either direction; the end
+/// nearest to the vertex location comes first.
+fn push_arc_vertices(
+    out: &mut Vec<Location>,
+    location: Location,
+    center: Location,
+    radius: u32,
+    start_angle: f64,
+    end_angle: f64,
+) {
+    const MAX_STEP: f64 = 5.0;
+
+    let point = |angle: f64| {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Location::new(
+            center.x + f64_to_i32(f64::from(radius) * cos),
+            center.y + f64_to_i32(f64::from(radius) * sin),
+        )
+    };
+    let dist = |a: Location| {
+        (f64::from(a.x) - f64::from(location.x)).hypot(f64::from(a.y) - f64::from(location.y))
+    };
+
+    let mut sweep = (end_angle - start_angle).rem_euclid(360.0);
+    if sweep == 0.0 {
+        sweep = 360.0;
+    }
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let steps = (sweep / MAX_STEP).ceil().max(1.0) as u32;
+    let mut points: Vec<_> = (0..=steps)
+        .map(|i| point(start_angle + sweep * f64::from(i) / f64::from(steps)))
+        .collect();
+    if dist(points[0]) > dist(points[points.len() - 1]) {
+        points.reverse();
+    }
+    out.extend(points);
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn f64_to_i32(val: f64) -> i32 {
+    val.round() as i32
+}
+
+/// Read a `u32` count followed by that many pairs of `f64`s
+fn read_vertices(rd: &mut Reader) -> Result<Vec<Location>, ErrorKind> {
+    let count = rd.u32()?;
+    // Each vertex is 16 bytes, don't trust the count for preallocation
+    let mut ret = Vec::with_capacity((count as usize).min(rd.remaining() / 16));
+    for _ in 0..count {
+        ret.push(rd.location_f64()?);
+    }
+    Ok(ret)
+}
+
+impl ComponentBody {
+    fn parse(buf: &[u8]) -> Result<Self, ErrorKind> {
+        let mut rd = Reader::new(buf);
+        let layer = Layer::from_v6_id(rd.u8()?);
+        rd.skip(6)?;
+        let component = rd.index()?;
+        rd.skip(9)?;
+        let props = rd.properties()?;
+
+        Ok(Self {
+            layer: props
+                .get("V7_LAYER")
+                .and_then(Layer::from_ident)
+                .unwrap_or(layer),
+            component,
+            identifier: props.get_str("IDENTIFIER"),
+            model_id: props.get_str("MODELID"),
+            model_name: props.get_str("MODEL.NAME"),
+            model_embedded: props.get_bool("MODEL.EMBED"),
+            model_checksum: props.get_str("MODEL.CHECKSUM"),
+            model_offset: Location::new(props.get_len("MODEL.2D.X")?, props.get_len("MODEL.2D.Y")?),
+            model_dz: props.get_len("MODEL.3D.DZ")?,
+            model_rotation: props.get_f64("MODEL.2D.ROTATION")?,
+            model_rotation_3d: [
+                props.get_f64("MODEL.3D.ROTX")?,
+                props.get_f64("MODEL.3D.ROTY")?,
+                props.get_f64("MODEL.3D.ROTZ")?,
+            ],
+            standoff_height: props.get_len("STANDOFFHEIGHT")?,
+            overall_height: props.get_len("OVERALLHEIGHT")?,
+            opacity: props.get_f64("BODYOPACITY3D")?,
+        })
+    }
+}
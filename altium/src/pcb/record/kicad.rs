@@ -0,0 +1,758 @@
+//! Conversion of footprint primitives to KiCad footprints
+
+use log::warn;
+
+use super::{
+    Arc,
+    ComponentBody,
+    Fill,
+    HoleShape,
+    Pad,
+    PadShape,
+    PadSize,
+    PcbRecord,
+    Region,
+    RegionKind,
+    Text,
+    TextKind,
+    Track,
+    Via,
+};
+use crate::common::Location;
+use crate::kicad::{LayerMap, SExpr};
+use crate::pcb::{Footprint, Layer};
+
+/// Size and thickness of the reference and value fields
+const FIELD_FONT_SIZE: i64 = 1_000_000;
+const FIELD_FONT_THICKNESS: i64 = 150_000;
+/// Distance between the footprint's extents and the reference or value
+const FIELD_OFFSET: i64 = 1_000_000;
+/// Line width used for polygons that KiCad requires to have an outline
+const OUTLINE_WIDTH: i64 = 50_000;
+/// Size of the anchor pad that custom pad shapes are built on
+const CUSTOM_ANCHOR_SIZE: i64 = 100_000;
+/// Octagonal pads become chamfered rectangles with this ratio
+const OCTAGON_CHAMFER_RATIO: f64 = 0.25;
+
+/// Convert a footprint to a KiCad `footprint` expression
+///
+/// `model_dir` is prepended to 3D model file names.
+pub(crate) fn kicad_footprint(footprint: &Footprint, layers: &LayerMap, model_dir: &str) -> SExpr {
+    let records = footprint.records();
+    let mut ret = SExpr::list(
+        "footprint",
+        [
+            SExpr::string(footprint.name()),
+            SExpr::pair("version", crate::kicad::FOOTPRINT_VERSION),
+            SExpr::pair("generator", crate::kicad::GENERATOR),
+            SExpr::list("layer", [SExpr::string("F.Cu")]),
+        ],
+    );
+
+    if !footprint.description().is_empty() {
+        ret.push(SExpr::list(
+            "descr",
+            [SExpr::string(footprint.description())],
+        ));
+    }
+
+    let pads = records.iter().filter_map(|record| match record {
+        PcbRecord::Pad(pad) => Some(pad),
+        _ => None,
+    });
+    if pads.clone().any(|pad| pad.hole_size > 0) {
+        ret.push(SExpr::pair("attr", "through_hole"));
+    } else if pads.clone().next().is_some() {
+        ret.push(SExpr::pair("attr", "smd"));
+    }
+
+    let (y_min, y_max) = y_extents(records);
+    ret.push(field(
+        "reference",
+        "REF**",
+        "F.SilkS",
+        -i64::from(y_max) - FIELD_OFFSET,
+    ));
+    ret.push(field(
+        "value",
+        footprint.name(),
+        "F.Fab",
+        -i64::from(y_min) + FIELD_OFFSET,
+    ));
+
+    // Copper regions that overlap a pad get merged into it as a custom pad so
+    // they stay connected
+    let mut custom_pads = Vec::new();
+    let mut graphics = Vec::new();
+    for record in records {
+        match record {
+            PcbRecord::Region(region) => {
+                if let Some(pad) = custom_pad_owner(region, pads.clone()) {
+                    custom_pads.push(custom_pad(region, pad, layers));
+                } else {
+                    graphics.extend(self::region(region, layers));
+                }
+            }
+            _ => graphics.extend(item(record, layers, model_dir)),
+        }
+    }
+
+    // KiCad expects graphics, then pads, then zones, then models
+    let order = |expr: &SExpr| match expr_name(expr) {
+        "pad" => 1,
+        "zone" => 2,
+        "model" => 3,
+        _ => 0,
+    };
+    graphics.extend(custom_pads);
+    graphics.sort_by_key(order);
+    for expr in graphics {
+        ret.push(expr);
+    }
+
+    ret
+}
+
+fn item(record: &PcbRecord, layers: &LayerMap, model_dir: &str) -> Option<SExpr> {
+    match record {
+        PcbRecord::Arc(v) => arc(v, layers),
+        PcbRecord::Pad(v) => Some(pad(v, layers)),
+        PcbRecord::Via(v) => Some(via(v)),
+        PcbRecord::Track(v) => track(v, layers),
+        PcbRecord::Text(v) => text(v, layers),
+        PcbRecord::Fill(v) => fill(v, layers),
+        PcbRecord::Region(v) => region(v, layers),
+        PcbRecord::ComponentBody(v) => model(v, model_dir),
+    }
+}
+
+fn track(track: &Track, layers: &LayerMap) -> Option<SExpr> {
+    let layer = graphic_layer(track.layer, layers)?;
+    Some(SExpr::list(
+        "fp_line",
+        [
+            xy("start", track.start),
+            xy("end", track.end),
+            layer,
+            width(track.width.into()),
+        ],
+    ))
+}
+
+fn arc(arc: &Arc, layers: &LayerMap) -> Option<SExpr> {
+    let layer = graphic_layer(arc.layer, layers)?;
+    let radius = to_i32(arc.radius);
+    let start = arc.start_angle;
+    let mut end = arc.end_angle;
+
+    if (end - start).rem_euclid(360.0) == 0.0 {
+        return Some(SExpr::list(
+            "fp_circle",
+            [
+                xy("center", arc.center),
+                xy("end", arc.center.add_x(radius)),
+                layer,
+                width(arc.width.into()),
+                SExpr::pair("fill", "none"),
+            ],
+        ));
+    }
+    if end < start {
+        end += 360.0;
+    }
+
+    // Altium arcs run counterclockwise from start to end, the midpoint tells
+    // KiCad which way to go
+    let [start, mid, end] =
+        [start, f64::midpoint(start, end), end].map(|angle| point_at(arc.center, radius, angle));
+
+    Some(SExpr::list(
+        "fp_arc",
+        [
+            xy("start", start),
+            xy("mid", mid),
+            xy("end", end),
+            layer,
+            width(arc.width.into()),
+        ],
+    ))
+}
+
+fn text(text: &Text, layers: &LayerMap) -> Option<SExpr> {
+    let layer = graphic_layer(text.layer, layers)?;
+    let content = match &*text.text {
+        ".Designator" => "${REFERENCE}".to_owned(),
+        ".Comment" => "${VALUE}".to_owned(),
+        s => s.replace("\r\n", "\n"),
+    };
+
+    let height = i64::from(text.height);
+    let thickness = match text.kind {
+        TextKind::Stroke => i64::from(text.stroke_width),
+        // Approximate the weight of TrueType fonts
+        TextKind::TrueType | TextKind::BarCode => height * 3 / 20,
+    };
+    let mut font = SExpr::list(
+        "font",
+        [
+            SExpr::list("size", [SExpr::mm(height), SExpr::mm(height)]),
+            SExpr::list("thickness", [SExpr::mm(thickness)]),
+        ],
+    );
+    if text.bold {
+        font.push(SExpr::atom("bold"));
+    }
+    if text.italic {
+        font.push(SExpr::atom("italic"));
+    }
+
+    // Altium positions text by its bottom left corner
+    let mut justify = SExpr::list("justify", [SExpr::atom("left"), SExpr::atom("bottom")]);
+    if text.mirrored {
+        justify.push(SExpr::atom("mirror"));
+    }
+
+    Some(SExpr::list(
+        "fp_text",
+        [
+            SExpr::atom("user"),
+            SExpr::string(&content),
+            at(text.location, text.rotation),
+            layer,
+            SExpr::list("effects", [font, justify]),
+        ],
+    ))
+}
+
+fn fill(fill: &Fill, layers: &LayerMap) -> Option<SExpr> {
+    let layer = graphic_layer(fill.layer, layers)?;
+    let (x1, y1) = (fill.corner1.x, fill.corner1.y);
+    let (x2, y2) = (fill.corner2.x, fill.corner2.y);
+    let center = Location::new(average(x1, x2), average(y1, y2));
+    let corners = [(x1, y1), (x2, y1), (x2, y2), (x1, y2)]
+        .map(|(x, y)| rotate_about(Location::new(x, y), center, fill.rotation));
+
+    Some(poly(&corners, layer, true))
+}
+
+fn region(region: &Region, layers: &LayerMap) -> Option<SExpr> {
+    if !region.holes.is_empty() {
+        warn!("KiCad footprint polygons can't have holes, ignoring them");
+    }
+
+    if region.keepout || region.layer == Layer::KeepOut {
+        return Some(keepout_zone(region, layers));
+    }
+
+    match region.kind {
+        RegionKind::Copper => {
+            let layer = graphic_layer(region.layer, layers)?;
+            Some(poly(&region.outline, layer, true))
+        }
+        RegionKind::BoardCutout => Some(poly(
+            &region.outline,
+            SExpr::list("layer", [SExpr::string("Edge.Cuts")]),
+            false,
+        )),
+        RegionKind::DashedOutline => {
+            let layer = graphic_layer(region.layer, layers)?;
+            Some(poly(&region.outline, layer, false))
+        }
+        RegionKind::PolygonCutout | RegionKind::CavityDefinition | RegionKind::Unknown(_) => {
+            warn!("skipping region of kind {:?}", region.kind);
+            None
+        }
+    }
+}
+
+fn keepout_zone(region: &Region, layers: &LayerMap) -> SExpr {
+    // Keepouts on the keepout layer apply to all copper
+    let layer = match region.layer {
+        Layer::KeepOut | Layer::MultiLayer => "*.Cu",
+        layer => layers.kicad_layer(layer).unwrap_or("*.Cu"),
+    };
+    let not_allowed = |name| SExpr::pair(name, "not_allowed");
+
+    SExpr::list(
+        "zone",
+        [
+            SExpr::pair("net", 0),
+            SExpr::list("net_name", [SExpr::string("")]),
+            SExpr::list("layers", [SExpr::string(layer)]),
+            SExpr::list("hatch", [SExpr::atom("edge"), SExpr::mm(508_000)]),
+            SExpr::list("connect_pads", [SExpr::list("clearance", [SExpr::mm(0)])]),
+            SExpr::list("min_thickness", [SExpr::mm(254_000)]),
+            SExpr::list(
+                "keepout",
+                [
+                    not_allowed("tracks"),
+                    not_allowed("vias"),
+                    not_allowed("pads"),
+                    not_allowed("copperpour"),
+                    SExpr::pair("footprints", "allowed"),
+                ],
+            ),
+            SExpr::list(
+                "fill",
+                [
+                    SExpr::list("thermal_gap", [SExpr::mm(508_000)]),
+                    SExpr::list("thermal_bridge_width", [SExpr::mm(508_000)]),
+                ],
+            ),
+            SExpr::list("polygon", [pts(&region.outline)]),
+        ],
+    )
+}
+
+/// Find a pad on the same side whose center is inside a copper region
+fn custom_pad_owner<'a, I>(region: &Region, mut pads: I) -> Option<&'a Pad>
+where
+    I: Iterator<Item = &'a Pad>,
+{
+    if region.kind != RegionKind::Copper
+        || region.keepout
+        || !matches!(region.layer, Layer::Top | Layer::Bottom)
+    {
+        return None;
+    }
+
+    pads.find(|pad| {
+        (pad.layer == region.layer || pad.layer == Layer::MultiLayer)
+            && contains(&region.outline, pad.location)
+    })
+}
+
+/// A custom pad anchored at `pad`'s center with the region as its shape
+fn custom_pad(region: &Region, pad: &Pad, layers: &LayerMap) -> SExpr {
+    let outline: Vec<Location> = region
+        .outline
+        .iter()
+        .map(|loc| Location::new(loc.x - pad.location.x, loc.y - pad.location.y))
+        .collect();
+    let layer_list = SExpr::list(
+        "layers",
+        smd_layers(region.layer, pad, layers)
+            .iter()
+            .map(|name| SExpr::string(name)),
+    );
+
+    SExpr::list(
+        "pad",
+        [
+            SExpr::string(&pad.designator),
+            SExpr::atom("smd"),
+            SExpr::atom("custom"),
+            at(pad.location, 0.0),
+            SExpr::list(
+                "size",
+                [SExpr::mm(CUSTOM_ANCHOR_SIZE), SExpr::mm(CUSTOM_ANCHOR_SIZE)],
+            ),
+            layer_list,
+            SExpr::list(
+                "options",
+                [
+                    SExpr::pair("clearance", "outline"),
+                    SExpr::pair("anchor", "circle"),
+                ],
+            ),
+            SExpr::list(
+                "primitives",
+                [SExpr::list(
+                    "gr_poly",
+                    [pts(&outline), width(0), SExpr::pair("fill", "yes")],
+                )],
+            ),
+        ],
+    )
+}
+
+fn pad(pad: &Pad, layers: &LayerMap) -> SExpr {
+    let (ty, layer_names) = if pad.hole_size > 0 {
+        let ty = if pad.plated {
+            "thru_hole"
+        } else {
+            "np_thru_hole"
+        };
+        let mut names = vec!["*.Cu".to_owned()];
+        match (pad.tented_top, pad.tented_bottom) {
+            (false, false) => names.push("*.Mask".to_owned()),
+            (false, true) => names.push("F.Mask".to_owned()),
+            (true, false) => names.push("B.Mask".to_owned()),
+            (true, true) => (),
+        }
+        (ty, names)
+    } else {
+        ("smd", smd_layers(pad.layer, pad, layers))
+    };
+
+    // Bottom side pads use the bottom shape, everything else the top
+    let size = if pad.layer == Layer::Bottom {
+        &pad.bottom
+    } else {
+        &pad.top
+    };
+
+    let mut ret = SExpr::list(
+        "pad",
+        [
+            SExpr::string(&pad.designator),
+            SExpr::atom(ty),
+            SExpr::atom(shape_name(size)),
+            at(pad.location, pad.rotation),
+            SExpr::list("size", [SExpr::mm(size.x.into()), SExpr::mm(size.y.into())]),
+        ],
+    );
+
+    if pad.hole_size > 0 {
+        ret.push(drill(pad));
+    }
+
+    ret.push(SExpr::list(
+        "layers",
+        layer_names.iter().map(|name| SExpr::string(name)),
+    ));
+
+    match size.shape {
+        PadShape::RoundRect { corner_radius } => ret.push(SExpr::list(
+            "roundrect_rratio",
+            [SExpr::float(f64::from(corner_radius.min(100)) / 200.0)],
+        )),
+        PadShape::Octagonal => {
+            ret.push(SExpr::list("roundrect_rratio", [SExpr::atom(0)]));
+            ret.push(SExpr::list(
+                "chamfer_ratio",
+                [SExpr::float(OCTAGON_CHAMFER_RATIO)],
+            ));
+            ret.push(SExpr::list(
+                "chamfer",
+                ["top_left", "top_right", "bottom_left", "bottom_right"].map(SExpr::atom),
+            ));
+        }
+        PadShape::Round | PadShape::Rect => (),
+    }
+
+    if let Some(v) = pad.solder_mask_expansion {
+        ret.push(SExpr::list("solder_mask_margin", [SExpr::mm(v.into())]));
+    }
+    if let Some(v) = pad.paste_mask_expansion {
+        ret.push(SExpr::list("solder_paste_margin", [SExpr::mm(v.into())]));
+    }
+
+    ret
+}
+
+/// Layers for a pad without a hole
+fn smd_layers(layer: Layer, pad: &Pad, layers: &LayerMap) -> Vec<String> {
+    let (side, tented) = match layer {
+        Layer::Top => ("F", pad.tented_top),
+        Layer::Bottom => ("B", pad.tented_bottom),
+        // Pads on a single non-copper layer, e.g. paste only
+        layer => {
+            return layers
+                .kicad_layer(layer)
+                .map(String::from)
+                .into_iter()
+                .collect()
+        }
+    };
+
+    let mut ret = vec![format!("{side}.Cu"), format!("{side}.Paste")];
+    if !tented {
+        ret.push(format!("{side}.Mask"));
+    }
+    ret
+}
+
+fn shape_name(size: &PadSize) -> &'static str {
+    match size.shape {
+        PadShape::Round if size.x == size.y => "circle",
+        PadShape::Round => "oval",
+        PadShape::Rect => "rect",
+        PadShape::RoundRect { corner_radius } if corner_radius >= 100 => {
+            if size.x == size.y {
+                "circle"
+            } else {
+                "oval"
+            }
+        }
+        PadShape::Octagonal | PadShape::RoundRect { .. } => "roundrect",
+    }
+}
+
+fn drill(pad: &Pad) -> SExpr {
+    let hole = i64::from(pad.hole_size);
+    let mut ret = match pad.hole_shape {
+        HoleShape::Slot => {
+            // KiCad slots are aligned with the pad
+            let rot = pad.slot_rotation.rem_euclid(180.0);
+            let slot = i64::from(pad.slot_size);
+            let (x, y) = if (rot - 90.0).abs() < 1.0 {
+                (hole, slot)
+            } else {
+                if rot > 1.0 && rot < 179.0 {
+                    warn!(
+                        "pad {} slot rotation {rot} is not supported",
+                        pad.designator
+                    );
+                }
+                (slot, hole)
+            };
+            SExpr::list("drill", [SExpr::atom("oval"), SExpr::mm(x), SExpr::mm(y)])
+        }
+        HoleShape::Square => {
+            warn!("pad {} has a square hole, using round", pad.designator);
+            SExpr::list("drill", [SExpr::mm(hole)])
+        }
+        HoleShape::Round => SExpr::list("drill", [SExpr::mm(hole)]),
+    };
+
+    if pad.hole_offset != Location::default() {
+        ret.push(xy("offset", pad.hole_offset));
+    }
+
+    ret
+}
+
+fn via(via: &Via) -> SExpr {
+    let mut layers = vec![SExpr::string("*.Cu")];
+    if !via.tented_top {
+        layers.push(SExpr::string("F.Mask"));
+    }
+    if !via.tented_bottom {
+        layers.push(SExpr::string("B.Mask"));
+    }
+
+    SExpr::list(
+        "pad",
+        [
+            SExpr::string(""),
+            SExpr::atom("thru_hole"),
+            SExpr::atom("circle"),
+            at(via.location, 0.0),
+            SExpr::list(
+                "size",
+                [
+                    SExpr::mm(via.diameter.into()),
+                    SExpr::mm(via.diameter.into()),
+                ],
+            ),
+            SExpr::list("drill", [SExpr::mm(via.hole_size.into())]),
+            SExpr::list("layers", layers),
+        ],
+    )
+}
+
+fn model(body: &ComponentBody, model_dir: &str) -> Option<SExpr> {
+    if body.model_name.is_empty() {
+        return None;
+    }
+
+    let path = if model_dir.is_empty() {
+        body.model_name.to_string()
+    } else {
+        format!("{}/{}", model_dir.trim_end_matches('/'), body.model_name)
+    };
+    let [rot_x, rot_y, rot_z] = body.model_rotation_3d;
+    let xyz = |name, vals: [SExpr; 3]| SExpr::list(name, [SExpr::list("xyz", vals)]);
+
+    // Model offsets are Y up in KiCad too
+    Some(SExpr::list(
+        "model",
+        [
+            SExpr::string(&path),
+            xyz(
+                "offset",
+                [
+                    SExpr::mm(body.model_offset.x.into()),
+                    SExpr::mm(body.model_offset.y.into()),
+                    SExpr::mm(body.model_dz.into()),
+                ],
+            ),
+            xyz("scale", [1, 1, 1].map(SExpr::atom)),
+            xyz(
+                "rotate",
+                [
+                    SExpr::float(normalize_angle(-rot_x)),
+                    SExpr::float(normalize_angle(-rot_y)),
+                    SExpr::float(normalize_angle(body.model_rotation - rot_z)),
+                ],
+            ),
+        ],
+    ))
+}
+
+fn field(kind: &str, value: &str, layer: &str, y: i64) -> SExpr {
+    let size = SExpr::list(
+        "size",
+        [SExpr::mm(FIELD_FONT_SIZE), SExpr::mm(FIELD_FONT_SIZE)],
+    );
+    let thickness = SExpr::list("thickness", [SExpr::mm(FIELD_FONT_THICKNESS)]);
+
+    SExpr::list(
+        "fp_text",
+        [
+            SExpr::atom(kind),
+            SExpr::string(value),
+            SExpr::list("at", [SExpr::mm(0), SExpr::mm(y)]),
+            SExpr::list("layer", [SExpr::string(layer)]),
+            SExpr::list("effects", [SExpr::list("font", [size, thickness])]),
+        ],
+    )
+}
+
+/// Lowest and highest Y coordinate of pads and graphics, used to place the
+/// reference and value
+fn y_extents(records: &[PcbRecord]) -> (i32, i32) {
+    let mut min = 0;
+    let mut max = 0;
+    let mut add = |y: i32, half: u32| {
+        let half = to_i32(half);
+        min = min.min(y.saturating_sub(half));
+        max = max.max(y.saturating_add(half));
+    };
+
+    for record in records {
+        match record {
+            PcbRecord::Pad(pad) => add(pad.location.y, pad.top.x.max(pad.top.y) / 2),
+            PcbRecord::Via(via) => add(via.location.y, via.diameter / 2),
+            PcbRecord::Track(track) => {
+                add(track.start.y, track.width / 2);
+                add(track.end.y, track.width / 2);
+            }
+            PcbRecord::Arc(arc) => add(arc.center.y, arc.radius + arc.width / 2),
+            PcbRecord::Fill(fill) => {
+                add(fill.corner1.y, 0);
+                add(fill.corner2.y, 0);
+            }
+            PcbRecord::Region(region) => region.outline.iter().for_each(|loc| add(loc.y, 0)),
+            PcbRecord::Text(_) | PcbRecord::ComponentBody(_) => (),
+        }
+    }
+
+    (min, max)
+}
+
+/// The KiCad layer for a graphic item, or `None` (with a warning) if there is
+/// no equivalent
+fn graphic_layer(layer: Layer, layers: &LayerMap) -> Option<SExpr> {
+    let Some(name) = layers.kicad_layer(layer) else {
+        warn!("skipping item on layer `{layer}`, it has no KiCad equivalent");
+        return None;
+    };
+    Some(SExpr::list("layer", [SExpr::string(name)]))
+}
+
+fn poly(outline: &[Location], layer: SExpr, solid: bool) -> SExpr {
+    let (line_width, fill) = if solid {
+        (0, "solid")
+    } else {
+        (OUTLINE_WIDTH, "none")
+    };
+    SExpr::list(
+        "fp_poly",
+        [
+            pts(outline),
+            layer,
+            width(line_width),
+            SExpr::pair("fill", fill),
+        ],
+    )
+}
+
+/// Even-odd point in polygon test
+fn contains(outline: &[Location], point: Location) -> bool {
+    let (px, py) = (f64::from(point.x), f64::from(point.y));
+    let mut inside = false;
+    let mut prev = match outline.last() {
+        Some(v) => *v,
+        None => return false,
+    };
+
+    for cur in outline {
+        let (x1, y1) = (f64::from(prev.x), f64::from(prev.y));
+        let (x2, y2) = (f64::from(cur.x), f64::from(cur.y));
+        if (y1 > py) != (y2 > py) && px < (x2 - x1) * (py - y1) / (y2 - y1) + x1 {
+            inside = !inside;
+        }
+        prev = *cur;
+    }
+
+    inside
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn point_at(center: Location, radius: i32, angle: f64) -> Location {
+    let angle = angle.to_radians();
+    let radius = f64::from(radius);
+    Location::new(
+        center.x + (radius * angle.cos()).round() as i32,
+        center.y + (radius * angle.sin()).round() as i32,
+    )
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn rotate_about(point: Location, center: Location, angle: f64) -> Location {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let dx = f64::from(point.x - center.x);
+    let dy = f64::from(point.y - center.y);
+    Location::new(
+        center.x + (dx * cos - dy * sin).round() as i32,
+        center.y + (dx * sin + dy * cos).round() as i32,
+    )
+}
+
+/// Map an angle to (-180, 180]
+fn normalize_angle(angle: f64) -> f64 {
+    let ret = angle.rem_euclid(360.0);
+    if ret > 180.0 {
+        ret - 360.0
+    } else {
+        ret
+    }
+}
+
+fn expr_name(expr: &SExpr) -> &str {
+    match expr {
+        SExpr::List(items) => match items.first() {
+            Some(SExpr::Atom(name)) => name,
+            _ => "",
+        },
+        _ => "",
+    }
+}
+
+fn pts(locations: &[Location]) -> SExpr {
+    SExpr::list("pts", locations.iter().map(|loc| xy("xy", *loc)))
+}
+
+/// KiCad footprints are Y down, so Y gets negated
+fn at(loc: Location, angle: f64) -> SExpr {
+    let mut ret = SExpr::list(
+        "at",
+        [SExpr::mm(loc.x.into()), SExpr::mm(-i64::from(loc.y))],
+    );
+    if angle != 0.0 {
+        ret.push(SExpr::float(angle));
+    }
+    ret
+}
+
+fn xy(name: &str, loc: Location) -> SExpr {
+    SExpr::list(
+        name,
+        [SExpr::mm(loc.x.into()), SExpr::mm(-i64::from(loc.y))],
+    )
+}
+
+fn width(val: i64) -> SExpr {
+    SExpr::list("width", [SExpr::mm(val)])
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn average(a: i32, b: i32) -> i32 {
+    ((i64::from(a) + i64::from(b)) / 2) as i32
+}
+
+fn to_i32(val: u32) -> i32 {
+    i32::try_from(val).unwrap_or(i32::MAX)
+}
@@ -0,0 +1,251 @@
+//! Helpers for reading the binary PCB streams
+
+use std::str;
+
+use crate::common::{split_once, str_from_utf8, Location};
+use crate::error::TruncBuf;
+use crate::parse::{extract_sized_buf, split_chunk, BufLenMatch};
+use crate::ErrorKind;
+
+/// Convert internal units (1/10000 mil) to nm, rounding to the nearest nm
+pub(crate) fn to_nm(raw: i32) -> i32 {
+    f64_to_nm(f64::from(raw))
+}
+
+/// Convert a floating point value in internal units to nm
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn f64_to_nm(raw: f64) -> i32 {
+    (raw * 2.54).round() as i32
+}
+
+/// Sequential little endian reader over a subrecord
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    /// Number of bytes not yet consumed
+    pub fn remaining(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn chunk<const N: usize>(&mut self) -> Result<[u8; N], ErrorKind> {
+        let (arr, rest) = split_chunk::<N>(self.buf)?;
+        self.buf = rest;
+        Ok(*arr)
+    }
+
+    pub fn skip(&mut self, n: usize) -> Result<(), ErrorKind> {
+        self.bytes(n).map(|_| ())
+    }
+
+    pub fn bytes(&mut self, n: usize) -> Result<&'a [u8], ErrorKind> {
+        if self.buf.len() < n {
+            return Err(ErrorKind::BufferTooShort(n, TruncBuf::new(self.buf)));
+        }
+        let (data, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(data)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, ErrorKind> {
+        self.chunk::<1>().map(|[v]| v)
+    }
+
+    pub fn bool(&mut self) -> Result<bool, ErrorKind> {
+        self.u8().map(|v| v != 0)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, ErrorKind> {
+        self.chunk().map(u16::from_le_bytes)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, ErrorKind> {
+        self.chunk().map(u32::from_le_bytes)
+    }
+
+    pub fn i32(&mut self) -> Result<i32, ErrorKind> {
+        self.chunk().map(i32::from_le_bytes)
+    }
+
+    pub fn f64(&mut self) -> Result<f64, ErrorKind> {
+        self.chunk().map(f64::from_le_bytes)
+    }
+
+    /// A net, polygon or component index, where `0xffff` means none
+    pub fn index(&mut self) -> Result<Option<u16>, ErrorKind> {
+        self.u16().map(|v| (v != u16::MAX).then_some(v))
+    }
+
+    /// A signed length, converted to nm
+    pub fn len(&mut self) -> Result<i32, ErrorKind> {
+        self.i32().map(to_nm)
+    }
+
+    /// An unsigned length, converted to nm
+    pub fn ulen(&mut self) -> Result<u32, ErrorKind> {
+        self.i32().map(|v| to_nm(v).unsigned_abs())
+    }
+
+    pub fn location(&mut self) -> Result<Location, ErrorKind> {
+        Ok(Location::new(self.len()?, self.len()?))
+    }
+
+    /// A location stored as two doubles in internal units
+    pub fn location_f64(&mut self) -> Result<Location, ErrorKind> {
+        Ok(Location::new(
+            f64_to_nm(self.f64()?),
+            f64_to_nm(self.f64()?),
+        ))
+    }
+
+    /// A fixed size, nul padded UTF-16 buffer
+    pub fn utf16(&mut self, len: usize) -> Result<String, ErrorKind> {
+        let units: Vec<u16> = self
+            .bytes(len)?
+            .chunks_exact(2)
+            .map(|ch| u16::from_le_bytes([ch[0], ch[1]]))
+            .take_while(|ch| *ch != 0)
+            .collect();
+        Ok(String::from_utf16_lossy(&units))
+    }
+
+    /// A string with a single byte length
+    pub fn u8_str(&mut self) -> Result<Box<str>, ErrorKind> {
+        let (buf, rest) = extract_sized_buf(self.buf, BufLenMatch::U8, false)?;
+        self.buf = rest;
+        Ok(String::from_utf8_lossy(buf).into())
+    }
+
+    /// A `|KEY=VALUE|...` property list with a 4 byte length
+    pub fn properties(&mut self) -> Result<Properties, ErrorKind> {
+        let (buf, rest) = extract_sized_buf(self.buf, BufLenMatch::U32, false)?;
+        self.buf = rest;
+        Properties::parse(buf)
+    }
+}
+
+/// Properties as found in PCB streams. Keys are uppercase and booleans are
+/// `TRUE` or `FALSE`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Properties(Vec<(Box<str>, Box<str>)>);
+
+impl Properties {
+    pub fn parse(buf: &[u8]) -> Result<Self, ErrorKind> {
+        let buf = buf.strip_suffix(&[0]).unwrap_or(buf);
+        let mut ret = Vec::new();
+
+        for item in buf.split(|b| *b == b'|').filter(|x| !x.is_empty()) {
+            let Some((key, val)) = split_once(item, b'=') else {
+                return Err(ErrorKind::RequiredSplit(
+                    String::from_utf8_lossy(item).into(),
+                ));
+            };
+            // Values are sometimes in a legacy codepage, don't fail on those
+            let val = str::from_utf8(val)
+                .map_or_else(|_| String::from_utf8_lossy(val).into(), Box::<str>::from);
+            ret.push((str_from_utf8(key)?.into(), val));
+        }
+
+        Ok(Self(ret))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (&**k, &**v))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| &**v)
+    }
+
+    /// Set a value, replacing an existing one or adding it to the end
+    pub fn set(&mut self, key: &str, val: &str) {
+        match self.0.iter_mut().find(|(k, _)| k.eq_ignore_ascii_case(key)) {
+            Some((_, v)) => *v = val.into(),
+            None => self.0.push((key.into(), val.into())),
+        }
+    }
+
+    /// Write as `|KEY=VALUE|...` with a nul terminator, the inverse of
+    /// [`Properties::parse`]
+    pub fn to_buf(&self) -> Vec<u8> {
+        let mut ret = Vec::new();
+        for (key, val) in &self.0 {
+            ret.push(b'|');
+            ret.extend_from_slice(key.as_bytes());
+            ret.push(b'=');
+            ret.extend_from_slice(val.as_bytes());
+        }
+        ret.push(0);
+        ret
+    }
+
+    pub fn get_str(&self, key: &str) -> Box<str> {
+        self.get(key).unwrap_or_default().into()
+    }
+
+    pub fn get_bool(&self, key: &str) -> bool {
+        self.get(key)
+            .is_some_and(|v| v.eq_ignore_ascii_case("TRUE"))
+    }
+
+    pub fn get_f64(&self, key: &str) -> Result<f64, ErrorKind> {
+        match self.get(key) {
+            Some(v) => v
+                .trim()
+                .parse()
+                .map_err(|e| ErrorKind::ExpectedFloat(v.into(), e)),
+            None => Ok(0.0),
+        }
+    }
+
+    pub fn get_int(&self, key: &str) -> Result<i32, ErrorKind> {
+        match self.get(key) {
+            Some(v) => v
+                .trim()
+                .parse()
+                .map_err(|e| ErrorKind::ExpectedInt(v.into(), e)),
+            None => Ok(0),
+        }
+    }
+
+    /// A length with units, e.g. `39.3701mil`, converted to nm
+    pub fn get_len(&self, key: &str) -> Result<i32, ErrorKind> {
+        self.get(key).map_or(Ok(0), parse_len)
+    }
+}
+
+/// Parse a length such as `10mil` or `0.5mm` to nm. Values without a unit are
+/// in mils.
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn parse_len(s: &str) -> Result<i32, ErrorKind> {
+    let s = s.trim();
+    let (num, factor) = if let Some(num) = s.strip_suffix("mil") {
+        (num, 25_400.0)
+    } else if let Some(num) = s.strip_suffix("mm") {
+        (num, 1_000_000.0)
+    } else {
+        (s, 25_400.0)
+    };
+
+    let val: f64 = num
+        .trim()
+        .parse()
+        .map_err(|e| ErrorKind::ExpectedFloat(s.into(), e))?;
+    Ok((val * factor).round() as i32)
+}
+
+/// Format a length in nm as mils, the way Altium writes lengths, e.g.
+/// `7.874mil`
+pub(crate) fn format_len(nm: i32) -> String {
+    let mils = format!("{:.4}", f64::from(nm) / 25_400.0);
+    let mils = mils.trim_end_matches('0').trim_end_matches('.');
+    format!("{mils}mil")
+}
@@ -0,0 +1,674 @@
+//! Design rules, as stored in a `PcbDoc`'s `Rules6` storage
+//!
+//! Every rule has a kind, a name, a priority (1 is the highest) and up to two
+//! scope query expressions, e.g. `InNet('GND')`. The kind's parameters are
+//! parsed into a [`RuleKind`] where we know them; everything else is still
+//! available as raw parameters so that rules can be written back unchanged.
+
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+use cfb::CompoundFile;
+
+use super::record::{format_len, Properties, Reader};
+use crate::error::{AddContext, ErrorKind};
+use crate::Error;
+
+/// Limits on a width or size, in nm
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub min: i32,
+    pub max: i32,
+    pub preferred: i32,
+}
+
+/// How pads connect to a plane or polygon pour
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectStyle {
+    Direct,
+    /// Thermal relief spokes
+    Relief,
+    NoConnect,
+}
+
+impl ConnectStyle {
+    fn from_name(name: &str) -> Self {
+        match name.trim() {
+            "Direct" => Self::Direct,
+            "NoConnect" => Self::NoConnect,
+            _ => Self::Relief,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Direct => "Direct",
+            Self::Relief => "Relief",
+            Self::NoConnect => "NoConnect",
+        }
+    }
+}
+
+/// Which pairs of nets a two-scope rule applies to
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NetScope {
+    #[default]
+    AnyNet,
+    DifferentNets,
+    SameNet,
+}
+
+impl NetScope {
+    fn from_name(name: &str) -> Self {
+        match name.trim() {
+            "DifferentNets" => Self::DifferentNets,
+            "SameNet" => Self::SameNet,
+            _ => Self::AnyNet,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::AnyNet => "AnyNet",
+            Self::DifferentNets => "DifferentNets",
+            Self::SameNet => "SameNet",
+        }
+    }
+}
+
+/// The kind of a rule and its parameters. Lengths are in nm.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuleKind {
+    /// Minimum gap between copper objects
+    Clearance {
+        gap: i32,
+    },
+    ShortCircuit {
+        allowed: bool,
+    },
+    UnroutedNet,
+    Width(Limits),
+    /// Via diameter and hole size used when routing
+    RoutingVias {
+        diameter: Limits,
+        hole: Limits,
+    },
+    HoleSize {
+        min: i32,
+        max: i32,
+    },
+    HoleToHoleClearance {
+        gap: i32,
+    },
+    PlaneClearance {
+        clearance: i32,
+    },
+    PlaneConnect {
+        style: ConnectStyle,
+        expansion: i32,
+        conductor_width: i32,
+        air_gap: i32,
+        entries: u8,
+    },
+    PolygonConnect {
+        style: ConnectStyle,
+        conductor_width: i32,
+        air_gap: i32,
+        entries: u8,
+    },
+    SolderMaskExpansion {
+        expansion: i32,
+    },
+    PasteMaskExpansion {
+        expansion: i32,
+    },
+    MinimumSolderMaskSliver {
+        width: i32,
+    },
+    SilkToSolderMaskClearance {
+        gap: i32,
+    },
+    SilkToSilkClearance {
+        gap: i32,
+    },
+    ComponentClearance {
+        gap: i32,
+        vertical_gap: i32,
+    },
+    Height(Limits),
+    DiffPairsRouting {
+        min_gap: i32,
+        max_gap: i32,
+        preferred_gap: i32,
+        max_uncoupled_length: i32,
+    },
+    /// A kind we don't have typed parameters for, with its `RULEKIND` name
+    Other(Box<str>),
+}
+
+/// `RULEKIND` names and the record ID that each is stored with
+const KIND_IDS: [(&str, u16); 31] = [
+    ("Clearance", 0),
+    ("Width", 2),
+    ("PlaneConnect", 6),
+    ("RoutingTopology", 7),
+    ("RoutingPriority", 8),
+    ("RoutingLayers", 9),
+    ("RoutingCorners", 10),
+    ("RoutingVias", 11),
+    ("PlaneClearance", 12),
+    ("SolderMaskExpansion", 13),
+    ("PasteMaskExpansion", 14),
+    ("ShortCircuit", 15),
+    ("UnRoutedNet", 16),
+    ("PolygonConnect", 20),
+    ("ComponentClearance", 24),
+    ("HoleSize", 42),
+    ("FabricationTestpoint", 43),
+    ("FabricationTestPointUsage", 44),
+    ("LayerPairs", 48),
+    ("FanoutControl", 49),
+    ("Height", 50),
+    ("DiffPairsRouting", 51),
+    ("HoleToHoleClearance", 52),
+    ("MinimumSolderMaskSliver", 53),
+    ("SilkToSolderMaskClearance", 54),
+    ("SilkToSilkClearance", 55),
+    ("NetAntennae", 56),
+    ("AssemblyTestpoint", 57),
+    ("AssemblyTestPointUsage", 58),
+    ("SilkToBoardRegionClearance", 59),
+    ("UnpouredPolygon", 62),
+];
+
+impl RuleKind {
+    /// The `RULEKIND` name, e.g. `Clearance`
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Clearance { .. } => "Clearance",
+            Self::ShortCircuit { .. } => "ShortCircuit",
+            Self::UnroutedNet => "UnRoutedNet",
+            Self::Width(_) => "Width",
+            Self::RoutingVias { .. } => "RoutingVias",
+            Self::HoleSize { .. } => "HoleSize",
+            Self::HoleToHoleClearance { .. } => "HoleToHoleClearance",
+            Self::PlaneClearance { .. } => "PlaneClearance",
+            Self::PlaneConnect { .. } => "PlaneConnect",
+            Self::PolygonConnect { .. } => "PolygonConnect",
+            Self::SolderMaskExpansion { .. } => "SolderMaskExpansion",
+            Self::PasteMaskExpansion { .. } => "PasteMaskExpansion",
+            Self::MinimumSolderMaskSliver { .. } => "MinimumSolderMaskSliver",
+            Self::SilkToSolderMaskClearance { .. } => "SilkToSolderMaskClearance",
+            Self::SilkToSilkClearance { .. } => "SilkToSilkClearance",
+            Self::ComponentClearance { .. } => "ComponentClearance",
+            Self::Height(_) => "Height",
+            Self::DiffPairsRouting { .. } => "DiffPairsRouting",
+            Self::Other(name) => name,
+        }
+    }
+
+    /// The record ID this kind is stored with, `None` for unknown kinds
+    fn id(&self) -> Option<u16> {
+        let name = self.name();
+        KIND_IDS
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, id)| *id)
+    }
+
+    fn from_props(props: &Properties) -> Result<Self, ErrorKind> {
+        let len = |key: &str| props.get_len(key);
+        let limits = |min: &str, max: &str, pref: &str| -> Result<Limits, ErrorKind> {
+            Ok(Limits {
+                min: len(min)?,
+                max: len(max)?,
+                preferred: len(pref)?,
+            })
+        };
+        let entries = || -> Result<u8, ErrorKind> {
+            Ok(u8::try_from(props.get_int("RELIEFENTRIES")?).unwrap_or(4))
+        };
+
+        let ret = match props.get("RULEKIND").unwrap_or_default().trim() {
+            "Clearance" => Self::Clearance { gap: len("GAP")? },
+            "ShortCircuit" => Self::ShortCircuit {
+                allowed: props.get_bool("ALLOWED"),
+            },
+            "UnRoutedNet" => Self::UnroutedNet,
+            "Width" => Self::Width(limits("MINLIMIT", "MAXLIMIT", "PREFEREDWIDTH")?),
+            "RoutingVias" => Self::RoutingVias {
+                diameter: limits("MINWIDTH", "MAXWIDTH", "WIDTH")?,
+                hole: limits("MINHOLEWIDTH", "MAXHOLEWIDTH", "HOLEWIDTH")?,
+            },
+            "HoleSize" => Self::HoleSize {
+                min: len("MINLIMIT")?,
+                max: len("MAXLIMIT")?,
+            },
+            "HoleToHoleClearance" => Self::HoleToHoleClearance { gap: len("GAP")? },
+            "PlaneClearance" => Self::PlaneClearance {
+                clearance: len("CLEARANCE")?,
+            },
+            "PlaneConnect" => Self::PlaneConnect {
+                style: ConnectStyle::from_name(props.get("PLANECONNECTSTYLE").unwrap_or_default()),
+                expansion: len("RELIEFEXPANSION")?,
+                conductor_width: len("RELIEFCONDUCTORWIDTH")?,
+                air_gap: len("RELIEFAIRGAP")?,
+                entries: entries()?,
+            },
+            "PolygonConnect" => Self::PolygonConnect {
+                style: ConnectStyle::from_name(props.get("CONNECTSTYLE").unwrap_or_default()),
+                conductor_width: len("RELIEFCONDUCTORWIDTH")?,
+                air_gap: len("AIRGAPWIDTH")?,
+                entries: entries()?,
+            },
+            "SolderMaskExpansion" => Self::SolderMaskExpansion {
+                expansion: len("EXPANSION")?,
+            },
+            "PasteMaskExpansion" => Self::PasteMaskExpansion {
+                expansion: len("EXPANSION")?,
+            },
+            "MinimumSolderMaskSliver" => Self::MinimumSolderMaskSliver {
+                width: len("MINSOLDERMASKWIDTH")?,
+            },
+            "SilkToSolderMaskClearance" => Self::SilkToSolderMaskClearance {
+                gap: len("MINSILKSCREENTOMASKGAP")?,
+            },
+            "SilkToSilkClearance" => Self::SilkToSilkClearance {
+                gap: len("SILKTOSILKCLEARANCE")?,
+            },
+            "ComponentClearance" => Self::ComponentClearance {
+                gap: len("GAP")?,
+                vertical_gap: len("VERTICALGAP")?,
+            },
+            "Height" => Self::Height(limits("MINHEIGHT", "MAXHEIGHT", "PREFHEIGHT")?),
+            "DiffPairsRouting" => Self::DiffPairsRouting {
+                min_gap: len("MINLIMIT")?,
+                max_gap: len("MAXLIMIT")?,
+                preferred_gap: len("MOSTFREQGAP")?,
+                max_uncoupled_length: len("MAXUNCOUPLEDLENGTH")?,
+            },
+            name => Self::Other(name.into()),
+        };
+        Ok(ret)
+    }
+
+    /// Write our parameters to `props`. Other kinds have nothing to write.
+    fn write_props(&self, props: &mut Properties) {
+        let mut set_len = |key: &str, val: i32| props.set(key, &format_len(val));
+        match *self {
+            Self::Clearance { gap } => {
+                set_len("GAP", gap);
+                set_len("GENERICCLEARANCE", gap);
+            }
+            Self::Width(limits) => {
+                set_len("MINLIMIT", limits.min);
+                set_len("MAXLIMIT", limits.max);
+                set_len("PREFEREDWIDTH", limits.preferred);
+            }
+            Self::RoutingVias { diameter, hole } => {
+                set_len("MINWIDTH", diameter.min);
+                set_len("MAXWIDTH", diameter.max);
+                set_len("WIDTH", diameter.preferred);
+                set_len("MINHOLEWIDTH", hole.min);
+                set_len("MAXHOLEWIDTH", hole.max);
+                set_len("HOLEWIDTH", hole.preferred);
+            }
+            Self::HoleSize { min, max } => {
+                set_len("MINLIMIT", min);
+                set_len("MAXLIMIT", max);
+            }
+            Self::HoleToHoleClearance { gap } => set_len("GAP", gap),
+            Self::PlaneClearance { clearance } => set_len("CLEARANCE", clearance),
+            Self::PlaneConnect {
+                style,
+                expansion,
+                conductor_width,
+                air_gap,
+                entries,
+            } => {
+                set_len("RELIEFEXPANSION", expansion);
+                set_len("RELIEFCONDUCTORWIDTH", conductor_width);
+                set_len("RELIEFAIRGAP", air_gap);
+                props.set("PLANECONNECTSTYLE", style.name());
+                props.set("RELIEFENTRIES", &entries.to_string());
+            }
+            Self::PolygonConnect {
+                style,
+                conductor_width,
+                air_gap,
+                entries,
+            } => {
+                set_len("RELIEFCONDUCTORWIDTH", conductor_width);
+                set_len("AIRGAPWIDTH", air_gap);
+                props.set("CONNECTSTYLE", style.name());
+                props.set("RELIEFENTRIES", &entries.to_string());
+            }
+            Self::SolderMaskExpansion { expansion } | Self::PasteMaskExpansion { expansion } => {
+                set_len("EXPANSION", expansion);
+            }
+            Self::MinimumSolderMaskSliver { width } => set_len("MINSOLDERMASKWIDTH", width),
+            Self::SilkToSolderMaskClearance { gap } => set_len("MINSILKSCREENTOMASKGAP", gap),
+            Self::SilkToSilkClearance { gap } => set_len("SILKTOSILKCLEARANCE", gap),
+            Self::ComponentClearance { gap, vertical_gap } => {
+                set_len("GAP", gap);
+                set_len("VERTICALGAP", vertical_gap);
+            }
+            Self::Height(limits) => {
+                set_len("MINHEIGHT", limits.min);
+                set_len("MAXHEIGHT", limits.max);
+                set_len("PREFHEIGHT", limits.preferred);
+            }
+            Self::DiffPairsRouting {
+                min_gap,
+                max_gap,
+                preferred_gap,
+                max_uncoupled_length,
+            } => {
+                set_len("MINLIMIT", min_gap);
+                set_len("MAXLIMIT", max_gap);
+                set_len("MOSTFREQGAP", preferred_gap);
+                set_len("MAXUNCOUPLEDLENGTH", max_uncoupled_length);
+            }
+            Self::ShortCircuit { allowed } => props.set("ALLOWED", bool_str(allowed)),
+            Self::UnroutedNet | Self::Other(_) => (),
+        }
+    }
+}
+
+/// A single design rule
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    /// Record ID, which depends on the kind
+    id: u16,
+    kind: RuleKind,
+    /// Every property, including those that `kind` is parsed from
+    props: Properties,
+}
+
+impl Rule {
+    /// A new enabled rule with priority 1 that applies to everything
+    ///
+    /// Kinds that we don't know the record ID of can't be written.
+    pub fn new(name: &str, kind: RuleKind) -> Result<Self, Error> {
+        let id = kind
+            .id()
+            .ok_or_else(|| ErrorKind::InvalidKey(kind.name().into()))
+            .context("creating a rule")?;
+        let mut props = Properties::default();
+        for (key, val) in [
+            ("SELECTION", "FALSE"),
+            ("LAYER", "UNKNOWN"),
+            ("LOCKED", "FALSE"),
+            ("POLYGONOUTLINE", "FALSE"),
+            ("USERROUTED", "TRUE"),
+            ("KEEPOUT", "FALSE"),
+            ("UNIONINDEX", "0"),
+            ("RULEKIND", kind.name()),
+            ("NETSCOPE", NetScope::AnyNet.name()),
+            ("LAYERKIND", "SameLayer"),
+            ("SCOPE1EXPRESSION", "All"),
+            ("SCOPE2EXPRESSION", "All"),
+            ("NAME", name),
+            ("ENABLED", "TRUE"),
+            ("PRIORITY", "1"),
+            ("COMMENT", ""),
+            ("DEFINEDBYLOGICALDOCUMENT", "FALSE"),
+        ] {
+            props.set(key, val);
+        }
+        kind.write_props(&mut props);
+
+        Ok(Self { id, kind, props })
+    }
+
+    pub fn kind(&self) -> &RuleKind {
+        &self.kind
+    }
+
+    pub fn name(&self) -> &str {
+        self.props.get("NAME").unwrap_or_default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.props.get_bool("ENABLED")
+    }
+
+    /// Priority among rules of the same kind, 1 is checked first
+    pub fn priority(&self) -> u32 {
+        self.props
+            .get("PRIORITY")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(1)
+    }
+
+    /// The query expression selecting the first object, e.g. `All`
+    pub fn scope1(&self) -> &str {
+        self.props.get("SCOPE1EXPRESSION").unwrap_or("All")
+    }
+
+    /// The query expression selecting the second object for rules between two
+    /// objects
+    pub fn scope2(&self) -> &str {
+        self.props.get("SCOPE2EXPRESSION").unwrap_or("All")
+    }
+
+    pub fn net_scope(&self) -> NetScope {
+        NetScope::from_name(self.props.get("NETSCOPE").unwrap_or_default())
+    }
+
+    pub fn comment(&self) -> &str {
+        self.props.get("COMMENT").unwrap_or_default()
+    }
+
+    pub fn unique_id(&self) -> &str {
+        self.props.get("UNIQUEID").unwrap_or_default()
+    }
+
+    /// A raw parameter, e.g. `GAP`
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.props.get(key)
+    }
+
+    /// Every raw parameter in the order they are stored
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.props.iter()
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.props.set("NAME", name);
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.props.set("ENABLED", bool_str(enabled));
+    }
+
+    pub fn set_priority(&mut self, priority: u32) {
+        self.props.set("PRIORITY", &priority.to_string());
+    }
+
+    pub fn set_scope1(&mut self, query: &str) {
+        self.props.set("SCOPE1EXPRESSION", query);
+    }
+
+    pub fn set_scope2(&mut self, query: &str) {
+        self.props.set("SCOPE2EXPRESSION", query);
+    }
+
+    pub fn set_net_scope(&mut self, scope: NetScope) {
+        self.props.set("NETSCOPE", scope.name());
+    }
+
+    pub fn set_comment(&mut self, comment: &str) {
+        self.props.set("COMMENT", comment);
+    }
+
+    /// Replace the typed parameters. The kind itself can't change.
+    pub fn set_kind_params(&mut self, kind: RuleKind) -> Result<(), Error> {
+        if kind.name() != self.kind.name() {
+            return Err(Error::from(ErrorKind::InvalidKey(kind.name().into()))
+                .context(format!("changing the kind of rule `{}`", self.name())));
+        }
+        kind.write_props(&mut self.props);
+        self.kind = kind;
+        Ok(())
+    }
+
+    /// Set a raw parameter, updating the typed parameters from it
+    pub fn set_param(&mut self, key: &str, val: &str) -> Result<(), Error> {
+        let mut props = self.props.clone();
+        props.set(key, val);
+        self.kind =
+            RuleKind::from_props(&props).or_context(|| format!("setting `{key}` to `{val}`"))?;
+        self.props = props;
+        Ok(())
+    }
+
+    fn from_record(id: u16, props: Properties) -> Result<Self, ErrorKind> {
+        Ok(Self {
+            id,
+            kind: RuleKind::from_props(&props)?,
+            props,
+        })
+    }
+}
+
+/// All design rules of a board
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DesignRules(Vec<Rule>);
+
+impl DesignRules {
+    const STORAGE: &'static str = "Rules6";
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rule> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Find a rule by name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&Rule> {
+        self.0
+            .iter()
+            .find(|rule| rule.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Find a rule by name to modify it
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Rule> {
+        self.0
+            .iter_mut()
+            .find(|rule| rule.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Enabled rules with the given `RULEKIND` name in priority order
+    pub fn of_kind<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a Rule> {
+        let mut ret: Vec<_> = self
+            .0
+            .iter()
+            .filter(|rule| rule.is_enabled() && rule.kind.name().eq_ignore_ascii_case(kind))
+            .collect();
+        ret.sort_by_key(|rule| rule.priority());
+        ret.into_iter()
+    }
+
+    /// Add a rule, replacing any with the same name
+    pub fn insert(&mut self, rule: Rule) {
+        match self
+            .0
+            .iter_mut()
+            .find(|r| r.name().eq_ignore_ascii_case(rule.name()))
+        {
+            Some(existing) => *existing = rule,
+            None => self.0.push(rule),
+        }
+    }
+
+    /// Remove a rule by name, returning it if it existed
+    pub fn remove(&mut self, name: &str) -> Option<Rule> {
+        let idx = self
+            .0
+            .iter()
+            .position(|rule| rule.name().eq_ignore_ascii_case(name))?;
+        Some(self.0.remove(idx))
+    }
+
+    /// The contents of the `Rules6/Data` stream
+    pub fn to_buf(&self) -> Vec<u8> {
+        let mut ret = Vec::new();
+        for rule in &self.0 {
+            let props = rule.props.to_buf();
+            let len = u32::try_from(props.len()).expect("rule properties too long");
+            ret.extend_from_slice(&rule.id.to_le_bytes());
+            ret.extend_from_slice(&len.to_le_bytes());
+            ret.extend_from_slice(&props);
+        }
+        ret
+    }
+
+    /// Write these rules into an existing `PcbDoc`, replacing the rules it has
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let mut cfile = cfb::open_rw(path)?;
+        self.write_storage(&mut cfile)
+            .or_context(|| format!("writing rules to {}", path.display()))?;
+        cfile.flush()?;
+        Ok(())
+    }
+
+    /// Parse a `Rules6/Data` stream. Each rule is a `u16` ID, then
+    /// properties with a `u32` length.
+    pub(crate) fn from_buf(buf: &[u8]) -> Result<Self, Error> {
+        let mut rd = Reader::new(buf);
+        let mut ret = Vec::new();
+        while rd.remaining() > 0 {
+            let parse_rule = |rd: &mut Reader| -> Result<Rule, ErrorKind> {
+                let id = rd.u16()?;
+                Rule::from_record(id, rd.properties()?)
+            };
+            ret.push(parse_rule(&mut rd).or_context(|| format!("reading rule {}", ret.len()))?);
+        }
+        Ok(Self(ret))
+    }
+
+    fn write_storage<F: Read + Write + Seek>(
+        &self,
+        cfile: &mut CompoundFile<F>,
+    ) -> Result<(), Error> {
+        if !cfile.is_storage(Self::STORAGE) {
+            cfile.create_storage(Self::STORAGE)?;
+        }
+        let count = u32::try_from(self.0.len()).expect("too many rules");
+        cfile
+            .create_stream(format!("{}/Header", Self::STORAGE))?
+            .write_all(&count.to_le_bytes())?;
+        cfile
+            .create_stream(format!("{}/Data", Self::STORAGE))?
+            .write_all(&self.to_buf())?;
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a DesignRules {
+    type Item = &'a Rule;
+    type IntoIter = std::slice::Iter<'a, Rule>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+fn bool_str(val: bool) -> &'static str {
+    if val {
+        "TRUE"
+    } else {
+        "FALSE"
+    }
+}
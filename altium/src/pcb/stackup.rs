@@ -0,0 +1,129 @@
+//! The physical layer stack, as set up in Altium's layer stack manager
+
+use super::record::{parse_len, Properties};
+use super::Layer;
+use crate::error::{AddContext, ErrorKind};
+use crate::Error;
+
+/// What a layer in the stack is made of
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StackLayerKind {
+    /// A signal or plane layer
+    Copper,
+    /// A rigid dielectric, usually laminate
+    Core,
+    /// Dielectric bonding cores or foils together
+    Prepreg,
+    SolderMask,
+    Overlay,
+    Paste,
+    /// Anything else, e.g. a surface finish or coverlay
+    Other,
+}
+
+/// One layer of the board's physical stack
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackLayer {
+    name: Box<str>,
+    kind: StackLayerKind,
+    layer: Option<Layer>,
+    thickness: u32,
+    dielectric_constant: Option<f64>,
+    material: Box<str>,
+}
+
+impl StackLayer {
+    /// A copper layer with no other information, for documents that don't
+    /// store a stack
+    pub(crate) fn copper(layer: Layer) -> Self {
+        Self {
+            name: layer.to_string().into(),
+            kind: StackLayerKind::Copper,
+            layer: Some(layer),
+            thickness: 0,
+            dielectric_constant: None,
+            material: "".into(),
+        }
+    }
+
+    /// Name as shown in the stack manager, e.g. `Dielectric 1`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> StackLayerKind {
+        self.kind
+    }
+
+    /// The layer primitives are placed on, `None` for dielectrics
+    pub fn layer(&self) -> Option<Layer> {
+        self.layer
+    }
+
+    /// Thickness in nm, 0 if not set
+    pub fn thickness(&self) -> u32 {
+        self.thickness
+    }
+
+    /// Relative permittivity of dielectrics and solder mask
+    pub fn dielectric_constant(&self) -> Option<f64> {
+        self.dielectric_constant
+    }
+
+    /// Material name, e.g. `FR-4`. Empty for copper.
+    pub fn material(&self) -> &str {
+        &self.material
+    }
+}
+
+/// Read the `V9_STACK_LAYERn_*` properties of `Board6`, from top to bottom
+pub(crate) fn parse_stackup(props: &Properties) -> Result<Vec<StackLayer>, Error> {
+    let mut ret = Vec::new();
+
+    for idx in 0.. {
+        let key = |name: &str| format!("V9_STACK_LAYER{idx}_{name}");
+        let Some(id) = props.get(&key("LAYERID")) else {
+            break;
+        };
+        let parse_layer = || -> Result<StackLayer, ErrorKind> {
+            let id: u32 = id
+                .trim()
+                .parse()
+                .map_err(|e| ErrorKind::ExpectedInt(id.into(), e))?;
+            let layer = Layer::from_v7_id(id);
+            let kind = match layer {
+                Some(layer) if layer.is_copper() => StackLayerKind::Copper,
+                Some(Layer::TopSolder | Layer::BottomSolder) => StackLayerKind::SolderMask,
+                Some(Layer::TopOverlay | Layer::BottomOverlay) => StackLayerKind::Overlay,
+                Some(Layer::TopPaste | Layer::BottomPaste) => StackLayerKind::Paste,
+                // Dielectric layers are the only group without a `Layer`
+                None if id >> 16 == 0x0104 => match props.get_int(&key("DIELTYPE"))? {
+                    1 => StackLayerKind::Prepreg,
+                    _ => StackLayerKind::Core,
+                },
+                _ => StackLayerKind::Other,
+            };
+            let thickness_key = if kind == StackLayerKind::Copper {
+                "COPTHICK"
+            } else {
+                "DIELHEIGHT"
+            };
+            let thickness = props.get(&key(thickness_key)).map_or(Ok(0), parse_len)?;
+
+            Ok(StackLayer {
+                name: props.get_str(&key("NAME")),
+                kind,
+                layer,
+                thickness: u32::try_from(thickness).unwrap_or_default(),
+                dielectric_constant: props
+                    .get(&key("DIELCONST"))
+                    .and_then(|val| val.trim().parse().ok()),
+                material: props.get_str(&key("DIELMATERIAL")),
+            })
+        };
+        ret.push(parse_layer().or_context(|| format!("reading stack layer {idx}"))?);
+    }
+
+    Ok(ret)
+}
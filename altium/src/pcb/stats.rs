@@ -0,0 +1,400 @@
+//! Summary numbers for design reviews
+
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+use serde::{Serialize, Serializer};
+
+use super::draw::{pad_outline, place};
+use super::drc::{arc_points, is_signal};
+use super::fab::{fill_corners, pad_size, rotate, BoardData};
+use super::record::{HoleShape, PcbRecord, RegionKind, Via};
+use super::Layer;
+use crate::common::Location;
+
+/// Row height used to measure copper area, 1 mil
+const AREA_STEP: f64 = 25_400.0;
+/// Points used for each half circle of round shapes
+const ROUND_STEPS: u32 = 16;
+
+/// Size, layer, drill, pad and component numbers of a board. Lengths are in
+/// nm.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct BoardStatistics {
+    /// Width of the board outline's bounding box
+    pub width: i32,
+    /// Height of the board outline's bounding box
+    pub height: i32,
+    pub layer_count: usize,
+    /// Number of vias of each drill size, smallest first
+    pub vias: Vec<DrillCount>,
+    pub smd_pads: usize,
+    pub through_hole_pads: usize,
+    pub top_components: usize,
+    pub bottom_components: usize,
+    pub net_count: usize,
+    /// The narrowest routed track or arc, ignoring polygon pours
+    pub min_track_width: Option<u32>,
+    /// The smallest pad or via hole
+    pub min_drill: Option<u32>,
+    /// Copper area of each copper layer, from top to bottom
+    pub copper_area: Vec<CopperArea>,
+}
+
+/// The number of vias with a drill size
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct DrillCount {
+    pub drill: u32,
+    pub count: usize,
+}
+
+/// Area covered by copper on a layer, with drilled holes removed
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct CopperArea {
+    #[serde(serialize_with = "serialize_layer")]
+    pub layer: Layer,
+    /// Area in mm²
+    pub area: f64,
+}
+
+/// Layers are written by name, serde requires the reference
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn serialize_layer<S: Serializer>(layer: &Layer, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(layer)
+}
+
+/// Closed rings making up one filled shape. Rings inside others are holes.
+type Rings = Vec<Vec<(f64, f64)>>;
+
+pub(super) fn statistics(board: &BoardData) -> BoardStatistics {
+    let (width, height) = outline_size(&board.outline);
+
+    let mut vias: BTreeMap<u32, usize> = BTreeMap::new();
+    for via in board.vias() {
+        *vias.entry(via.hole_size).or_default() += 1;
+    }
+    let through_hole_pads = board.pads().filter(|pad| pad.hole_size > 0).count();
+    let bottom_components = board.components.iter().filter(|c| c.is_bottom()).count();
+
+    let min_track_width = board
+        .records
+        .iter()
+        .filter_map(|record| match record {
+            PcbRecord::Track(t) if !t.keepout && t.polygon.is_none() && is_signal(t.layer) => {
+                Some(t.width)
+            }
+            PcbRecord::Arc(a) if !a.keepout && a.polygon.is_none() && is_signal(a.layer) => {
+                Some(a.width)
+            }
+            _ => None,
+        })
+        .min();
+    let min_drill = board
+        .pads()
+        .map(|pad| pad.hole_size)
+        .chain(board.vias().map(|via| via.hole_size))
+        .filter(|hole| *hole > 0)
+        .min();
+
+    let copper_area = board
+        .stack
+        .iter()
+        .map(|&layer| CopperArea {
+            layer,
+            area: filled_area(&copper_shapes(board, layer), &drilled_holes(board, layer)) / 1e12,
+        })
+        .collect();
+
+    BoardStatistics {
+        width,
+        height,
+        layer_count: board.stack.len(),
+        vias: vias
+            .into_iter()
+            .map(|(drill, count)| DrillCount { drill, count })
+            .collect(),
+        smd_pads: board.pads().count() - through_hole_pads,
+        through_hole_pads,
+        top_components: board.components.len() - bottom_components,
+        bottom_components,
+        net_count: board.nets.len(),
+        min_track_width,
+        min_drill,
+        copper_area,
+    }
+}
+
+fn outline_size(outline: &[Location]) -> (i32, i32) {
+    let span = |vals: &mut dyn Iterator<Item = i32>| {
+        let (min, max) = vals.fold((i32::MAX, i32::MIN), |(min, max), v| {
+            (min.min(v), max.max(v))
+        });
+        max.checked_sub(min).filter(|span| *span >= 0).unwrap_or(0)
+    };
+    (
+        span(&mut outline.iter().map(|loc| loc.x)),
+        span(&mut outline.iter().map(|loc| loc.y)),
+    )
+}
+
+/// Every copper shape on a layer, without drilled holes
+fn copper_shapes(board: &BoardData, layer: Layer) -> Vec<Rings> {
+    let mut ret = Vec::new();
+    let ring = |outline: &[Location]| -> Vec<(f64, f64)> {
+        outline
+            .iter()
+            .map(|loc| (f64::from(loc.x), f64::from(loc.y)))
+            .collect()
+    };
+    let stack_pos = board.stack.iter().position(|l| *l == layer);
+    let via_on_layer = |via: &Via| {
+        let (start, end) = board.via_span(via);
+        stack_pos.is_some_and(|pos| (start..=end).contains(&pos))
+    };
+
+    for record in &board.records {
+        match record {
+            PcbRecord::Track(t) if !t.keepout && t.layer == layer => {
+                let points = [t.start, t.end].map(|loc| (f64::from(loc.x), f64::from(loc.y)));
+                ret.push(vec![capsule(points[0], points[1], t.width)]);
+            }
+            PcbRecord::Arc(a) if !a.keepout && a.layer == layer => {
+                let points = arc_points(a);
+                ret.extend(
+                    points
+                        .windows(2)
+                        .map(|pair| vec![capsule(pair[0], pair[1], a.width)]),
+                );
+            }
+            PcbRecord::Fill(f) if !f.keepout && f.layer == layer => {
+                ret.push(vec![ring(&fill_corners(f))]);
+            }
+            PcbRecord::Region(r)
+                if !r.keepout && r.kind == RegionKind::Copper && r.layer == layer =>
+            {
+                ret.push(
+                    [&r.outline]
+                        .into_iter()
+                        .chain(&r.holes)
+                        .map(|outline| ring(outline))
+                        .collect(),
+                );
+            }
+            PcbRecord::Pad(pad) if pad.layer == layer || pad.layer == Layer::MultiLayer => {
+                let size = pad_size(pad, layer);
+                if size.x == 0 || size.y == 0 {
+                    continue;
+                }
+                ret.push(vec![ring(&place(
+                    &pad_outline(size, 0),
+                    pad.location,
+                    pad.rotation,
+                ))]);
+            }
+            PcbRecord::Via(via) if via_on_layer(via) => {
+                let center = (f64::from(via.location.x), f64::from(via.location.y));
+                ret.push(vec![capsule(center, center, via.diameter)]);
+            }
+            _ => (),
+        }
+    }
+
+    ret
+}
+
+/// Holes drilled through a copper layer. These remove copper from every
+/// shape, unlike the holes of a region.
+fn drilled_holes(board: &BoardData, layer: Layer) -> Vec<Rings> {
+    let stack_pos = board.stack.iter().position(|l| *l == layer);
+    let pads = board
+        .pads()
+        .filter(|pad| pad.hole_size > 0 && pad.layer == Layer::MultiLayer)
+        .map(|pad| {
+            let offset = pad
+                .location
+                .add_x(pad.hole_offset.x)
+                .add_y(pad.hole_offset.y);
+            let center = rotate(offset, pad.location, pad.rotation);
+            let half = if pad.hole_shape == HoleShape::Slot {
+                i32::try_from(pad.slot_size.saturating_sub(pad.hole_size) / 2).unwrap_or(0)
+            } else {
+                0
+            };
+            let angle = pad.rotation + pad.slot_rotation;
+            let [start, end] = [-half, half]
+                .map(|dx| rotate(center.add_x(dx), center, angle))
+                .map(|loc| (f64::from(loc.x), f64::from(loc.y)));
+            vec![capsule(start, end, pad.hole_size)]
+        });
+    let vias = board
+        .vias()
+        .filter(|via| {
+            let (start, end) = board.via_span(via);
+            via.hole_size > 0 && stack_pos.is_some_and(|pos| (start..=end).contains(&pos))
+        })
+        .map(|via| {
+            let center = (f64::from(via.location.x), f64::from(via.location.y));
+            vec![capsule(center, center, via.hole_size)]
+        });
+    pads.chain(vias).collect()
+}
+
+/// Outline of a round ended line. A single point gives a circle.
+fn capsule(start: (f64, f64), end: (f64, f64), width: u32) -> Vec<(f64, f64)> {
+    let radius = f64::from(width) / 2.0;
+    let dir = (end.1 - start.1).atan2(end.0 - start.0);
+    let mut ret = Vec::new();
+    for (center, offset) in [(end, -PI / 2.0), (start, PI / 2.0)] {
+        for i in 0..=ROUND_STEPS {
+            let angle = dir + offset + PI * f64::from(i) / f64::from(ROUND_STEPS);
+            let (sin, cos) = angle.sin_cos();
+            ret.push((center.0 + radius * cos, center.1 + radius * sin));
+        }
+    }
+    ret
+}
+
+/// Area covered by the union of shapes minus the union of holes in nm²,
+/// measured one row at a time
+fn filled_area(shapes: &[Rings], holes: &[Rings]) -> f64 {
+    let y_range = |rings: &Rings| {
+        let ys = rings.first()?.iter().map(|p| p.1);
+        let (min, max) = ys.fold((f64::MAX, f64::MIN), |(min, max), y| {
+            (min.min(y), max.max(y))
+        });
+        (min < max).then_some((min, max))
+    };
+    // (min y, max y, rings, is a hole)
+    let mut items: Vec<(f64, f64, &Rings, bool)> = shapes
+        .iter()
+        .map(|rings| (rings, false))
+        .chain(holes.iter().map(|rings| (rings, true)))
+        .filter_map(|(rings, hole)| {
+            let (min, max) = y_range(rings)?;
+            Some((min, max, rings, hole))
+        })
+        .collect();
+    items.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let Some(start) = items.iter().filter(|s| !s.3).map(|s| s.0).reduce(f64::min) else {
+        return 0.0;
+    };
+    let end = items.iter().map(|s| s.1).fold(f64::MIN, f64::max);
+
+    let mut area = 0.0;
+    let mut next = 0;
+    let mut active: Vec<(f64, f64, &Rings, bool)> = Vec::new();
+    let mut spans = Vec::new();
+    let mut hole_spans = Vec::new();
+    let mut y = start + AREA_STEP / 2.0;
+    while y < end {
+        while next < items.len() && items[next].0 <= y {
+            active.push(items[next]);
+            next += 1;
+        }
+        active.retain(|s| s.1 >= y);
+
+        spans.clear();
+        hole_spans.clear();
+        for &(.., rings, hole) in &active {
+            let out = if hole { &mut hole_spans } else { &mut spans };
+            row_spans(rings, y, out);
+        }
+        let copper = merge_spans(&mut spans);
+        let holes = merge_spans(&mut hole_spans);
+
+        // Subtract the parts of the row that holes overlap
+        let mut covered: f64 = copper.iter().map(|(lo, hi)| hi - lo).sum();
+        for &(lo, hi) in &copper {
+            for &(hlo, hhi) in &holes {
+                covered -= (hi.min(hhi) - lo.max(hlo)).max(0.0);
+            }
+        }
+
+        area += covered * AREA_STEP;
+        y += AREA_STEP;
+    }
+
+    area
+}
+
+/// Sort spans and join the ones that overlap
+fn merge_spans(spans: &mut [(f64, f64)]) -> Vec<(f64, f64)> {
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut ret: Vec<(f64, f64)> = Vec::new();
+    for &(lo, hi) in spans.iter() {
+        match ret.last_mut() {
+            Some(last) if lo <= last.1 => last.1 = last.1.max(hi),
+            _ => ret.push((lo, hi)),
+        }
+    }
+    ret
+}
+
+/// Parts of a row inside a shape, using the even-odd rule
+fn row_spans(rings: &Rings, y: f64, out: &mut Vec<(f64, f64)>) {
+    let mut crossings = Vec::new();
+    for ring in rings {
+        let n = ring.len();
+        for i in 0..n {
+            let ((x1, y1), (x2, y2)) = (ring[i], ring[(i + 1) % n]);
+            if (y1 > y) != (y2 > y) {
+                crossings.push(x1 + (y - y1) * (x2 - x1) / (y2 - y1));
+            }
+        }
+    }
+    crossings.sort_by(f64::total_cmp);
+    out.extend(crossings.chunks_exact(2).map(|pair| (pair[0], pair[1])));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pcb::record::Track;
+    use crate::pcb::DesignRules;
+
+    #[test]
+    fn test_track_ending_on_via() {
+        // A 40 mil track from a via's center, the via has a 20 mil hole. The
+        // track covers the hole, which must still be removed. Sizes are whole
+        // mils so that rows line up with the edges.
+        let records = vec![
+            PcbRecord::Track(Track {
+                layer: Layer::Top,
+                start: Location::new(0, 0),
+                end: Location::new(10_160_000, 0),
+                width: 1_016_000,
+                ..Default::default()
+            }),
+            PcbRecord::Via(Via {
+                diameter: 1_016_000,
+                hole_size: 508_000,
+                start_layer: Layer::Top,
+                end_layer: Layer::Bottom,
+                ..Default::default()
+            }),
+        ];
+        let board = BoardData {
+            records,
+            components: Vec::new(),
+            nets: Vec::new(),
+            polygon_nets: Vec::new(),
+            plane_nets: Vec::new(),
+            rules: DesignRules::default(),
+            outline: Vec::new(),
+            origin: Location::default(),
+            stack: vec![Layer::Top, Layer::Bottom],
+            stackup: Vec::new(),
+        };
+        let stats = statistics(&board);
+
+        // The track with both round ends, less the hole
+        let expected = 10.16 * 1.016 + PI * 0.508f64.powi(2) - PI * 0.254f64.powi(2);
+        let top = &stats.copper_area[0];
+        assert!((top.area - expected).abs() < 0.01, "{}", top.area);
+        // Only the via's ring is on the bottom
+        let ring = PI * (0.508f64.powi(2) - 0.254f64.powi(2));
+        let bottom = &stats.copper_area[1];
+        assert!((bottom.area - ring).abs() < 0.01, "{}", bottom.area);
+    }
+}
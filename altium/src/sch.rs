@@ -2,6 +2,7 @@
 //! libraries (`.SchLib`)
 
 mod component;
+mod model;
 mod params;
 mod pin;
 mod schdoc;
@@ -12,12 +13,17 @@ pub mod storage;
 pub mod record;
 
 pub use component::Component;
+pub use model::{Model, ModelDatafile, ModelKind, ModelLibrary};
 pub use params::{Justification, SheetStyle};
 #[doc(inline)]
 pub use pin::{ElectricalType, PinError, SchPin};
 #[doc(inline)]
 pub use record::{SchDrawCtx, SchRecord};
 pub use schdoc::SchDoc;
-pub use schlib::{ComponentMeta, ComponentsIter, SchLib};
+pub use schlib::{ComponentMeta, ComponentsIter, SchLib, SchLibWriter};
 #[doc(inline)]
 pub use storage::Storage;
+
+/// Altium's schematic unit is 10 mils, but records are parsed as if it were 1
+/// mil. Schematic lengths need to be multiplied by this to get physical nm.
+pub const SCH_UNIT_SCALE: i32 = 10;
@@ -1,17 +1,15 @@
 //! Things related to the entire component
 
 use std::cmp::Ordering;
-use std::fs::OpenOptions;
 use std::io;
 use std::path::Path;
 use std::sync::Arc;
 
-use svg::node::element::SVG as Svg;
-
-use super::record::parse_all_records;
+use super::record::{component_group, parse_all_records, push_implementation, Parameter};
 use super::storage::Storage;
-use super::{SchDrawCtx, SchRecord};
-use crate::draw::{Canvas, Draw, SvgCtx};
+use super::{Model, ModelKind};
+use super::{SchDrawCtx, SchRecord, SCH_UNIT_SCALE};
+use crate::draw::{Canvas, Draw, DxfCtx, RenderOptions, Svg, SvgCtx};
 use crate::font::FontCollection;
 use crate::Error;
 
@@ -44,28 +42,30 @@ impl Component {
 
     /// Draw this component to a SVG
     pub fn svg(&self) -> Svg {
-        let mut draw = SvgCtx::new();
-        let ctx = SchDrawCtx {
-            fonts: &self.fonts,
-            storage: &self.storage,
-            name: &self.name,
-        };
+        self.svg_with_options(&RenderOptions::default())
+    }
 
-        for record in &self.records {
-            record.draw(&mut draw, &ctx);
-        }
+    /// Draw this component to a SVG using the given theme, filters and scale
+    pub fn svg_with_options(&self, options: &RenderOptions) -> Svg {
+        let mut draw = SvgCtx::with_options(options);
+        draw.set_unit_scale(SCH_UNIT_SCALE);
+        self.draw(&mut draw, options);
         draw.svg()
     }
 
     /// Draw this component to a SVG and write it to a file. Will create the file
     /// if it does not exist.
     pub fn save_svg<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        let file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(path)?;
-        svg::write(&file, &self.svg())
+        self.svg().save(path)
+    }
+
+    /// Draw this component's outlines to a DXF file (in millimeters). Will
+    /// create the file if it does not exist.
+    pub fn save_dxf<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut draw = DxfCtx::new();
+        draw.set_unit_scale(SCH_UNIT_SCALE);
+        self.draw(&mut draw, &RenderOptions::default());
+        draw.save(path)
     }
 
     /// The name of this part
@@ -98,6 +98,106 @@ impl Component {
     pub fn records(&self) -> &[SchRecord] {
         self.records.as_slice()
     }
+
+    /// The component's own parameters as `(name, value)`, not including
+    /// those of its models
+    pub fn parameters(&self) -> Vec<(&str, &str)> {
+        self.records
+            .iter()
+            .filter_map(|record| match record {
+                SchRecord::Parameter(param) if record.owner_index() == Some(0) => {
+                    Some((&*param.name, &*param.text))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// All models linked to this component
+    pub fn models(&self) -> Vec<Model<'_>> {
+        Model::from_records(&self.records)
+    }
+
+    /// Footprints linked to this component
+    pub fn footprints(&self) -> Vec<Model<'_>> {
+        self.models_of(&ModelKind::Footprint)
+    }
+
+    /// Simulation models linked to this component
+    pub fn sim_models(&self) -> Vec<Model<'_>> {
+        self.models_of(&ModelKind::Simulation)
+    }
+
+    /// Signal integrity models linked to this component
+    pub fn signal_integrity_models(&self) -> Vec<Model<'_>> {
+        self.models_of(&ModelKind::SignalIntegrity)
+    }
+
+    /// The footprint that is selected for this component. If none is marked
+    /// as current, this is the first footprint.
+    pub fn current_footprint(&self) -> Option<Model<'_>> {
+        let mut footprints = self.footprints();
+        let idx = footprints.iter().position(Model::is_current).unwrap_or(0);
+        (idx < footprints.len()).then(|| footprints.swap_remove(idx))
+    }
+
+    /// Set one of the component's own parameters, adding a hidden parameter
+    /// if it doesn't have one with that name
+    pub(crate) fn set_parameter(&mut self, name: &str, value: &str) {
+        let existing = self.records.iter_mut().find_map(|record| {
+            let is_own = record.owner_index() == Some(0);
+            match record {
+                SchRecord::Parameter(param) if is_own && param.name.eq_ignore_ascii_case(name) => {
+                    Some(param)
+                }
+                _ => None,
+            }
+        });
+
+        match existing {
+            Some(param) => param.text = value.into(),
+            None => self
+                .records
+                .push(SchRecord::Parameter(Parameter::hidden(name, value))),
+        }
+    }
+
+    /// Link a footprint, adding it if it isn't already linked. If `current`
+    /// is set, it becomes the only current footprint.
+    pub(crate) fn link_footprint(&mut self, name: &str, current: bool) {
+        let mut found = false;
+        for record in &mut self.records {
+            let SchRecord::Implementation(imp) = record else {
+                continue;
+            };
+            if !imp.model_type.eq_ignore_ascii_case("PCBLIB") {
+                continue;
+            }
+            let is_this = imp.model_name.eq_ignore_ascii_case(name);
+            found |= is_this;
+            if current {
+                imp.is_current = is_this;
+            }
+        }
+
+        if !found {
+            push_implementation(&mut self.records, name);
+            if let Some(SchRecord::Implementation(imp)) = self
+                .records
+                .iter_mut()
+                .rev()
+                .find(|record| matches!(record, SchRecord::Implementation(_)))
+            {
+                imp.is_current = current;
+            }
+        }
+    }
+
+    fn models_of(&self, kind: &ModelKind) -> Vec<Model<'_>> {
+        let mut ret = self.models();
+        ret.retain(|model| model.kind() == *kind);
+        ret
+    }
 }
 
 impl Draw for &[SchRecord] {
@@ -109,15 +209,18 @@ impl Draw for &[SchRecord] {
 }
 
 impl Draw for Component {
-    type Context<'a> = ();
+    type Context<'a> = RenderOptions;
 
-    fn draw<C: Canvas>(&self, canvas: &mut C, _ctx: &()) {
+    fn draw<C: Canvas>(&self, canvas: &mut C, options: &RenderOptions) {
         let ctx = SchDrawCtx {
             fonts: &self.fonts,
             storage: &self.storage,
             name: &self.name,
+            options,
         };
 
+        canvas.start_group(component_group(&self.name, &self.records));
         self.records.as_slice().draw(canvas, &ctx);
+        canvas.end_group();
     }
 }
@@ -0,0 +1,148 @@
+//! Models linked to a component, such as footprints and simulation models
+
+use serde::{Deserialize, Serialize};
+
+use super::record::{Implementation, SchRecord};
+
+/// What a model is used for, from an implementation's model type
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ModelKind {
+    /// PCB footprint (`PCBLIB`)
+    Footprint,
+    /// Simulation model (`SIM`)
+    Simulation,
+    /// Signal integrity model (`SI`)
+    SignalIntegrity,
+    /// 3D model (`PCB3DLIB`)
+    Pcb3d,
+    /// Any other model type, as written
+    Other(Box<str>),
+}
+
+impl ModelKind {
+    fn from_model_type(ty: &str) -> Self {
+        match ty.to_ascii_uppercase().as_str() {
+            "PCBLIB" => Self::Footprint,
+            "SIM" => Self::Simulation,
+            "SI" => Self::SignalIntegrity,
+            "PCB3DLIB" => Self::Pcb3d,
+            _ => Self::Other(ty.into()),
+        }
+    }
+}
+
+/// Where a model is expected to be found
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelLibrary {
+    /// Any library available to the project
+    Any,
+    /// The same library as the component, e.g. its integrated library
+    Component,
+}
+
+/// A file entity that provides a model, e.g. a footprint named `SOIC8` in a
+/// `PCBLib`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelDatafile {
+    /// Name of the entity within its library
+    pub entity: Box<str>,
+    /// Kind of library file that holds the entity, e.g. `PCBLib`
+    pub kind: Box<str>,
+}
+
+/// A model linked to a component, such as a footprint or simulation model
+#[derive(Clone, Debug)]
+pub struct Model<'a> {
+    imp: &'a Implementation,
+    params: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Model<'a> {
+    /// Collect the models of a component's records. Owner indices are
+    /// positions in `records`.
+    pub(crate) fn from_records(records: &'a [SchRecord]) -> Vec<Self> {
+        let owned_by = |owner: usize| {
+            records.iter().enumerate().filter(move |(_, record)| {
+                record
+                    .owner_index()
+                    .is_some_and(|idx| usize::from(idx) == owner)
+            })
+        };
+
+        records
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, record)| match record {
+                SchRecord::Implementation(imp) => Some((idx, imp)),
+                _ => None,
+            })
+            .map(|(idx, imp)| {
+                // Parameters belong to a parameter list owned by the implementation
+                let params = owned_by(idx)
+                    .filter(|(_, record)| matches!(record, SchRecord::ImplementationChild2(_)))
+                    .flat_map(|(list_idx, _)| owned_by(list_idx))
+                    .filter_map(|(_, record)| match record {
+                        SchRecord::Parameter(param) => Some((&*param.name, &*param.text)),
+                        _ => None,
+                    })
+                    .collect();
+
+                Self { imp, params }
+            })
+            .collect()
+    }
+
+    /// Name of the model, e.g. the footprint name
+    pub fn name(&self) -> &'a str {
+        &self.imp.model_name
+    }
+
+    /// Description of the model
+    pub fn description(&self) -> &'a str {
+        &self.imp.description
+    }
+
+    /// What this model is used for
+    pub fn kind(&self) -> ModelKind {
+        ModelKind::from_model_type(&self.imp.model_type)
+    }
+
+    /// The model type as written, e.g. `PCBLIB`
+    pub fn model_type(&self) -> &'a str {
+        &self.imp.model_type
+    }
+
+    /// Whether this is the selected model of its kind
+    pub fn is_current(&self) -> bool {
+        self.imp.is_current
+    }
+
+    /// Where this model should be found
+    pub fn library(&self) -> ModelLibrary {
+        if self.imp.use_component_library {
+            ModelLibrary::Component
+        } else {
+            ModelLibrary::Any
+        }
+    }
+
+    /// Every file entity that provides this model
+    pub fn datafiles(&self) -> &'a [ModelDatafile] {
+        &self.imp.datafiles
+    }
+
+    /// Parameters of this model as `(name, value)`
+    pub fn parameters(&self) -> &[(&'a str, &'a str)] {
+        &self.params
+    }
+
+    /// Value of a parameter by name, ignoring case
+    pub fn parameter(&self, name: &str) -> Option<&'a str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, val)| *val)
+    }
+}
@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     common::{PosHoriz, PosVert},
     parse::{FromUtf8, ParseUtf8},
+    write::ToUtf8,
     ErrorKind,
 };
 
@@ -131,6 +132,12 @@ impl FromUtf8<'_> for Justification {
     }
 }
 
+impl ToUtf8 for Justification {
+    fn to_utf8(&self) -> String {
+        (*self as u8).to_string()
+    }
+}
+
 impl From<Justification> for (PosHoriz, PosVert) {
     fn from(value: Justification) -> Self {
         match value {
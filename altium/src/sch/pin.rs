@@ -8,7 +8,7 @@ use log::warn;
 use serde::{Deserialize, Serialize};
 
 use super::SchRecord;
-use crate::common::{mils_to_nm, Location, Rotation90, Visibility};
+use crate::common::{mils_to_nm, nm_to_mils, Location, Rotation90, Visibility};
 use crate::error::AddContext;
 use crate::parse::ParseUtf8;
 use crate::parse::{FromRecord, FromUtf8};
@@ -23,21 +23,27 @@ use crate::{ErrorKind, Result, UniqueId};
 #[from_record(id = 2, record_variant = Pin)]
 pub struct SchPin {
     pub(super) formal_type: u8,
-    pub(super) owner_index: u8,
-    pub(super) owner_part_id: u8,
+    pub(super) owner_index: u16,
+    pub(super) owner_part_id: i8,
+    pub(super) owner_part_display_mode: i8,
     pub description: Box<str>,
     // #[from_record(rename = b"PinDesignator")]
     pub designator: Box<str>,
     pub(super) name: Box<str>,
     pub location: Location,
     pub electrical: ElectricalType,
-    #[from_record(rename = b"PinLength")]
+    #[from_record(rename = b"PinLength", convert = mils_to_nm)]
     pub(super) length: u32,
     #[from_record(rename = b"SwapIDPart")]
     pub(super) swap_id_part: Box<str>,
     pub designator_vis: Visibility,
     pub name_vis: Visibility,
+    /// The entire pin is hidden
+    pub is_hidden: bool,
     pub(super) rotation: Rotation90,
+    /// Rotation and visibility flags, only used when parsing text records
+    #[from_record(rename = b"PinConglomerate")]
+    pub(super) conglomerate: u8,
     #[from_record(rename = b"PinPropagationDelay")]
     pub(super) propegation_delay: f32,
     pub(super) unique_id: UniqueId,
@@ -45,20 +51,23 @@ pub struct SchPin {
 
 impl SchPin {
     pub(crate) fn parse(buf: &[u8]) -> Result<SchRecord> {
-        // 6 bytes unknown
+        // Record type then one byte unknown
         let (_unknown, rest) = buf
-            .split_first_chunk::<6>()
+            .split_first_chunk::<5>()
             .ok_or(PinError::TooShort(buf.len(), "initial group"))?;
 
-        // 6 more bytes unknown - symbols
+        let ([p0, p1, display_mode], rest) = rest
+            .split_first_chunk()
+            .ok_or(PinError::TooShort(rest.len(), "owner part"))?;
+
+        // 4 bytes unknown - symbols
         let (_unknown, rest) = rest
-            .split_first_chunk::<6>()
+            .split_first_chunk::<4>()
             .ok_or(PinError::TooShort(rest.len(), "second group"))?;
 
         let (description, rest) = sized_buf_to_utf8(rest, "description")?;
 
-        // TODO: ty_info
-        let ([formal_type, _ty_info, rot_hide, l0, l1, x0, x1, y0, y1], rest) = rest
+        let ([formal_type, ty_info, rot_hide, l0, l1, x0, x1, y0, y1], rest) = rest
             .split_first_chunk()
             .ok_or(PinError::TooShort(rest.len(), "position extraction"))?;
 
@@ -66,7 +75,9 @@ impl SchPin {
             *formal_type, 1,
             "expected formal type of 1 but got {formal_type}"
         );
-        let (rotation, des_vis, name_vis) = get_rotation_and_hiding(*rot_hide);
+        let (rotation, is_hidden, des_vis, name_vis) = get_rotation_and_hiding(*rot_hide);
+        let electrical = ElectricalType::try_from(*ty_info).context("parsing pin")?;
+        let owner_part_id = i16::from_le_bytes([*p0, *p1]);
         let length = u16::from_le_bytes([*l0, *l1]);
         let location_x = i16::from_le_bytes([*x0, *x1]);
         let location_y = i16::from_le_bytes([*y0, *y1]);
@@ -89,17 +100,23 @@ impl SchPin {
         let retval = Self {
             formal_type: *formal_type,
             owner_index: 0,
-            owner_part_id: 0,
+            owner_part_id: i8::try_from(owner_part_id).unwrap_or_else(|_| {
+                warn!("pin owner part {owner_part_id} out of range");
+                -1
+            }),
+            owner_part_display_mode: i8::from_le_bytes([*display_mode]),
             description: description.into(),
             designator: designator.into(),
             name: name.into(),
             location,
+            electrical,
             length: mils_to_nm(u32::from(length))?,
             // location_x: i32::from(location_x) * 10,
             // location_y: i32::from(location_y) * 10,
             // length: u32::from(length) * 10,
             designator_vis: des_vis,
             name_vis,
+            is_hidden,
             rotation,
             ..Default::default()
         };
@@ -107,6 +124,67 @@ impl SchPin {
         Ok(SchRecord::Pin(retval))
     }
 
+    /// Parse a pin stored as a text record, which schematic documents use. The
+    /// rotation and visibility are packed the same way as in binary records.
+    pub(crate) fn parse_text_if_matches(record_id: u32, buf: &[u8]) -> Option<Result<SchRecord>> {
+        let mut ret = Self::parse_if_matches(record_id, buf)?;
+        if let Ok(SchRecord::Pin(pin)) = &mut ret {
+            (
+                pin.rotation,
+                pin.is_hidden,
+                pin.designator_vis,
+                pin.name_vis,
+            ) = get_rotation_and_hiding(pin.conglomerate);
+        }
+        Some(ret)
+    }
+
+    /// Write this pin in the binary format used by schematic libraries, the
+    /// inverse of `parse`. The record's null terminator is not included.
+    pub(crate) fn to_binary(&self) -> Vec<u8> {
+        // Record ID 2 then one unknown byte
+        let mut buf = vec![0x02, 0x00, 0x00, 0x00, 0x00];
+        buf.extend_from_slice(&i16::from(self.owner_part_id).to_le_bytes());
+        buf.extend_from_slice(&self.owner_part_display_mode.to_le_bytes());
+        // Symbols
+        buf.extend_from_slice(&[0; 4]);
+
+        push_sized_str(&mut buf, &self.description);
+
+        let to_i16 = |val: i32| {
+            i16::try_from(nm_to_mils(val)).unwrap_or_else(|_| {
+                warn!("pin coordinate {val} out of range");
+                if val < 0 {
+                    i16::MIN
+                } else {
+                    i16::MAX
+                }
+            })
+        };
+        let length = u16::try_from(nm_to_mils(self.length)).unwrap_or(u16::MAX);
+
+        buf.push(1); // formal type
+        buf.push(self.electrical as u8);
+        buf.push(set_rotation_and_hiding(
+            self.rotation,
+            self.is_hidden,
+            self.designator_vis,
+            self.name_vis,
+        ));
+        buf.extend_from_slice(&length.to_le_bytes());
+        buf.extend_from_slice(&to_i16(self.location.x).to_le_bytes());
+        buf.extend_from_slice(&to_i16(self.location.y).to_le_bytes());
+        // Colors
+        buf.extend_from_slice(&[0; 4]);
+
+        push_sized_str(&mut buf, &self.name);
+        push_sized_str(&mut buf, &self.designator);
+
+        // Trailer that Altium always seems to write
+        buf.extend_from_slice(&[0x00, 0x03, b'|', b'&', b'|']);
+        buf
+    }
+
     /// Nonconnecting point of this pin
     pub(crate) fn location(&self) -> Location {
         self.location
@@ -144,13 +222,28 @@ fn sized_buf_to_utf8<'a>(
     Ok((text, &rest[text_len..]))
 }
 
+/// Write a string with a `u8` length, truncating to a char boundary if needed
+fn push_sized_str(buf: &mut Vec<u8>, s: &str) {
+    let mut end = s.len().min(u8::MAX.into());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    if end < s.len() {
+        warn!("truncating pin text `{s}`");
+    }
+
+    buf.push(u8::try_from(end).unwrap());
+    buf.extend_from_slice(&s.as_bytes()[..end]);
+}
+
 /// Given a byte representing rotation and hiding, extract that info
 ///
-/// Returns `(rotation, designator_vis, name_vis)`
-fn get_rotation_and_hiding(val: u8) -> (Rotation90, Visibility, Visibility) {
+/// Returns `(rotation, is_hidden, designator_vis, name_vis)`
+fn get_rotation_and_hiding(val: u8) -> (Rotation90, bool, Visibility, Visibility) {
     const ROT_MASK: u8 = 0b00000011;
-    const VIS_DES_MASK: u8 = 0b00001000;
-    const VIS_NAME_MASK: u8 = 0b00010000;
+    const HIDDEN_MASK: u8 = 0b00000100;
+    const VIS_NAME_MASK: u8 = 0b00001000;
+    const VIS_DES_MASK: u8 = 0b00010000;
 
     let rotation = match val & ROT_MASK {
         x if x == Rotation90::R0 as u8 => Rotation90::R0,
@@ -172,7 +265,36 @@ fn get_rotation_and_hiding(val: u8) -> (Rotation90, Visibility, Visibility) {
         Visibility::Visible
     };
 
-    (rotation, des_vis, name_vis)
+    let is_hidden = (val & HIDDEN_MASK) != 0;
+
+    (rotation, is_hidden, des_vis, name_vis)
+}
+
+/// Inverse of `get_rotation_and_hiding`
+fn set_rotation_and_hiding(
+    rotation: Rotation90,
+    is_hidden: bool,
+    des_vis: Visibility,
+    name_vis: Visibility,
+) -> u8 {
+    const HIDDEN_MASK: u8 = 0b00000100;
+    const VIS_NAME_MASK: u8 = 0b00001000;
+    const VIS_DES_MASK: u8 = 0b00010000;
+    // Always set in files that Altium writes
+    const UNKNOWN_MASK: u8 = 0b00100000;
+
+    let mut val = rotation as u8 | UNKNOWN_MASK;
+    if is_hidden {
+        val |= HIDDEN_MASK;
+    }
+    if name_vis == Visibility::Visible {
+        val |= VIS_NAME_MASK;
+    }
+    if des_vis == Visibility::Visible {
+        val |= VIS_DES_MASK;
+    }
+
+    val
 }
 
 #[repr(u8)]
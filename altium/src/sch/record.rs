@@ -51,15 +51,23 @@
 //! We provide a derive macro for `FromRecord`, so most types in this module
 //! don't need to do anything special.
 mod draw;
+mod kicad;
+mod netlist;
 mod parse;
+mod write;
 
 use std::str;
 
-use altium_macros::FromRecord;
+use altium_macros::{FromRecord, ToRecord};
 pub use draw::SchDrawCtx;
+pub(crate) use draw::{component_group, draw_grouped};
+pub(crate) use kicad::{kicad_pin_type, kicad_symbol, push_implementation, symbol_records};
+pub(crate) use netlist::extract_netlist;
 pub(super) use parse::parse_all_records;
 use serde::{Deserialize, Serialize};
+pub(super) use write::write_all_records;
 
+use super::model::ModelDatafile;
 use super::params::Justification;
 use super::pin::SchPin;
 use crate::common::{mils_to_nm, Location, LocationFract, ReadOnlyState, UniqueId};
@@ -161,6 +169,113 @@ impl SchRecord {
             Self::ImplementationChild2(_) => "ImplementationChild2",
         }
     }
+
+    /// This record's unique ID, if it has one
+    pub fn unique_id(&self) -> Option<UniqueId> {
+        let id = match self {
+            Self::MetaData(v) => Some(v.unique_id),
+            Self::Pin(v) => Some(v.unique_id),
+            Self::Label(v) => Some(v.unique_id),
+            Self::Bezier(v) => Some(v.unique_id),
+            Self::PolyLine(v) => Some(v.unique_id),
+            Self::Polygon(v) => Some(v.unique_id),
+            Self::Ellipse(v) => Some(v.unique_id),
+            Self::RectangleRounded(v) => Some(v.unique_id),
+            Self::ElipticalArc(v) => Some(v.unique_id),
+            Self::Arc(v) => Some(v.unique_id),
+            Self::Line(v) => Some(v.unique_id),
+            Self::Rectangle(v) => Some(v.unique_id),
+            Self::SheetSymbol(v) => Some(v.unique_id),
+            Self::SheetEntry(v) => Some(v.unique_id),
+            Self::PowerPort(v) => Some(v.unique_id),
+            Self::Port(v) => Some(v.unique_id),
+            Self::NoErc(v) => Some(v.unique_id),
+            Self::NetLabel(v) => Some(v.unique_id),
+            Self::Bus(v) => Some(v.unique_id),
+            Self::Wire(v) => Some(v.unique_id),
+            Self::TextFrame(v) => Some(v.unique_id),
+            Self::Image(v) => Some(v.unique_id),
+            Self::SheetName(v) => Some(v.unique_id),
+            Self::FileName(v) => Some(v.unique_id),
+            Self::Designator(v) => Some(v.unique_id),
+            Self::Parameter(v) => Some(v.unique_id),
+            Self::Implementation(v) => Some(v.unique_id),
+            _ => None,
+        };
+
+        // Records without a unique ID (e.g. binary pins) get the default
+        id.filter(|id| *id != UniqueId::default())
+    }
+
+    /// Index of the record that owns this one, if it has an owner. This is the
+    /// record's position in the file, not in a list of parsed records.
+    pub(crate) fn owner_index(&self) -> Option<u16> {
+        let idx = match self {
+            Self::Pin(v) => v.owner_index,
+            Self::IeeeSymbol(v) => v.owner_index,
+            Self::Label(v) => v.owner_index,
+            Self::Bezier(v) => v.owner_index,
+            Self::PolyLine(v) => v.owner_index,
+            Self::Polygon(v) => v.owner_index,
+            Self::Ellipse(v) => v.owner_index,
+            Self::Piechart(v) => v.owner_index,
+            Self::RectangleRounded(v) => v.owner_index,
+            Self::ElipticalArc(v) => v.owner_index,
+            Self::Arc(v) => v.owner_index,
+            Self::Line(v) => v.owner_index,
+            Self::Rectangle(v) => v.owner_index,
+            Self::SheetSymbol(v) => v.owner_index,
+            Self::SheetEntry(v) => v.owner_index,
+            Self::PowerPort(v) => v.owner_index,
+            Self::Port(v) => v.owner_index,
+            Self::NoErc(v) => v.owner_index,
+            Self::NetLabel(v) => v.owner_index,
+            Self::Bus(v) => v.owner_index,
+            Self::Wire(v) => v.owner_index,
+            Self::TextFrame(v) => v.owner_index,
+            Self::Junction(v) => v.owner_index,
+            Self::Image(v) => v.owner_index,
+            Self::SheetName(v) => v.owner_index,
+            Self::FileName(v) => v.owner_index,
+            Self::Designator(v) => v.owner_index,
+            Self::BusEntry(v) => v.owner_index,
+            Self::Template(v) => v.owner_index,
+            Self::Parameter(v) => v.owner_index,
+            Self::ImplementationList(v) => v.owner_index,
+            Self::Implementation(v) => v.owner_index,
+            Self::ImplementationChild1(v) => v.owner_index,
+            Self::ImplementationChild2(v) => v.owner_index,
+            _ => return None,
+        };
+        Some(idx)
+    }
+
+    /// The font used by this record, if it has text
+    pub(crate) fn font_id_mut(&mut self) -> Option<&mut u16> {
+        match self {
+            Self::Label(v) => Some(&mut v.font_id),
+            Self::SheetSymbol(v) => Some(&mut v.font_id),
+            Self::SheetEntry(v) => Some(&mut v.text_font_id),
+            Self::PowerPort(v) => Some(&mut v.font_id),
+            Self::Port(v) => Some(&mut v.font_id),
+            Self::NetLabel(v) => Some(&mut v.font_id),
+            Self::TextFrame(v) => Some(&mut v.font_id),
+            Self::SheetName(v) => Some(&mut v.font_id),
+            Self::FileName(v) => Some(&mut v.font_id),
+            Self::Designator(v) => Some(&mut v.font_id),
+            Self::Parameter(v) => Some(&mut v.font_id),
+            _ => None,
+        }
+    }
+
+    /// Path of an image stored in the `Storage` stream, if this record uses
+    /// one
+    pub(crate) fn embedded_image(&self) -> Option<&str> {
+        match self {
+            Self::Image(v) if v.embed_image => Some(&v.file_name),
+            _ => None,
+        }
+    }
 }
 
 /// Try all known record types (excludes binary pins)
@@ -180,7 +295,7 @@ pub fn parse_any_record(buf: &[u8]) -> Result<SchRecord, Error> {
     // Try parsing all our types, they will just skip to the next one if the
     // record ID doesn't match
     MetaData::parse_if_matches(record_id, to_parse)
-        .or_else(|| SchPin::parse_if_matches(record_id, to_parse))
+        .or_else(|| SchPin::parse_text_if_matches(record_id, to_parse))
         .or_else(|| IeeeSymbol::parse_if_matches(record_id, to_parse))
         .or_else(|| Label::parse_if_matches(record_id, to_parse))
         .or_else(|| Bezier::parse_if_matches(record_id, to_parse))
@@ -223,7 +338,7 @@ pub fn parse_any_record(buf: &[u8]) -> Result<SchRecord, Error> {
 
 /// Component metadata (AKA "Component")
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 1, use_box = true)]
 pub struct MetaData {
     all_pin_count: u32,
@@ -233,6 +348,8 @@ pub struct MetaData {
     database_table_name: Box<str>,
     #[from_record(rename = b"ComponentDescription")]
     pub(crate) description: Option<Box<str>>,
+    /// Current display mode
+    display_mode: i8,
     /// Alternative display modes
     display_mode_count: u8,
     index_in_sheet: i16,
@@ -253,18 +370,25 @@ pub struct MetaData {
     unique_id: UniqueId,
 }
 
+impl MetaData {
+    /// Number of parts, plus one
+    pub(crate) fn part_count(&self) -> u8 {
+        self.part_count
+    }
+}
+
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 3)]
 pub struct IeeeSymbol {
     is_not_accessible: bool,
     pub location: Location,
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 4)]
 pub struct Label {
     pub color: Rgb,
@@ -274,15 +398,16 @@ pub struct Label {
     is_mirrored: bool,
     pub location: LocationFract,
     orientation: i32,
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
+    owner_part_display_mode: i8,
     text: Box<str>,
     pub unique_id: UniqueId,
     pub justification: Justification,
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 5)]
 pub struct Bezier {
     color: Rgb,
@@ -292,18 +417,19 @@ pub struct Bezier {
     line_width: u32,
     #[from_record(array_map = (X -> x, Y -> y), count = b"LocationCount", convert = mils_to_nm)]
     pub locations: Vec<Location>,
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     owner_part_display_mode: i8,
     pub unique_id: UniqueId,
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 6)]
 pub struct PolyLine {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
+    owner_part_display_mode: i8,
     is_not_accessible: bool,
     index_in_sheet: i16,
     #[from_record(convert = mils_to_nm)]
@@ -315,7 +441,7 @@ pub struct PolyLine {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 7)]
 pub struct Polygon {
     pub area_color: Rgb,
@@ -327,14 +453,14 @@ pub struct Polygon {
     line_width: u32,
     #[from_record(array_map = (X -> x, Y -> y), count = b"LocationCount", convert = mils_to_nm)]
     pub locations: Vec<Location>,
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     owner_part_display_mode: i8,
     pub unique_id: UniqueId,
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 8)]
 pub struct Ellipse {
     pub area_color: Rgb,
@@ -345,24 +471,26 @@ pub struct Ellipse {
     #[from_record(convert = mils_to_nm)]
     line_width: u32,
     pub location: Location,
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     owner_part_display_mode: i8,
+    #[from_record(convert = mils_to_nm)]
     radius: i32,
+    #[from_record(convert = mils_to_nm)]
     secondary_radius: i32,
     pub unique_id: UniqueId,
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 9)]
 pub struct Piechart {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 10)]
 pub struct RectangleRounded {
     pub area_color: Rgb,
@@ -376,7 +504,7 @@ pub struct RectangleRounded {
     #[from_record(convert = mils_to_nm)]
     line_width: u32,
     location: Location,
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     owner_part_display_mode: i8,
     transparent: bool,
@@ -384,11 +512,12 @@ pub struct RectangleRounded {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 11)]
 pub struct ElipticalArc {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
+    owner_part_display_mode: i8,
     is_not_accessible: bool,
     index_in_sheet: i16,
     location: LocationFract,
@@ -407,11 +536,12 @@ pub struct ElipticalArc {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 12)]
 pub struct Arc {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
+    owner_part_display_mode: i8,
     is_not_accessible: bool,
     index_in_sheet: i16,
     pub location: LocationFract,
@@ -430,11 +560,13 @@ pub struct Arc {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 13)]
 pub struct Line {
     pub color: Rgb,
+    #[from_record(convert = mils_to_nm)]
     corner_x: i32,
+    #[from_record(convert = mils_to_nm)]
     corner_y: i32,
     index_in_sheet: i16,
     is_not_accessible: bool,
@@ -442,16 +574,18 @@ pub struct Line {
     #[from_record(convert = mils_to_nm)]
     line_width: u32,
     location_count: u16,
+    #[from_record(convert = mils_to_nm)]
     location_x: i32,
+    #[from_record(convert = mils_to_nm)]
     location_y: i32,
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     owner_part_display_mode: i8,
     pub unique_id: UniqueId,
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 14)]
 pub struct Rectangle {
     pub area_color: Rgb,
@@ -465,7 +599,7 @@ pub struct Rectangle {
     line_width: u32,
     /// Bottom left corner
     pub location: LocationFract,
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     owner_part_display_mode: i8,
     pub transparent: bool,
@@ -473,10 +607,10 @@ pub struct Rectangle {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 15)]
 pub struct SheetSymbol {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     index_in_sheet: i16,
     #[from_record(convert = mils_to_nm)]
@@ -498,10 +632,10 @@ pub struct SheetSymbol {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 16)]
 pub struct SheetEntry {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     index_in_sheet: i16,
     pub text_color: Rgb,
@@ -519,10 +653,10 @@ pub struct SheetEntry {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 17)]
 pub struct PowerPort {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     is_cross_sheet_connector: bool,
     index_in_sheet: i16,
@@ -537,7 +671,7 @@ pub struct PowerPort {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 18)]
 pub struct Port {
     alignment: u16,
@@ -545,24 +679,26 @@ pub struct Port {
     border_width: i32,
     pub color: Rgb,
     font_id: u16,
+    #[from_record(convert = mils_to_nm)]
     height: i32,
+    #[from_record(convert = mils_to_nm)]
     width: i32,
     index_in_sheet: i16,
     #[from_record(rename = b"IOType")]
     io_type: u16,
     pub location: Location,
     name: Box<str>,
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     pub text_color: Rgb,
     pub unique_id: UniqueId,
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 22)]
 pub struct NoErc {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     index_in_sheet: i16,
     orientation: i16,
@@ -575,10 +711,10 @@ pub struct NoErc {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 25)]
 pub struct NetLabel {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     index_in_sheet: i16,
     pub location: Location,
@@ -589,10 +725,10 @@ pub struct NetLabel {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 26)]
 pub struct Bus {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     index_in_sheet: i16,
     #[from_record(convert = mils_to_nm)]
@@ -604,10 +740,10 @@ pub struct Bus {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 27)]
 pub struct Wire {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     #[from_record(convert = mils_to_nm)]
     line_width: u32,
@@ -619,13 +755,13 @@ pub struct Wire {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 28)]
 pub struct TextFrame {
     location: LocationFract,
     corner: LocationFract,
     pub area_color: Rgb,
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     font_id: u16,
     alignment: u16,
@@ -637,18 +773,20 @@ pub struct TextFrame {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 29)]
 pub struct Junction {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
+    pub location: Location,
+    pub color: Rgb,
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 30)]
 pub struct Image {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     is_not_accessible: bool,
     index_in_sheet: i16,
@@ -663,10 +801,10 @@ pub struct Image {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 31)]
 pub struct Sheet {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     snap_grid_size: i32,
     snap_grid_on: bool,
@@ -697,10 +835,10 @@ pub struct Sheet {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 32)]
 pub struct SheetName {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     index_in_sheet: i16,
     pub location: Location,
@@ -711,10 +849,10 @@ pub struct SheetName {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 33)]
 pub struct FileName {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     index_in_sheet: i16,
     pub location: Location,
@@ -725,10 +863,10 @@ pub struct FileName {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 34)]
 pub struct Designator {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     pub location: Location,
     pub color: Rgb,
@@ -742,28 +880,28 @@ pub struct Designator {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 37)]
 pub struct BusEntry {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 39)]
 pub struct Template {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     is_not_accessible: bool,
     file_name: Box<str>,
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 41)]
 pub struct Parameter {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
     pub location: Location,
     index_in_sheet: i16,
@@ -776,46 +914,74 @@ pub struct Parameter {
     pub text: Box<str>,
 }
 
+impl Parameter {
+    /// A hidden parameter owned by the component itself
+    pub(crate) fn hidden(name: &str, text: &str) -> Self {
+        Self {
+            owner_part_id: -1,
+            index_in_sheet: -1,
+            font_id: 1,
+            unique_id: UniqueId::new_random(),
+            name: name.into(),
+            is_hidden: true,
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Container for a component's models
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 44)]
 pub struct ImplementationList {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
 }
 
-/// Things like models, including footprints
+/// Things like models, including footprints. See [`Model`](crate::sch::Model)
+/// for a typed view.
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 45)]
 pub struct Implementation {
-    owner_index: u8,
-    owner_part_id: i8,
-    use_component_library: bool,
-    model_name: Box<str>,
-    model_type: Box<str>,
-    datafile_count: u16,
-    model_datafile_entity0: Box<str>,
-    model_datafile_kind0: Box<str>,
-    is_current: bool,
+    owner_index: u16,
+    owner_part_id: i8,
+    pub(crate) description: Box<str>,
+    /// Look for the model in the component's own library rather than any
+    /// available library
+    pub(crate) use_component_library: bool,
+    pub(crate) model_name: Box<str>,
+    pub(crate) model_type: Box<str>,
+    #[from_record(
+        array_map = (ModelDatafileEntity -> entity, ModelDatafileKind -> kind),
+        count = b"DatafileCount",
+        index_base = 0
+    )]
+    pub(crate) datafiles: Vec<ModelDatafile>,
+    pub(crate) is_current: bool,
     datalinks_locked: bool,
     database_datalinks_locked: bool,
     pub unique_id: UniqueId,
     index_in_sheet: i16,
 }
 
+/// Pin mapping between the symbol and an implementation's model. Owned by an
+/// [`Implementation`].
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 46)]
 pub struct ImplementationChild1 {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
 }
 
+/// Parameters for an implementation's model, which are [`Parameter`] records
+/// owned by this one. Owned by an [`Implementation`].
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, FromRecord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, FromRecord, ToRecord, Serialize, Deserialize)]
 #[from_record(id = 48)]
 pub struct ImplementationChild2 {
-    owner_index: u8,
+    owner_index: u16,
     owner_part_id: i8,
 }
@@ -5,16 +5,12 @@ use log::warn;
 use crate::common::{Location, LocationFract, PosHoriz, PosVert, Rgb, Rotation90, Visibility};
 use crate::draw::canvas::{Canvas, DrawLine, DrawText};
 use crate::draw::canvas::{DrawRectangle, LineCap};
-use crate::draw::{Draw, DrawArc, DrawPolyLine, DrawPolygon};
+use crate::draw::{Draw, DrawArc, DrawGroup, DrawImage, DrawPolyLine, DrawPolygon, RenderOptions};
 use crate::font::FontCollection;
 use crate::sch::pin::SchPin;
 use crate::sch::record;
 use crate::sch::storage::Storage;
 
-// 500k embedded
-#[allow(unused)]
-const MAX_EMBED_SIZE: usize = 500_000;
-
 /// Grid width used for reference; 0.25mm (?? should be )
 const GW: i32 = 250_000;
 const GW_H: i32 = GW / 2;
@@ -34,12 +30,87 @@ pub struct SchDrawCtx<'a> {
     pub storage: &'a Storage,
     /// Just for reference
     pub name: &'a str,
+    /// Theme, filters, etc
+    pub options: &'a RenderOptions,
+}
+
+/// Group information for a component given its metadata and the records that
+/// it owns
+pub(crate) fn component_group<'a>(
+    name: &'a str,
+    records: impl IntoIterator<Item = &'a record::SchRecord>,
+) -> DrawGroup<'a> {
+    let mut group = DrawGroup {
+        kind: "Component",
+        name: Some(name),
+        ..Default::default()
+    };
+
+    for record in records {
+        match record {
+            record::SchRecord::MetaData(meta) => group.unique_id = Some(meta.unique_id),
+            record::SchRecord::Designator(des) if &*des.name == "Designator" => {
+                group.designator = Some(&des.text);
+            }
+            _ => (),
+        }
+    }
+
+    group
+}
+
+/// Draw records from a document, grouping each component with the records
+/// that it owns. `owner_pos` maps an owner index to a position in `records`.
+pub(crate) fn draw_grouped<C: Canvas>(
+    records: &[record::SchRecord],
+    canvas: &mut C,
+    ctx: &SchDrawCtx<'_>,
+    owner_pos: impl Fn(usize) -> Option<usize>,
+) {
+    // Children of each component, indexed by the component's position
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); records.len()];
+    let mut is_owned = vec![false; records.len()];
+
+    for (idx, record) in records.iter().enumerate() {
+        let Some(owner) = record.owner_index().and_then(|o| owner_pos(o.into())) else {
+            continue;
+        };
+        if matches!(records.get(owner), Some(record::SchRecord::MetaData(_))) {
+            children[owner].push(idx);
+            is_owned[idx] = true;
+        }
+    }
+
+    for (idx, record) in records.iter().enumerate() {
+        if is_owned[idx] {
+            continue;
+        }
+
+        let record::SchRecord::MetaData(meta) = record else {
+            record.draw(canvas, ctx);
+            continue;
+        };
+
+        let owned = children[idx].iter().map(|&i| &records[i]);
+        canvas.start_group(component_group(&meta.libref, owned.clone().chain([record])));
+        record.draw(canvas, ctx);
+        owned.for_each(|r| r.draw(canvas, ctx));
+        canvas.end_group();
+    }
 }
 
 impl Draw for record::SchRecord {
     type Context<'a> = SchDrawCtx<'a>;
 
     fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &SchDrawCtx<'_>) {
+        canvas.start_group(self.draw_group());
+        self.draw_ungrouped(canvas, ctx);
+        canvas.end_group();
+    }
+}
+
+impl record::SchRecord {
+    fn draw_ungrouped<C: Canvas>(&self, canvas: &mut C, ctx: &SchDrawCtx<'_>) {
         match self {
             // record::SchRecord::MetaData(v) => v.draw(canvas, ctx),
             record::SchRecord::Pin(v) => v.draw(canvas, ctx),
@@ -79,19 +150,44 @@ impl Draw for record::SchRecord {
             _ => (),
         }
     }
+
+    /// Metadata for the group that contains this record's drawing
+    fn draw_group(&self) -> DrawGroup<'_> {
+        let mut group = DrawGroup {
+            kind: self.name(),
+            unique_id: self.unique_id(),
+            ..Default::default()
+        };
+
+        match self {
+            record::SchRecord::Pin(v) => {
+                group.name = Some(&v.name);
+                group.designator = Some(&v.designator);
+            }
+            record::SchRecord::PowerPort(v) => group.net = Some(&v.text),
+            record::SchRecord::Port(v) => group.net = Some(&v.name),
+            record::SchRecord::NetLabel(v) => group.net = Some(&v.text),
+            record::SchRecord::Parameter(v) => group.name = Some(&v.name),
+            _ => (),
+        }
+
+        group
+    }
 }
 
 impl Draw for SchPin {
     type Context<'a> = SchDrawCtx<'a>;
 
-    fn draw<C: Canvas>(&self, canvas: &mut C, _ctx: &SchDrawCtx<'_>) {
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &SchDrawCtx<'_>) {
         use PosHoriz::{Left, Right};
         use PosVert::{Bottom, Middle};
         use Rotation90::{R0, R180, R270, R90};
 
         const X_WIDTH: u32 = 5_000;
 
-        canvas.add_comment(format!("{self:#?}"));
+        if self.is_hidden && !ctx.options.show_hidden_pins {
+            return;
+        }
 
         let start = self.location();
         let end = self.location_conn();
@@ -99,7 +195,7 @@ impl Draw for SchPin {
         canvas.draw_line(DrawLine {
             start,
             end,
-            color: Rgb::black(),
+            color: ctx.options.stroke(Rgb::black()),
             width: THICK_LINE_WIDTH,
             start_cap: LineCap::Round,
             ..Default::default()
@@ -110,7 +206,7 @@ impl Draw for SchPin {
         canvas.draw_line(DrawLine {
             start: end.add_x(10000),
             end: end.add_x(-10000),
-            color: Rgb::white(),
+            color: ctx.options.background(),
             width: X_WIDTH,
             ..Default::default()
         });
@@ -118,7 +214,7 @@ impl Draw for SchPin {
         canvas.draw_line(DrawLine {
             start: end.add_y(10000),
             end: end.add_y(-10000),
-            color: Rgb::white(),
+            color: ctx.options.background(),
             width: X_WIDTH,
             ..Default::default()
         });
@@ -144,12 +240,13 @@ impl Draw for SchPin {
                 text: &self.name,
                 anchor_h,
                 anchor_v,
+                color: ctx.options.stroke(Rgb::black()),
                 rotation: txt_rotation,
                 ..Default::default()
             });
         }
 
-        if self.designator_vis == Visibility::Visible {
+        if self.designator_vis == Visibility::Visible && ctx.options.show_pin_numbers {
             let (anchor_h, anchor_v) = match self.rotation {
                 R0 | R90 => (Left, Bottom),
                 R180 | R270 => (Right, Bottom),
@@ -161,6 +258,7 @@ impl Draw for SchPin {
                 text: &self.designator,
                 anchor_h,
                 anchor_v,
+                color: ctx.options.stroke(Rgb::black()),
                 rotation: txt_rotation,
                 ..Default::default()
             });
@@ -184,7 +282,7 @@ impl Draw for record::Label {
             font,
             anchor_h,
             anchor_v,
-            color: self.color,
+            color: ctx.options.stroke(self.color),
             ..Default::default() // rotation: todo!(),
         });
     }
@@ -195,7 +293,7 @@ impl Draw for record::Label {
 impl Draw for record::PolyLine {
     type Context<'a> = SchDrawCtx<'a>;
 
-    fn draw<C: Canvas>(&self, canvas: &mut C, _ctx: &Self::Context<'_>) {
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &Self::Context<'_>) {
         let locations: Vec<_> = self
             .locations
             .iter()
@@ -205,7 +303,7 @@ impl Draw for record::PolyLine {
 
         canvas.draw_polyline(DrawPolyLine {
             locations: &locations,
-            color: self.color,
+            color: ctx.options.stroke(self.color),
             width: self.line_width,
             ..Default::default()
         });
@@ -215,11 +313,11 @@ impl Draw for record::PolyLine {
 impl Draw for record::Polygon {
     type Context<'a> = SchDrawCtx<'a>;
 
-    fn draw<C: Canvas>(&self, canvas: &mut C, _ctx: &SchDrawCtx<'_>) {
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &SchDrawCtx<'_>) {
         canvas.draw_polygon(DrawPolygon {
             locations: &self.locations,
-            fill_color: self.area_color,
-            stroke_color: self.color,
+            fill_color: ctx.options.fill(self.area_color),
+            stroke_color: ctx.options.stroke(self.color),
             stroke_width: self.line_width,
             ..Default::default()
         });
@@ -232,7 +330,7 @@ impl Draw for record::Polygon {
 impl Draw for record::RectangleRounded {
     type Context<'a> = SchDrawCtx<'a>;
 
-    fn draw<C: Canvas>(&self, canvas: &mut C, _ctx: &SchDrawCtx<'_>) {
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &SchDrawCtx<'_>) {
         let width = self.corner.x - self.location.x;
         let height = self.corner.y - self.location.y;
 
@@ -242,8 +340,8 @@ impl Draw for record::RectangleRounded {
             y: self.location.y,
             width,
             height,
-            fill_color: self.area_color,
-            stroke_color: self.color,
+            fill_color: ctx.options.fill(self.area_color),
+            stroke_color: ctx.options.stroke(self.color),
             stroke_width: self.line_width,
             ..Default::default()
         });
@@ -254,14 +352,14 @@ impl Draw for record::RectangleRounded {
 impl Draw for record::Arc {
     type Context<'a> = SchDrawCtx<'a>;
 
-    fn draw<C: Canvas>(&self, canvas: &mut C, _ctx: &Self::Context<'_>) {
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &Self::Context<'_>) {
         canvas.draw_arc(DrawArc {
             center: self.location.as_location(),
             x_radius: self.radius,
             y_radius: self.secondary_radius,
             start_angle: self.start_angle.to_radians(),
             end_angle: self.end_angle.to_radians(),
-            color: self.color,
+            color: ctx.options.stroke(self.color),
             width: self.line_width,
             ..Default::default()
         });
@@ -271,11 +369,11 @@ impl Draw for record::Arc {
 impl Draw for record::Line {
     type Context<'a> = SchDrawCtx<'a>;
 
-    fn draw<C: Canvas>(&self, canvas: &mut C, _ctx: &Self::Context<'_>) {
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &Self::Context<'_>) {
         canvas.draw_line(DrawLine {
             start: Location::new(self.location_x, self.location_y),
             end: Location::new(self.corner_x, self.corner_y),
-            color: self.color,
+            color: ctx.options.stroke(self.color),
             width: self.line_width,
             ..Default::default()
         });
@@ -285,7 +383,7 @@ impl Draw for record::Line {
 impl Draw for record::Rectangle {
     type Context<'a> = SchDrawCtx<'a>;
 
-    fn draw<C: Canvas>(&self, canvas: &mut C, _ctx: &SchDrawCtx<'_>) {
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &SchDrawCtx<'_>) {
         let width = self.corner.x - self.location.x;
         let height = self.corner.y - self.location.y;
 
@@ -294,8 +392,8 @@ impl Draw for record::Rectangle {
             y: self.location.y,
             width,
             height,
-            fill_color: self.area_color,
-            stroke_color: self.color,
+            fill_color: ctx.options.fill(self.area_color),
+            stroke_color: ctx.options.stroke(self.color),
             stroke_width: self.line_width,
             ..Default::default()
         });
@@ -305,14 +403,14 @@ impl Draw for record::Rectangle {
 impl Draw for record::SheetSymbol {
     type Context<'a> = SchDrawCtx<'a>;
 
-    fn draw<C: Canvas>(&self, canvas: &mut C, _ctx: &SchDrawCtx<'_>) {
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &SchDrawCtx<'_>) {
         canvas.draw_rectangle(DrawRectangle {
             x: self.location.x,
             y: self.location.y - self.y_size,
             width: self.x_size,
             height: self.y_size,
-            fill_color: self.area_color,
-            stroke_color: self.color,
+            fill_color: ctx.options.fill(self.area_color),
+            stroke_color: ctx.options.stroke(self.color),
             stroke_width: self.line_width,
             ..Default::default()
         });
@@ -359,7 +457,7 @@ impl Draw for record::PowerPort {
             for points in lines {
                 canvas.draw_polyline(DrawPolyLine {
                     locations: points,
-                    color: self.color,
+                    color: ctx.options.stroke(self.color),
                     width: THIN_LINE_WIDTH,
                     ..Default::default()
                 });
@@ -404,7 +502,7 @@ impl Draw for record::PowerPort {
             canvas.draw_line(DrawLine {
                 start: *start,
                 end: *end,
-                color: self.color,
+                color: ctx.options.stroke(self.color),
                 width: THIN_LINE_WIDTH,
                 ..Default::default()
             });
@@ -431,8 +529,8 @@ impl Draw for record::Port {
 
         canvas.draw_polygon(DrawPolygon {
             locations: &locations,
-            fill_color: self.area_color,
-            stroke_color: self.color,
+            fill_color: ctx.options.fill(self.area_color),
+            stroke_color: ctx.options.stroke(self.color),
             stroke_width: self.border_width.try_into().unwrap(),
             ..Default::default()
         });
@@ -442,7 +540,7 @@ impl Draw for record::Port {
             x: self.location.x,
             y: self.location.y,
             text: &self.name,
-            color: self.text_color,
+            color: ctx.options.stroke(self.text_color),
             font,
             ..Default::default()
         });
@@ -457,7 +555,7 @@ impl Draw for record::NoErc {
             let mut line = DrawLine {
                 start: Location::new(self.location.x - GW_Q, self.location.y - GW_Q),
                 end: Location::new(self.location.x + GW_Q, self.location.y + GW_Q),
-                color: Rgb::red(),
+                color: ctx.options.stroke(Rgb::red()),
                 width: THIN_LINE_WIDTH,
                 ..Default::default()
             };
@@ -485,7 +583,7 @@ impl Draw for record::NetLabel {
             x: self.location.x,
             y: self.location.y,
             text: &self.text,
-            color: self.color,
+            color: ctx.options.stroke(self.color),
             font,
             ..Default::default()
         });
@@ -495,10 +593,10 @@ impl Draw for record::NetLabel {
 impl Draw for record::Bus {
     type Context<'a> = SchDrawCtx<'a>;
 
-    fn draw<C: Canvas>(&self, canvas: &mut C, _ctx: &Self::Context<'_>) {
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &Self::Context<'_>) {
         canvas.draw_polyline(DrawPolyLine {
             locations: &self.locations,
-            color: self.color,
+            color: ctx.options.stroke(self.color),
             width: self.line_width,
             ..Default::default()
         });
@@ -508,10 +606,10 @@ impl Draw for record::Bus {
 impl Draw for record::Wire {
     type Context<'a> = SchDrawCtx<'a>;
 
-    fn draw<C: Canvas>(&self, canvas: &mut C, _ctx: &Self::Context<'_>) {
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &Self::Context<'_>) {
         canvas.draw_polyline(DrawPolyLine {
             locations: &self.locations,
-            color: self.color,
+            color: ctx.options.stroke(self.color),
             width: self.line_width,
             ..Default::default()
         });
@@ -523,40 +621,41 @@ impl Draw for record::Wire {
 impl Draw for record::Image {
     type Context<'a> = SchDrawCtx<'a>;
 
-    fn draw<C: Canvas>(&self, _canvas: &mut C, _ctx: &SchDrawCtx<'_>) {
-        // TODO
-        // TODO: just set to the URL. Maybe set whether or not to encode
-        // somehow?
-        // if !self.embed_image {
-        //     return;
-        // }
-
-        // let Some(data) = ctx.storage.get_data(&self.file_name) else {
-        //     eprintln!("unable to find image at {}", self.file_name);
-        //     return;
-        // };
-
-        // let width = self.corner_x - self.location_x;
-        // let height = self.corner_y - self.location_y;
-
-        // let mut b64_str = "data:image/png;base64,".to_owned();
-        // STANDARD_NO_PAD.encode_string(data, &mut b64_str);
-        // assert!(
-        //     b64_str.len() < MAX_EMBED_SIZE,
-        //     "max size {MAX_EMBED_SIZE} bytes but got {}",
-        //     b64_str.len()
-        // );
-
-        // let node = el::Image::new()
-        //     .set("width", width)
-        //     .set("height", height)
-        //     // Need top left corner to set location
-        //     .set("x", svg.x_coord(self.location_x, width))
-        //     .set("y", svg.y_coord(self.location_y, height))
-        //     .set("xlink:href", b64_str);
-        // svg.add_node(node);
-
-        // svg.enable_inline_images();
+    fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &SchDrawCtx<'_>) {
+        // Embedded images are stored in `Storage` keyed by their original path.
+        // If the image isn't embedded (or we can't find it), the canvas gets
+        // only the path and can decide what to do.
+        let data = if self.embed_image {
+            match ctx.storage.try_get_data(&self.file_name) {
+                Some(Ok(data)) => Some(data),
+                Some(Err(e)) => {
+                    warn!(
+                        "unable to load image '{}' in '{}': {e}",
+                        self.file_name, ctx.name
+                    );
+                    None
+                }
+                None => {
+                    warn!(
+                        "embedded image '{}' not found in storage for '{}'",
+                        self.file_name, ctx.name
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        canvas.draw_image(DrawImage {
+            x: self.location.x,
+            y: self.location.y,
+            width: self.corner.x - self.location.x,
+            height: self.corner.y - self.location.y,
+            keep_aspect: self.keep_aspect,
+            data,
+            file_name: &self.file_name,
+        });
     }
 }
 // impl Draw for record::Sheet {}
@@ -569,12 +668,17 @@ impl Draw for record::Parameter {
     type Context<'a> = SchDrawCtx<'a>;
 
     fn draw<C: Canvas>(&self, canvas: &mut C, ctx: &SchDrawCtx<'_>) {
+        if self.is_hidden && !ctx.options.show_hidden_params {
+            return;
+        }
+
         let font = &ctx.fonts.get_idx(self.font_id.into());
         canvas.draw_text(DrawText {
             x: self.location.x,
             y: self.location.y,
             text: &self.text,
             font,
+            color: ctx.options.stroke(self.color),
             ..Default::default()
         });
     }
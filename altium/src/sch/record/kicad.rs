@@ -0,0 +1,549 @@
+//! Conversion between component records and KiCad symbols
+
+mod import;
+
+use std::collections::BTreeMap;
+
+pub(crate) use import::{push_implementation, symbol_records};
+use log::warn;
+
+use crate::common::{Location, PosHoriz, PosVert, Visibility};
+use crate::kicad::SExpr;
+use crate::sch::pin::{ElectricalType, SchPin};
+use crate::sch::record::{self, SchRecord};
+use crate::sch::SCH_UNIT_SCALE;
+
+/// KiCad needs real sizes so we scale back up
+const UNIT_SCALE: i64 = SCH_UNIT_SCALE as i64;
+/// Size of all text, KiCad's default of 50 mils
+const FONT_SIZE: i64 = 1_270_000;
+/// Distance between pin names and the body outline
+const PIN_NAME_OFFSET: i64 = 1_016_000;
+/// KiCad symbols have no ellipses, so they get approximated with this many
+/// segments per full turn
+const ELLIPSE_SEGMENTS: f64 = 36.0;
+
+/// Fields that every KiCad symbol has; parameters can't reuse these names
+const RESERVED_FIELDS: [&str; 5] = [
+    "Reference",
+    "Value",
+    "Footprint",
+    "Datasheet",
+    "ki_description",
+];
+
+/// Convert a component's records to a KiCad `symbol` expression
+pub(crate) fn kicad_symbol(name: &str, records: &[SchRecord]) -> SExpr {
+    // `:` separates the library and symbol name in KiCad
+    let name = name.replace(':', "_");
+    let mut symbol = SExpr::list("symbol", [SExpr::string(&name)]);
+
+    // KiCad can only hide pin names and numbers for the entire symbol, so only do
+    // that if every pin hides them
+    let pins = records.iter().filter_map(|record| match record {
+        SchRecord::Pin(pin) => Some(pin),
+        _ => None,
+    });
+    let all_hidden = |vis: fn(&SchPin) -> Visibility| {
+        pins.clone().next().is_some() && pins.clone().all(|pin| vis(pin) == Visibility::Hidden)
+    };
+
+    if all_hidden(|pin| pin.designator_vis) {
+        symbol.push(SExpr::list("pin_numbers", [SExpr::atom("hide")]));
+    }
+
+    let mut pin_names = SExpr::list(
+        "pin_names",
+        [SExpr::list("offset", [SExpr::mm(PIN_NAME_OFFSET)])],
+    );
+    if all_hidden(|pin| pin.name_vis) {
+        pin_names.push(SExpr::atom("hide"));
+    }
+    symbol.push(pin_names);
+    symbol.push(SExpr::pair("in_bom", "yes"));
+    symbol.push(SExpr::pair("on_board", "yes"));
+
+    for field in fields(&name, records) {
+        symbol.push(field);
+    }
+
+    // Graphics and pins grouped by `(unit, body style)`
+    let mut bodies: BTreeMap<(u8, u8), Vec<SExpr>> = BTreeMap::new();
+    for record in records {
+        let Some((part, mode, items)) = body_items(record) else {
+            continue;
+        };
+        let Some(key) = unit_and_style(part, mode) else {
+            warn!("skipping record in display mode {mode} of {name}, KiCad only supports two");
+            continue;
+        };
+        bodies.entry(key).or_default().extend(items);
+    }
+
+    for ((unit, style), items) in bodies {
+        let body_name = SExpr::string(&format!("{name}_{unit}_{style}"));
+        symbol.push(SExpr::list("symbol", [body_name].into_iter().chain(items)));
+    }
+
+    symbol
+}
+
+/// KiCad `(unit, body_style)` for an Altium part and display mode. Unit 0 is
+/// shared by all units; body style 2 is De Morgan.
+fn unit_and_style(part: i8, mode: i8) -> Option<(u8, u8)> {
+    let unit = u8::try_from(part).unwrap_or(0);
+    let style = match mode {
+        0 => 1,
+        1 => 2,
+        _ => return None,
+    };
+    Some((unit, style))
+}
+
+/// Symbol properties from the designator, parameters, metadata and footprint
+/// models
+fn fields(name: &str, records: &[SchRecord]) -> Vec<SExpr> {
+    let mut designator = None;
+    let mut comment = None;
+    let mut datasheet = None;
+    let mut description = None;
+    let mut footprint = None;
+    let mut params = Vec::new();
+
+    for record in records {
+        match record {
+            SchRecord::MetaData(meta) => description = meta.description.as_deref(),
+            SchRecord::Designator(des) if &*des.name == "Designator" => designator = Some(des),
+            SchRecord::Parameter(param) if param.name.eq_ignore_ascii_case("Comment") => {
+                comment = Some(param);
+            }
+            SchRecord::Parameter(param) if param.name.eq_ignore_ascii_case("Datasheet") => {
+                datasheet = Some(param);
+            }
+            SchRecord::Parameter(param) => params.push(param),
+            SchRecord::Implementation(imp)
+                if imp.is_current && imp.model_type.eq_ignore_ascii_case("PCBLIB") =>
+            {
+                footprint = Some(&*imp.model_name);
+            }
+            _ => (),
+        }
+    }
+
+    // Altium uses `U?` or `*` for unannotated designators, KiCad just wants the
+    // prefix
+    let reference = designator
+        .map(|des| des.text.trim_end_matches('?'))
+        .filter(|prefix| !prefix.is_empty() && *prefix != "*")
+        .unwrap_or("U");
+    let ref_loc = designator.map(|des| des.location).unwrap_or_default();
+
+    // The comment is Altium's closest equivalent to a value, unless it is
+    // indirect (`=Param`) or unset
+    let value = comment
+        .map(|param| &*param.text)
+        .filter(|text| !text.is_empty() && *text != "*" && !text.starts_with('='))
+        .unwrap_or(name);
+    let (value_loc, value_hidden) =
+        comment.map_or((ref_loc, false), |param| (param.location, param.is_hidden));
+
+    let mut ret = vec![
+        property("Reference", reference, 0, ref_loc, false),
+        property("Value", value, 1, value_loc, value_hidden),
+        property(
+            "Footprint",
+            footprint.unwrap_or_default(),
+            2,
+            Location::default(),
+            true,
+        ),
+        property(
+            "Datasheet",
+            datasheet.map(|param| &*param.text).unwrap_or_default(),
+            3,
+            datasheet.map(|param| param.location).unwrap_or_default(),
+            true,
+        ),
+    ];
+
+    if let Some(description) = description.filter(|d| !d.is_empty()) {
+        ret.push(property(
+            "ki_description",
+            description,
+            ret.len(),
+            Location::default(),
+            true,
+        ));
+    }
+
+    let mut used: Vec<&str> = Vec::new();
+    for param in params {
+        if RESERVED_FIELDS.contains(&&*param.name) || used.contains(&&*param.name) {
+            warn!("skipping duplicate parameter {} in {name}", param.name);
+            continue;
+        }
+        used.push(&param.name);
+        ret.push(property(
+            &param.name,
+            &param.text,
+            ret.len(),
+            param.location,
+            param.is_hidden,
+        ));
+    }
+
+    ret
+}
+
+/// Body items for a record along with its owner part and display mode, if it
+/// has an equivalent in KiCad
+fn body_items(record: &SchRecord) -> Option<(i8, i8, Vec<SExpr>)> {
+    let ret = match record {
+        SchRecord::Pin(pin) => (
+            pin.owner_part_id,
+            pin.owner_part_display_mode,
+            vec![kicad_pin(pin)],
+        ),
+        SchRecord::Label(v) => (v.owner_part_id, v.owner_part_display_mode, vec![label(v)]),
+        SchRecord::Bezier(v) => (
+            v.owner_part_id,
+            v.owner_part_display_mode,
+            // Altium chains cubic segments that share their end points
+            (0..v.locations.len().saturating_sub(3))
+                .step_by(3)
+                .map(|i| {
+                    SExpr::list(
+                        "bezier",
+                        [
+                            pts(&v.locations[i..i + 4]),
+                            stroke(v.line_width),
+                            fill(false),
+                        ],
+                    )
+                })
+                .collect(),
+        ),
+        SchRecord::PolyLine(v) => {
+            let locations: Vec<_> = v.locations.iter().map(|loc| loc.as_location()).collect();
+            (
+                v.owner_part_id,
+                v.owner_part_display_mode,
+                vec![polyline(&locations, v.line_width, false)],
+            )
+        }
+        SchRecord::Polygon(v) => {
+            let mut locations = v.locations.clone();
+            locations.extend(v.locations.first().copied());
+            (
+                v.owner_part_id,
+                v.owner_part_display_mode,
+                vec![polyline(&locations, v.line_width, v.is_solid)],
+            )
+        }
+        SchRecord::Ellipse(v) => {
+            let item = if v.radius == v.secondary_radius {
+                circle(v.location, v.radius, v.line_width, v.is_solid)
+            } else {
+                let locations =
+                    ellipse_points(v.location, v.radius, v.secondary_radius, 0.0, 360.0);
+                polyline(&locations, v.line_width, v.is_solid)
+            };
+            (v.owner_part_id, v.owner_part_display_mode, vec![item])
+        }
+        SchRecord::RectangleRounded(v) => (
+            v.owner_part_id,
+            v.owner_part_display_mode,
+            vec![rectangle(
+                v.location,
+                v.corner,
+                v.line_width,
+                v.is_solid && !v.transparent,
+            )],
+        ),
+        SchRecord::ElipticalArc(v) => {
+            let locations = ellipse_points(
+                v.location.as_location(),
+                to_i32(v.radius),
+                to_i32(v.secondary_radius),
+                v.start_angle.into(),
+                v.end_angle.into(),
+            );
+            (
+                v.owner_part_id,
+                v.owner_part_display_mode,
+                vec![polyline(&locations, v.line_width, false)],
+            )
+        }
+        SchRecord::Arc(v) => (v.owner_part_id, v.owner_part_display_mode, vec![arc(v)]),
+        SchRecord::Line(v) => {
+            let locations = [
+                Location::new(v.location_x, v.location_y),
+                Location::new(v.corner_x, v.corner_y),
+            ];
+            (
+                v.owner_part_id,
+                v.owner_part_display_mode,
+                vec![polyline(&locations, v.line_width, false)],
+            )
+        }
+        SchRecord::Rectangle(v) => (
+            v.owner_part_id,
+            v.owner_part_display_mode,
+            vec![rectangle(
+                v.location.as_location(),
+                v.corner.as_location(),
+                v.line_width,
+                v.is_solid && !v.transparent,
+            )],
+        ),
+        _ => return None,
+    };
+
+    Some(ret)
+}
+
+/// KiCad's name for a pin's electrical type
+pub(crate) fn kicad_pin_type(ty: ElectricalType) -> &'static str {
+    match ty {
+        ElectricalType::Input => "input",
+        ElectricalType::Id => "bidirectional",
+        ElectricalType::Output => "output",
+        ElectricalType::OpenCollector => "open_collector",
+        ElectricalType::Passive => "passive",
+        ElectricalType::HighZ => "tri_state",
+        ElectricalType::OpenEmitter => "open_emitter",
+        ElectricalType::Power => "power_in",
+    }
+}
+
+fn kicad_pin(pin: &SchPin) -> SExpr {
+    let ty = kicad_pin_type(pin.electrical);
+
+    // KiCad places pins at their electrical end and points them toward the
+    // body. Altium does the opposite for both.
+    let mut ret = SExpr::list(
+        "pin",
+        [
+            SExpr::atom(ty),
+            SExpr::atom("line"),
+            at(
+                pin.location_conn(),
+                (i32::from(pin.rotation.as_int()) + 180) % 360,
+            ),
+            SExpr::list("length", [scaled(pin.length.into())]),
+        ],
+    );
+
+    if pin.is_hidden {
+        ret.push(SExpr::atom("hide"));
+    }
+
+    ret.push(SExpr::list(
+        "name",
+        [SExpr::string(&pin.name), effects(false, None)],
+    ));
+    ret.push(SExpr::list(
+        "number",
+        [SExpr::string(&pin.designator), effects(false, None)],
+    ));
+    ret
+}
+
+fn label(label: &record::Label) -> SExpr {
+    let (horiz, vert) = label.justification.into();
+    let justify: Vec<_> = [
+        match horiz {
+            PosHoriz::Left => Some("left"),
+            PosHoriz::Center => None,
+            PosHoriz::Right => Some("right"),
+        },
+        match vert {
+            PosVert::Top => Some("top"),
+            PosVert::Middle => None,
+            PosVert::Bottom => Some("bottom"),
+        },
+    ]
+    .into_iter()
+    .flatten()
+    .map(SExpr::atom)
+    .collect();
+    let justify = (!justify.is_empty()).then(|| SExpr::list("justify", justify));
+
+    // Text angles in symbol libraries are in tenths of a degree
+    SExpr::list(
+        "text",
+        [
+            SExpr::string(&label.text),
+            at(label.location.as_location(), label.orientation * 900),
+            effects(false, justify),
+        ],
+    )
+}
+
+fn arc(arc: &record::Arc) -> SExpr {
+    let center = arc.location.as_location();
+    let radius = to_i32(arc.radius);
+    let start = f64::from(arc.start_angle);
+    let mut end = f64::from(arc.end_angle);
+
+    if (end - start).rem_euclid(360.0) == 0.0 {
+        return circle(center, radius, arc.line_width, false);
+    }
+    if end < start {
+        end += 360.0;
+    }
+
+    // Altium arcs run counterclockwise from start to end, the midpoint tells
+    // KiCad which way to go
+    let [start, mid, end] = [start, f64::midpoint(start, end), end]
+        .map(|angle| point_at(center, radius, radius, angle));
+
+    SExpr::list(
+        "arc",
+        [
+            xy("start", start),
+            xy("mid", mid),
+            xy("end", end),
+            stroke(arc.line_width),
+            fill(false),
+        ],
+    )
+}
+
+fn rectangle(start: Location, end: Location, line_width: u32, solid: bool) -> SExpr {
+    SExpr::list(
+        "rectangle",
+        [
+            xy("start", start),
+            xy("end", end),
+            stroke(line_width),
+            fill(solid),
+        ],
+    )
+}
+
+fn circle(center: Location, radius: i32, line_width: u32, solid: bool) -> SExpr {
+    SExpr::list(
+        "circle",
+        [
+            xy("center", center),
+            SExpr::list("radius", [scaled(radius.into())]),
+            stroke(line_width),
+            fill(solid),
+        ],
+    )
+}
+
+fn polyline(locations: &[Location], line_width: u32, solid: bool) -> SExpr {
+    SExpr::list(
+        "polyline",
+        [pts(locations), stroke(line_width), fill(solid)],
+    )
+}
+
+/// Points along an elliptical arc running counterclockwise from `start` to
+/// `end` (in degrees)
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn ellipse_points(center: Location, rx: i32, ry: i32, start: f64, end: f64) -> Vec<Location> {
+    let mut sweep = (end - start).rem_euclid(360.0);
+    if sweep == 0.0 {
+        sweep = 360.0;
+    }
+    let steps = (sweep / 360.0 * ELLIPSE_SEGMENTS).ceil().max(1.0) as u32;
+
+    (0..=steps)
+        .map(|i| {
+            point_at(
+                center,
+                rx,
+                ry,
+                start + sweep * f64::from(i) / f64::from(steps),
+            )
+        })
+        .collect()
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn point_at(center: Location, rx: i32, ry: i32, angle: f64) -> Location {
+    let angle = angle.to_radians();
+    Location::new(
+        center.x + (f64::from(rx) * angle.cos()).round() as i32,
+        center.y + (f64::from(ry) * angle.sin()).round() as i32,
+    )
+}
+
+fn pts(locations: &[Location]) -> SExpr {
+    SExpr::list("pts", locations.iter().map(|loc| xy("xy", *loc)))
+}
+
+fn at(loc: Location, angle: i32) -> SExpr {
+    SExpr::list(
+        "at",
+        [
+            scaled(loc.x.into()),
+            scaled(loc.y.into()),
+            SExpr::atom(angle),
+        ],
+    )
+}
+
+fn xy(name: &str, loc: Location) -> SExpr {
+    SExpr::list(name, [scaled(loc.x.into()), scaled(loc.y.into())])
+}
+
+fn property(name: &str, value: &str, id: usize, loc: Location, hidden: bool) -> SExpr {
+    SExpr::list(
+        "property",
+        [
+            SExpr::string(name),
+            SExpr::string(value),
+            SExpr::pair("id", id),
+            at(loc, 0),
+            effects(hidden, None),
+        ],
+    )
+}
+
+fn effects(hidden: bool, justify: Option<SExpr>) -> SExpr {
+    let size = SExpr::list("size", [SExpr::mm(FONT_SIZE), SExpr::mm(FONT_SIZE)]);
+    let mut ret = SExpr::list("effects", [SExpr::list("font", [size])]);
+    if let Some(justify) = justify {
+        ret.push(justify);
+    }
+    if hidden {
+        ret.push(SExpr::atom("hide"));
+    }
+    ret
+}
+
+/// Altium line widths are really an enum (smallest, small, medium, large) that
+/// gets parsed as mils
+fn stroke(line_width: u32) -> SExpr {
+    let width = match line_width / 25_400 {
+        0 => 0,
+        1 => 254_000,
+        2 => 508_000,
+        _ => 762_000,
+    };
+    SExpr::list(
+        "stroke",
+        [
+            SExpr::list("width", [SExpr::mm(width)]),
+            SExpr::pair("type", "default"),
+        ],
+    )
+}
+
+fn fill(solid: bool) -> SExpr {
+    let ty = if solid { "background" } else { "none" };
+    SExpr::list("fill", [SExpr::pair("type", ty)])
+}
+
+/// A length from a record, written in millimeters
+fn scaled(val: i64) -> SExpr {
+    SExpr::mm(val * UNIT_SCALE)
+}
+
+fn to_i32(val: u32) -> i32 {
+    i32::try_from(val).unwrap_or(i32::MAX)
+}
@@ -0,0 +1,625 @@
+//! Conversion of KiCad symbols to component records, the inverse of
+//! `kicad_symbol`
+
+use std::f64::consts::PI;
+
+use log::warn;
+
+use super::UNIT_SCALE;
+use crate::common::{
+    Location,
+    LocationFract,
+    ReadOnlyState,
+    Rgb,
+    Rotation90,
+    UniqueId,
+    Visibility,
+};
+use crate::error::AddContext;
+use crate::kicad::SExpr;
+use crate::sch::params::Justification;
+use crate::sch::pin::{ElectricalType, SchPin};
+use crate::sch::record::{self, SchRecord};
+use crate::sch::ModelDatafile;
+use crate::{Error, ErrorKind};
+
+/// Altium's default outline color for new graphics
+const COLOR: Rgb = Rgb::from_hex(0x80, 0x00, 0x00);
+/// Altium's default fill color for new graphics
+const AREA_COLOR: Rgb = Rgb::from_hex(0xff, 0xff, 0xb0);
+/// Altium's default color for designators and parameters
+const TEXT_COLOR: Rgb = Rgb::from_hex(0x00, 0x00, 0x80);
+/// One Altium unit in our internal nm
+const UNIT: f64 = 25_400.0;
+/// KiCad writes a width of 0 for "use the default", which is 6 mils
+const DEFAULT_LINE_WIDTH_MM: f64 = 0.1524;
+
+/// A sub-symbol's body items and the unit and body style they belong to
+struct Body<'a> {
+    unit: u8,
+    style: u8,
+    items: &'a [SExpr],
+}
+
+/// Convert a KiCad `symbol` expression to component records. `lib` holds the
+/// library's other symbols, which are needed to resolve `extends`.
+pub(crate) fn symbol_records(symbol: &SExpr, lib: &[SExpr]) -> Result<Vec<SchRecord>, Error> {
+    let name = symbol.arg(0).unwrap_or_default();
+    let parent = match symbol.find("extends").and_then(|ext| ext.arg(0)) {
+        Some(parent_name) => Some(
+            lib.iter()
+                .find(|sym| sym.arg(0) == Some(parent_name))
+                .ok_or_else(|| ErrorKind::MissingSection(parent_name.to_owned()))
+                .context("resolving `extends`")?,
+        ),
+        None => None,
+    };
+
+    // Graphics and pins come from the parent for derived symbols
+    let graphic_source = parent.unwrap_or(symbol);
+    let bodies = bodies(graphic_source);
+    let unit_count = bodies.iter().map(|b| b.unit).max().unwrap_or(1).max(1);
+    let style_count = if bodies.iter().any(|b| b.style == 2) {
+        2
+    } else {
+        1
+    };
+
+    let properties = properties(parent, symbol);
+
+    let hide_names = graphic_source.find("pin_names").is_some_and(is_hidden);
+    let hide_numbers = graphic_source.find("pin_numbers").is_some_and(is_hidden);
+
+    let mut records = vec![SchRecord::Undefined];
+    let mut description = None;
+    let mut footprint = None;
+
+    for prop in &properties {
+        let (prop_name, value) = (
+            prop.arg(0).unwrap_or_default(),
+            prop.arg(1).unwrap_or_default(),
+        );
+        match prop_name {
+            "Reference" => records.push(designator(prop, value)?),
+            "Value" => records.push(parameter(prop, "Comment", value)?),
+            "Datasheet" if value.is_empty() || value == "~" => (),
+            "ki_description" | "Description" => description = Some(value),
+            "Footprint" => footprint = Some(value).filter(|v| !v.is_empty()),
+            _ if prop_name.starts_with("ki_") => (),
+            _ => records.push(parameter(prop, prop_name, value)?),
+        }
+    }
+
+    let mut pin_count = 0;
+    for body in &bodies {
+        // Unit 0 and body style 0 are shared by all units and styles
+        let units = if body.unit == 0 {
+            1..=unit_count
+        } else {
+            body.unit..=body.unit
+        };
+        let styles = if body.style == 0 {
+            1..=style_count
+        } else {
+            body.style..=body.style
+        };
+
+        for unit in units {
+            for style in styles.clone() {
+                let part = i8::try_from(unit).unwrap_or(i8::MAX);
+                let mode = i8::try_from(style - 1).unwrap_or(0);
+
+                for item in body.items {
+                    let Some(mut record) = body_record(item, hide_names, hide_numbers)
+                        .or_context(|| format!("converting `{name}`"))?
+                    else {
+                        continue;
+                    };
+                    if matches!(record, SchRecord::Pin(_)) {
+                        pin_count += 1;
+                    }
+                    set_owner_part(&mut record, part, mode);
+                    records.push(record);
+                }
+            }
+        }
+    }
+
+    if let Some(footprint) = footprint {
+        push_implementation(&mut records, footprint);
+    }
+
+    records[0] = SchRecord::MetaData(Box::new(record::MetaData {
+        all_pin_count: pin_count,
+        area_color: AREA_COLOR,
+        color: COLOR,
+        current_part_id: 1,
+        description: description.map(Into::into),
+        display_mode_count: style_count,
+        index_in_sheet: -1,
+        library_path: "*".into(),
+        libref: name.into(),
+        owner_part_id: -1,
+        part_count: unit_count.saturating_add(1),
+        part_id_locked: true,
+        sheet_part_file_name: "*".into(),
+        source_library_name: "*".into(),
+        target_file_name: "*".into(),
+        unique_id: UniqueId::new_random(),
+        ..Default::default()
+    }));
+
+    Ok(records)
+}
+
+/// Properties of a symbol, where a derived symbol's properties override its
+/// parent's
+fn properties<'a>(parent: Option<&'a SExpr>, symbol: &'a SExpr) -> Vec<&'a SExpr> {
+    let mut ret: Vec<&SExpr> = Vec::new();
+    for prop in parent
+        .into_iter()
+        .chain([symbol])
+        .flat_map(|sym| sym.find_all("property"))
+    {
+        let prop_name = prop.arg(0);
+        ret.retain(|existing| existing.arg(0) != prop_name);
+        ret.push(prop);
+    }
+    ret
+}
+
+/// Collect the sub-symbols (`NAME_unit_style`) of a symbol
+fn bodies(symbol: &SExpr) -> Vec<Body<'_>> {
+    let mut ret = Vec::new();
+
+    for sub in symbol.find_all("symbol") {
+        let sub_name = sub.arg(0).unwrap_or_default();
+        let mut parts = sub_name.rsplitn(3, '_');
+        let (Some(Ok(style)), Some(Ok(unit))) =
+            (parts.next().map(str::parse), parts.next().map(str::parse))
+        else {
+            warn!("skipping sub-symbol with unexpected name `{sub_name}`");
+            continue;
+        };
+
+        // The first item is the name
+        ret.push(Body {
+            unit,
+            style,
+            items: sub.items().get(1..).unwrap_or_default(),
+        });
+    }
+
+    ret
+}
+
+/// Convert a single body item. Returns `None` for things we don't support.
+fn body_record(
+    item: &SExpr,
+    hide_names: bool,
+    hide_numbers: bool,
+) -> Result<Option<SchRecord>, ErrorKind> {
+    let record = match item.name() {
+        Some("pin") => SchRecord::Pin(pin(item, hide_names, hide_numbers)?),
+        Some("rectangle") => {
+            let start = xy(item.find("start"))?;
+            let end = xy(item.find("end"))?;
+            let (line_width, is_solid) = stroke_and_fill(item)?;
+            SchRecord::Rectangle(record::Rectangle {
+                area_color: AREA_COLOR,
+                color: COLOR,
+                corner: fract((start.0.max(end.0), start.1.max(end.1))),
+                is_solid,
+                line_width,
+                location: fract((start.0.min(end.0), start.1.min(end.1))),
+                unique_id: UniqueId::new_random(),
+                ..Default::default()
+            })
+        }
+        Some("polyline") => {
+            let mut locations = points(item)?;
+            let (line_width, is_solid) = stroke_and_fill(item)?;
+
+            if is_solid && locations.len() > 2 {
+                // Polygons close themselves
+                if locations.first() == locations.last() {
+                    locations.pop();
+                }
+                SchRecord::Polygon(record::Polygon {
+                    area_color: AREA_COLOR,
+                    color: COLOR,
+                    is_solid,
+                    line_width,
+                    locations,
+                    unique_id: UniqueId::new_random(),
+                    ..Default::default()
+                })
+            } else {
+                SchRecord::PolyLine(record::PolyLine {
+                    color: COLOR,
+                    line_width,
+                    locations: locations
+                        .into_iter()
+                        .map(|loc| LocationFract {
+                            x: loc.x,
+                            y: loc.y,
+                            ..Default::default()
+                        })
+                        .collect(),
+                    unique_id: UniqueId::new_random(),
+                    ..Default::default()
+                })
+            }
+        }
+        Some("circle") => {
+            let center = rounded(xy(item.find("center"))?);
+            let radius = round_len(num(item.find("radius"), 0)?);
+            let (line_width, is_solid) = stroke_and_fill(item)?;
+            SchRecord::Ellipse(record::Ellipse {
+                area_color: AREA_COLOR,
+                color: COLOR,
+                is_solid,
+                line_width,
+                location: center,
+                radius,
+                secondary_radius: radius,
+                unique_id: UniqueId::new_random(),
+                ..Default::default()
+            })
+        }
+        Some("arc") => SchRecord::Arc(arc(item)?),
+        Some("bezier") => {
+            let (line_width, _) = stroke_and_fill(item)?;
+            SchRecord::Bezier(record::Bezier {
+                color: COLOR,
+                line_width,
+                locations: points(item)?,
+                unique_id: UniqueId::new_random(),
+                ..Default::default()
+            })
+        }
+        Some("text") => SchRecord::Label(label(item)?),
+        Some(other) => {
+            warn!("unsupported symbol item `{other}`");
+            return Ok(None);
+        }
+        None => return Ok(None),
+    };
+
+    Ok(Some(record))
+}
+
+fn pin(item: &SExpr, hide_names: bool, hide_numbers: bool) -> Result<SchPin, ErrorKind> {
+    let electrical = match item.arg(0).unwrap_or_default() {
+        "input" => ElectricalType::Input,
+        "output" => ElectricalType::Output,
+        "bidirectional" => ElectricalType::Id,
+        "tri_state" => ElectricalType::HighZ,
+        "open_collector" => ElectricalType::OpenCollector,
+        "open_emitter" => ElectricalType::OpenEmitter,
+        "power_in" | "power_out" => ElectricalType::Power,
+        _ => ElectricalType::Passive,
+    };
+
+    // KiCad gives the electrical end and the direction toward the body, Altium
+    // wants the body end and the direction away from it. Altium only has four
+    // directions.
+    let at = item.find("at");
+    let conn = rounded(xy(at)?);
+    let length = round_len(num(item.find("length"), 0)?).abs();
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let (rotation, location) = match (num(at, 2)? / 90.0).round().rem_euclid(4.0) as u8 {
+        0 => (Rotation90::R180, Location::new(conn.x + length, conn.y)),
+        1 => (Rotation90::R270, Location::new(conn.x, conn.y + length)),
+        2 => (Rotation90::R0, Location::new(conn.x - length, conn.y)),
+        _ => (Rotation90::R90, Location::new(conn.x, conn.y - length)),
+    };
+
+    // `~` is an empty name in KiCad
+    let text = |key: &str| {
+        item.find(key)
+            .and_then(|expr| expr.arg(0))
+            .filter(|s| *s != "~")
+            .unwrap_or_default()
+            .into()
+    };
+    let vis = |hidden: bool| {
+        if hidden {
+            Visibility::Hidden
+        } else {
+            Visibility::Visible
+        }
+    };
+
+    Ok(SchPin {
+        formal_type: 1,
+        name: text("name"),
+        designator: text("number"),
+        location,
+        electrical,
+        length: length.unsigned_abs(),
+        designator_vis: vis(hide_numbers),
+        name_vis: vis(hide_names),
+        is_hidden: is_hidden(item),
+        rotation,
+        ..Default::default()
+    })
+}
+
+fn arc(item: &SExpr) -> Result<record::Arc, ErrorKind> {
+    let start = xy(item.find("start"))?;
+    let end = xy(item.find("end"))?;
+    let (line_width, _) = stroke_and_fill(item)?;
+
+    let (center, radius) = if let Some(mid) = item.find("mid") {
+        let mid = xy(Some(mid))?;
+        circumcircle(start, mid, end).unwrap_or_else(|| {
+            // Degenerate, just draw something reasonable
+            let center = (f64::midpoint(start.0, end.0), f64::midpoint(start.1, end.1));
+            (center, (start.0 - center.0).hypot(start.1 - center.1))
+        })
+    } else {
+        // Older files give the center and radius
+        let radius = item.find("radius");
+        let center = xy(radius.and_then(|r| r.find("at")))?;
+        let len = num(radius.and_then(|r| r.find("length")), 0)?;
+        (center, len)
+    };
+
+    let angle = |pt: (f64, f64)| (pt.1 - center.1).atan2(pt.0 - center.0) * 180.0 / PI;
+    let (mut start_angle, mut end_angle) = (angle(start), angle(end));
+
+    // Altium arcs always run counterclockwise
+    let is_ccw = match item.find("mid") {
+        Some(mid) => {
+            let mid_angle = angle(xy(Some(mid))?);
+            (mid_angle - start_angle).rem_euclid(360.0)
+                < (end_angle - start_angle).rem_euclid(360.0)
+        }
+        // Assume the shorter way around
+        None => (end_angle - start_angle).rem_euclid(360.0) <= 180.0,
+    };
+    if !is_ccw {
+        (start_angle, end_angle) = (end_angle, start_angle);
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    Ok(record::Arc {
+        location: fract(center),
+        radius: round_len(radius).unsigned_abs(),
+        line_width,
+        start_angle: start_angle.rem_euclid(360.0) as f32,
+        end_angle: end_angle.rem_euclid(360.0) as f32,
+        color: COLOR,
+        unique_id: UniqueId::new_random(),
+        ..Default::default()
+    })
+}
+
+fn label(item: &SExpr) -> Result<record::Label, ErrorKind> {
+    let at = item.find("at");
+    let (x, y) = xy(at)?;
+
+    // Symbol library text angles are in tenths of a degree, but newer files use
+    // degrees
+    let mut angle = num(at, 2)?;
+    if angle.abs() >= 360.0 {
+        angle /= 10.0;
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let orientation = (angle / 90.0).round().rem_euclid(4.0) as i32;
+
+    let justify = item
+        .find("effects")
+        .and_then(|effects| effects.find("justify"));
+    let has = |atom: &str| justify.is_some_and(|j| j.has_atom(atom));
+    let justification = match (has("left"), has("right"), has("top"), has("bottom")) {
+        (true, _, true, _) => Justification::TopLeft,
+        (true, _, _, true) => Justification::BottomLeft,
+        (true, ..) => Justification::CenterLeft,
+        (_, true, true, _) => Justification::TopRight,
+        (_, true, _, true) => Justification::BottomRight,
+        (_, true, ..) => Justification::CenterRight,
+        (.., true, _) => Justification::TopCenter,
+        (.., true) => Justification::BottomCenter,
+        _ => Justification::CenterCenter,
+    };
+
+    Ok(record::Label {
+        color: TEXT_COLOR,
+        font_id: 1,
+        location: fract((x, y)),
+        orientation,
+        text: item.arg(0).unwrap_or_default().into(),
+        unique_id: UniqueId::new_random(),
+        justification,
+        ..Default::default()
+    })
+}
+
+fn designator(prop: &SExpr, value: &str) -> Result<SchRecord, ErrorKind> {
+    // KiCad stores just the prefix
+    let text = if value.ends_with('?') {
+        value.to_owned()
+    } else {
+        format!("{value}?")
+    };
+
+    Ok(SchRecord::Designator(record::Designator {
+        owner_part_id: -1,
+        location: rounded(xy(prop.find("at"))?),
+        color: TEXT_COLOR,
+        font_id: 1,
+        unique_id: UniqueId::new_random(),
+        name: "Designator".into(),
+        index_in_sheet: -1,
+        text: text.into(),
+        read_only_state: ReadOnlyState::ReadOnly,
+        ..Default::default()
+    }))
+}
+
+fn parameter(prop: &SExpr, name: &str, value: &str) -> Result<SchRecord, ErrorKind> {
+    Ok(SchRecord::Parameter(record::Parameter {
+        owner_part_id: -1,
+        location: rounded(xy(prop.find("at"))?),
+        index_in_sheet: -1,
+        color: TEXT_COLOR,
+        font_id: 1,
+        unique_id: UniqueId::new_random(),
+        name: name.into(),
+        is_hidden: prop.find("effects").is_some_and(is_hidden),
+        text: value.into(),
+        ..Default::default()
+    }))
+}
+
+/// Add a footprint model, `lib:name` in KiCad
+pub(crate) fn push_implementation(records: &mut Vec<SchRecord>, footprint: &str) {
+    let model_name = footprint
+        .split_once(':')
+        .map_or(footprint, |(_, name)| name);
+    let Ok(list_idx) = u16::try_from(records.len()) else {
+        warn!("too many records to add footprint {footprint}");
+        return;
+    };
+
+    records.push(SchRecord::ImplementationList(
+        record::ImplementationList::default(),
+    ));
+    records.push(SchRecord::Implementation(record::Implementation {
+        owner_index: list_idx,
+        use_component_library: true,
+        model_name: model_name.into(),
+        model_type: "PCBLIB".into(),
+        datafiles: vec![ModelDatafile {
+            entity: model_name.into(),
+            kind: "PCBLib".into(),
+        }],
+        is_current: true,
+        datalinks_locked: true,
+        database_datalinks_locked: true,
+        unique_id: UniqueId::new_random(),
+        index_in_sheet: -1,
+        ..Default::default()
+    }));
+    records.push(SchRecord::ImplementationChild1(
+        record::ImplementationChild1 {
+            owner_index: list_idx + 1,
+            ..Default::default()
+        },
+    ));
+    records.push(SchRecord::ImplementationChild2(
+        record::ImplementationChild2 {
+            owner_index: list_idx + 1,
+            ..Default::default()
+        },
+    ));
+}
+
+fn set_owner_part(record: &mut SchRecord, part: i8, mode: i8) {
+    let (id, display_mode) = match record {
+        SchRecord::Pin(v) => (&mut v.owner_part_id, &mut v.owner_part_display_mode),
+        SchRecord::Label(v) => (&mut v.owner_part_id, &mut v.owner_part_display_mode),
+        SchRecord::Bezier(v) => (&mut v.owner_part_id, &mut v.owner_part_display_mode),
+        SchRecord::PolyLine(v) => (&mut v.owner_part_id, &mut v.owner_part_display_mode),
+        SchRecord::Polygon(v) => (&mut v.owner_part_id, &mut v.owner_part_display_mode),
+        SchRecord::Ellipse(v) => (&mut v.owner_part_id, &mut v.owner_part_display_mode),
+        SchRecord::Arc(v) => (&mut v.owner_part_id, &mut v.owner_part_display_mode),
+        SchRecord::Rectangle(v) => (&mut v.owner_part_id, &mut v.owner_part_display_mode),
+        _ => return,
+    };
+    *id = part;
+    *display_mode = mode;
+}
+
+/// Line width enum and whether the shape is filled
+fn stroke_and_fill(item: &SExpr) -> Result<(u32, bool), ErrorKind> {
+    let width = item
+        .find("stroke")
+        .and_then(|stroke| stroke.find("width"))
+        .map_or(Ok(0.0), |width| num(Some(width), 0))?;
+    let width = if width == 0.0 {
+        DEFAULT_LINE_WIDTH_MM
+    } else {
+        width
+    };
+
+    // Smallest, small (10 mil), medium, large
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let idx = (width / 0.254).round().clamp(0.0, 3.0) as u32;
+
+    let fill = item
+        .find("fill")
+        .and_then(|fill| fill.find("type"))
+        .and_then(|ty| ty.arg(0))
+        .unwrap_or("none");
+
+    Ok((idx * 25_400, fill != "none"))
+}
+
+/// Whether an item has `hide` or `(hide yes)`
+fn is_hidden(item: &SExpr) -> bool {
+    item.has_atom("hide") || item.find("hide").is_some_and(|h| h.arg(0) == Some("yes"))
+}
+
+/// Center and radius of the circle through three points, if they aren't on a
+/// line
+#[allow(clippy::many_single_char_names)]
+fn circumcircle(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> Option<((f64, f64), f64)> {
+    let d = 2.0 * (a.0 * (b.1 - c.1) + b.0 * (c.1 - a.1) + c.0 * (a.1 - b.1));
+    if d.abs() < f64::EPSILON {
+        return None;
+    }
+
+    let sq = |p: (f64, f64)| p.0 * p.0 + p.1 * p.1;
+    let x = (sq(a) * (b.1 - c.1) + sq(b) * (c.1 - a.1) + sq(c) * (a.1 - b.1)) / d;
+    let y = (sq(a) * (c.0 - b.0) + sq(b) * (a.0 - c.0) + sq(c) * (b.0 - a.0)) / d;
+    Some(((x, y), (a.0 - x).hypot(a.1 - y)))
+}
+
+/// All `(xy x y)` points of a `(pts ...)` list, rounded to whole units
+fn points(item: &SExpr) -> Result<Vec<Location>, ErrorKind> {
+    item.find("pts")
+        .into_iter()
+        .flat_map(|pts| pts.find_all("xy"))
+        .map(|pt| xy(Some(pt)).map(rounded))
+        .collect()
+}
+
+/// The first two values of an item as internal nm, unrounded
+fn xy(item: Option<&SExpr>) -> Result<(f64, f64), ErrorKind> {
+    Ok((to_internal(num(item, 0)?), to_internal(num(item, 1)?)))
+}
+
+/// Round a position to a whole Altium unit
+#[allow(clippy::cast_possible_truncation)]
+fn rounded((x, y): (f64, f64)) -> Location {
+    let round = |v: f64| ((v / UNIT).round() * UNIT) as i32;
+    Location::new(round(x), round(y))
+}
+
+/// A position that keeps the fractional part
+#[allow(clippy::cast_possible_truncation)]
+fn fract((x, y): (f64, f64)) -> LocationFract {
+    LocationFract::from_nm(x.round() as i32, y.round() as i32)
+}
+
+/// A length in millimeters as internal nm, rounded to a whole Altium unit
+fn round_len(mm: f64) -> i32 {
+    rounded((to_internal(mm), 0.0)).x
+}
+
+/// Millimeters to our internal nm, see `UNIT_SCALE`
+#[allow(clippy::cast_precision_loss)]
+fn to_internal(mm: f64) -> f64 {
+    mm * 1_000_000.0 / UNIT_SCALE as f64
+}
+
+/// A numeric value at `idx` in a list, zero if it doesn't exist
+fn num(item: Option<&SExpr>, idx: usize) -> Result<f64, ErrorKind> {
+    match item.and_then(|item| item.arg(idx)) {
+        Some(v) => v.parse().map_err(|e| ErrorKind::ExpectedFloat(v.into(), e)),
+        None => Ok(0.0),
+    }
+}
@@ -0,0 +1,367 @@
+//! Connectivity extraction for a single schematic sheet
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use log::warn;
+
+use crate::common::Location;
+use crate::netlist::{natural_cmp, Net, NetComponent, NetPin, Netlist, Node};
+use crate::sch::record::{self, SchRecord};
+
+/// Something that can connect to a net at a point
+#[derive(Clone, Copy, Debug)]
+enum Anchor<'a> {
+    /// A pin's electrical end, given as indices into the netlist's components
+    /// and that component's pins
+    Pin(usize, usize),
+    /// A name that connects everything using it, with a priority for naming
+    /// the net
+    Name(&'a str, NamePriority),
+    Junction,
+}
+
+/// The pins on a net and the best name found for it
+type NetNodes<'a> = (Vec<Node>, Option<(NamePriority, &'a str)>);
+
+/// Which name wins when a net has several. Higher is stronger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum NamePriority {
+    Port,
+    NetLabel,
+    PowerPort,
+}
+
+/// Build a netlist from the records of a schematic document. `owner_pos` maps
+/// an owner index to a position in `records`.
+///
+/// Wires connect to anything at their ends or vertices and to wire ends that
+/// land on their segments. Net labels, power ports and ports connect every
+/// item with the same name. Buses, harnesses and sheet symbols are not
+/// followed.
+pub(crate) fn extract_netlist<F>(records: &[SchRecord], owner_pos: F, title: &str) -> Netlist
+where
+    F: Fn(usize) -> Option<usize>,
+{
+    let mut netlist = Netlist::new(title);
+    let mut anchors: Vec<(Location, Anchor<'_>)> = Vec::new();
+
+    // Components first, their pins are the start of every net
+    let (components, pin_locations) = components(records, &owner_pos);
+    for comp in components {
+        netlist.add_component(comp);
+    }
+    for (loc, comp_idx, pin_idx) in pin_locations {
+        anchors.push((loc, Anchor::Pin(comp_idx, pin_idx)));
+    }
+
+    let mut wires: Vec<&[Location]> = Vec::new();
+    for record in records {
+        match record {
+            SchRecord::Wire(wire) if !wire.locations.is_empty() => wires.push(&wire.locations),
+            SchRecord::NetLabel(label) => anchors.push((
+                label.location,
+                Anchor::Name(&label.text, NamePriority::NetLabel),
+            )),
+            SchRecord::PowerPort(port) => anchors.push((
+                port.location,
+                Anchor::Name(&port.text, NamePriority::PowerPort),
+            )),
+            SchRecord::Port(port) => {
+                // Ports connect at either end
+                let name = Anchor::Name(&port.name, NamePriority::Port);
+                let end = Location::new(port.location.x + port.width, port.location.y);
+                anchors.push((port.location, name));
+                anchors.push((end, name));
+            }
+            SchRecord::Junction(junction) => anchors.push((junction.location, Anchor::Junction)),
+            _ => (),
+        }
+    }
+
+    // Anchors come first in the union-find, then one entry per wire
+    let mut sets = DisjointSet::new(anchors.len() + wires.len());
+
+    for (wire_idx, wire) in wires.iter().enumerate() {
+        let wire_id = anchors.len() + wire_idx;
+
+        for (anchor_id, (loc, _)) in anchors.iter().enumerate() {
+            if on_wire(*loc, wire) {
+                sets.union(anchor_id, wire_id);
+            }
+        }
+
+        // Ends of other wires that touch this one
+        for (other_idx, other) in wires.iter().enumerate() {
+            let ends = [other.first(), other.last()];
+            if other_idx != wire_idx && ends.into_iter().flatten().any(|loc| on_wire(*loc, wire)) {
+                sets.union(wire_id, anchors.len() + other_idx);
+            }
+        }
+    }
+
+    // Anchors at the same point (e.g. a power port on a pin) and anchors with
+    // the same name are connected
+    let mut by_loc: HashMap<(i32, i32), usize> = HashMap::new();
+    let mut by_name: HashMap<&str, usize> = HashMap::new();
+    for (anchor_id, (loc, anchor)) in anchors.iter().enumerate() {
+        let first = *by_loc.entry((loc.x, loc.y)).or_insert(anchor_id);
+        sets.union(first, anchor_id);
+
+        if let Anchor::Name(name, _) = anchor {
+            let first = *by_name.entry(name).or_insert(anchor_id);
+            sets.union(first, anchor_id);
+        }
+    }
+
+    // Collect the pins and best name of each net
+    let mut nets: BTreeMap<usize, NetNodes<'_>> = BTreeMap::new();
+    for (anchor_id, (_, anchor)) in anchors.iter().enumerate() {
+        let entry = nets.entry(sets.find(anchor_id)).or_default();
+        match *anchor {
+            Anchor::Pin(comp_idx, pin_idx) => {
+                let comp = &netlist.components()[comp_idx];
+                entry.0.push(Node {
+                    designator: comp.designator.clone(),
+                    pin: comp.pins[pin_idx].designator.clone(),
+                });
+            }
+            Anchor::Name(name, priority) => {
+                // Highest priority wins, then the first name alphabetically
+                let better = entry.1.is_none_or(|(best_priority, best_name)| {
+                    priority > best_priority || (priority == best_priority && name < best_name)
+                });
+                if better {
+                    entry.1 = Some((priority, name));
+                }
+            }
+            Anchor::Junction => (),
+        }
+    }
+
+    let mut ret = named_nets(nets.into_values());
+    ret.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+    for net in ret {
+        netlist.add_net(net);
+    }
+
+    netlist
+}
+
+/// Sort each net's nodes and give it a name. Nets without a name are named
+/// after their first pin, and single unnamed pins are dropped.
+fn named_nets<'a>(nets: impl Iterator<Item = NetNodes<'a>>) -> Vec<Net> {
+    let mut ret: Vec<Net> = Vec::new();
+    let mut used: HashSet<String> = HashSet::new();
+
+    for (mut nodes, name) in nets {
+        nodes.sort_by(|a, b| {
+            natural_cmp(&a.designator, &b.designator).then_with(|| natural_cmp(&a.pin, &b.pin))
+        });
+        nodes.dedup();
+
+        let name = match name {
+            Some((_, name)) if !nodes.is_empty() => name.to_owned(),
+            None if nodes.len() > 1 => {
+                // Unannotated designators can repeat, so generated names may too
+                let base = format!("Net{}_{}", nodes[0].designator, nodes[0].pin);
+                let mut name = base.clone();
+                for i in 2.. {
+                    if !used.contains(&name) {
+                        break;
+                    }
+                    name = format!("{base}_{i}");
+                }
+                name
+            }
+            _ => continue,
+        };
+
+        used.insert(name.clone());
+        ret.push(Net {
+            name: name.into(),
+            nodes,
+        });
+    }
+
+    ret
+}
+
+/// Placed components and the locations of their pins' electrical ends as
+/// `(location, component index, pin index)`
+fn components<F>(
+    records: &[SchRecord],
+    owner_pos: &F,
+) -> (Vec<NetComponent>, Vec<(Location, usize, usize)>)
+where
+    F: Fn(usize) -> Option<usize>,
+{
+    // Component that ultimately owns each record, following owners through
+    // e.g. implementation lists
+    let root = |mut idx: usize| {
+        for _ in 0..records.len() {
+            match &records[idx] {
+                SchRecord::MetaData(_) => return Some(idx),
+                record => idx = owner_pos(record.owner_index()?.into())?,
+            }
+        }
+        None
+    };
+
+    let mut children: BTreeMap<usize, Vec<&SchRecord>> = BTreeMap::new();
+    for (idx, record) in records.iter().enumerate() {
+        if matches!(record, SchRecord::MetaData(_)) {
+            continue;
+        }
+        if let Some(comp_idx) = root(idx) {
+            children.entry(comp_idx).or_default().push(record);
+        }
+    }
+
+    let mut components: Vec<NetComponent> = Vec::new();
+    let mut pin_locations = Vec::new();
+
+    for (idx, record) in records.iter().enumerate() {
+        let SchRecord::MetaData(meta) = record else {
+            continue;
+        };
+        let children = children.remove(&idx).unwrap_or_default();
+        let comp = component(meta, &children);
+
+        // Parts of a multipart component are separate records that share a
+        // designator. Unannotated designators can't be merged.
+        let existing = components.iter().position(|existing| {
+            meta.part_count > 2
+                && !comp.designator.ends_with('?')
+                && existing.designator == comp.designator
+        });
+        let comp_idx = existing.unwrap_or_else(|| {
+            components.push(comp);
+            components.len() - 1
+        });
+
+        let pins = children.iter().filter_map(|record| match record {
+            SchRecord::Pin(pin)
+                if pin.owner_part_display_mode == meta.display_mode
+                    && (pin.owner_part_id <= 0
+                        || pin.owner_part_id
+                            == i8::try_from(meta.current_part_id).unwrap_or(-1)) =>
+            {
+                Some(pin)
+            }
+            _ => None,
+        });
+
+        for pin in pins {
+            let comp = &mut components[comp_idx];
+            let existing = comp
+                .pins
+                .iter()
+                .position(|existing| existing.designator == pin.designator);
+            let pin_idx = existing.unwrap_or_else(|| {
+                comp.pins.push(NetPin {
+                    designator: pin.designator.clone(),
+                    name: pin.name.clone(),
+                    electrical: pin.electrical,
+                });
+                comp.pins.len() - 1
+            });
+            pin_locations.push((pin.location_conn(), comp_idx, pin_idx));
+        }
+    }
+
+    (components, pin_locations)
+}
+
+/// Properties of a single component
+fn component(meta: &record::MetaData, children: &[&SchRecord]) -> NetComponent {
+    let param = |name: &str| {
+        children.iter().find_map(|record| match record {
+            SchRecord::Parameter(param) if param.name.eq_ignore_ascii_case(name) => {
+                Some(&*param.text)
+            }
+            _ => None,
+        })
+    };
+
+    let designator = children
+        .iter()
+        .find_map(|record| match record {
+            SchRecord::Designator(des) if &*des.name == "Designator" => Some(&*des.text),
+            _ => None,
+        })
+        .unwrap_or_else(|| {
+            warn!("component {} has no designator", meta.libref);
+            "?"
+        });
+
+    // Comments can refer to another parameter with `=Name`
+    let value = match param("Comment") {
+        Some(comment) => match comment.strip_prefix('=') {
+            Some(other) => param(other).unwrap_or(comment),
+            None => comment,
+        },
+        None => "",
+    };
+
+    let model = |ty: &str| {
+        children.iter().find_map(|record| match record {
+            SchRecord::Implementation(imp)
+                if imp.is_current && imp.model_type.eq_ignore_ascii_case(ty) =>
+            {
+                Some(imp.model_name.clone())
+            }
+            _ => None,
+        })
+    };
+
+    NetComponent {
+        designator: designator.into(),
+        libref: meta.libref.clone(),
+        value: value.into(),
+        description: meta.description.clone().unwrap_or_default(),
+        footprint: model("PCBLIB"),
+        sim_model: model("SIM"),
+        pins: Vec::new(),
+    }
+}
+
+/// Whether a point lies on any segment of a wire
+fn on_wire(loc: Location, wire: &[Location]) -> bool {
+    match wire {
+        [single] => *single == loc,
+        _ => wire.windows(2).any(|seg| on_segment(loc, seg[0], seg[1])),
+    }
+}
+
+fn on_segment(p: Location, a: Location, b: Location) -> bool {
+    let cross =
+        i64::from(b.x - a.x) * i64::from(p.y - a.y) - i64::from(b.y - a.y) * i64::from(p.x - a.x);
+
+    cross == 0
+        && (a.x.min(b.x)..=a.x.max(b.x)).contains(&p.x)
+        && (a.y.min(b.y)..=a.y.max(b.y)).contains(&p.y)
+}
+
+/// Minimal union-find
+struct DisjointSet(Vec<usize>);
+
+impl DisjointSet {
+    fn new(len: usize) -> Self {
+        Self((0..len).collect())
+    }
+
+    fn find(&mut self, mut idx: usize) -> usize {
+        while self.0[idx] != idx {
+            self.0[idx] = self.0[self.0[idx]];
+            idx = self.0[idx];
+        }
+        idx
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.0[b.max(a)] = a.min(b);
+        }
+    }
+}
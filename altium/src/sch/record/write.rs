@@ -0,0 +1,86 @@
+use log::warn;
+
+use super::SchRecord;
+use crate::write::{MapWriter, ToRecord};
+
+/// Our info u32 is `0xttllllll`, see `parse_all_records`
+const TY_SHIFT: u32 = 24;
+const LEN_MASK: u32 = 0x00ffffff;
+const UTF8_RECORD_TY: u32 = 0x00;
+const PIN_RECORD_TY: u32 = 0x01;
+
+/// Write records in the format of a component's `Data` stream, the inverse of
+/// `parse_all_records`
+pub fn write_all_records(records: &[SchRecord]) -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    for record in records {
+        let (ty, mut data) = match record {
+            SchRecord::Pin(pin) => (PIN_RECORD_TY, pin.to_binary()),
+            SchRecord::Undefined => {
+                warn!("skipping undefined record");
+                continue;
+            }
+            _ => (UTF8_RECORD_TY, write_any_record(record)),
+        };
+
+        // The length includes the null terminator
+        data.push(0);
+        let len = u32::try_from(data.len())
+            .ok()
+            .filter(|len| *len <= LEN_MASK)
+            .expect("record too long to write");
+
+        buf.extend_from_slice(&((ty << TY_SHIFT) | len).to_le_bytes());
+        buf.extend_from_slice(&data);
+    }
+
+    buf
+}
+
+/// Write any text record to a `|RECORD=n|...` buffer, the inverse of
+/// `parse_any_record` (excludes binary pins)
+fn write_any_record(record: &SchRecord) -> Vec<u8> {
+    let mut writer = MapWriter::new();
+
+    match record {
+        SchRecord::Undefined | SchRecord::Pin(_) => unreachable!("not a text record"),
+        SchRecord::MetaData(v) => v.to_record(&mut writer),
+        SchRecord::IeeeSymbol(v) => v.to_record(&mut writer),
+        SchRecord::Label(v) => v.to_record(&mut writer),
+        SchRecord::Bezier(v) => v.to_record(&mut writer),
+        SchRecord::PolyLine(v) => v.to_record(&mut writer),
+        SchRecord::Polygon(v) => v.to_record(&mut writer),
+        SchRecord::Ellipse(v) => v.to_record(&mut writer),
+        SchRecord::Piechart(v) => v.to_record(&mut writer),
+        SchRecord::RectangleRounded(v) => v.to_record(&mut writer),
+        SchRecord::ElipticalArc(v) => v.to_record(&mut writer),
+        SchRecord::Arc(v) => v.to_record(&mut writer),
+        SchRecord::Line(v) => v.to_record(&mut writer),
+        SchRecord::Rectangle(v) => v.to_record(&mut writer),
+        SchRecord::SheetSymbol(v) => v.to_record(&mut writer),
+        SchRecord::SheetEntry(v) => v.to_record(&mut writer),
+        SchRecord::PowerPort(v) => v.to_record(&mut writer),
+        SchRecord::Port(v) => v.to_record(&mut writer),
+        SchRecord::NoErc(v) => v.to_record(&mut writer),
+        SchRecord::NetLabel(v) => v.to_record(&mut writer),
+        SchRecord::Bus(v) => v.to_record(&mut writer),
+        SchRecord::Wire(v) => v.to_record(&mut writer),
+        SchRecord::TextFrame(v) => v.to_record(&mut writer),
+        SchRecord::Junction(v) => v.to_record(&mut writer),
+        SchRecord::Image(v) => v.to_record(&mut writer),
+        SchRecord::Sheet(v) => v.to_record(&mut writer),
+        SchRecord::SheetName(v) => v.to_record(&mut writer),
+        SchRecord::FileName(v) => v.to_record(&mut writer),
+        SchRecord::Designator(v) => v.to_record(&mut writer),
+        SchRecord::BusEntry(v) => v.to_record(&mut writer),
+        SchRecord::Template(v) => v.to_record(&mut writer),
+        SchRecord::Parameter(v) => v.to_record(&mut writer),
+        SchRecord::ImplementationList(v) => v.to_record(&mut writer),
+        SchRecord::Implementation(v) => v.to_record(&mut writer),
+        SchRecord::ImplementationChild1(v) => v.to_record(&mut writer),
+        SchRecord::ImplementationChild2(v) => v.to_record(&mut writer),
+    }
+
+    writer.finish()
+}
@@ -9,13 +9,14 @@ use std::sync::Arc;
 
 use cfb::CompoundFile;
 
-use super::record::{parse_all_records, Sheet};
+use super::record::{draw_grouped, extract_netlist, parse_all_records, Sheet};
 use super::storage::Storage;
 use super::{SchDrawCtx, SchRecord};
 use crate::common::split_altium_map;
-use crate::draw::{Canvas, Draw};
+use crate::draw::{Canvas, RenderOptions};
 use crate::error::AddContext;
 use crate::font::Font;
+use crate::netlist::Netlist;
 use crate::parse::{extract_sized_buf, BufLenMatch, ParseUtf8};
 use crate::{Error, ErrorKind, UniqueId};
 
@@ -29,6 +30,9 @@ pub struct SchDoc<F> {
     #[allow(dead_code)]
     cfile: RefCell<CompoundFile<F>>,
     sheet: Sheet,
+    /// Position of the sheet record in the file, before it was removed from
+    /// `records`
+    sheet_pos: Option<usize>,
     records: Vec<SchRecord>,
     unique_id: UniqueId,
     storage: Arc<Storage>,
@@ -73,13 +77,38 @@ impl<F: Read + Seek> SchDoc<F> {
     }
 
     /// Draw this schematic document
-    pub fn draw<C: Canvas>(&self, canvas: &mut C) {
+    pub fn draw<C: Canvas>(&self, canvas: &mut C, options: &RenderOptions) {
         let ctx = SchDrawCtx {
             storage: &self.storage,
             fonts: &self.sheet.fonts,
             name: &self.name,
+            options,
         };
-        self.records().for_each(|r| r.draw(canvas, &ctx));
+
+        draw_grouped(&self.records, canvas, &ctx, |idx| self.owner_pos(idx));
+    }
+
+    /// Extract the components and nets of this sheet
+    ///
+    /// Connections are found from wires, junctions, net labels, power ports and
+    /// ports. Components without an annotated designator keep their `?`, so
+    /// annotate the schematic first to get a usable netlist.
+    pub fn netlist(&self) -> Netlist {
+        let title = Path::new(&*self.name).file_stem().map_or_else(
+            || self.name.to_string(),
+            |stem| stem.to_string_lossy().into_owned(),
+        );
+        extract_netlist(&self.records, |idx| self.owner_pos(idx), &title)
+    }
+
+    /// Position in `records` for an owner index. Owner indices count the sheet
+    /// record, which we store separately.
+    fn owner_pos(&self, idx: usize) -> Option<usize> {
+        match self.sheet_pos {
+            Some(pos) if idx == pos => None,
+            Some(pos) if idx > pos => Some(idx - 1),
+            _ => Some(idx),
+        }
     }
 
     /// Create a `SchLib` representation from any `Read`able compound file.
@@ -114,6 +143,7 @@ impl<F: Read + Seek> SchDoc<F> {
             cfile: RefCell::new(cfile),
             records,
             sheet,
+            sheet_pos,
             storage: storage.into(),
             unique_id,
             name: name.into(),
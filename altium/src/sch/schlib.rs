@@ -1,4 +1,5 @@
 mod section_keys;
+mod write;
 
 use std::cell::RefCell;
 use std::fs::File;
@@ -9,6 +10,7 @@ use std::{fmt, str};
 
 use cfb::CompoundFile;
 use section_keys::update_section_keys;
+pub use write::SchLibWriter;
 
 use crate::common::{buf2lstr, split_altium_map, Rgb, UniqueId};
 use crate::error::{AddContext, ErrorKind};
@@ -74,7 +76,7 @@ impl<F: Read + Seek> SchLib<F> {
     }
 
     /// Lookup a single component by its libref, propegating errors if they arise
-    fn try_get_component(&self, libref: &str) -> Result<Option<Component>, Error> {
+    pub fn try_get_component(&self, libref: &str) -> Result<Option<Component>, Error> {
         let Some(meta) = &self
             .header
             .components
@@ -132,7 +134,7 @@ impl<F: Read + Seek> SchLib<F> {
     }
 
     /// Create a `SchLib` representation from any `Read`able compound file.
-    fn from_cfile(mut cfile: CompoundFile<F>) -> Result<Self, Error> {
+    pub(crate) fn from_cfile(mut cfile: CompoundFile<F>) -> Result<Self, Error> {
         let mut tmp_buf: Vec<u8> = Vec::new(); // scratch memory
 
         let mut header = SchLibMeta::parse_cfile(&mut cfile, &mut tmp_buf)?;
@@ -10,7 +10,7 @@ use crate::logging::log_unsupported_key;
 use crate::parse::ParseUtf8;
 use crate::Error;
 
-const SEC_KEY_STREAM: &str = "SectionKeys";
+pub(super) const SEC_KEY_STREAM: &str = "SectionKeys";
 const PFX_LEN: usize = 5;
 const SFX: &[u8] = &[0x00];
 const LIBREF: &[u8] = b"LibRef";
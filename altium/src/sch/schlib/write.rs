@@ -0,0 +1,300 @@
+//! Writing schematic libraries
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Cursor, Write};
+use std::path::Path;
+
+use cfb::CompoundFile;
+use log::warn;
+
+use super::section_keys::SEC_KEY_STREAM;
+use super::SchLibMeta;
+use crate::common::UniqueId;
+use crate::font::Font;
+use crate::sch::record::write_all_records;
+use crate::sch::storage::Storage;
+use crate::sch::{Component, SchRecord};
+use crate::write::MapWriter;
+use crate::Error;
+
+/// Longest stream name allowed in a compound file, in UTF-16 units
+const MAX_SEC_KEY_LEN: usize = 31;
+/// Minor version that we write
+const MINOR_VERSION: u8 = 9;
+
+/// Builder for a schematic library (`.SchLib`) that can be written to a file
+///
+/// Components can come from any source, such as another library or an imported
+/// KiCad symbol. Fonts get merged into a single table and embedded images are
+/// copied along with the components that use them.
+///
+/// ```no_run
+/// use altium::sch::SchLibWriter;
+/// use altium::SchLib;
+///
+/// let schlib = SchLib::open("example.SchLib").unwrap();
+/// let mut writer = SchLibWriter::new();
+/// for component in schlib.components() {
+///     writer.add_component(&component);
+/// }
+/// writer.save("copy.SchLib").unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct SchLibWriter {
+    components: Vec<Entry>,
+    fonts: Vec<Font>,
+    storage: Storage,
+}
+
+/// A component ready to be written
+#[derive(Debug)]
+struct Entry {
+    libref: Box<str>,
+    description: Box<str>,
+    part_count: u8,
+    records: Vec<SchRecord>,
+}
+
+impl SchLibWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a component to this library. A component with the same name gets
+    /// replaced.
+    pub fn add_component(&mut self, component: &Component) {
+        let mut records = component.records().to_vec();
+
+        // Point font IDs at our own table. We add all of the component's fonts
+        // so libraries get the same table back when copied.
+        let font_map: Vec<u16> = component.fonts.iter().map(|f| self.font_id(f)).collect();
+        for font_id in records.iter_mut().filter_map(SchRecord::font_id_mut) {
+            if let Some(new_id) = usize::from(*font_id)
+                .checked_sub(1)
+                .and_then(|idx| font_map.get(idx))
+            {
+                *font_id = *new_id;
+            }
+        }
+
+        for path in records.iter().filter_map(SchRecord::embedded_image) {
+            if !self.storage.copy_from(&component.storage, path) {
+                warn!(
+                    "embedded image '{path}' not found for '{}'",
+                    component.name()
+                );
+            }
+        }
+
+        let part_count = records
+            .iter()
+            .find_map(|record| match record {
+                SchRecord::MetaData(meta) => Some(meta.part_count()),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let entry = Entry {
+            libref: component.name().into(),
+            description: component.description().into(),
+            part_count,
+            records,
+        };
+
+        if let Some(existing) = self
+            .components
+            .iter_mut()
+            .find(|existing| existing.libref == entry.libref)
+        {
+            warn!("replacing duplicate component '{}'", entry.libref);
+            *existing = entry;
+        } else {
+            self.components.push(entry);
+        }
+    }
+
+    /// Number of components in this library
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// Write the library to a writer
+    pub fn write_to<W: io::Write>(&self, mut writer: W) -> Result<(), Error> {
+        let mut cfile = CompoundFile::create(Cursor::new(Vec::new()))?;
+        let sec_keys = self.section_keys();
+
+        let mut stream = cfile.create_stream(SchLibMeta::STREAMNAME)?;
+        stream.write_all(&self.file_header())?;
+        drop(stream);
+
+        let mut stream = cfile.create_stream(Storage::STREAMNAME)?;
+        stream.write_all(&self.storage.write()?)?;
+        drop(stream);
+
+        // Only components that can't use their name as a storage need a key
+        let mut mapped: Vec<(&str, &str)> = self
+            .components
+            .iter()
+            .zip(&sec_keys)
+            .filter(|(comp, key)| comp.libref.replace('/', "_") != ***key)
+            .map(|(comp, key)| (&*comp.libref, &**key))
+            .collect();
+
+        if !mapped.is_empty() {
+            // Lookup is a binary search so these need to be sorted
+            mapped.sort_unstable();
+            let mut text = format!("|KeyCount={}", mapped.len());
+            for (idx, (libref, key)) in mapped.iter().enumerate() {
+                write!(text, "|LibRef{idx}={libref}|SectionKey{idx}={key}").unwrap();
+            }
+
+            let mut stream = cfile.create_stream(SEC_KEY_STREAM)?;
+            stream.write_all(&sized_stream(text.into_bytes()))?;
+        }
+
+        for (comp, key) in self.components.iter().zip(&sec_keys) {
+            let storage_path = format!("/{key}");
+            cfile.create_storage(&storage_path)?;
+            let mut stream = cfile.create_stream(format!("{storage_path}/Data"))?;
+            stream.write_all(&write_all_records(&comp.records))?;
+        }
+
+        cfile.flush()?;
+        writer.write_all(&cfile.into_inner().into_inner())?;
+        Ok(())
+    }
+
+    /// Write the library to a file, creating it if needed
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Get the one-based index of a font, adding it if needed
+    fn font_id(&mut self, font: &Font) -> u16 {
+        let idx = self
+            .fonts
+            .iter()
+            .position(|f| f == font)
+            .unwrap_or_else(|| {
+                self.fonts.push(font.clone());
+                self.fonts.len() - 1
+            });
+        u16::try_from(idx + 1).expect("too many fonts")
+    }
+
+    /// Contents of the `FileHeader` stream
+    fn file_header(&self) -> Vec<u8> {
+        let default_font = Font {
+            name: "Times New Roman".into(),
+            size: 10,
+        };
+        let fonts = if self.fonts.is_empty() {
+            std::slice::from_ref(&default_font)
+        } else {
+            &self.fonts
+        };
+        let weight: usize = self.components.iter().map(|c| c.records.len()).sum();
+
+        let mut w = MapWriter::new();
+        w.field(b"Weight", &weight);
+        w.field(b"MinorVersion", &MINOR_VERSION);
+        w.field(b"UniqueID", &UniqueId::new_random());
+        w.field(b"FontIdCount", &fonts.len());
+        for (idx, font) in fonts.iter().enumerate() {
+            w.field(format!("Size{}", idx + 1).as_bytes(), &font.size);
+            w.field(format!("FontName{}", idx + 1).as_bytes(), &font.name);
+        }
+
+        // Defaults from a new Altium library
+        w.field(b"UseMBCS", &true);
+        w.field(b"IsBOC", &true);
+        w.field(b"SheetStyle", &9u8);
+        w.field(b"BorderOn", &true);
+        w.field(b"SheetNumberSpaceSize", &12u8);
+        w.field(b"AreaColor", &16_317_695u32);
+        w.field(b"SnapGridOn", &true);
+        w.field(b"SnapGridSize", &10u8);
+        w.field(b"VisibleGridOn", &true);
+        w.field(b"VisibleGridSize", &10u8);
+        w.field(b"CustomX", &18000u16);
+        w.field(b"CustomY", &18000u16);
+        w.field(b"UseCustomSheet", &true);
+        w.field(b"ReferenceZonesOn", &true);
+        w.field(b"Display_Unit", &0u8);
+
+        w.field(b"CompCount", &self.components.len());
+        for (idx, comp) in self.components.iter().enumerate() {
+            w.field(format!("LibRef{idx}").as_bytes(), &comp.libref);
+            w.field(format!("CompDescr{idx}").as_bytes(), &comp.description);
+            w.field(format!("PartCount{idx}").as_bytes(), &comp.part_count);
+        }
+
+        let mut buf = vec![b'|'];
+        buf.extend_from_slice(SchLibMeta::HEADER);
+        buf.extend_from_slice(&w.finish());
+        sized_stream(buf)
+    }
+
+    /// Pick a storage name for each component
+    fn section_keys(&self) -> Vec<Box<str>> {
+        let mut keys: Vec<Box<str>> = Vec::with_capacity(self.components.len());
+
+        for comp in &self.components {
+            let base: String = comp
+                .libref
+                .chars()
+                .map(|ch| {
+                    if matches!(ch, '/' | '\\' | ':' | '!') {
+                        '_'
+                    } else {
+                        ch
+                    }
+                })
+                .collect();
+
+            let mut key = truncate_utf16(&base, MAX_SEC_KEY_LEN).to_owned();
+            let mut n = 1;
+            while keys.iter().any(|k| k.to_lowercase() == key.to_lowercase()) {
+                let sfx = format!("_{n}");
+                key = format!(
+                    "{}{sfx}",
+                    truncate_utf16(&base, MAX_SEC_KEY_LEN - sfx.len())
+                );
+                n += 1;
+            }
+
+            keys.push(key.into());
+        }
+
+        keys
+    }
+}
+
+/// Prefix a buffer with its length and add the null terminator, which is
+/// included in the length
+fn sized_stream(mut buf: Vec<u8>) -> Vec<u8> {
+    buf.push(0);
+    let mut ret = u32::try_from(buf.len()).unwrap().to_le_bytes().to_vec();
+    ret.append(&mut buf);
+    ret
+}
+
+/// Truncate a string to at most `max` UTF-16 code units
+fn truncate_utf16(s: &str, max: usize) -> &str {
+    let mut units = 0;
+    for (idx, ch) in s.char_indices() {
+        units += ch.len_utf16();
+        if units > max {
+            return &s[..idx];
+        }
+    }
+    s
+}
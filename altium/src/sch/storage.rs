@@ -2,12 +2,14 @@
 //! represented as zlib-compressed data.
 
 use core::fmt;
-use std::io::{Cursor, Read, Seek};
+use std::io::{Cursor, Read, Seek, Write};
 use std::sync::Mutex;
 use std::{collections::BTreeMap, sync::Arc};
 
 use cfb::CompoundFile;
 use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
 
 use crate::common::split_altium_map;
 use crate::error::{AddContext, TruncBuf};
@@ -44,7 +46,7 @@ pub enum CompressedData {
 }
 
 impl Storage {
-    const STREAMNAME: &'static str = "Storage";
+    pub(crate) const STREAMNAME: &'static str = "Storage";
 
     /// Get the data from a key (path) name if available
     ///
@@ -69,6 +71,51 @@ impl Storage {
         self.0.keys().map(AsRef::as_ref)
     }
 
+    /// Copy an item from another storage, if it exists. Returns `true` if
+    /// something was copied.
+    pub(crate) fn copy_from(&mut self, other: &Self, path: &str) -> bool {
+        let Some(mtx) = other.0.get(path) else {
+            return false;
+        };
+        let data = mtx.lock().unwrap().clone();
+        self.0.insert(path.into(), Mutex::new(data));
+        true
+    }
+
+    /// Write the `Storage` stream, the inverse of `parse`
+    pub(crate) fn write(&self) -> Result<Vec<u8>, ErrorKind> {
+        // Entries are `0x01llllll`, where `l` is the length of the rest
+        const ENTRY_MAGIC: u32 = 0x01 << 24;
+        const ENTRY_TY: u8 = 0xd0;
+
+        let mut header = b"|HEADER=Icon storage".to_vec();
+        if !self.0.is_empty() {
+            header.extend_from_slice(format!("|Weight={}", self.0.len()).as_bytes());
+        }
+        header.push(0);
+
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&u32::try_from(header.len()).unwrap().to_le_bytes());
+        buf.extend_from_slice(&header);
+
+        for (path, mtx) in &self.0 {
+            let data = mtx.lock().unwrap().compressed()?;
+            let path_len =
+                u8::try_from(path.len()).map_err(|_| ErrorKind::InvalidKey(path.clone()))?;
+            let data_len = u32::try_from(data.len()).unwrap();
+            let entry_len = 2 + u32::from(path_len) + 4 + data_len;
+
+            buf.extend_from_slice(&(ENTRY_MAGIC | entry_len).to_le_bytes());
+            buf.push(ENTRY_TY);
+            buf.push(path_len);
+            buf.extend_from_slice(path.as_bytes());
+            buf.extend_from_slice(&data_len.to_le_bytes());
+            buf.extend_from_slice(&data);
+        }
+
+        Ok(buf)
+    }
+
     pub(crate) fn parse_cfile<F: Read + Seek>(
         cfile: &mut CompoundFile<F>,
         tmp_buf: &mut Vec<u8>,
@@ -166,6 +213,21 @@ impl CompressedData {
     }
 }
 
+impl CompressedData {
+    /// Get the data in compressed form, compressing it again if it has been
+    /// expanded
+    fn compressed(&self) -> Result<Box<[u8]>, ErrorKind> {
+        match self {
+            Self::Compressed(d) => Ok(d.clone()),
+            Self::Expanded(d) => {
+                let mut z = ZlibEncoder::new(Vec::new(), Compression::default());
+                z.write_all(d)?;
+                Ok(z.finish()?.into())
+            }
+        }
+    }
+}
+
 impl fmt::Debug for CompressedData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let (name, data): (_, &[u8]) = match self {
@@ -0,0 +1,278 @@
+//! Consistency checks across schematic and PCB libraries
+
+use std::fmt;
+use std::io::{Read, Seek};
+
+use crate::pcb::{Footprint, PcbLib};
+use crate::sch::{Component, SchLib, SchRecord};
+use crate::Error;
+
+/// Checks that symbols link to footprints that exist and that their pins and
+/// pads agree
+///
+/// Footprints are looked up by name in every added `PcbLib`, ignoring case
+/// and which library the model says it is in.
+///
+/// ```no_run
+/// use altium::validate::LinkValidator;
+/// use altium::{PcbLib, SchLib};
+///
+/// let mut validator = LinkValidator::new();
+/// let schlib = SchLib::open("Passives.SchLib").unwrap();
+/// validator.add_schlib("Passives.SchLib", &schlib).unwrap();
+/// let pcblib = PcbLib::open("Passives.PcbLib").unwrap();
+/// validator.add_pcblib("Passives.PcbLib", &pcblib).unwrap();
+///
+/// for issue in validator.validate() {
+///     println!("{issue}");
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LinkValidator {
+    symbols: Vec<Symbol>,
+    footprints: Vec<FootprintPads>,
+}
+
+/// What we need to know about a symbol
+#[derive(Clone, Debug)]
+struct Symbol {
+    library: Box<str>,
+    libref: Box<str>,
+    footprints: Vec<Box<str>>,
+    pins: Vec<Box<str>>,
+}
+
+/// What we need to know about a footprint
+#[derive(Clone, Debug)]
+struct FootprintPads {
+    library: Box<str>,
+    name: Box<str>,
+    pads: Vec<Box<str>>,
+}
+
+impl LinkValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add every component in a schematic library. `library` is the name used
+    /// in reports.
+    pub fn add_schlib<F: Read + Seek>(
+        &mut self,
+        library: &str,
+        schlib: &SchLib<F>,
+    ) -> Result<(), Error> {
+        for meta in schlib.component_meta() {
+            if let Some(comp) = schlib.try_get_component(meta.libref())? {
+                self.add_component(library, &comp);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a single component
+    pub fn add_component(&mut self, library: &str, comp: &Component) {
+        let mut pins: Vec<Box<str>> = Vec::new();
+        for record in comp.records() {
+            if let SchRecord::Pin(pin) = record {
+                if !pins.contains(&pin.designator) {
+                    pins.push(pin.designator.clone());
+                }
+            }
+        }
+
+        self.symbols.push(Symbol {
+            library: library.into(),
+            libref: comp.name().into(),
+            footprints: comp
+                .footprints()
+                .iter()
+                .map(|fp| fp.name().into())
+                .collect(),
+            pins,
+        });
+    }
+
+    /// Add every footprint in a PCB library. `library` is the name used in
+    /// reports.
+    pub fn add_pcblib<F: Read + Seek>(
+        &mut self,
+        library: &str,
+        pcblib: &PcbLib<F>,
+    ) -> Result<(), Error> {
+        for meta in pcblib.footprint_meta() {
+            if let Some(footprint) = pcblib.try_get_footprint(meta.name())? {
+                self.add_footprint(library, &footprint);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a single footprint
+    pub fn add_footprint(&mut self, library: &str, footprint: &Footprint) {
+        let mut pads: Vec<Box<str>> = Vec::new();
+        // Pads without a designator are mechanical and never connect to pins
+        for pad in footprint.pads().filter(|pad| !pad.designator.is_empty()) {
+            if !pads.contains(&pad.designator) {
+                pads.push(pad.designator.clone());
+            }
+        }
+
+        self.footprints.push(FootprintPads {
+            library: library.into(),
+            name: footprint.name().into(),
+            pads,
+        });
+    }
+
+    /// Check everything that was added, in the order symbols were added
+    pub fn validate(&self) -> Vec<LinkIssue> {
+        let mut ret = Vec::new();
+
+        for (idx, sym) in self.symbols.iter().enumerate() {
+            let earlier = &self.symbols[..idx];
+            let is_first = !earlier
+                .iter()
+                .any(|other| other.libref.eq_ignore_ascii_case(&sym.libref));
+            let libraries: Vec<Box<str>> = self
+                .symbols
+                .iter()
+                .filter(|other| other.libref.eq_ignore_ascii_case(&sym.libref))
+                .map(|other| other.library.clone())
+                .collect();
+            if is_first && libraries.len() > 1 {
+                ret.push(LinkIssue::DuplicateLibref {
+                    libref: sym.libref.clone(),
+                    libraries,
+                });
+            }
+
+            if sym.footprints.is_empty() {
+                ret.push(LinkIssue::NoFootprint {
+                    library: sym.library.clone(),
+                    libref: sym.libref.clone(),
+                });
+            }
+
+            for name in &sym.footprints {
+                self.check_footprint(sym, name, &mut ret);
+            }
+        }
+
+        ret
+    }
+
+    /// Check a single footprint link of a symbol
+    fn check_footprint(&self, sym: &Symbol, name: &str, issues: &mut Vec<LinkIssue>) {
+        let Some(footprint) = self
+            .footprints
+            .iter()
+            .find(|fp| fp.name.eq_ignore_ascii_case(name))
+        else {
+            issues.push(LinkIssue::MissingFootprint {
+                library: sym.library.clone(),
+                libref: sym.libref.clone(),
+                footprint: name.into(),
+            });
+            return;
+        };
+
+        for pin in sym.pins.iter().filter(|pin| !footprint.pads.contains(pin)) {
+            issues.push(LinkIssue::UnmatchedPin {
+                library: sym.library.clone(),
+                libref: sym.libref.clone(),
+                footprint: footprint.name.clone(),
+                pin: pin.clone(),
+            });
+        }
+
+        for pad in footprint.pads.iter().filter(|pad| !sym.pins.contains(pad)) {
+            issues.push(LinkIssue::UnusedPad {
+                library: footprint.library.clone(),
+                footprint: footprint.name.clone(),
+                libref: sym.libref.clone(),
+                pad: pad.clone(),
+            });
+        }
+    }
+}
+
+/// A problem found by [`LinkValidator`]
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkIssue {
+    /// A symbol doesn't link to any footprint
+    NoFootprint { library: Box<str>, libref: Box<str> },
+    /// A symbol links to a footprint that isn't in any library
+    MissingFootprint {
+        library: Box<str>,
+        libref: Box<str>,
+        footprint: Box<str>,
+    },
+    /// A symbol's pin has no pad with the same designator
+    UnmatchedPin {
+        library: Box<str>,
+        libref: Box<str>,
+        footprint: Box<str>,
+        pin: Box<str>,
+    },
+    /// A footprint's pad isn't used by a symbol that links to it. `library`
+    /// is the footprint's library.
+    UnusedPad {
+        library: Box<str>,
+        footprint: Box<str>,
+        libref: Box<str>,
+        pad: Box<str>,
+    },
+    /// The same libref is in more than one library, or more than once in one
+    DuplicateLibref {
+        libref: Box<str>,
+        libraries: Vec<Box<str>>,
+    },
+}
+
+impl fmt::Display for LinkIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoFootprint { library, libref } => {
+                write!(f, "{library}: `{libref}` has no footprint")
+            }
+            Self::MissingFootprint {
+                library,
+                libref,
+                footprint,
+            } => write!(
+                f,
+                "{library}: `{libref}` links to footprint `{footprint}` which doesn't exist"
+            ),
+            Self::UnmatchedPin {
+                library,
+                libref,
+                footprint,
+                pin,
+            } => write!(
+                f,
+                "{library}: pin `{pin}` of `{libref}` has no pad in footprint `{footprint}`"
+            ),
+            Self::UnusedPad {
+                library,
+                footprint,
+                libref,
+                pad,
+            } => write!(
+                f,
+                "{library}: pad `{pad}` of `{footprint}` isn't used by `{libref}`"
+            ),
+            Self::DuplicateLibref { libref, libraries } => {
+                write!(f, "`{libref}` is defined more than once, in ")?;
+                for (idx, library) in libraries.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(", ")?;
+                    }
+                    f.write_str(library)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
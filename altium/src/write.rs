@@ -0,0 +1,6 @@
+//! Traits to help with writing, the inverse of `parse`
+mod to_record;
+mod utf8;
+
+pub use to_record::{MapWriter, ToRecord};
+pub use utf8::ToUtf8;
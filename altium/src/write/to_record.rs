@@ -0,0 +1,76 @@
+//! Trait for writing records, the inverse of `FromRecord`
+
+use super::ToUtf8;
+
+/// Write a type as a `|RECORD=n|Key=Value...` record
+///
+/// See the [`crate::sch::record`] module for more information.
+pub trait ToRecord {
+    /// Write this record's keys and values
+    fn to_record(&self, writer: &mut MapWriter);
+}
+
+/// Builder for Altium's `|Key1=Val1|Key2=Val2...` maps, the inverse of
+/// `split_altium_map`
+#[derive(Clone, Debug, Default)]
+pub struct MapWriter {
+    buf: Vec<u8>,
+}
+
+impl MapWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write a key and value.
+    ///
+    /// Non-ASCII text gets written under `%UTF8%Key` with a lossy copy under
+    /// `Key`, the same as Altium.
+    pub fn field<T: ToUtf8 + ?Sized>(&mut self, key: &[u8], val: &T) {
+        let val = val.to_utf8();
+
+        if val.is_ascii() {
+            self.write_pair(b"", key, val.as_bytes());
+        } else {
+            self.write_pair(b"%UTF8%", key, val.as_bytes());
+            let lossy: String = val
+                .chars()
+                .map(|ch| if ch.is_ascii() { ch } else { '?' })
+                .collect();
+            self.write_pair(b"", key, lossy.as_bytes());
+        }
+    }
+
+    /// Write a key and value, unless the value is the type's default
+    pub fn nondefault_field<T: ToUtf8 + Default + PartialEq>(&mut self, key: &[u8], val: &T) {
+        if *val != T::default() {
+            self.field(key, val);
+        }
+    }
+
+    /// Write a key with a (1-based) index suffix such as `X1`, unless the value
+    /// is the type's default
+    pub fn indexed_field<T: ToUtf8 + Default + PartialEq>(
+        &mut self,
+        key: &[u8],
+        idx: usize,
+        val: &T,
+    ) {
+        let mut indexed = key.to_vec();
+        indexed.extend_from_slice(idx.to_string().as_bytes());
+        self.nondefault_field(&indexed, val);
+    }
+
+    /// Get the written map
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+
+    fn write_pair(&mut self, pfx: &[u8], key: &[u8], val: &[u8]) {
+        self.buf.push(b'|');
+        self.buf.extend_from_slice(pfx);
+        self.buf.extend_from_slice(key);
+        self.buf.push(b'=');
+        self.buf.extend_from_slice(val);
+    }
+}
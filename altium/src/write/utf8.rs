@@ -0,0 +1,51 @@
+//! Writing values as utf8 strings, the inverse of `FromUtf8`
+
+/// Trait saying that a type can be written as a utf8/ASCII string in a way
+/// that `FromUtf8` can read back.
+pub trait ToUtf8 {
+    fn to_utf8(&self) -> String;
+}
+
+impl ToUtf8 for str {
+    fn to_utf8(&self) -> String {
+        self.to_owned()
+    }
+}
+
+impl ToUtf8 for String {
+    fn to_utf8(&self) -> String {
+        self.clone()
+    }
+}
+
+impl ToUtf8 for Box<str> {
+    fn to_utf8(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ToUtf8 for bool {
+    fn to_utf8(&self) -> String {
+        if *self { "T" } else { "F" }.to_owned()
+    }
+}
+
+impl<T: ToUtf8> ToUtf8 for Option<T> {
+    fn to_utf8(&self) -> String {
+        self.as_ref().map(ToUtf8::to_utf8).unwrap_or_default()
+    }
+}
+
+macro_rules! impl_to_utf8_display {
+    ($($ty:ty),*) => {
+        $(
+            impl ToUtf8 for $ty {
+                fn to_utf8(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )*
+    };
+}
+
+impl_to_utf8_display!(u8, i8, u16, i16, u32, i32, usize, f32);
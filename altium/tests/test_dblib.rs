@@ -0,0 +1,139 @@
+include!("include_test_util.rs");
+
+use altium::dblib::{CsvBackend, DbBackend, DbLib, MappingTarget, MemoryBackend, SqliteBackend};
+
+const DBLIB: &str = "tests/samples/dblib/Parts.DbLib";
+const SQLITE: &str = "tests/samples/dblib/Parts.db";
+const CSV_DIR: &str = "tests/samples/dblib/csv";
+
+#[test]
+fn test_parse() {
+    test_init_once();
+
+    let dblib = DbLib::open(DBLIB).unwrap();
+    assert_eq!(
+        dblib.connection_string(),
+        "Driver={SQLite3 ODBC Driver};Database=Parts.db"
+    );
+
+    let tables = dblib.tables();
+    assert_eq!(tables.len(), 3);
+    assert_eq!(tables[0].name(), "Old");
+    assert!(!tables[0].enabled());
+    assert_eq!(tables[1].key(), "Part Number");
+    assert!(tables[1].mappings().is_empty());
+    assert_eq!(tables[2].key(), "PN");
+    assert_eq!(tables[2].mappings().len(), 3);
+    assert_eq!(tables[2].mappings()[0].target, MappingTarget::LibraryRef);
+    assert_eq!(
+        tables[2].mappings()[1].target,
+        MappingTarget::Parameter("Value".into())
+    );
+}
+
+#[test]
+fn test_sqlite() {
+    test_init_once();
+
+    let db = SqliteBackend::open(SQLITE).unwrap();
+    assert_eq!(
+        db.table_names().unwrap(),
+        ["Resistors", "Capacitors", "Old"]
+    );
+
+    // Enough rows to need interior pages
+    let rows = db.rows("resistors").unwrap();
+    assert_eq!(rows.len(), 300);
+    assert_eq!(rows[0].get("id"), Some("1"));
+    assert_eq!(rows[0].get("Part Number"), Some("RES-0001"));
+    assert_eq!(rows[0].get("Library Ref"), Some("PinProperties"));
+    assert_eq!(rows[0].get("Tolerance"), Some("0.01"));
+    assert_eq!(rows[0].get("Stock"), Some("10"));
+    assert_eq!(rows[1].get("Footprint Ref 2"), Some(""));
+    assert_eq!(rows[299].get("Part Number"), Some("RES-0300"));
+    // Spills onto overflow pages
+    assert_eq!(rows[149].get("Notes").unwrap(), "x".repeat(3000));
+
+    assert!(db.rows("Nope").is_err());
+}
+
+#[test]
+fn test_resolve_sqlite() {
+    test_init_once();
+
+    let dblib = DbLib::open(DBLIB).unwrap();
+    let backend = dblib.open_backend().unwrap();
+
+    let part = dblib.part(&*backend, "RES-0001").unwrap().unwrap();
+    // The disabled table also has this key but isn't searched
+    assert_eq!(part.table(), "Resistors");
+    assert_eq!(part.library_ref(), "PinProperties");
+    assert_eq!(part.library_path(), "..\\schlib\\simple.SchLib");
+    let footprints: Vec<_> = part.footprints().iter().map(|fp| fp.name()).collect();
+    assert_eq!(footprints, ["CAPC1608X09L", "Four pads"]);
+    assert_eq!(part.parameter("value"), Some("1k"));
+    assert_eq!(part.parameter("Part Number"), Some("RES-0001"));
+    assert_eq!(part.parameter("Library Ref"), None);
+
+    let comp = dblib.component(&*backend, "RES-0001").unwrap().unwrap();
+    assert_eq!(comp.name(), "PinProperties");
+    let params = comp.parameters();
+    assert!(params.contains(&("Value", "1k")), "{params:?}");
+    assert!(params.contains(&("Stock", "10")), "{params:?}");
+    assert_eq!(comp.current_footprint().unwrap().name(), "CAPC1608X09L");
+    assert_eq!(comp.footprints().len(), 2);
+
+    // Explicit mappings only use the mapped fields
+    let part = dblib.part(&*backend, "CAP-0001").unwrap().unwrap();
+    assert_eq!(part.library_ref(), "Mixed with shape and text");
+    let params: Vec<_> = part.parameters().collect();
+    assert_eq!(params, [("Value", "100nF"), ("Part Number", "CAP-0001")]);
+    assert!(part.footprints().is_empty());
+
+    assert!(dblib.part(&*backend, "NOPE").unwrap().is_none());
+    assert_eq!(dblib.parts(&*backend).unwrap().len(), 301);
+}
+
+#[test]
+fn test_csv() {
+    test_init_once();
+
+    let db = CsvBackend::open_dir(CSV_DIR).unwrap();
+    let rows = db.rows("Resistors").unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].get("Notes"), Some("Says \"hi\",\nover two lines"));
+    assert_eq!(rows[1].get("Value"), Some("2k"));
+
+    let dblib = DbLib::open(DBLIB).unwrap();
+    let comp = dblib.component(&db, "RES-0002").unwrap().unwrap();
+    assert_eq!(comp.current_footprint().unwrap().name(), "CAPC1608X09L");
+    assert!(comp.parameters().contains(&("Value", "2k")));
+}
+
+#[test]
+fn test_memory() {
+    test_init_once();
+
+    let dblib = DbLib::from_string(
+        "[DatabaseLinks]\nConnectionString=Data Source=C:\\parts.mdb\n\
+         [Table1]\nTableName=Diodes\nKey=MPN\n",
+    )
+    .unwrap();
+    assert!(dblib.open_backend().is_err());
+
+    let mut db = MemoryBackend::new();
+    db.insert(
+        "Diodes",
+        [
+            ("MPN", "1N4148"),
+            ("Library Ref", "D"),
+            ("Footprint Ref", "SOD-123"),
+        ]
+        .into_iter()
+        .collect(),
+    );
+    let part = dblib.part(&db, "1N4148").unwrap().unwrap();
+    assert_eq!(part.library_ref(), "D");
+    assert_eq!(part.footprints()[0].name(), "SOD-123");
+    assert_eq!(part.parameter("MPN"), Some("1N4148"));
+}
@@ -0,0 +1,97 @@
+include!("include_test_util.rs");
+
+use std::fs;
+use std::io::{Cursor, Write};
+
+use altium::kicad::SymbolLib;
+use altium::sch::SchLibWriter;
+use altium::{IntLib, PcbLib};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+const PCBLIB_SIMPLE: &str = "tests/samples/pcblib/Simple.PcbLib";
+
+/// Package libraries the way we expect Altium to compile them, with a prefix
+/// byte before each zlib stream. This is not checked against a real `.IntLib`.
+fn intlib(sources: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut cfile = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    cfile
+        .create_stream("/Version.Txt")
+        .unwrap()
+        .write_all(b"Version=1.1")
+        .unwrap();
+    for (path, data) in sources {
+        let (storage, _) = path.rsplit_once('/').unwrap();
+        if !cfile.is_storage(storage) {
+            cfile.create_storage(storage).unwrap();
+        }
+        let mut enc = ZlibEncoder::new(vec![0x02], Compression::default());
+        enc.write_all(data).unwrap();
+        let buf = enc.finish().unwrap();
+        cfile.create_stream(path).unwrap().write_all(&buf).unwrap();
+    }
+    cfile.flush().unwrap();
+    cfile.into_inner().into_inner()
+}
+
+#[test]
+fn test_intlib() {
+    test_init_once();
+
+    let sym = r#"(kicad_symbol_lib (version 20231120) (generator "test")
+        (symbol "Cap" (in_bom yes) (on_board yes)
+            (property "Reference" "C" (at 0 0 0))
+            (property "Footprint" "Capacitors:CAPC1608X09L" (at 0 0 0))
+            (property "Manufacturer" "Acme" (at 0 0 0))
+            (symbol "Cap_1_1")
+        )
+        (symbol "Missing" (in_bom yes) (on_board yes)
+            (property "Reference" "U" (at 0 0 0))
+            (property "Footprint" "NOPE" (at 0 0 0))
+            (symbol "Missing_1_1")
+        )
+    )"#;
+    let symbols = SymbolLib::from_kicad_sym(sym).unwrap();
+    let mut writer = SchLibWriter::new();
+    for comp in symbols.components().unwrap() {
+        writer.add_component(&comp);
+    }
+    let mut schlib = Vec::new();
+    writer.write_to(&mut schlib).unwrap();
+    let pcblib = fs::read(PCBLIB_SIMPLE).unwrap();
+
+    let buf = intlib(&[("/SchLib/0", &schlib), ("/PCBLib/0", &pcblib)]);
+    let intlib = IntLib::from_buffer(&buf).unwrap();
+    println!("{intlib:#?}");
+
+    let names: Vec<_> = intlib.schlibs().map(|(name, _)| name).collect();
+    assert_eq!(names, ["SchLib/0"]);
+    let (name, lib) = intlib.pcblibs().next().unwrap();
+    assert_eq!(name, "PCBLib/0");
+    assert_eq!(
+        lib.footprint_meta().len(),
+        PcbLib::open(PCBLIB_SIMPLE).unwrap().footprint_meta().len()
+    );
+
+    assert_eq!(
+        intlib.try_get_component("Cap").unwrap().unwrap().name(),
+        "Cap"
+    );
+    let footprint = intlib.try_get_footprint("capc1608x09l").unwrap().unwrap();
+    assert_eq!(footprint.name(), "CAPC1608X09L");
+    assert!(intlib.try_get_footprint("NOPE").unwrap().is_none());
+
+    let refs = intlib.cross_refs().unwrap();
+    assert_eq!(refs.len(), 2);
+    assert_eq!(refs[0].libref(), "Cap");
+    assert_eq!(refs[0].schlib(), 0);
+    assert_eq!(refs[0].parameter("manufacturer"), Some("Acme"));
+    let fp = &refs[0].footprints()[0];
+    assert_eq!(
+        (fp.name(), fp.is_current(), fp.pcblib()),
+        ("CAPC1608X09L", true, Some(0))
+    );
+
+    assert_eq!(refs[1].libref(), "Missing");
+    assert_eq!(refs[1].footprints()[0].pcblib(), None);
+}
@@ -0,0 +1,97 @@
+include!("include_test_util.rs");
+
+use altium::matlib::{Dimension, MatLib, MaterialKind, SurfaceFinish};
+use altium::Rgb;
+
+const SIMPLE: &str = "tests/samples/matlib/simple-matlib.xml";
+
+#[test]
+fn test_parse() {
+    test_init_once();
+
+    let lib = MatLib::open(SIMPLE).unwrap();
+    assert_eq!(lib.library_id(), "7da0bbdf-506b-45fd-b330-696f14e1f8e5");
+    assert_eq!(lib.version(), "1.1.0.0");
+
+    let kinds: Vec<_> = lib
+        .materials()
+        .iter()
+        .map(|mat| mat.kind().clone())
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            MaterialKind::SolderMask,
+            MaterialKind::Coverlay,
+            MaterialKind::Copper,
+            MaterialKind::SurfaceFinish(SurfaceFinish::Osp),
+            MaterialKind::SurfaceFinish(SurfaceFinish::ImmersionTin),
+            MaterialKind::SurfaceFinish(SurfaceFinish::ImmersionGold),
+            MaterialKind::SurfaceFinish(SurfaceFinish::Hasl),
+            MaterialKind::SurfaceFinish(SurfaceFinish::Enig),
+            MaterialKind::Core,
+            MaterialKind::Prepreg,
+        ]
+    );
+
+    let mask = &lib.materials()[0];
+    assert_eq!(mask.name(), Some("Test Solder Mask 2"));
+    assert_eq!(mask.manufacturer(), Some("TestMfgrSM"));
+    assert_eq!(mask.dielectric_constant(), Some(4.0));
+    assert_eq!(mask.loss_tangent(), Some(0.01));
+    assert_eq!(mask.thickness().unwrap().to_nm(), Some(50_000));
+    assert_eq!(mask.frequency().unwrap().si_value(), Some(1e9));
+    assert_eq!(mask.color(), Some((Rgb::from_hex(0xf0, 0xf0, 0xf0), 0xf0)));
+
+    let core = lib.materials_of(&MaterialKind::Core).next().unwrap();
+    assert_eq!(core.name(), Some("Core Product Name"));
+    assert_eq!(core.constructions(), Some("1080"));
+    assert_eq!(core.dielectric_constant(), Some(4.36));
+    assert_eq!(core.resin_content().unwrap().si_value(), Some(0.4));
+    let tg = core.glass_transition_temp().unwrap();
+    assert_eq!(tg.dimension(), &Dimension::Temperature);
+    assert_eq!((tg.value(), tg.unit()), (180.0, "C"));
+
+    // Revision IDs aren't always valid GUIDs
+    let prepreg = lib.get("206B4F47-5A8D-42D4-95CC-3E5A961B51AA").unwrap();
+    assert_eq!(prepreg.kind(), &MaterialKind::Prepreg);
+    assert_eq!(
+        prepreg.revision_id(),
+        "3fffevb0-15a4-4be5-90f9-30df6eb698b5"
+    );
+    assert_eq!(prepreg.thickness().unwrap().to_nm(), Some(100_000));
+    assert_eq!(prepreg.loss_tangent(), Some(0.001));
+
+    let enig = lib
+        .materials_of(&MaterialKind::SurfaceFinish(SurfaceFinish::Enig))
+        .next()
+        .unwrap();
+    assert_eq!(enig.name(), Some("Goooooold"));
+    assert_eq!(enig.process(), Some("enigtest"));
+}
+
+#[test]
+fn test_units() {
+    test_init_once();
+
+    let lib = MatLib::open(SIMPLE).unwrap();
+    let coverlay = &lib.materials()[1];
+    let strength = coverlay.property("DielectricStrength").unwrap();
+    let altium::matlib::PropertyValue::Dim(strength) = &strength.value else {
+        panic!("expected a DimValue");
+    };
+    assert_eq!(strength.unit(), "kV/mm");
+    assert_eq!(strength.si_value(), Some(110e6));
+
+    let resistivity = coverlay.property("VolumeResistivity").unwrap();
+    let altium::matlib::PropertyValue::Dim(resistivity) = &resistivity.value else {
+        panic!("expected a DimValue");
+    };
+    assert_eq!(resistivity.value(), 1e12);
+    assert_eq!(resistivity.unit(), "ohm*cm");
+    assert_eq!(resistivity.si_value(), Some(1e10));
+
+    let copper = &lib.materials()[2];
+    assert_eq!(copper.weight().unwrap().unit(), "g");
+    assert_eq!(copper.thickness().unwrap().to_nm(), Some(35_000));
+}
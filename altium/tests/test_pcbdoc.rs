@@ -0,0 +1,1321 @@
+include!("include_test_util.rs");
+
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::{env, fs};
+
+use altium::draw::RenderOptions;
+use altium::pcb::drc::{self, DrcInput, DrcOptions, ViolationKind};
+use altium::pcb::fab::{
+    BomPart,
+    FabFile,
+    FabFileKind,
+    FabOptions,
+    Ipc2581Options,
+    PlacementOptions,
+    PlacementOrigin,
+    Units,
+};
+use altium::pcb::record::{Pad, PadShape, PadSize, Region, RegionKind, Track, Via};
+use altium::pcb::rule::Limits;
+use altium::pcb::{
+    Component,
+    DesignRules,
+    DrillCount,
+    HatchStyle,
+    Layer,
+    MechanicalKind,
+    PcbRecord,
+    PolygonKind,
+    PourOver,
+    Rule,
+    RuleKind,
+    StackLayer,
+    StackLayerKind,
+};
+use altium::{Location, PcbDoc, Rgb};
+
+const PCBDOC_SIMPLE_EXTRACTED: &str = "tests/samples/pcbdoc/simple-extracted";
+const IPC2581_XSD: &str = "tests/samples/ipc2581/IPC-2581C.xsd";
+
+/// Reassemble a document from its extracted storages and streams
+fn pcbdoc_from_extracted(dir: &str) -> Vec<u8> {
+    fn add_dir<F: std::io::Read + Write + std::io::Seek>(
+        cfile: &mut cfb::CompoundFile<F>,
+        dir: &Path,
+        storage: &str,
+    ) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let entry = entry.unwrap();
+            let name = entry.file_name().into_string().unwrap();
+            let path = format!("{storage}/{name}");
+            if entry.file_type().unwrap().is_dir() {
+                cfile.create_storage(&path).unwrap();
+                add_dir(cfile, &entry.path(), &path);
+            } else {
+                let data = fs::read(entry.path()).unwrap();
+                cfile
+                    .create_stream(&path)
+                    .unwrap()
+                    .write_all(&data)
+                    .unwrap();
+            }
+        }
+    }
+
+    let mut cfile = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    add_dir(&mut cfile, Path::new(dir), "");
+    cfile.flush().unwrap();
+    cfile.into_inner().into_inner()
+}
+
+#[test]
+fn test_models() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    let models = pcbdoc.models().unwrap();
+    assert_eq!(models.len(), 1);
+
+    let model = models.iter().next().unwrap();
+    assert_eq!(model.name(), "CAPC1608X09L.step");
+    assert_eq!(model.checksum(), 1_687_013_168);
+    assert!(model.step_data().unwrap().starts_with(b"ISO-10303-21;"));
+
+    let bodies = pcbdoc.component_bodies().unwrap();
+    println!("{bodies:#?}");
+    assert!(!bodies.is_empty());
+    let body = bodies
+        .iter()
+        .find(|body| body.model_embedded)
+        .expect("a body with an embedded model");
+    assert!(models.get(&body.model_id).is_some(), "{body:#?}");
+}
+
+#[test]
+fn test_board() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    assert_eq!(
+        pcbdoc.nets().unwrap(),
+        ["Net4", "Net3", "Net2", "Net1"].map(Box::<str>::from)
+    );
+    assert_eq!(pcbdoc.copper_layers().unwrap(), [Layer::Top, Layer::Bottom]);
+    assert_eq!(pcbdoc.origin().unwrap().x(), 39_999_999);
+
+    // A 60x40 mm rectangle starting at the origin
+    let outline = pcbdoc.board_outline().unwrap();
+    assert!(outline.len() >= 4, "{outline:?}");
+    let max_x = outline.iter().map(|loc| loc.x()).max().unwrap();
+    assert_eq!(max_x - pcbdoc.origin().unwrap().x(), 59_999_999);
+
+    let records = pcbdoc.records().unwrap();
+    assert!(records.iter().any(|rec| matches!(rec, PcbRecord::Pad(_))));
+    assert!(records.iter().any(|rec| matches!(rec, PcbRecord::Via(_))));
+    // Regions come from `ShapeBasedRegions6` only, not duplicated from
+    // `Regions6`
+    let regions = records
+        .iter()
+        .filter(|rec| matches!(rec, PcbRecord::Region(_)))
+        .count();
+    assert_eq!(regions, 2);
+}
+
+#[test]
+fn test_layers() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    let layers = pcbdoc.layers().unwrap();
+
+    let courtyard = layers.get(Layer::Mechanical(15)).unwrap();
+    assert_eq!(courtyard.name(), "Top Courtyard");
+    assert!(courtyard.is_enabled());
+    assert_eq!(
+        courtyard.mechanical_kind(),
+        Some(MechanicalKind::CourtyardTop)
+    );
+    assert_eq!(
+        layers.find_kind(MechanicalKind::Body3DBottom),
+        Some(Layer::Mechanical(14))
+    );
+    assert_eq!(
+        layers.mechanical_kind(Layer::Mechanical(3)),
+        Some(MechanicalKind::AssemblyTop)
+    );
+    assert!(!layers.get(Layer::Mechanical(2)).unwrap().is_enabled());
+    assert!(layers.get(Layer::Mechanical(32)).is_some());
+    assert!(layers.get(Layer::Mid(30)).is_some());
+    assert!(layers.get(Layer::InternalPlane(16)).is_some());
+    assert!(layers.get(Layer::Top).unwrap().is_used());
+
+    assert_eq!(
+        layers.paired_layer(Layer::Mechanical(16)),
+        Some(Layer::Mechanical(15))
+    );
+    assert_eq!(layers.mechanical_pairs().len(), 3);
+    assert_eq!(layers.drill_pairs(), [(Layer::Top, Layer::Bottom)]);
+
+    for info in layers.layers() {
+        let layer = info.layer();
+        assert_eq!(Layer::from_v7_id(layer.to_v7_id().unwrap()), Some(layer));
+        if let Some(id) = layer.to_v6_id() {
+            assert_eq!(Layer::from_v6_id(id), layer);
+        }
+    }
+}
+
+#[test]
+fn test_rules() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    let rules = pcbdoc.rules().unwrap();
+    assert_eq!(rules.len(), 35);
+
+    let clearance = rules.get("Clearance").unwrap();
+    assert_eq!(clearance.kind(), &RuleKind::Clearance { gap: 200_000 });
+    assert_eq!(clearance.scope1(), "All");
+    assert!(clearance.is_enabled());
+    assert_eq!(
+        rules.get("Width").unwrap().kind(),
+        &RuleKind::Width(Limits {
+            min: 200_000,
+            max: 200_000,
+            preferred: 200_000
+        })
+    );
+
+    let fanout: Vec<_> = rules.of_kind("FanoutControl").map(Rule::name).collect();
+    assert_eq!(
+        fanout,
+        [
+            "Fanout_BGA",
+            "Fanout_LCC",
+            "Fanout_SOIC",
+            "Fanout_Small",
+            "Fanout_Default"
+        ]
+    );
+    let small = rules.get("Fanout_Small").unwrap();
+    assert_eq!(small.kind(), &RuleKind::Other("FanoutControl".into()));
+    assert_eq!(small.scope1(), "(CompPinCount < 5)");
+    assert_eq!(small.param("FANOUTDIRECTION"), Some("OutThenIn"));
+
+    // Unchanged rules are written back byte for byte
+    let orig = fs::read(Path::new(PCBDOC_SIMPLE_EXTRACTED).join("Rules6/Data")).unwrap();
+    assert_eq!(rules.to_buf(), orig);
+
+    // Transplant modified rules into another board
+    let mut rules = rules;
+    let mut power = Rule::new("Power", RuleKind::Width(Limits::default())).unwrap();
+    power.set_scope1("InNetClass('Power')");
+    power
+        .set_kind_params(RuleKind::Width(Limits {
+            min: 254_000,
+            max: 1_270_000,
+            preferred: 508_000,
+        }))
+        .unwrap();
+    power.set_priority(2);
+    rules.insert(power);
+    rules
+        .get_mut("Clearance")
+        .unwrap()
+        .set_param("GAP", "0.2mm")
+        .unwrap();
+    assert!(rules.remove("NetAntennae").is_some());
+
+    let mut out_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    out_dir.extend(["test_output", "rules"]);
+    fs::create_dir_all(&out_dir).unwrap();
+    let out_path = out_dir.join("Rules.PcbDoc");
+    fs::write(&out_path, &buf).unwrap();
+    rules.save_to(&out_path).unwrap();
+
+    let saved = PcbDoc::open(&out_path).unwrap().rules().unwrap();
+    assert_eq!(saved, rules);
+    assert_eq!(saved.len(), 35);
+    let power = saved.get("Power").unwrap();
+    assert_eq!(power.scope1(), "InNetClass('Power')");
+    assert_eq!(power.param("PREFEREDWIDTH"), Some("20mil"));
+    assert_eq!(
+        saved.get("Clearance").unwrap().kind(),
+        &RuleKind::Clearance { gap: 200_000 }
+    );
+    assert!(Rule::new("Nope", RuleKind::Other("Nope".into())).is_err());
+}
+
+#[test]
+fn test_fab_outputs() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    let options = FabOptions {
+        name: "simple".to_owned(),
+        ..Default::default()
+    };
+    let out = pcbdoc.fab_outputs(&options).unwrap();
+    let names: Vec<_> = out.files().iter().map(FabFile::name).collect();
+    println!("{names:?}");
+
+    let top = out.get("simple-top_layer.gbr").unwrap();
+    assert_eq!(top.kind(), FabFileKind::Gerber(Layer::Top));
+    let gbr = top.contents();
+    assert!(gbr.contains("%TF.FileFunction,Copper,L1,Top,Signal*%"));
+    assert!(gbr.contains("%FSLAX46Y46*%"));
+    assert!(gbr.contains("%TA.AperFunction,SMDPad,CuDef*%"));
+    assert!(gbr.contains("%TA.AperFunction,ViaPad*%"));
+    assert!(gbr.contains("%TO.N,Net1*%"));
+    // The polygon pour, which takes its net from the polygon
+    let pour = gbr.find("G36*").unwrap();
+    assert!(gbr[..pour].contains("%TO.N,Net1*%"), "{gbr}");
+    assert!(!gbr.contains("%LPC*%"), "{gbr}");
+    assert!(gbr.ends_with("M02*\n"));
+
+    let mask = out.get("simple-top_solder.gbr").unwrap().contents();
+    assert!(mask.contains("%TF.FilePolarity,Negative*%"));
+    assert!(mask.contains("D03*"));
+
+    let drill = out.get("simple-PTH.drl").unwrap();
+    assert_eq!(
+        drill.kind(),
+        FabFileKind::Drill {
+            plated: true,
+            from: 1,
+            to: 2
+        }
+    );
+    let drl = drill.contents();
+    assert!(drl.starts_with("M48\n"));
+    assert!(drl.contains("; #@! TF.FileFunction,Plated,1,2,PTH"));
+    assert!(drl.contains("T1C0.200\n"));
+    assert!(drl.contains("X18.000Y15.000\n"));
+    assert!(out.get("simple-NPTH.drl").is_none());
+
+    let job = out.get("simple.gbrjob").unwrap().contents();
+    for name in names.iter().filter(|name| !name.ends_with(".gbrjob")) {
+        assert!(job.contains(&format!("\"Path\": \"{name}\"")), "{name}");
+    }
+    assert!(job.contains("\"LayerNumber\": 2"));
+}
+
+/// The sample document with some `Board6` properties changed
+fn pcbdoc_with_board_properties(edits: &[(&str, &str)]) -> Vec<u8> {
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let mut cfile = cfb::CompoundFile::open(Cursor::new(buf)).unwrap();
+    let mut data = Vec::new();
+    cfile
+        .open_stream("/Board6/Data")
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+
+    let mut props: Vec<u8> = Vec::new();
+    for field in data[4..].split(|&b| b == b'|') {
+        let key = field.split(|&b| b == b'=').next().unwrap();
+        if !props.is_empty() || !field.is_empty() {
+            props.push(b'|');
+        }
+        match edits.iter().find(|(k, _)| k.as_bytes() == key) {
+            Some((k, v)) => props.extend_from_slice(format!("{k}={v}").as_bytes()),
+            None => props.extend_from_slice(field),
+        }
+    }
+    if !props.ends_with(&[0]) {
+        props.push(0);
+    }
+
+    let mut stream = cfile.create_stream("/Board6/Data").unwrap();
+    let len = u32::try_from(props.len()).unwrap();
+    stream.write_all(&len.to_le_bytes()).unwrap();
+    stream.write_all(&props).unwrap();
+    drop(stream);
+    cfile.flush().unwrap();
+    cfile.into_inner().into_inner()
+}
+
+#[test]
+fn test_plane_clearances() {
+    test_init_once();
+
+    // Two internal planes between top and bottom, the first on the vias' net
+    let buf = pcbdoc_with_board_properties(&[
+        ("LAYER1NEXT", "39"),
+        ("LAYER39PREV", "1"),
+        ("LAYER39NEXT", "40"),
+        ("LAYER40PREV", "39"),
+        ("LAYER40NEXT", "32"),
+        ("LAYER32PREV", "40"),
+        ("PLANE1NETNAME", "Net1"),
+    ]);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    let out = pcbdoc.fab_outputs(&FabOptions::default()).unwrap();
+
+    // Both vias are on Net1 and connect to the first plane
+    let plane1 = out.get("board-internal_plane_1.gbr").unwrap().contents();
+    assert!(plane1.contains("%TF.FileFunction,Copper,L2,Inr,Plane*%"));
+    assert!(plane1.contains("%TF.FilePolarity,Negative*%"));
+    assert!(!plane1.contains("D03*"), "{plane1}");
+
+    // The second plane has no net, so the vias are cleared by 20 mil
+    let plane2 = out.get("board-internal_plane_2.gbr").unwrap().contents();
+    assert!(plane2.contains("%TF.FileFunction,Copper,L3,Inr,Plane*%"));
+    assert!(plane2.contains("%TA.AperFunction,AntiPad*%"), "{plane2}");
+    assert!(plane2.contains("%ADD10C,1.216000*%"), "{plane2}");
+    assert!(plane2.contains("X18000002Y15000000D03*"), "{plane2}");
+    assert_eq!(plane2.matches("D03*").count(), 2, "{plane2}");
+    assert!(!plane2.contains("ViaPad"), "{plane2}");
+}
+
+#[test]
+fn test_components() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    let components = pcbdoc.components().unwrap();
+    let footprints: Vec<_> = components.iter().map(Component::footprint).collect();
+    assert_eq!(footprints, ["TPS180", "FID90X190", "CAPC1608X09L"]);
+
+    let cap = &components[2];
+    assert_eq!(cap.layer(), Layer::Top);
+    assert_eq!(cap.rotation(), 0.0);
+    assert_eq!(cap.height(), 900_001);
+    assert_eq!(cap.unique_id().unwrap().to_string(), "IRPESYIC");
+}
+
+#[test]
+fn test_pick_and_place() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    let csv = pcbdoc.pick_and_place(&PlacementOptions::default()).unwrap();
+    println!("{csv}");
+    let lines: Vec<_> = csv.lines().collect();
+    assert!(lines[0].starts_with("Designator,Footprint,Description,Center-X(mm),Center-Y(mm)"));
+    assert_eq!(lines.len(), 4);
+
+    // The capacitor's center is between its two pads, 21 mm above the origin
+    let cap: Vec<_> = lines[3].split(',').collect();
+    assert_eq!(cap[1], "CAPC1608X09L");
+    assert_eq!(
+        &cap[cap.len() - 6..],
+        ["8.0000", "21.0000", "8.0000", "21.0000", "0", "Top"]
+    );
+
+    let options = PlacementOptions {
+        origin: PlacementOrigin::Absolute,
+        units: Units::Mil,
+    };
+    let csv = pcbdoc.pick_and_place(&options).unwrap();
+    assert!(csv.contains(",1889.76,2401.57,"), "{csv}");
+}
+
+#[test]
+fn test_ipc356() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    let netlist = pcbdoc.ipc356_netlist(&FabOptions::default()).unwrap();
+    println!("{netlist}");
+
+    assert!(netlist.contains("P  UNITS CUST 1\n"));
+    assert!(netlist.ends_with("999\n"));
+    let records: Vec<_> = netlist
+        .lines()
+        .filter(|line| line.starts_with("317") || line.starts_with("327"))
+        .collect();
+    // Four pads and two vias
+    assert_eq!(records.len(), 6);
+    // The fixed columns line up
+    for record in &records {
+        assert_eq!(record.len(), 74, "{record}");
+        assert_eq!(&record[26..27], "-");
+        assert_eq!(&record[38..39], "A");
+    }
+
+    let pad = records.iter().find(|r| r.starts_with("327Net1")).unwrap();
+    assert_eq!(&pad[38..57], "A01X+008000Y+011000");
+    assert_eq!(&pad[57..], "X1850Y1850R000 S1");
+    let via = records.iter().find(|r| r.starts_with("317")).unwrap();
+    assert!(via.contains("VIA   -     D0200P"), "{via}");
+}
+
+/// An element with its attributes and children
+#[derive(Debug)]
+struct XmlNode {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<XmlNode>,
+}
+
+impl XmlNode {
+    fn parse(xml: &str) -> Self {
+        use quick_xml::events::{BytesStart, Event};
+
+        fn node(e: &BytesStart) -> XmlNode {
+            let attrs = e
+                .attributes()
+                .map(|attr| {
+                    let attr = attr.unwrap();
+                    let key = String::from_utf8(attr.key.as_ref().to_vec()).unwrap();
+                    (key, attr.unescape_value().unwrap().into_owned())
+                })
+                .collect();
+            XmlNode {
+                name: String::from_utf8(e.name().as_ref().to_vec()).unwrap(),
+                attrs,
+                children: Vec::new(),
+            }
+        }
+
+        let mut reader = quick_xml::Reader::from_str(xml);
+        let mut stack = vec![XmlNode {
+            name: String::new(),
+            attrs: Vec::new(),
+            children: Vec::new(),
+        }];
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(e) => stack.push(node(&e)),
+                Event::Empty(e) => stack.last_mut().unwrap().children.push(node(&e)),
+                Event::End(_) => {
+                    let done = stack.pop().unwrap();
+                    stack.last_mut().unwrap().children.push(done);
+                }
+                Event::Text(text) => assert!(text.trim_ascii().is_empty()),
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+        assert_eq!(stack.len(), 1, "unclosed elements");
+        stack.pop().unwrap().children.pop().unwrap()
+    }
+
+    fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn child(&self, name: &str) -> &XmlNode {
+        self.children.iter().find(|c| c.name == name).unwrap()
+    }
+
+    fn all(&self) -> Vec<&XmlNode> {
+        let mut ret = vec![self];
+        for child in &self.children {
+            ret.extend(child.all());
+        }
+        ret
+    }
+}
+
+const MANY: usize = usize::MAX;
+
+/// Alternative element names, with the minimum and maximum count
+type Particle = (&'static str, usize, usize);
+
+/// Child order of the IPC-2581C elements that are written, as a sequence of
+/// (choice of names, min, max). Transcribed from the content models in the
+/// IPC-2581C schema (`IPC-2581C.xsd`); the comments give the path in the schema
+/// that each group of entries comes from. Children that we never write are left
+/// out.
+const IPC2581_CHILDREN: &[(&str, &[Particle])] = &[
+    // IPC-2581 (root)
+    (
+        "IPC-2581",
+        &[
+            ("Content", 1, 1),
+            ("LogisticHeader", 0, 1),
+            ("HistoryRecord", 0, 1),
+            ("Bom", 0, MANY),
+            ("Ecad", 0, 1),
+        ],
+    ),
+    // IPC-2581/Content
+    (
+        "Content",
+        &[
+            ("FunctionMode", 1, 1),
+            ("StepRef", 1, MANY),
+            ("LayerRef", 1, MANY),
+            ("BomRef", 0, MANY),
+            ("DictionaryStandard", 0, 1),
+        ],
+    ),
+    // IPC-2581/Content/DictionaryStandard
+    ("DictionaryStandard", &[("EntryStandard", 1, MANY)]),
+    (
+        "EntryStandard",
+        &[("Circle|RectCenter|Oval|RectRound|RectCham", 1, 1)],
+    ),
+    // IPC-2581/LogisticHeader
+    (
+        "LogisticHeader",
+        &[
+            ("Role", 1, MANY),
+            ("Enterprise", 1, MANY),
+            ("Person", 0, MANY),
+        ],
+    ),
+    // IPC-2581/Bom
+    ("Bom", &[("BomHeader", 1, 1), ("BomItem", 1, MANY)]),
+    ("BomHeader", &[("StepRef", 0, MANY)]),
+    ("BomItem", &[("RefDes", 0, MANY), ("Characteristics", 1, 1)]),
+    ("Characteristics", &[("Textual", 0, MANY)]),
+    // IPC-2581/Ecad
+    ("Ecad", &[("CadHeader", 1, 1), ("CadData", 1, 1)]),
+    // IPC-2581/Ecad/CadHeader
+    ("CadHeader", &[("Spec", 0, MANY)]),
+    ("Spec", &[("Dielectric", 1, MANY)]),
+    ("Dielectric", &[("Property", 1, MANY)]),
+    // IPC-2581/Ecad/CadData
+    (
+        "CadData",
+        &[("Layer", 1, MANY), ("Stackup", 0, MANY), ("Step", 1, MANY)],
+    ),
+    ("Layer", &[("Span", 0, 1)]),
+    // IPC-2581/Ecad/CadData/Stackup
+    ("Stackup", &[("StackupGroup", 1, MANY)]),
+    ("StackupGroup", &[("StackupLayer", 1, MANY)]),
+    ("StackupLayer", &[("SpecRef", 0, MANY)]),
+    // IPC-2581/Ecad/CadData/Step
+    (
+        "Step",
+        &[
+            ("NonstandardAttribute", 0, MANY),
+            ("PadStackDef", 0, MANY),
+            ("Datum", 1, 1),
+            ("Profile", 1, 1),
+            ("Package", 0, MANY),
+            ("Component", 0, MANY),
+            ("LogicalNet", 0, MANY),
+            ("LayerFeature", 0, MANY),
+        ],
+    ),
+    // IPC-2581/Ecad/CadData/Step/PadStackDef
+    (
+        "PadStackDef",
+        &[("PadstackHoleDef", 0, 1), ("PadstackPadDef", 0, MANY)],
+    ),
+    (
+        "PadstackPadDef",
+        &[
+            ("Xform", 0, 1),
+            ("Location", 1, 1),
+            ("StandardPrimitiveRef", 1, 1),
+        ],
+    ),
+    // IPC-2581/Ecad/CadData/Step/Profile
+    ("Profile", &[("Polygon", 1, 1), ("Cutout", 0, MANY)]),
+    // IPC-2581/Ecad/CadData/Step/Profile (shared polygon types)
+    (
+        "Polygon|Cutout",
+        &[
+            ("PolyBegin", 1, 1),
+            ("PolyStepSegment|PolyStepCurve", 1, MANY),
+        ],
+    ),
+    // IPC-2581/Ecad/CadData/Step/Package
+    ("Package", &[("Outline", 1, 1), ("Pin", 0, MANY)]),
+    ("Outline", &[("Polygon", 1, 1), ("LineDesc", 1, 1)]),
+    (
+        "Pin",
+        &[
+            ("Xform", 0, 1),
+            ("Location", 1, 1),
+            ("StandardPrimitiveRef", 0, 1),
+        ],
+    ),
+    // IPC-2581/Ecad/CadData/Step/Component
+    ("Component", &[("Xform", 0, 1), ("Location", 1, 1)]),
+    // IPC-2581/Ecad/CadData/Step/LogicalNet
+    ("LogicalNet", &[("PinRef", 0, MANY)]),
+    // IPC-2581/Ecad/CadData/Step/LayerFeature
+    ("LayerFeature", &[("Set", 1, MANY)]),
+    (
+        "Set",
+        &[("Pad", 0, MANY), ("Hole", 0, MANY), ("Features", 0, MANY)],
+    ),
+    // IPC-2581/Ecad/CadData/Step/LayerFeature/Set
+    (
+        "Pad",
+        &[
+            ("Xform", 0, 1),
+            ("Location", 1, 1),
+            ("StandardPrimitiveRef", 1, 1),
+            ("PinRef", 0, 1),
+        ],
+    ),
+    (
+        "Features",
+        &[("Location", 1, 1), ("Line|Arc|Contour", 1, 1)],
+    ),
+    // IPC-2581/Ecad/CadData/Step/LayerFeature/Set/Features
+    ("Line|Arc", &[("LineDesc", 1, 1)]),
+    ("Contour", &[("Polygon", 1, 1), ("Cutout", 0, MANY)]),
+];
+
+/// Attributes that IPC-2581C requires (`use="required"` in `IPC-2581C.xsd`),
+/// with the schema path of each group of elements
+const IPC2581_ATTRS: &[(&str, &[&str])] = &[
+    // IPC-2581 (root)
+    ("IPC-2581", &["revision"]),
+    // IPC-2581/Content
+    ("Content", &["roleRef"]),
+    ("FunctionMode", &["mode"]),
+    // IPC-2581/Content/*Ref, Bom, Ecad and Step
+    (
+        "StepRef|LayerRef|BomRef|Bom|Ecad|Step|PadStackDef",
+        &["name"],
+    ),
+    // IPC-2581/Content/DictionaryStandard and references to its entries
+    ("EntryStandard|StandardPrimitiveRef|SpecRef", &["id"]),
+    // IPC-2581/LogisticHeader
+    ("Role", &["id", "roleFunction"]),
+    ("Enterprise", &["id", "code"]),
+    ("Person", &["name", "enterpriseRef", "roleRef"]),
+    // IPC-2581/Bom
+    ("BomHeader", &["assembly", "revision"]),
+    ("BomItem", &["OEMDesignNumberRef", "quantity", "category"]),
+    ("RefDes", &["name", "packageRef", "populate", "layerRef"]),
+    // IPC-2581/Ecad/CadHeader
+    ("CadHeader", &["units"]),
+    // IPC-2581/Ecad/CadData
+    ("Layer", &["name", "layerFunction", "side", "polarity"]),
+    ("Span", &["fromLayer", "toLayer"]),
+    // IPC-2581/Ecad/CadData/Stackup
+    (
+        "Stackup",
+        &[
+            "name",
+            "overallThickness",
+            "whereMeasured",
+            "tolPlus",
+            "tolMinus",
+        ],
+    ),
+    (
+        "StackupLayer",
+        &[
+            "layerOrGroupRef",
+            "thickness",
+            "tolPlus",
+            "tolMinus",
+            "sequence",
+        ],
+    ),
+    // IPC-2581/Ecad/CadData/Step/PadStackDef and LayerFeature/Set
+    (
+        "PadstackHoleDef|Hole",
+        &[
+            "name",
+            "diameter",
+            "platingStatus",
+            "plusTol",
+            "minusTol",
+            "x",
+            "y",
+        ],
+    ),
+    // IPC-2581/Ecad/CadData/Step/PadStackDef
+    ("PadstackPadDef", &["layerRef", "padUse"]),
+    // IPC-2581/Ecad/CadData/Step (shared location and polygon types)
+    ("Location|Datum|PolyBegin|PolyStepSegment", &["x", "y"]),
+    // IPC-2581/Ecad/CadData/Step/Package
+    ("Package", &["name", "type"]),
+    ("Pin", &["number"]),
+    // IPC-2581/Ecad/CadData/Step/Component
+    (
+        "Component",
+        &["refDes", "packageRef", "layerRef", "mountType"],
+    ),
+    // IPC-2581/Ecad/CadData/Step/LogicalNet
+    ("LogicalNet", &["name"]),
+    ("PinRef", &["componentRef", "pin"]),
+    // IPC-2581/Ecad/CadData/Step/LayerFeature
+    ("LayerFeature", &["layerRef"]),
+    // IPC-2581/Ecad/CadData/Step/LayerFeature/Set/Features
+    ("Line", &["startX", "startY", "endX", "endY"]),
+    (
+        "Arc",
+        &[
+            "startX",
+            "startY",
+            "endX",
+            "endY",
+            "centerX",
+            "centerY",
+            "clockwise",
+        ],
+    ),
+    // IPC-2581/Ecad/CadData/Step/LayerFeature/Set/Features (shared line type)
+    ("LineDesc", &["lineEnd", "lineWidth"]),
+    // IPC-2581/Content/DictionaryStandard/EntryStandard
+    ("Circle", &["diameter"]),
+    ("RectCenter|Oval", &["width", "height"]),
+];
+
+fn matches_name(pattern: &str, name: &str) -> bool {
+    pattern.split('|').any(|alt| alt == name)
+}
+
+/// Check child order, occurrence counts and required attributes. This only
+/// covers what the tables above list, [`test_ipc2581_schema`] checks against
+/// the full schema.
+fn validate_ipc2581(node: &XmlNode) {
+    for (_, required) in IPC2581_ATTRS
+        .iter()
+        .filter(|(names, _)| matches_name(names, &node.name))
+    {
+        for key in *required {
+            assert!(node.attr(key).is_some(), "{} needs `{key}`", node.name);
+        }
+    }
+
+    if let Some((_, sequence)) = IPC2581_CHILDREN
+        .iter()
+        .find(|(names, _)| matches_name(names, &node.name))
+    {
+        let mut children = node.children.iter().peekable();
+        for (names, min, max) in *sequence {
+            let mut count = 0;
+            while children
+                .peek()
+                .is_some_and(|child| matches_name(names, &child.name))
+            {
+                children.next();
+                count += 1;
+            }
+            assert!(
+                (*min..=*max).contains(&count),
+                "{}: {count} of {names}",
+                node.name
+            );
+        }
+        if let Some(extra) = children.next() {
+            panic!("{}: unexpected {}", node.name, extra.name);
+        }
+    }
+
+    for child in &node.children {
+        validate_ipc2581(child);
+    }
+}
+
+fn ipc2581_options() -> Ipc2581Options {
+    Ipc2581Options {
+        fab: FabOptions {
+            name: "simple".to_owned(),
+            ..Default::default()
+        },
+        parameters: vec![("Revision".to_owned(), "B".to_owned())],
+        parts: vec![BomPart {
+            designator: "COMP3".to_owned(),
+            part_number: "GRM188R71H104KA93D".to_owned(),
+            description: "100nF <X7R>".to_owned(),
+            manufacturer: "Murata".to_owned(),
+        }],
+    }
+}
+
+/// Validate the export against the IPC-2581C schema with `xmllint`. The
+/// schema isn't in the repository yet, see `tests/samples/README.md`.
+#[test]
+#[ignore = "needs tests/samples/ipc2581/IPC-2581C.xsd and xmllint"]
+fn test_ipc2581_schema() {
+    test_init_once();
+
+    let schema = Path::new(IPC2581_XSD);
+    assert!(schema.is_file(), "missing {IPC2581_XSD}");
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    let xml = pcbdoc.ipc2581(&ipc2581_options()).unwrap();
+
+    let mut child = Command::new("xmllint")
+        .args(["--noout", "--schema"])
+        .arg(schema)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("xmllint should be installed");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(xml.as_bytes())
+        .unwrap();
+    let out = child.wait_with_output().unwrap();
+    let errors = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "{errors}\n{xml}");
+}
+
+#[test]
+fn test_ipc2581() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+
+    let stackup = pcbdoc.stackup().unwrap();
+    let names: Vec<_> = stackup.iter().map(StackLayer::name).collect();
+    assert_eq!(names.len(), 9);
+    assert_eq!(names[3..6], ["Top Layer", "Dielectric 1", "Bottom Layer"]);
+    assert_eq!(stackup[3].layer(), Some(Layer::Top));
+    assert_eq!(stackup[4].kind(), StackLayerKind::Core);
+    assert_eq!(stackup[4].material(), "FR-4");
+    assert_eq!(stackup[4].thickness(), 320_040);
+    assert_eq!(stackup[4].dielectric_constant(), Some(4.8));
+
+    let xml = pcbdoc.ipc2581(&ipc2581_options()).unwrap();
+    println!("{xml}");
+    let root = XmlNode::parse(&xml);
+    assert_eq!(root.name, "IPC-2581");
+    assert_eq!(root.attr("revision"), Some("C"));
+    validate_ipc2581(&root);
+
+    // Every reference resolves
+    let nodes = root.all();
+    let names_of = |element: &str, key: &str| -> Vec<&str> {
+        nodes
+            .iter()
+            .filter(|node| node.name == element)
+            .filter_map(|node| node.attr(key))
+            .collect()
+    };
+    let layers = names_of("Layer", "name");
+    let primitives = names_of("EntryStandard", "id");
+    let padstacks = names_of("PadStackDef", "name");
+    let packages = names_of("Package", "name");
+    let refdes = names_of("Component", "refDes");
+    let specs = names_of("Spec", "name");
+    let nets = names_of("LogicalNet", "name");
+    for list in [&layers, &primitives, &padstacks, &packages, &refdes] {
+        let mut sorted = list.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), list.len(), "duplicate names in {list:?}");
+    }
+    assert_eq!(names_of("LayerRef", "name"), layers);
+
+    for node in &nodes {
+        let check = |key: &str, targets: &[&str]| {
+            if let Some(val) = node.attr(key) {
+                assert!(targets.contains(&val), "{} {key}={val}", node.name);
+            }
+        };
+        for key in ["layerRef", "fromLayer", "toLayer"] {
+            check(key, &layers);
+        }
+        if node.name == "StackupLayer" {
+            check("layerOrGroupRef", &layers);
+        }
+        if node.name == "StandardPrimitiveRef" {
+            check("id", &primitives);
+        }
+        if node.name == "SpecRef" {
+            check("id", &specs);
+        }
+        if node.name == "Set" {
+            check("net", &nets);
+        }
+        check("padstackDefRef", &padstacks);
+        check("packageRef", &packages);
+        check("componentRef", &refdes);
+    }
+
+    // Stackup, with solder mask and dielectric but not overlay or paste
+    let stackup = root.child("Ecad").child("CadData").child("Stackup");
+    assert_eq!(stackup.attr("overallThickness"), Some("0.41148"));
+    let stackup_layers: Vec<_> = stackup.child("StackupGroup").children.iter().collect();
+    let refs: Vec<_> = stackup_layers
+        .iter()
+        .filter_map(|layer| layer.attr("layerOrGroupRef"))
+        .collect();
+    assert_eq!(
+        refs,
+        [
+            "TOP_SOLDER",
+            "TOP_LAYER",
+            "DIELECTRIC_1",
+            "BOTTOM_LAYER",
+            "BOTTOM_SOLDER"
+        ]
+    );
+    assert_eq!(
+        stackup_layers[2].child("SpecRef").attr("id"),
+        Some("SPEC_DIELECTRIC_1")
+    );
+
+    // Components, packages and the BOM
+    assert_eq!(refdes, ["COMP1", "COMP2", "COMP3"]);
+    assert_eq!(packages, ["TPS180", "FID90X190", "CAPC1608X09L"]);
+    let bom_refs = names_of("RefDes", "name");
+    assert_eq!(bom_refs.len(), 3);
+    let cap_item = nodes
+        .iter()
+        .find(|node| node.attr("OEMDesignNumberRef") == Some("GRM188R71H104KA93D"))
+        .unwrap();
+    assert_eq!(cap_item.attr("quantity"), Some("1"));
+    assert_eq!(cap_item.child("RefDes").attr("name"), Some("COMP3"));
+    assert!(xml.contains("textualCharacteristicValue=\"100nF &lt;X7R&gt;\""));
+    let cap = nodes
+        .iter()
+        .find(|node| node.name == "Component" && node.attr("refDes") == Some("COMP3"))
+        .unwrap();
+    assert_eq!(cap.attr("part"), Some("GRM188R71H104KA93D"));
+    assert_eq!(cap.child("Location").attr("y"), Some("21.000001"));
+
+    // Project parameters
+    let step = root.child("Ecad").child("CadData").child("Step");
+    assert_eq!(step.children[0].attr("name"), Some("Revision"));
+
+    // Nets, padstacks and holes
+    assert_eq!(nets, ["Net4", "Net3", "Net2", "Net1"]);
+    let holes: Vec<_> = nodes.iter().filter(|node| node.name == "Hole").collect();
+    assert_eq!(holes.len(), 2);
+    assert!(holes
+        .iter()
+        .all(|hole| hole.attr("diameter") == Some("0.2")));
+    assert!(layers.contains(&"DRILL_1-2"));
+    assert!(primitives.contains(&"CIRCLE_0.399999"));
+}
+
+#[test]
+fn test_render() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    let out = pcbdoc.svg().unwrap().to_string();
+
+    // Black background with the default top, bottom and via hole colors
+    assert!(out.contains(r##"fill="#000000""##), "{out}");
+    assert!(out.contains(r##"fill="#ff0000""##), "{out}");
+    assert!(out.contains(r##"fill="#0000ff""##), "{out}");
+    assert!(out.contains(r##"fill="#808040""##), "{out}");
+    assert!(out.contains(r#"data-record-type="BoardOutline""#), "{out}");
+    assert!(
+        out.contains(r#"data-record-type="Via" data-net="Net1""#),
+        "{out}"
+    );
+    // The poured region is drawn but its polygon cutout isn't
+    assert_eq!(out.matches(r#"data-record-type="Region""#).count(), 1);
+
+    // Top copper is drawn after bottom copper
+    let top = out.find(r##"fill="#ff0000""##).unwrap();
+    let bottom = out.find(r##"fill="#0000ff""##).unwrap();
+    assert!(bottom < top);
+
+    let options = RenderOptions::default();
+    let mut ctx = pcbdoc.draw_ctx(&options).unwrap();
+    ctx.set_visible(Layer::Top, false);
+    ctx.colors.insert(Layer::Bottom, Rgb::green());
+    let out = pcbdoc.svg_with_ctx(&ctx).unwrap().to_string();
+    assert!(!out.contains("#ff0000"), "{out}");
+    assert!(!out.contains("#0000ff"), "{out}");
+    assert!(out.contains(r##"fill="#00ff00""##), "{out}");
+
+    let out = pcbdoc
+        .svg_with_options(&RenderOptions::monochrome())
+        .unwrap()
+        .to_string();
+    assert!(!out.contains("#ff0000"), "{out}");
+    assert!(out.contains(r##"fill="#ffffff""##), "{out}");
+}
+
+#[test]
+fn test_drc() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    assert_eq!(pcbdoc.drc(&DrcOptions::default()).unwrap(), []);
+
+    // Move a pad of another net onto the routed pad
+    let mut records = pcbdoc.records().unwrap();
+    let PcbRecord::Pad(routed) = records[1].clone() else {
+        panic!("expected a pad");
+    };
+    let PcbRecord::Pad(pad) = &mut records[2] else {
+        panic!("expected a pad");
+    };
+    pad.net = Some(0);
+    pad.location = Location::new(routed.location.x(), routed.location.y() - 1_200_000);
+
+    let nets = pcbdoc.nets().unwrap();
+    let rules = pcbdoc.rules().unwrap();
+    let input = DrcInput {
+        records: &records,
+        nets: &nets,
+        copper_layers: &[Layer::Top, Layer::Bottom],
+        rules: &rules,
+    };
+    let violations: Vec<_> = drc::check(&input, &DrcOptions::default())
+        .into_iter()
+        .filter(|v| v.objects.iter().any(|obj| obj.record == 2))
+        .collect();
+    assert_eq!(violations.len(), 1, "{violations:?}");
+    let v = &violations[0];
+    assert_eq!(
+        v.kind,
+        ViolationKind::Clearance {
+            gap: 0,
+            required: 200_000
+        }
+    );
+    assert_eq!(v.rule.as_deref(), Some("Clearance"));
+    assert_eq!(v.layer, Some(Layer::Top));
+    let ids: Vec<_> = v.objects.iter().map(|obj| obj.record).collect();
+    assert_eq!(ids, [1, 2]);
+    assert!(v.objects.iter().all(|obj| obj.unique_id.is_some()));
+
+    // Synthetic board: a thin track close to another net, an unrouted pad
+    // pair and a via with a small ring next to a pad hole
+    let track = |net, y, width| {
+        PcbRecord::Track(Track {
+            layer: Layer::Top,
+            net: Some(net),
+            start: Location::new(0, y),
+            end: Location::new(10_000_000, y),
+            width,
+            ..Default::default()
+        })
+    };
+    let pad = |net, x| {
+        let size = PadSize {
+            x: 1_500_000,
+            y: 1_500_000,
+            shape: PadShape::Round,
+        };
+        PcbRecord::Pad(Pad {
+            layer: Layer::MultiLayer,
+            net: Some(net),
+            location: Location::new(x, 20_000_000),
+            top: size,
+            middle: size,
+            bottom: size,
+            hole_size: 800_000,
+            plated: true,
+            ..Default::default()
+        })
+    };
+    let records = [
+        track(0, 0, 300_000),
+        track(1, 400_000, 100_000),
+        pad(0, 0),
+        pad(0, 5_000_000),
+        PcbRecord::Via(Via {
+            net: Some(1),
+            location: Location::new(5_800_000, 20_000_000),
+            diameter: 500_000,
+            hole_size: 400_000,
+            start_layer: Layer::Top,
+            end_layer: Layer::Bottom,
+            ..Default::default()
+        }),
+    ];
+    let nets = ["A".into(), "B".into()];
+    let rules = DesignRules::default();
+    let input = DrcInput {
+        records: &records,
+        nets: &nets,
+        copper_layers: &[Layer::Top, Layer::Bottom],
+        rules: &rules,
+    };
+    let violations = drc::check(&input, &DrcOptions::default());
+    let kinds: Vec<_> = violations
+        .iter()
+        .map(|v| (v.kind, v.objects.len()))
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            (
+                ViolationKind::Clearance {
+                    gap: 200_000,
+                    required: 254_000
+                },
+                2
+            ),
+            (
+                ViolationKind::Clearance {
+                    gap: 0,
+                    required: 254_000
+                },
+                2
+            ),
+            (
+                ViolationKind::TrackWidth {
+                    width: 100_000,
+                    required: 254_000
+                },
+                1
+            ),
+            (
+                ViolationKind::AnnularRing {
+                    ring: 50_000,
+                    required: 50_800
+                },
+                1
+            ),
+            (
+                ViolationKind::HoleToHole {
+                    gap: 200_000,
+                    required: 254_000
+                },
+                2
+            ),
+            (ViolationKind::Unrouted, 2),
+        ]
+    );
+    let unrouted = violations.last().unwrap();
+    assert_eq!(unrouted.net.as_deref(), Some("A"));
+    assert_eq!(unrouted.location, Location::new(2_500_000, 20_000_000));
+
+    // A pad inside a hole of another net's pour is clear of it
+    let square = |half: i32| {
+        vec![
+            Location::new(-half, -half),
+            Location::new(half, -half),
+            Location::new(half, half),
+            Location::new(-half, half),
+        ]
+    };
+    let mut pour = Region {
+        layer: Layer::Top,
+        net: Some(1),
+        kind: RegionKind::Copper,
+        outline: square(5_000_000),
+        holes: vec![square(2_000_000)],
+        ..Default::default()
+    };
+    let mut records = vec![
+        PcbRecord::Pad(Pad {
+            layer: Layer::Top,
+            net: Some(0),
+            top: PadSize {
+                x: 1_000_000,
+                y: 1_000_000,
+                shape: PadShape::Rect,
+            },
+            ..Default::default()
+        }),
+        PcbRecord::Region(pour.clone()),
+    ];
+    let options = DrcOptions {
+        check_unrouted: false,
+        ..Default::default()
+    };
+    let input = DrcInput {
+        records: &records,
+        nets: &nets,
+        copper_layers: &[Layer::Top, Layer::Bottom],
+        rules: &rules,
+    };
+    assert_eq!(drc::check(&input, &options), []);
+
+    pour.holes.clear();
+    records[1] = PcbRecord::Region(pour);
+    let input = DrcInput {
+        records: &records,
+        nets: &nets,
+        copper_layers: &[Layer::Top, Layer::Bottom],
+        rules: &rules,
+    };
+    let violations = drc::check(&input, &options);
+    assert_eq!(violations.len(), 1, "{violations:?}");
+}
+
+#[test]
+fn test_statistics() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    let stats = pcbdoc.statistics().unwrap();
+
+    assert_eq!((stats.width, stats.height), (59_999_999, 39_999_998));
+    assert_eq!(stats.layer_count, 2);
+    assert_eq!(
+        stats.vias,
+        [DrillCount {
+            drill: 200_000,
+            count: 2
+        }]
+    );
+    assert_eq!((stats.smd_pads, stats.through_hole_pads), (4, 0));
+    assert_eq!((stats.top_components, stats.bottom_components), (3, 0));
+    assert_eq!(stats.net_count, 4);
+    assert_eq!(stats.min_track_width, Some(200_000));
+    assert_eq!(stats.min_drill, Some(200_000));
+
+    // One 0.2mm wide track between vias at (54, 51) and (58, 55), which have
+    // a 0.4mm diameter and a 0.2mm hole. The track ends in the holes, so its
+    // round ends add nothing and the holes stay open.
+    let bottom = &stats.copper_area[1];
+    assert_eq!(bottom.layer, Layer::Bottom);
+    let pi = std::f64::consts::PI;
+    let track = 4.0 * 2f64.sqrt() * 0.2;
+    let via = pi * 0.2f64.powi(2);
+    let hole = pi * 0.1f64.powi(2);
+    // Part of a via within 0.1mm of the track's axis, on the track's side
+    let overlap = 2.0 * (0.05 * 0.03f64.sqrt() + 0.02 * 0.5f64.asin());
+    let expected = track + 2.0 * (via - overlap - hole);
+    assert!((bottom.area - expected).abs() < 0.01, "{}", bottom.area);
+    assert!(stats.copper_area[0].area > 200.0);
+}
+
+#[test]
+fn test_polygons() {
+    test_init_once();
+
+    let buf = pcbdoc_from_extracted(PCBDOC_SIMPLE_EXTRACTED);
+    let pcbdoc = PcbDoc::from_buffer(&buf).unwrap();
+    let polygons = pcbdoc.polygons().unwrap();
+    assert_eq!(polygons.len(), 1);
+
+    let poly = &polygons[0];
+    assert_eq!(poly.name(), "NET1_L01_P000");
+    assert_eq!(poly.kind(), &PolygonKind::Polygon);
+    assert_eq!(poly.layer(), Layer::Top);
+    let nets = pcbdoc.nets().unwrap();
+    assert_eq!(&*nets[usize::from(poly.net().unwrap())], "Net1");
+    assert_eq!(poly.hatch_style(), HatchStyle::Solid);
+    assert_eq!(poly.pour_over(), PourOver::AllSameNetObjects);
+    assert!(!poly.remove_dead_copper());
+    assert!(poly.remove_necks());
+    assert_eq!(poly.neck_width(), 120_000);
+    assert_eq!(poly.arc_resolution(), 10_000);
+    assert_eq!(poly.track_width(), 203_200);
+    assert!((poly.island_area_threshold() - 1.8).abs() < 0.001);
+    assert!(!poly.shelved());
+
+    // Straight edges plus one segmented arc
+    let outline = poly.outline();
+    assert_eq!(outline.first(), outline.last());
+    assert_eq!(outline[1], Location::new(63_999_999, 63_999_999));
+    assert!(outline.len() > 9);
+
+    let records = pcbdoc.records().unwrap();
+    let poured: Vec<_> = poly.poured(&records).collect();
+    assert_eq!(poured.len(), 1);
+    let PcbRecord::Region(region) = poured[0] else {
+        panic!("expected a region");
+    };
+    assert_eq!(region.layer, Layer::Top);
+    assert!(region.outline.len() > 4);
+}
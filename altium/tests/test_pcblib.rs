@@ -0,0 +1,231 @@
+include!("include_test_util.rs");
+
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::{env, fs};
+
+use altium::kicad::{FootprintLib, LayerMap};
+use altium::pcb::record::PadShape;
+use altium::pcb::{EmbeddedModel, Layer, MechanicalKind, PcbRecord};
+use altium::PcbLib;
+
+const PCBLIB_EMPTY: &str = "tests/samples/pcblib/Empty.PcbLib";
+const PCBLIB_SIMPLE: &str = "tests/samples/pcblib/Simple.PcbLib";
+
+#[test]
+fn test_parse() {
+    test_init_once();
+
+    for path in [PCBLIB_EMPTY, PCBLIB_SIMPLE] {
+        let pcblib = PcbLib::open(path).unwrap();
+        println!("{pcblib:#?}");
+        for footprint in pcblib.footprints() {
+            println!("{footprint:#?}");
+        }
+    }
+}
+
+#[test]
+fn test_long_name() {
+    test_init_once();
+
+    // Storage names are truncated, make sure we still find the footprint
+    let name = "SQFP50P800X800X300_HS-33T300X300";
+    let pcblib = PcbLib::open(PCBLIB_SIMPLE).unwrap();
+    let footprint = pcblib.get_footprint(name).unwrap();
+    assert_eq!(footprint.name(), name);
+    assert_eq!(footprint.pads().count(), 33);
+}
+
+#[test]
+fn test_pads() {
+    test_init_once();
+
+    let pcblib = PcbLib::open(PCBLIB_SIMPLE).unwrap();
+    let footprint = pcblib.get_footprint("Four pads").unwrap();
+    let pads: Vec<_> = footprint.pads().collect();
+    assert_eq!(pads.len(), 4);
+
+    assert_eq!(&*pads[0].designator, "Pin1");
+    assert_eq!(pads[0].layer, Layer::Top);
+    assert_eq!((pads[0].location.x(), pads[0].location.y()), (0, 0));
+    assert_eq!((pads[0].top.x, pads[0].top.y), (1_000_001, 1_000_001));
+    assert_eq!(pads[0].top.shape, PadShape::Rect);
+
+    assert_eq!(
+        (pads[1].location.x(), pads[1].location.y()),
+        (0, -2_000_001)
+    );
+    assert_eq!(pads[2].top.shape, PadShape::RoundRect { corner_radius: 50 });
+
+    assert_eq!(pads[3].layer, Layer::MultiLayer);
+    assert_eq!(pads[3].top.shape, PadShape::Round);
+    assert_eq!(pads[3].hole_size, 1_000_001);
+    assert!(!pads[3].plated);
+
+    assert!(footprint
+        .records()
+        .iter()
+        .all(|rec| matches!(rec, PcbRecord::Pad(_))));
+}
+
+#[test]
+fn test_skip_unknown_record() {
+    test_init_once();
+
+    // Append a record of an unknown type to a footprint
+    let buf = fs::read(PCBLIB_SIMPLE).unwrap();
+    let mut cfile = cfb::CompoundFile::open(Cursor::new(buf)).unwrap();
+    let mut stream = cfile.open_stream("/Four pads/Data").unwrap();
+    stream.seek(SeekFrom::End(0)).unwrap();
+    stream.write_all(&[99, 3, 0, 0, 0, 1, 2, 3]).unwrap();
+    drop(stream);
+    cfile.flush().unwrap();
+    let mut buf = Vec::new();
+    let mut inner = cfile.into_inner();
+    inner.rewind().unwrap();
+    inner.read_to_end(&mut buf).unwrap();
+
+    let pcblib = PcbLib::from_buffer(&buf).unwrap();
+    let footprint = pcblib.try_get_footprint("Four pads").unwrap().unwrap();
+    assert_eq!(footprint.records().len(), 4);
+    assert_eq!(pcblib.footprints().count(), pcblib.footprint_meta().len());
+}
+
+#[test]
+fn test_footprint_svg() {
+    test_init_once();
+
+    let pcblib = PcbLib::open(PCBLIB_SIMPLE).unwrap();
+    let footprint = pcblib.get_footprint("Four pads").unwrap();
+    let out = footprint.svg().to_string();
+
+    assert!(
+        out.contains(r#"data-record-type="Footprint" data-name="Four pads""#),
+        "{out}"
+    );
+    assert_eq!(out.matches(r#"data-designator="Pin1""#).count(), 2, "{out}");
+    // The through hole pad is on both sides and has a hole
+    assert!(out.contains(r##"fill="#0000ff""##), "{out}");
+    assert!(out.contains(r##"fill="#00e3e3""##), "{out}");
+}
+
+#[test]
+fn test_kicad_footprints() {
+    test_init_once();
+
+    let pcblib = PcbLib::open(PCBLIB_SIMPLE).unwrap();
+    let mut lib = FootprintLib::new();
+    for footprint in pcblib.footprints() {
+        lib.add_footprint(&footprint);
+    }
+    assert_eq!(lib.len(), pcblib.footprint_meta().len());
+
+    let out = lib.kicad_mod("CAPC1608X09L").unwrap();
+    assert!(out.starts_with("(footprint \"CAPC1608X09L\"\n"), "{out}");
+    assert!(out.contains("(attr smd)"), "{out}");
+    assert!(out.contains("(pad \"1\" smd roundrect"), "{out}");
+    assert!(out.contains("(roundrect_rratio 0.125)"), "{out}");
+    assert!(out.contains("(layer \"F.CrtYd\")"), "{out}");
+    assert!(out.contains("(layer \"F.Fab\")"), "{out}");
+    assert!(out.contains("(model \"CAPC1608X09L.step\""), "{out}");
+
+    let out = lib.kicad_mod("Four pads").unwrap();
+    assert!(out.contains("(pad \"Pin5\" np_thru_hole circle"), "{out}");
+    assert!(out.contains("(layers \"*.Cu\" \"*.Mask\")"), "{out}");
+}
+
+#[test]
+fn test_layer_kinds() {
+    test_init_once();
+
+    let pcblib = PcbLib::open(PCBLIB_SIMPLE).unwrap();
+    let layers = pcblib.layers().unwrap();
+    assert_eq!(
+        layers.find_kind(MechanicalKind::CourtyardBottom),
+        Some(Layer::Mechanical(16))
+    );
+    assert_eq!(layers.name(Layer::Mechanical(15)), "Top Courtyard");
+    assert_eq!(layers.mechanical_kind(Layer::Mechanical(13)), None);
+
+    let map = LayerMap::from_layers(&layers);
+    assert_eq!(map.kicad_layer(Layer::Mechanical(15)), Some("F.CrtYd"));
+    assert_eq!(map.kicad_layer(Layer::Mechanical(13)), Some("F.Fab"));
+    assert_eq!(map.kicad_layer(Layer::Mechanical(1)), Some("Dwgs.User"));
+}
+
+#[test]
+fn test_models() {
+    test_init_once();
+
+    let pcblib = PcbLib::open(PCBLIB_SIMPLE).unwrap();
+    let models = pcblib.models().unwrap();
+    let names: Vec<_> = models.iter().map(|model| model.name()).collect();
+    assert_eq!(
+        names,
+        ["CAPC1608X09L.step", "SQFP50P800X800X300_HS-33N.step"]
+    );
+
+    let cap = models.by_name("capc1608x09l.STEP").unwrap();
+    assert_eq!(cap.id(), "{0B98D05C-546A-46AB-AB45-E921BFE962DC}");
+    assert_eq!(cap.checksum(), 1_687_024_371);
+    assert_eq!(cap.rotation(), [0.0; 3]);
+    assert_eq!(models.get(cap.id()), Some(cap));
+    assert_eq!(
+        models
+            .get("{E9E72259-E0E9-418F-A88B-26D0F045F1DD}")
+            .unwrap()
+            .checksum(),
+        -1_983_613_053
+    );
+
+    // The footprint's body refers to the embedded model
+    let footprint = pcblib.get_footprint("CAPC1608X09L").unwrap();
+    let body = footprint.bodies().next().unwrap();
+    assert!(body.model_id.eq_ignore_ascii_case(cap.id()));
+    assert_eq!(&*body.model_name, cap.name());
+
+    let mut out_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    out_dir.extend(["test_output", "models"]);
+    fs::create_dir_all(&out_dir).unwrap();
+
+    for model in &models {
+        let step = model.step_data().unwrap();
+        assert!(step.starts_with(b"ISO-10303-21;"), "{}", model.name());
+        model.save_step(out_dir.join(model.name())).unwrap();
+    }
+
+    assert!(PcbLib::open("tests/samples/pcblib/Empty.PcbLib")
+        .unwrap()
+        .models()
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_replace_model() {
+    test_init_once();
+
+    let mut out_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    out_dir.extend(["test_output", "models"]);
+    fs::create_dir_all(&out_dir).unwrap();
+    let out_path = out_dir.join("Replaced.PcbLib");
+    fs::copy(PCBLIB_SIMPLE, &out_path).unwrap();
+
+    let mut models = PcbLib::open(&out_path).unwrap().models().unwrap();
+    let step = b"ISO-10303-21;\nHEADER;\nENDSEC;\nEND-ISO-10303-21;\n";
+    let id = models.by_name("CAPC1608X09L.step").unwrap().id().to_owned();
+    models.get_mut(&id).unwrap().set_step_data(step).unwrap();
+    let added = EmbeddedModel::new("Extra.step", step).unwrap();
+    let added_id = added.id().to_owned();
+    models.insert(added);
+    models.save_to(&out_path).unwrap();
+
+    let pcblib = PcbLib::open(&out_path).unwrap();
+    let reread = pcblib.models().unwrap();
+    assert_eq!(reread, models);
+    assert_eq!(reread.get(&id).unwrap().step_data().unwrap(), step);
+    assert_eq!(reread.get(&added_id).unwrap().name(), "Extra.step");
+    // Footprints are untouched
+    assert_eq!(pcblib.footprints().count(), pcblib.footprint_meta().len());
+}
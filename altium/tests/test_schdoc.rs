@@ -1,8 +1,52 @@
 include!("include_test_util.rs");
 
-use altium::sch::SchDoc;
+use std::fs;
+use std::io::{Cursor, Write};
+
+use altium::draw::{Draw, RenderOptions, SvgCtx};
+use altium::font::{Font, FontCollection};
+use altium::netlist::{
+    Edif,
+    KicadNetlist,
+    NetComponent,
+    NetPin,
+    Netlist,
+    NetlistWriter,
+    Protel2,
+    Spice,
+};
+use altium::sch::record::parse_any_record;
+use altium::sch::{SchDoc, SchDrawCtx, Storage};
 
 const SCHDOC_SIMPLE: &str = "tests/samples/schdoc/simple.SchDoc";
+const SCHDOC_SIMPLE_EXTRACTED: &str = "tests/samples/schdoc/simple-extracted";
+
+/// Reassemble a document from its extracted streams
+fn schdoc_from_extracted(dir: &str) -> Vec<u8> {
+    schdoc_with_records(dir, &[])
+}
+
+/// Reassemble a document, appending text records to its `FileHeader`
+fn schdoc_with_records(dir: &str, records: &[&str]) -> Vec<u8> {
+    let mut cfile = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    for name in ["FileHeader", "Storage", "Additional"] {
+        let mut data = fs::read(format!("{dir}/{name}")).unwrap();
+        if name == "FileHeader" {
+            for record in records {
+                // Length includes the nul terminator, then two type bytes
+                let len = u16::try_from(record.len() + 1).unwrap();
+                data.extend_from_slice(&len.to_le_bytes());
+                data.extend_from_slice(&[0, 0]);
+                data.extend_from_slice(record.as_bytes());
+                data.push(0);
+            }
+        }
+        let mut stream = cfile.create_stream(format!("/{name}")).unwrap();
+        stream.write_all(&data).unwrap();
+    }
+    cfile.flush().unwrap();
+    cfile.into_inner().into_inner()
+}
 
 #[test]
 fn test_parse() {
@@ -11,3 +55,172 @@ fn test_parse() {
     let schdoc = SchDoc::open(SCHDOC_SIMPLE).unwrap();
     println!("{schdoc:#?}");
 }
+
+#[test]
+fn test_netlist() {
+    test_init_once();
+
+    let buf = schdoc_from_extracted(SCHDOC_SIMPLE_EXTRACTED);
+    let schdoc = SchDoc::from_buffer(&buf).unwrap();
+    let netlist = schdoc.netlist();
+    println!("{netlist:#?}");
+
+    // Two resistors and an AND gate, not annotated
+    assert_eq!(netlist.components().len(), 3);
+    let gate = netlist.component("U?").unwrap();
+    assert_eq!(gate.value.as_ref(), "SN74AHC1G08DBVR");
+    assert_eq!(gate.footprint.as_deref(), Some("NA"));
+    assert_eq!(gate.pins.len(), 5);
+
+    // Power ports name their nets
+    for (net, pin) in [("GND", "3"), ("VCC", "5"), ("OUT", "4")] {
+        let nodes = &netlist.net(net).unwrap().nodes;
+        assert_eq!(nodes.len(), 1, "{net}: {nodes:?}");
+        assert_eq!((&*nodes[0].designator, &*nodes[0].pin), ("U?", pin));
+    }
+    // A port and net label on the same wire
+    assert_eq!(netlist.net("IN1").unwrap().nodes.len(), 1);
+    assert_eq!(netlist.net("IN2").unwrap().nodes.len(), 1);
+    // Unnamed nets between the resistors and the gate inputs
+    let unnamed: Vec<_> = netlist
+        .nets()
+        .iter()
+        .filter(|net| net.name.starts_with("Net"))
+        .collect();
+    assert_eq!(unnamed.len(), 2, "{unnamed:?}");
+    assert!(unnamed.iter().all(|net| net.nodes.len() == 2));
+    assert_ne!(unnamed[0].name, unnamed[1].name);
+    // Net labels on wires without pins don't make nets
+    assert!(netlist.net("BUS0").is_none());
+
+    let protel = Protel2.write_string(&netlist);
+    assert!(
+        protel.starts_with("PROTEL NETLIST 2.0\n[DSC\nDESIGNATOR\nR?\n"),
+        "{protel}"
+    );
+    assert!(
+        protel.contains("(\nNETNAME\nGND\nU?-3 GND PASSIVE\n)"),
+        "{protel}"
+    );
+
+    let kicad = KicadNetlist.write_string(&netlist);
+    assert!(kicad.starts_with("(export\n  (version \"E\")"), "{kicad}");
+    assert!(
+        kicad.contains(
+            "(node (ref \"U?\") (pin \"3\") (pinfunction \"GND\") (pintype \"passive\"))"
+        ),
+        "{kicad}"
+    );
+
+    let edif = Edif.write_string(&netlist);
+    assert!(
+        edif.starts_with("(edif buffer\n  (edifVersion 2 0 0)"),
+        "{edif}"
+    );
+    assert!(edif.contains("(portRef P3 (instanceRef U_))"), "{edif}");
+
+    // Nothing here has a simulation model
+    let spice = Spice.write_string(&netlist);
+    assert!(spice.contains("* U? has no simulation model"), "{spice}");
+    assert!(spice.ends_with(".end\n"), "{spice}");
+}
+
+#[test]
+fn test_spice_models() {
+    test_init_once();
+
+    // Give the first resistor (record 72) and the gate (record 98) simulation
+    // models by adding implementations to their implementation lists
+    let buf = schdoc_with_records(
+        SCHDOC_SIMPLE_EXTRACTED,
+        &[
+            "|RECORD=45|OwnerIndex=94|ModelName=RESISTOR|ModelType=SIM|IsCurrent=T",
+            "|RECORD=45|OwnerIndex=131|ModelName=SN74AHC1G08|ModelType=SIM|IsCurrent=T",
+        ],
+    );
+    let schdoc = SchDoc::from_buffer(&buf).unwrap();
+    let netlist = schdoc.netlist();
+    let spice = Spice.write_string(&netlist);
+
+    // Resistors are primitives that take their value, the gate becomes a
+    // subcircuit instance named after its model
+    let lines: Vec<_> = spice.lines().collect();
+    assert_eq!(
+        lines[1..],
+        [
+            "R? IN1 NetR?_2 MCR03EZPFX1003",
+            "XU? NetR?_2 NetR?_2_2 0 OUT VCC SN74AHC1G08",
+            "* R? has no simulation model",
+            ".end",
+        ],
+        "{spice}"
+    );
+
+    // Sources take their value, controlled sources need more than a model name
+    // so they become subcircuits too
+    let mut netlist = Netlist::new("sources");
+    for (designator, value) in [("V1", "5"), ("E1", "")] {
+        netlist.add_component(NetComponent {
+            designator: designator.into(),
+            value: value.into(),
+            sim_model: Some("MODEL".into()),
+            pins: ["1", "2"]
+                .map(|pin| NetPin {
+                    designator: pin.into(),
+                    ..Default::default()
+                })
+                .into(),
+            ..Default::default()
+        });
+    }
+    let spice = Spice.write_string(&netlist);
+    assert!(spice.contains("\nV1 NC_V1_1 NC_V1_2 5\n"), "{spice}");
+    assert!(spice.contains("\nXE1 NC_E1_1 NC_E1_2 MODEL\n"), "{spice}");
+}
+
+#[test]
+fn test_draw_text_records() {
+    test_init_once();
+
+    let (fonts, storage, options) = (
+        FontCollection::from(vec![Font::default()]),
+        Storage::default(),
+        RenderOptions::default(),
+    );
+    let ctx = SchDrawCtx {
+        fonts: &fonts,
+        storage: &storage,
+        name: "text records",
+        options: &options,
+    };
+    let draw = |buf: &[u8]| {
+        let mut svg = SvgCtx::new();
+        parse_any_record(buf).unwrap().draw(&mut svg, &ctx);
+        svg.svg().to_string()
+    };
+
+    // Pin lengths are in mils, and the conglomerate packs the rotation and
+    // visibility (only the name is shown here)
+    let out = draw(
+        b"|RECORD=2|OwnerPartId=1|Location.X=10|Location.Y=0|PinLength=30\
+        |PinConglomerate=8|Name=A|Designator=1",
+    );
+    assert!(
+        out.contains(r#"<line x1="254000" y1="0" x2="1016000" y2="0""#),
+        "{out}"
+    );
+    assert!(out.contains(">A</text>"), "{out}");
+    assert!(!out.contains(">1</text>"), "{out}");
+
+    // Port sizes are in mils
+    let out = draw(
+        b"|RECORD=18|OwnerPartId=-1|Location.X=0|Location.Y=0|Width=50|Height=10\
+        |FontID=1|Name=P1|Color=128|AreaColor=128",
+    );
+    assert!(
+        out.contains(
+            r#"points="0,-127000 1143000,-127000 1270000,0 1143000,127000 0,127000 0,-127000""#
+        ),
+        "{out}"
+    );
+}
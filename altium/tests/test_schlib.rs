@@ -8,7 +8,21 @@ use std::{
     path::PathBuf,
 };
 
-use altium::sch::{storage::file_name, SchLib};
+use altium::draw::{Draw, RenderOptions, SvgCtx};
+use altium::font::FontCollection;
+use altium::kicad::SymbolLib;
+use altium::sch::{
+    record::parse_any_record,
+    storage::file_name,
+    Component,
+    ModelKind,
+    ModelLibrary,
+    SchDrawCtx,
+    SchLib,
+    SchLibWriter,
+    SchRecord,
+    Storage,
+};
 
 const SCHLIB_EMPTY: &str = "tests/samples/schlib/empty.SchLib";
 const SCHLIB_GRAPHIC: &str = "tests/samples/schlib/graphic-mixed.SchLib";
@@ -72,13 +86,231 @@ fn test_draw_single_svg() {
     dbg!(&comp);
     let mut out_path = out_dir.clone();
     out_path.push(format!("{}.svg", comp.name().replace(' ', "_")));
-    svg::save(&out_path, &node).unwrap();
+    node.save(&out_path).unwrap();
 
     dbg!(schlib.storage());
 
     dbg!(altium::__private::num_unsupported_keys());
 }
 
+#[test]
+fn test_draw_themed_svg() {
+    test_init_once();
+
+    let schlib = SchLib::open(SCHLIB_SIMPLE).unwrap();
+    let comp = schlib.get_component("CombinedPinsRectGraphic").unwrap();
+
+    let default = comp.svg().to_string();
+    let opts = RenderOptions {
+        show_pin_numbers: false,
+        scale: Some(2.0),
+        ..RenderOptions::monochrome()
+    };
+    let mono = comp.svg_with_options(&opts).to_string();
+
+    // Only scaled output gets a physical size
+    let header = |s: &str| s.split('>').next().unwrap().to_owned();
+    assert!(!header(&default).contains("height="), "{default}");
+    assert!(header(&mono).contains("mm\""), "{mono}");
+    // 3084400 units wide, at 10 nm per unit and twice the physical size
+    assert!(
+        header(&mono).contains(r#"width="61.688mm" height="27.94mm""#),
+        "{mono}"
+    );
+    for line in mono.lines().filter(|l| l.contains("stroke=")) {
+        assert!(
+            line.contains("stroke=\"#000000\"") || line.contains("stroke=\"#ffffff\""),
+            "{line}"
+        );
+    }
+    assert!(mono.matches("<text").count() < default.matches("<text").count());
+}
+
+#[test]
+fn test_draw_pin_visibility() {
+    test_init_once();
+
+    let schlib = SchLib::open(SCHLIB_SIMPLE).unwrap();
+    let comp = schlib.get_component(SIMPLE_COMP_NAME2).unwrap();
+    let out = comp.svg().to_string();
+    let pin_group = |name: &str| {
+        let start = out
+            .find(&format!(r#"<g data-record-type="Pin" data-name="{name}""#))
+            .unwrap();
+        let len = out[start..].find("</g>").unwrap();
+        out[start..start + len].to_owned()
+    };
+
+    // "HiddenDesignator" (pin 7) only shows its name, "HiddenName" only shows
+    // its designator
+    let hidden_des = pin_group("HiddenDesignator");
+    assert!(
+        hidden_des.contains(">HiddenDesignator</text>"),
+        "{hidden_des}"
+    );
+    assert!(!hidden_des.contains(">7</text>"), "{hidden_des}");
+    let hidden_name = pin_group("HiddenName");
+    assert!(
+        hidden_name.contains(">(HiddenName)</text>"),
+        "{hidden_name}"
+    );
+    assert!(!hidden_name.contains(">HiddenName</text>"), "{hidden_name}");
+    let normal = pin_group("Normal");
+    assert!(normal.contains(">Normal</text>"), "{normal}");
+    assert!(normal.contains(">1</text>"), "{normal}");
+}
+
+#[test]
+fn test_draw_line_units() {
+    test_init_once();
+
+    // Line coordinates are stored in mils like every other schematic location
+    let rec = parse_any_record(
+        b"|RECORD=13|OwnerPartId=1|Location.X=10|Location.Y=20\
+        |Corner.X=110|Corner.Y=20|LineWidth=1|Color=128",
+    )
+    .unwrap();
+    let (fonts, storage, options) = (
+        FontCollection::default(),
+        Storage::default(),
+        RenderOptions::default(),
+    );
+    let ctx = SchDrawCtx {
+        fonts: &fonts,
+        storage: &storage,
+        name: "line",
+        options: &options,
+    };
+    let mut svg = SvgCtx::new();
+    rec.draw(&mut svg, &ctx);
+    let out = svg.svg().to_string();
+
+    assert!(
+        out.contains(r#"<line x1="254000" y1="-508000" x2="2794000" y2="-508000""#),
+        "{out}"
+    );
+}
+
+#[test]
+fn test_draw_dxf_units() {
+    test_init_once();
+
+    let schlib = SchLib::open(SCHLIB_SIMPLE).unwrap();
+    let comp = schlib.get_component(SIMPLE_COMP_NAME2).unwrap();
+    let mut out_path = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    out_path.extend(["test_output", "dxf"]);
+    fs::create_dir_all(&out_path).unwrap();
+    out_path.push(format!("{SIMPLE_COMP_NAME2}.dxf"));
+    comp.save_dxf(&out_path).unwrap();
+    let out = fs::read_to_string(&out_path).unwrap();
+
+    // Pin "Len500" is 500 mils long and 100 mils below the origin
+    assert!(
+        out.contains("LINE\n  8\nPin\n 62\n7\n 10\n0\n 20\n-2.54\n 11\n12.7\n 21\n-2.54\n"),
+        "{out}"
+    );
+    assert!(!out.contains("$INSUNITS"), "{out}");
+}
+
+#[test]
+fn test_draw_monochrome_fill() {
+    test_init_once();
+
+    // A dark red and a light yellow solid rectangle
+    let dark = parse_any_record(
+        b"|RECORD=14|OwnerPartId=1|Location.X=0|Location.Y=0|Corner.X=10|Corner.Y=10\
+        |Color=128|AreaColor=128|IsSolid=T",
+    )
+    .unwrap();
+    let light = parse_any_record(
+        b"|RECORD=14|OwnerPartId=1|Location.X=20|Location.Y=0|Corner.X=30|Corner.Y=10\
+        |Color=128|AreaColor=11599871|IsSolid=T",
+    )
+    .unwrap();
+    let (fonts, storage, options) = (
+        FontCollection::default(),
+        Storage::default(),
+        RenderOptions::monochrome(),
+    );
+    let ctx = SchDrawCtx {
+        fonts: &fonts,
+        storage: &storage,
+        name: "fill",
+        options: &options,
+    };
+    let mut svg = SvgCtx::new();
+    dark.draw(&mut svg, &ctx);
+    light.draw(&mut svg, &ctx);
+    let out = svg.svg().to_string();
+
+    let fills: Vec<_> = out
+        .split("fill=\"")
+        .skip(1)
+        .map(|s| s.split('"').next().unwrap())
+        .collect();
+    assert_eq!(fills, ["#000000", "#ffffff"], "{out}");
+}
+
+#[test]
+fn test_svg_groups() {
+    test_init_once();
+
+    let schlib = SchLib::open(SCHLIB_SIMPLE).unwrap();
+    let comp = schlib.get_component("CombinedPinsRectGraphic").unwrap();
+    let out = comp.svg().to_string();
+
+    assert!(
+        out.contains(r#"<g data-record-type="Component" data-unique-id="#),
+        "{out}"
+    );
+    assert!(
+        out.contains(r#"data-name="CombinedPinsRectGraphic" data-designator="*""#),
+        "{out}"
+    );
+    assert!(
+        out.contains(r#"<g data-record-type="Pin" data-name="Pin1" data-designator="1">"#),
+        "{out}"
+    );
+    assert!(!out.contains("<!--"), "{out}");
+}
+
+#[test]
+fn test_kicad_symbols() {
+    test_init_once();
+
+    let schlib = SchLib::open(SCHLIB_SIMPLE).unwrap();
+    let mut lib = SymbolLib::new();
+    for name in ["Multipart 1", "Multimode 1", SIMPLE_COMP_NAME2] {
+        lib.add_component(&schlib.get_component(name).unwrap());
+    }
+    let out = lib.kicad_sym();
+
+    assert!(out.starts_with("(kicad_symbol_lib\n  (version "), "{out}");
+    // Three parts become three units; the alternate mode becomes De Morgan
+    for body in [
+        "Multipart 1_1_1",
+        "Multipart 1_2_1",
+        "Multipart 1_3_1",
+        "Multimode 1_1_1",
+        "Multimode 1_1_2",
+    ] {
+        assert!(
+            out.contains(&format!("(symbol \"{body}\"")),
+            "{body}: {out}"
+        );
+    }
+    assert!(!out.contains("Multipart 1_4_"), "{out}");
+
+    assert!(out.contains(r#"(property "Reference" "MP""#), "{out}");
+    assert!(out.contains("(pin power_in line"), "{out}");
+    assert!(
+        out.contains("(pin passive line\n        (at 12.7 -2.54 180)\n        (length 12.7)"),
+        "{out}"
+    );
+    // Only one pin hides its number, KiCad can only hide them all
+    assert!(!out.contains("(pin_numbers hide)"), "{out}");
+}
+
 #[test]
 fn test_draw_all_svgs() {
     test_init_once();
@@ -97,7 +329,7 @@ fn test_draw_all_svgs() {
 
             // Some have long names, truncate them
             out_file.push(format!("{}.svg", &fname[..min(40, fname.len())]));
-            svg::save(&out_file, &node).unwrap();
+            node.save(&out_file).unwrap();
             eprintln!("wrote {}", out_file.display());
         }
     }
@@ -123,3 +355,136 @@ fn test_storage() {
         eprintln!("wrote {}", out_file.display());
     }
 }
+
+#[test]
+fn test_write_roundtrip() {
+    test_init_once();
+
+    for schlib_path in ALL_SCHLIBS {
+        let schlib = SchLib::open(schlib_path).unwrap();
+        let mut writer = SchLibWriter::new();
+        for comp in schlib.components() {
+            writer.add_component(&comp);
+        }
+
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf).unwrap();
+        let written = SchLib::from_buffer(&buf).unwrap();
+
+        assert_eq!(
+            written.component_meta().len(),
+            schlib.component_meta().len(),
+            "{schlib_path}"
+        );
+        for (orig, new) in schlib.components().zip(written.components()) {
+            assert_eq!(orig.name(), new.name(), "{schlib_path}");
+            assert_eq!(orig.description(), new.description(), "{schlib_path}");
+            assert_eq!(orig.records(), new.records(), "{schlib_path}");
+        }
+        assert_eq!(
+            written.storage().keys().count(),
+            schlib.storage().keys().count(),
+            "{schlib_path}"
+        );
+    }
+}
+
+#[test]
+fn test_kicad_import() {
+    test_init_once();
+
+    let schlib = SchLib::open(SCHLIB_SIMPLE).unwrap();
+    let mut lib = SymbolLib::new();
+    for comp in schlib.components() {
+        lib.add_component(&comp);
+    }
+
+    let imported = SymbolLib::from_kicad_sym(&lib.kicad_sym()).unwrap();
+    assert_eq!(imported.len(), lib.len());
+
+    let pins = |comp: &Component| {
+        let mut ret: Vec<_> = comp
+            .records()
+            .iter()
+            .filter_map(|record| match record {
+                SchRecord::Pin(pin) => Some((pin.designator.clone(), pin.location, pin.electrical)),
+                _ => None,
+            })
+            .collect();
+        ret.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        ret
+    };
+
+    let orig = schlib.get_component(SIMPLE_COMP_NAME2).unwrap();
+    let new = imported.component(SIMPLE_COMP_NAME2).unwrap().unwrap();
+    assert_eq!(new.name(), orig.name());
+    assert_eq!(pins(&new), pins(&orig));
+
+    let multipart = imported.component("Multipart 1").unwrap().unwrap();
+    let designator = multipart.records().iter().find_map(|record| match record {
+        SchRecord::Designator(des) => Some(&*des.text),
+        _ => None,
+    });
+    assert_eq!(designator, Some("MP?"));
+
+    // Everything can be written as a SchLib and read back
+    let mut writer = SchLibWriter::new();
+    for comp in imported.components().unwrap() {
+        writer.add_component(&comp);
+    }
+    let mut buf = Vec::new();
+    writer.write_to(&mut buf).unwrap();
+    let written = SchLib::from_buffer(&buf).unwrap();
+    assert_eq!(written.component_meta().len(), lib.len());
+    assert_eq!(
+        pins(&written.get_component(SIMPLE_COMP_NAME2).unwrap()),
+        pins(&orig)
+    );
+}
+
+#[test]
+fn test_models() {
+    test_init_once();
+
+    // Sample libraries don't link any models, so start with a KiCad symbol
+    let sym = r#"(kicad_symbol_lib (version 20231120) (generator "test")
+        (symbol "R" (in_bom yes) (on_board yes)
+            (property "Reference" "R" (at 0 0 0))
+            (property "Value" "R" (at 0 0 0))
+            (property "Footprint" "Resistor_SMD:R_0603_1608Metric" (at 0 0 0))
+            (symbol "R_1_1"
+                (pin passive line (at 0 3.81 270) (length 1.27)
+                    (name "~" (effects (font (size 1.27 1.27))))
+                    (number "1" (effects (font (size 1.27 1.27)))))
+            )
+        )
+    )"#;
+    let lib = SymbolLib::from_kicad_sym(sym).unwrap();
+
+    let check = |comp: &Component| {
+        let models = comp.models();
+        assert_eq!(models.len(), 1);
+        assert!(comp.sim_models().is_empty());
+        assert!(comp.signal_integrity_models().is_empty());
+
+        let footprint = comp.current_footprint().unwrap();
+        assert_eq!(footprint.name(), "R_0603_1608Metric");
+        assert_eq!(footprint.kind(), ModelKind::Footprint);
+        assert_eq!(footprint.library(), ModelLibrary::Component);
+        assert_eq!(footprint.datafiles().len(), 1);
+        assert_eq!(&*footprint.datafiles()[0].entity, "R_0603_1608Metric");
+        assert_eq!(&*footprint.datafiles()[0].kind, "PCBLib");
+        assert!(footprint.parameters().is_empty());
+    };
+
+    let comp = lib.component("R").unwrap().unwrap();
+    check(&comp);
+
+    // Datafiles survive being written
+    let mut writer = SchLibWriter::new();
+    writer.add_component(&comp);
+    let mut buf = Vec::new();
+    writer.write_to(&mut buf).unwrap();
+    let written = SchLib::from_buffer(&buf).unwrap();
+    check(&written.get_component("R").unwrap());
+}
@@ -0,0 +1,117 @@
+include!("include_test_util.rs");
+
+use altium::kicad::SymbolLib;
+use altium::validate::{LinkIssue, LinkValidator};
+use altium::PcbLib;
+
+const PCBLIB_SIMPLE: &str = "tests/samples/pcblib/Simple.PcbLib";
+
+/// A symbol with the given pins and footprint
+fn symbol(name: &str, footprint: &str, pins: &[&str]) -> String {
+    let pins: String = pins
+        .iter()
+        .map(|pin| {
+            format!(
+                r#"(pin passive line (at 0 0 0) (length 2.54)
+                    (name "~" (effects (font (size 1.27 1.27))))
+                    (number "{pin}" (effects (font (size 1.27 1.27)))))"#
+            )
+        })
+        .collect();
+    format!(
+        r#"(symbol "{name}" (in_bom yes) (on_board yes)
+            (property "Reference" "U" (at 0 0 0))
+            (property "Footprint" "{footprint}" (at 0 0 0))
+            (symbol "{name}_1_1" {pins})
+        )"#
+    )
+}
+
+#[test]
+fn test_footprint_links() {
+    test_init_once();
+
+    let sym = format!(
+        "(kicad_symbol_lib (version 20231120) (generator \"test\") {} {} {} {})",
+        symbol("Cap", "Capacitors:CAPC1608X09L", &["1", "2"]),
+        symbol("Cap3", "CAPC1608X09L", &["1", "2", "3"]),
+        symbol("Missing", "NOPE", &["1"]),
+        symbol("Unlinked", "", &["1"]),
+    );
+    let symbols = SymbolLib::from_kicad_sym(&sym).unwrap();
+
+    let mut validator = LinkValidator::new();
+    for comp in symbols.components().unwrap() {
+        validator.add_component("A.SchLib", &comp);
+    }
+    // The same libref in another library
+    validator.add_component("B.SchLib", &symbols.component("Cap").unwrap().unwrap());
+    let pcblib = PcbLib::open(PCBLIB_SIMPLE).unwrap();
+    validator.add_pcblib("Simple.PcbLib", &pcblib).unwrap();
+
+    let issues = validator.validate();
+    for issue in &issues {
+        println!("{issue}");
+    }
+
+    let expected = [
+        LinkIssue::DuplicateLibref {
+            libref: "Cap".into(),
+            libraries: vec!["A.SchLib".into(), "B.SchLib".into()],
+        },
+        LinkIssue::UnmatchedPin {
+            library: "A.SchLib".into(),
+            libref: "Cap3".into(),
+            footprint: "CAPC1608X09L".into(),
+            pin: "3".into(),
+        },
+        LinkIssue::MissingFootprint {
+            library: "A.SchLib".into(),
+            libref: "Missing".into(),
+            footprint: "NOPE".into(),
+        },
+        LinkIssue::NoFootprint {
+            library: "A.SchLib".into(),
+            libref: "Unlinked".into(),
+        },
+    ];
+    assert_eq!(issues, expected);
+    assert_eq!(
+        issues[0].to_string(),
+        "`Cap` is defined more than once, in A.SchLib, B.SchLib"
+    );
+}
+
+#[test]
+fn test_unused_pads() {
+    test_init_once();
+
+    let sym = format!(
+        "(kicad_symbol_lib (version 20231120) (generator \"test\") {})",
+        symbol("Pads", "Four pads", &["Pin1", "Pin2"]),
+    );
+    let symbols = SymbolLib::from_kicad_sym(&sym).unwrap();
+    let pcblib = PcbLib::open(PCBLIB_SIMPLE).unwrap();
+    let pads: Vec<_> = pcblib
+        .get_footprint("Four pads")
+        .unwrap()
+        .pads()
+        .map(|pad| pad.designator.clone())
+        .filter(|des| !des.is_empty() && !["Pin1", "Pin2"].contains(&&**des))
+        .collect();
+
+    let mut validator = LinkValidator::new();
+    validator.add_component("A.SchLib", &symbols.component("Pads").unwrap().unwrap());
+    validator.add_pcblib("Simple.PcbLib", &pcblib).unwrap();
+
+    let issues = validator.validate();
+    let unused: Vec<_> = issues
+        .iter()
+        .map(|issue| match issue {
+            LinkIssue::UnusedPad { pad, .. } => pad.clone(),
+            _ => panic!("unexpected issue {issue}"),
+        })
+        .collect();
+    assert!(!unused.is_empty());
+    assert_eq!(unused, pads);
+}
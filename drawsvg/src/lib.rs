@@ -1,63 +1,880 @@
-#![allow(unused)]
+//! A small SVG writer with no dependencies
+//!
+//! Build an [`Svg`] out of [`Element`]s, then write it with [`Svg::write_to`]
+//! (which streams to any [`io::Write`]) or via [`fmt::Display`].
+//!
+//! ```
+//! use drawsvg::{Line, Svg, ViewBox};
+//!
+//! let svg = Svg::new()
+//!     .view_box(ViewBox::new(0, 0, 10, 10))
+//!     .add(Line::new(0, 0, 10, 10).stroke("#000000").stroke_width(1));
+//!
+//! assert!(svg.to_string().contains(r#"<line x1="0" y1="0" x2="10" y2="10""#));
+//! ```
 
 use core::fmt;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path as FsPath;
 
-use traits::WriteBuf;
+pub use traits::WriteBuf;
 mod traits;
 
+/// The root `<svg>` element
+#[derive(Clone, Debug, Default)]
 pub struct Svg {
-    attributes: Vec<SvgAttribute>,
+    view_box: Option<ViewBox>,
+    width: Option<Length>,
+    height: Option<Length>,
+    attributes: Attributes,
     elements: Vec<Element>,
 }
 
+impl Svg {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn view_box(mut self, view_box: ViewBox) -> Self {
+        self.view_box = Some(view_box);
+        self
+    }
+
+    #[must_use]
+    pub fn width(mut self, width: Length) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    #[must_use]
+    pub fn height(mut self, height: Length) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    /// Set an arbitrary attribute on the root element
+    #[must_use]
+    pub fn set(mut self, name: impl Into<Cow<'static, str>>, value: impl fmt::Display) -> Self {
+        self.attributes.set(name, value);
+        self
+    }
+
+    /// Append a child element
+    #[must_use]
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, element: impl Into<Element>) -> Self {
+        self.push(element);
+        self
+    }
+
+    /// Append a child element
+    pub fn push(&mut self, element: impl Into<Element>) {
+        self.elements.push(element.into());
+    }
+
+    /// Insert a child element at `index`. Earlier elements are drawn below
+    /// later ones.
+    pub fn insert(&mut self, index: usize, element: impl Into<Element>) {
+        self.elements.insert(index, element.into());
+    }
+
+    /// All child elements
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    /// Write this SVG document to a writer as it is serialized, without
+    /// building an intermediate string
+    pub fn write_to<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        let mut adapter = IoAdapter {
+            inner: writer,
+            error: None,
+        };
+
+        match self.write_buf(&mut adapter) {
+            Ok(()) => Ok(()),
+            Err(fmt::Error) => Err(adapter
+                .error
+                .unwrap_or_else(|| io::Error::other("formatting error"))),
+        }
+    }
+
+    /// Write this SVG document to a file, creating it if needed
+    pub fn save<P: AsRef<FsPath>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        io::Write::flush(&mut writer)
+    }
+}
+
 impl WriteBuf for Svg {
-    fn write_buf<W: fmt::Write>(&self, f: &mut W) -> Result<(), fmt::Error> {
-        write!(f, "<")?;
-        write!(f, r#"xmlns="http://www.w3.org/2000/svg">"#)?;
+    fn write_buf<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+        write!(f, r#"<svg xmlns="http://www.w3.org/2000/svg""#)?;
+        if let Some(view_box) = &self.view_box {
+            write!(f, r#" viewBox="{view_box}""#)?;
+        }
+        if let Some(width) = &self.width {
+            write!(f, r#" width="{width}""#)?;
+        }
+        if let Some(height) = &self.height {
+            write!(f, r#" height="{height}""#)?;
+        }
+        self.attributes.write_buf(f)?;
+        writeln!(f, ">")?;
+
+        for element in &self.elements {
+            element.write_buf(f)?;
+        }
+
+        writeln!(f, "</svg>")
     }
 }
 
-pub enum SvgAttribute {
-    ViewBox(ViewBox),
-    Width(Length),
-    Height(Length),
+impl fmt::Display for Svg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_buf(f)
+    }
 }
 
-pub struct ViewBox {
-    min_x: Number,
-    min_y: Number,
-    width: Number,
-    height: Number,
+/// Forward `fmt::Write` to `io::Write`, keeping the real error around
+struct IoAdapter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> fmt::Write for IoAdapter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
 }
 
+/// A numeric value, written without a unit
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Number {
-    F(f32),
-    I(i32),
+    F(f64),
+    I(i64),
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::F(v) => write!(f, "{v}"),
+            Number::I(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+macro_rules! number_from {
+    ($variant:ident, $conv:ty: $($ty:ty),+) => {
+        $(
+            impl From<$ty> for Number {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(<$conv>::from(value))
+                }
+            }
+        )+
+    };
 }
 
+number_from!(I, i64: i8, i16, i32, i64, u8, u16, u32);
+number_from!(F, f64: f32, f64);
+
+/// A number with a unit
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Length {
     Em(Number),
-    #[default]
     Px(Number),
     Mm(Number),
     Pt(Number),
     Pct(Number),
 }
 
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Length::Em(v) => write!(f, "{v}em"),
+            Length::Px(v) => write!(f, "{v}px"),
+            Length::Mm(v) => write!(f, "{v}mm"),
+            Length::Pt(v) => write!(f, "{v}pt"),
+            Length::Pct(v) => write!(f, "{v}%"),
+        }
+    }
+}
+
+/// The visible region of the document, in user units
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewBox {
+    pub min_x: Number,
+    pub min_y: Number,
+    pub width: Number,
+    pub height: Number,
+}
+
+impl ViewBox {
+    pub fn new(
+        min_x: impl Into<Number>,
+        min_y: impl Into<Number>,
+        width: impl Into<Number>,
+        height: impl Into<Number>,
+    ) -> Self {
+        Self {
+            min_x: min_x.into(),
+            min_y: min_y.into(),
+            width: width.into(),
+            height: height.into(),
+        }
+    }
+}
+
+impl fmt::Display for ViewBox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.min_x, self.min_y, self.width, self.height
+        )
+    }
+}
+
+/// One entry in a `transform` attribute. Angles are in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transform {
+    Translate(Number, Number),
+    Rotate(Number),
+    /// Rotate around a point `(angle, x, y)`
+    RotateAbout(Number, Number, Number),
+    Scale(Number, Number),
+    Matrix([Number; 6]),
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transform::Translate(x, y) => write!(f, "translate({x} {y})"),
+            Transform::Rotate(a) => write!(f, "rotate({a})"),
+            Transform::RotateAbout(a, x, y) => write!(f, "rotate({a} {x} {y})"),
+            Transform::Scale(x, y) => write!(f, "scale({x} {y})"),
+            Transform::Matrix([a, b, c, d, e, g]) => {
+                write!(f, "matrix({a} {b} {c} {d} {e} {g})")
+            }
+        }
+    }
+}
+
+/// Attributes of an element, written in insertion order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Attributes {
+    items: Vec<(Cow<'static, str>, String)>,
+    transforms: Vec<Transform>,
+}
+
+impl Attributes {
+    /// Set an attribute, replacing any previous value
+    pub fn set(&mut self, name: impl Into<Cow<'static, str>>, value: impl fmt::Display) {
+        let name = name.into();
+        let value = value.to_string();
+        match self.items.iter_mut().find(|(n, _)| *n == name) {
+            Some(item) => item.1 = value,
+            None => self.items.push((name, value)),
+        }
+    }
+
+    /// Get the value of an attribute
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.items
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Append a transform. Transforms are applied right to left, as in SVG.
+    pub fn push_transform(&mut self, transform: Transform) {
+        self.transforms.push(transform);
+    }
+}
+
+impl WriteBuf for Attributes {
+    fn write_buf<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+        for (name, value) in &self.items {
+            write!(f, " {name}=\"")?;
+            escape(value, f)?;
+            write!(f, "\"")?;
+        }
+
+        if !self.transforms.is_empty() {
+            write!(f, " transform=\"")?;
+            for (idx, transform) in self.transforms.iter().enumerate() {
+                if idx > 0 {
+                    write!(f, " ")?;
+                }
+                write!(f, "{transform}")?;
+            }
+            write!(f, "\"")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Escape text for use in content or a quoted attribute
+fn escape<W: fmt::Write>(s: &str, f: &mut W) -> fmt::Result {
+    let mut last = 0;
+    for (idx, ch) in s.char_indices() {
+        let replacement = match ch {
+            '&' => "&amp;",
+            '<' => "&lt;",
+            '>' => "&gt;",
+            '"' => "&quot;",
+            '\'' => "&apos;",
+            _ => continue,
+        };
+        f.write_str(&s[last..idx])?;
+        f.write_str(replacement)?;
+        last = idx + ch.len_utf8();
+    }
+    f.write_str(&s[last..])
+}
+
+/// Any element that can be a child of [`Svg`] or [`Group`]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Element {
+    Path(Path),
+    Line(Line),
+    Polyline(Polyline),
+    Polygon(Polygon),
+    Rect(Rect),
+    Ellipse(Ellipse),
+    Circle(Circle),
     Text(Text),
+    Image(Image),
+    Group(Group),
+    Comment(Comment),
 }
 
+impl WriteBuf for Element {
+    fn write_buf<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+        match self {
+            Element::Path(v) => v.write_buf(f),
+            Element::Line(v) => v.write_buf(f),
+            Element::Polyline(v) => v.write_buf(f),
+            Element::Polygon(v) => v.write_buf(f),
+            Element::Rect(v) => v.write_buf(f),
+            Element::Ellipse(v) => v.write_buf(f),
+            Element::Circle(v) => v.write_buf(f),
+            Element::Text(v) => v.write_buf(f),
+            Element::Image(v) => v.write_buf(f),
+            Element::Group(v) => v.write_buf(f),
+            Element::Comment(v) => v.write_buf(f),
+        }
+    }
+}
+
+/// Implement shared builder methods and conversion to [`Element`]
+macro_rules! element_common {
+    ($ty:ident) => {
+        impl $ty {
+            /// Set an arbitrary attribute
+            #[must_use]
+            pub fn set(
+                mut self,
+                name: impl Into<Cow<'static, str>>,
+                value: impl fmt::Display,
+            ) -> Self {
+                self.attributes.set(name, value);
+                self
+            }
+
+            #[must_use]
+            pub fn id(self, id: impl fmt::Display) -> Self {
+                self.set("id", id)
+            }
+
+            #[must_use]
+            pub fn class(self, class: impl fmt::Display) -> Self {
+                self.set("class", class)
+            }
+
+            #[must_use]
+            pub fn stroke(self, color: impl fmt::Display) -> Self {
+                self.set("stroke", color)
+            }
+
+            #[must_use]
+            pub fn stroke_width(self, width: impl Into<Number>) -> Self {
+                self.set("stroke-width", width.into())
+            }
+
+            #[must_use]
+            pub fn fill(self, color: impl fmt::Display) -> Self {
+                self.set("fill", color)
+            }
+
+            /// Append a transform
+            #[must_use]
+            pub fn transform(mut self, transform: Transform) -> Self {
+                self.attributes.push_transform(transform);
+                self
+            }
+
+            pub fn attributes(&self) -> &Attributes {
+                &self.attributes
+            }
+        }
+
+        impl From<$ty> for Element {
+            fn from(value: $ty) -> Self {
+                Element::$ty(value)
+            }
+        }
+    };
+}
+
+/// Implement `WriteBuf` for elements that have no children
+macro_rules! empty_element {
+    ($ty:ident, $tag:literal) => {
+        element_common!($ty);
+
+        impl WriteBuf for $ty {
+            fn write_buf<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+                write!(f, concat!("<", $tag))?;
+                self.attributes.write_buf(f)?;
+                writeln!(f, "/>")
+            }
+        }
+    };
+}
+
+/// Data for a `<path>`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PathData(String);
+
+impl PathData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(mut self, cmd: fmt::Arguments) -> Self {
+        use fmt::Write;
+
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        self.0.write_fmt(cmd).unwrap();
+        self
+    }
+
+    #[must_use]
+    pub fn move_to(self, x: impl Into<Number>, y: impl Into<Number>) -> Self {
+        self.push(format_args!("M {} {}", x.into(), y.into()))
+    }
+
+    #[must_use]
+    pub fn line_to(self, x: impl Into<Number>, y: impl Into<Number>) -> Self {
+        self.push(format_args!("L {} {}", x.into(), y.into()))
+    }
+
+    /// Elliptical arc to `end` with radii `(rx, ry)`, with no axis rotation
+    #[must_use]
+    pub fn arc_to<N: Into<Number>>(
+        self,
+        (rx, ry): (N, N),
+        large_arc: bool,
+        sweep: bool,
+        (x, y): (N, N),
+    ) -> Self {
+        self.push(format_args!(
+            "A {} {} 0 {} {} {} {}",
+            rx.into(),
+            ry.into(),
+            u8::from(large_arc),
+            u8::from(sweep),
+            x.into(),
+            y.into()
+        ))
+    }
+
+    #[must_use]
+    pub fn close(self) -> Self {
+        self.push(format_args!("Z"))
+    }
+}
+
+/// `<path>`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    attributes: Attributes,
+}
+
+impl Path {
+    pub fn new(data: &PathData) -> Self {
+        let mut attributes = Attributes::default();
+        attributes.set("d", &data.0);
+        Self { attributes }
+    }
+}
+
+empty_element!(Path, "path");
+
+/// `<line>`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    attributes: Attributes,
+}
+
+impl Line {
+    pub fn new(
+        x1: impl Into<Number>,
+        y1: impl Into<Number>,
+        x2: impl Into<Number>,
+        y2: impl Into<Number>,
+    ) -> Self {
+        let mut attributes = Attributes::default();
+        attributes.set("x1", x1.into());
+        attributes.set("y1", y1.into());
+        attributes.set("x2", x2.into());
+        attributes.set("y2", y2.into());
+        Self { attributes }
+    }
+}
+
+empty_element!(Line, "line");
+
+/// Format a list of points for the `points` attribute
+fn points_attr<N: Into<Number>>(points: impl IntoIterator<Item = (N, N)>) -> String {
+    use fmt::Write;
+
+    let mut s = String::new();
+    for (x, y) in points {
+        if !s.is_empty() {
+            s.push(' ');
+        }
+        write!(s, "{},{}", x.into(), y.into()).unwrap();
+    }
+    s
+}
+
+/// `<polyline>`, an open shape
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polyline {
+    attributes: Attributes,
+}
+
+impl Polyline {
+    pub fn new<N: Into<Number>>(points: impl IntoIterator<Item = (N, N)>) -> Self {
+        let mut attributes = Attributes::default();
+        attributes.set("points", points_attr(points));
+        Self { attributes }
+    }
+}
+
+empty_element!(Polyline, "polyline");
+
+/// `<polygon>`, a closed shape
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    attributes: Attributes,
+}
+
+impl Polygon {
+    pub fn new<N: Into<Number>>(points: impl IntoIterator<Item = (N, N)>) -> Self {
+        let mut attributes = Attributes::default();
+        attributes.set("points", points_attr(points));
+        Self { attributes }
+    }
+}
+
+empty_element!(Polygon, "polygon");
+
+/// `<rect>`. `(x, y)` is the top left corner.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rect {
+    attributes: Attributes,
+}
+
+impl Rect {
+    pub fn new(
+        x: impl Into<Number>,
+        y: impl Into<Number>,
+        width: impl Into<Number>,
+        height: impl Into<Number>,
+    ) -> Self {
+        let mut attributes = Attributes::default();
+        attributes.set("x", x.into());
+        attributes.set("y", y.into());
+        attributes.set("width", width.into());
+        attributes.set("height", height.into());
+        Self { attributes }
+    }
+
+    /// Round the corners with radius `r`
+    #[must_use]
+    pub fn corner_radius(self, r: impl Into<Number>) -> Self {
+        self.set("rx", r.into())
+    }
+}
+
+empty_element!(Rect, "rect");
+
+/// `<ellipse>`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ellipse {
+    attributes: Attributes,
+}
+
+impl Ellipse {
+    pub fn new(
+        cx: impl Into<Number>,
+        cy: impl Into<Number>,
+        rx: impl Into<Number>,
+        ry: impl Into<Number>,
+    ) -> Self {
+        let mut attributes = Attributes::default();
+        attributes.set("cx", cx.into());
+        attributes.set("cy", cy.into());
+        attributes.set("rx", rx.into());
+        attributes.set("ry", ry.into());
+        Self { attributes }
+    }
+}
+
+empty_element!(Ellipse, "ellipse");
+
+/// `<circle>`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Circle {
+    attributes: Attributes,
+}
+
+impl Circle {
+    pub fn new(cx: impl Into<Number>, cy: impl Into<Number>, r: impl Into<Number>) -> Self {
+        let mut attributes = Attributes::default();
+        attributes.set("cx", cx.into());
+        attributes.set("cy", cy.into());
+        attributes.set("r", r.into());
+        Self { attributes }
+    }
+}
+
+empty_element!(Circle, "circle");
+
+/// `<image>`. The link is written as both `href` and `xlink:href`, so the
+/// document needs `xmlns:xlink` set for older viewers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    attributes: Attributes,
+}
+
+impl Image {
+    pub fn new(
+        x: impl Into<Number>,
+        y: impl Into<Number>,
+        width: impl Into<Number>,
+        height: impl Into<Number>,
+        href: impl fmt::Display,
+    ) -> Self {
+        let href = href.to_string();
+        let mut attributes = Attributes::default();
+        attributes.set("x", x.into());
+        attributes.set("y", y.into());
+        attributes.set("width", width.into());
+        attributes.set("height", height.into());
+        attributes.set("href", &href);
+        attributes.set("xlink:href", href);
+        Self { attributes }
+    }
+}
+
+empty_element!(Image, "image");
+
+/// `<text>`
+#[derive(Clone, Debug, PartialEq)]
 pub struct Text {
-    attributes: Vec<SvgAttribute>,
+    attributes: Attributes,
     content: String,
 }
 
 impl Text {
-    fn new(text: &str) -> Self {
+    pub fn new(x: impl Into<Number>, y: impl Into<Number>, content: impl Into<String>) -> Self {
+        let mut attributes = Attributes::default();
+        attributes.set("x", x.into());
+        attributes.set("y", y.into());
         Self {
-            attributes: Vec::new(),
-            content: text.to_owned(),
+            attributes,
+            content: content.into(),
         }
     }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+element_common!(Text);
+
+impl WriteBuf for Text {
+    fn write_buf<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+        write!(f, "<text")?;
+        self.attributes.write_buf(f)?;
+        write!(f, ">")?;
+        escape(&self.content, f)?;
+        writeln!(f, "</text>")
+    }
+}
+
+/// `<g>`, a group of elements that share attributes and transforms
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Group {
+    attributes: Attributes,
+    elements: Vec<Element>,
+}
+
+impl Group {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a child element
+    #[must_use]
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, element: impl Into<Element>) -> Self {
+        self.push(element);
+        self
+    }
+
+    /// Append a child element
+    pub fn push(&mut self, element: impl Into<Element>) {
+        self.elements.push(element.into());
+    }
+
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+element_common!(Group);
+
+impl WriteBuf for Group {
+    fn write_buf<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+        write!(f, "<g")?;
+        self.attributes.write_buf(f)?;
+        writeln!(f, ">")?;
+        for element in &self.elements {
+            element.write_buf(f)?;
+        }
+        writeln!(f, "</g>")
+    }
+}
+
+/// `<!-- comment -->`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comment(String);
+
+impl Comment {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self(text.into())
+    }
+}
+
+impl From<Comment> for Element {
+    fn from(value: Comment) -> Self {
+        Element::Comment(value)
+    }
+}
+
+impl WriteBuf for Comment {
+    fn write_buf<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+        // `--` may not appear within a comment, so split every run of dashes.
+        // The padding keeps a leading `>` or trailing `-` from touching the
+        // delimiters.
+        let mut text = String::with_capacity(self.0.len());
+        for ch in self.0.chars() {
+            if ch == '-' && text.ends_with('-') {
+                text.push(' ');
+            }
+            text.push(ch);
+        }
+        writeln!(f, "<!-- {text} -->")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        let svg = Svg::new().add(Text::new(0, 0, "a < b & \"c\"").fill("#000000"));
+        let out = svg.to_string();
+        assert!(
+            out.contains(
+                r##"<text x="0" y="0" fill="#000000">a &lt; b &amp; &quot;c&quot;</text>"##
+            ),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn test_comment_dashes() {
+        let comment = |text: &str| {
+            let mut out = String::new();
+            Comment::new(text).write_buf(&mut out).unwrap();
+            out
+        };
+
+        let out = comment("a---b");
+        assert_eq!(out, "<!-- a- - -b -->\n");
+        assert!(!out[4..out.len() - 4].contains("--"), "{out}");
+
+        let out = comment("trailing--");
+        assert_eq!(out, "<!-- trailing- - -->\n");
+        assert!(!out.contains("--->"), "{out}");
+    }
+
+    #[test]
+    fn test_group_and_path() {
+        let path = PathData::new()
+            .move_to(0, 0)
+            .arc_to((5, 5), false, true, (10, 0))
+            .close();
+        let svg = Svg::new()
+            .view_box(ViewBox::new(-1, -1.5, 12, 3))
+            .width(Length::Mm(Number::F(2.5)))
+            .add(
+                Group::new()
+                    .id("grp")
+                    .transform(Transform::Translate(1.into(), 2.into()))
+                    .transform(Transform::Rotate(90.into()))
+                    .add(Path::new(&path).stroke("red")),
+            );
+
+        let mut buf = Vec::new();
+        svg.write_to(&mut buf).unwrap();
+        let out = String::from_utf8(buf).unwrap();
+
+        assert_eq!(out, svg.to_string());
+        assert!(
+            out.starts_with(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-1 -1.5 12 3" width="2.5mm">"#
+            ),
+            "{out}"
+        );
+        assert!(
+            out.contains(r#"<g id="grp" transform="translate(1 2) rotate(90)">"#),
+            "{out}"
+        );
+        assert!(
+            out.contains(r#"<path d="M 0 0 A 5 5 0 0 1 10 0 Z" stroke="red"/>"#),
+            "{out}"
+        );
+    }
 }
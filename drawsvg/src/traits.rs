@@ -1,8 +1,6 @@
-use convert_case::Case;
-use core::fmt::Error;
-use core::fmt::Write;
-use std::any::type_name;
+use core::fmt::{Error, Write};
 
+/// Anything that can write itself as SVG markup
 pub trait WriteBuf {
     fn write_buf<W: Write>(&self, f: &mut W) -> Result<(), Error>;
 }
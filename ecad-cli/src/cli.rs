@@ -15,6 +15,10 @@ pub enum Subcommand {
     Schdoc(CmdSchdoc),
     #[command(subcommand, alias = "pl")]
     Pcblib(CmdPcblib),
+    #[command(subcommand, alias = "p")]
+    Pcbdoc(CmdPcbdoc),
+    /// Check that symbols link to existing footprints with matching pads
+    Validate(ValidateArgs),
     /// Tools for working with the CFB format, which is used by Altium files.
     #[command(subcommand)]
     Cfb(CmdCfb),
@@ -35,6 +39,26 @@ pub enum CmdPcblib {
     List(LibListArgs),
 }
 
+#[derive(Debug, clap::Subcommand)]
+pub enum CmdPcbdoc {
+    /// Print board size, layer, via, pad and component counts, the smallest
+    /// track and drill, and copper area per layer as JSON
+    Stats(PcbdocArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct PcbdocArgs {
+    /// Name of the file to open
+    pub fname: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ValidateArgs {
+    /// `.SchLib` and `.PcbLib` files to check together
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+}
+
 #[derive(Debug, clap::Args)]
 pub struct LibListArgs {
     /// Name of the file to open
@@ -6,6 +6,9 @@ use std::{
 
 use altium::{
     sch::{Component, ComponentMeta, SchRecord},
+    validate::LinkValidator,
+    PcbDoc,
+    PcbLib,
     SchDoc,
     SchLib,
 };
@@ -13,11 +16,11 @@ use anyhow::Context;
 use cap_std::fs::{Dir, OpenOptions};
 use cfb::CompoundFile;
 use clap::Parser;
-use cli::{CmdSchdoc, CmdSchlib};
+use cli::{CmdPcbdoc, CmdSchdoc, CmdSchlib};
 use regex::Regex;
 use serde_json::{json, Value};
 
-use crate::cli::{CfbArgs, CmdCfb, Subcommand};
+use crate::cli::{CfbArgs, CmdCfb, Subcommand, ValidateArgs};
 
 mod cli;
 
@@ -30,6 +33,8 @@ fn main() {
         Subcommand::Pcblib(_pcblib_cmd) => {
             unimplemented!("not yet implemented")
         }
+        Subcommand::Pcbdoc(pcbdoc_cmd) => handle_pcbdoc_cmd(pcbdoc_cmd),
+        Subcommand::Validate(args) => handle_validate(args),
         Subcommand::Cfb(cfb_cmd) => handle_cfb_cmd(cfb_cmd),
     };
 }
@@ -82,6 +87,51 @@ fn handle_schdoc_cmd(cmd: CmdSchdoc) {
     }
 }
 
+fn handle_pcbdoc_cmd(cmd: CmdPcbdoc) {
+    match cmd {
+        CmdPcbdoc::Stats(args) => {
+            let doc = PcbDoc::open(&args.fname).unwrap();
+            let stats = doc.statistics().unwrap();
+            let s = serde_json::to_string_pretty(&stats).unwrap();
+            println!("{s}");
+        }
+    }
+}
+
+fn handle_validate(args: ValidateArgs) {
+    let mut validator = LinkValidator::new();
+
+    for path in &args.files {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let ext = path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_ascii_lowercase();
+
+        match ext.as_str() {
+            "schlib" => {
+                let lib = SchLib::open(path).unwrap();
+                validator.add_schlib(&name, &lib).unwrap();
+            }
+            "pcblib" => {
+                let lib = PcbLib::open(path).unwrap();
+                validator.add_pcblib(&name, &lib).unwrap();
+            }
+            _ => panic!("unsupported file type `{}`", path.display()),
+        }
+    }
+
+    let issues = validator.validate();
+    for issue in &issues {
+        println!("{issue}");
+    }
+    if !issues.is_empty() {
+        eprintln!("{} issues found", issues.len());
+        std::process::exit(1);
+    }
+}
+
 fn to_re(re: &str) -> Regex {
     regex::RegexBuilder::new(re)
         .case_insensitive(true)
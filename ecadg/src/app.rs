@@ -1,6 +1,8 @@
 use std::sync::Arc;
 use std::{path::PathBuf, sync::atomic::Ordering::SeqCst};
 
+use altium::draw::{RenderOptions, Theme};
+use altium::pcb::Layer;
 use altium::sch::SchRecord;
 use egui::{ScrollArea, TextStyle, Ui};
 use egui_extras::{Column, TableBuilder};
@@ -9,6 +11,9 @@ use log::debug;
 use crate::backend::{
     open_file_async,
     GlobalQueue,
+    PcbDocTab,
+    PcbLayers,
+    PcbLibTab,
     SchDocTab,
     SchLibTab,
     TabData,
@@ -32,6 +37,9 @@ pub struct GuiApp {
 
     recent_files: Vec<PathBuf>,
 
+    /// Theme and filters used when drawing
+    render_options: RenderOptions,
+
     /// Index of the active tab
     #[serde(skip)]
     active_tab: Option<usize>,
@@ -81,6 +89,8 @@ impl GuiApp {
                         ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
                     }
                 });
+
+                ui.menu_button("View", |ui| make_view_menu(ui, &mut self.render_options));
             });
         });
 
@@ -168,6 +178,8 @@ fn make_left_panel(app: &mut GuiApp, ui: &mut Ui) {
         match &mut tab.inner {
             TabDataInner::SchLib(tab) => make_left_panel_schlib(ui, tab),
             TabDataInner::SchDoc(tab) => make_left_panel_schdoc(ui, tab),
+            TabDataInner::PcbLib(tab) => make_left_panel_pcblib(ui, tab),
+            TabDataInner::PcbDoc(tab) => make_layer_list(ui, &mut tab.layers),
         }
     }
 
@@ -216,6 +228,56 @@ fn make_left_panel_schlib(ui: &mut Ui, tab: &mut SchLibTab) {
 #[allow(clippy::needless_pass_by_ref_mut)]
 fn make_left_panel_schdoc(ui: &Ui, tab: &mut SchDocTab) {}
 
+fn make_left_panel_pcblib(ui: &mut Ui, tab: &mut PcbLibTab) {
+    make_layer_list(ui, &mut tab.layers);
+    ui.separator();
+
+    ui.horizontal(|ui| ui.text_edit_singleline(&mut tab.search_query));
+    tab.hide_items.clear();
+    if !tab.search_query.is_empty() {
+        let sq_lc = tab.search_query.to_lowercase();
+        for (idx, fp) in tab.footprints.iter().enumerate() {
+            if !fp.name().to_lowercase().contains(&sq_lc) {
+                tab.hide_items.push(idx);
+            }
+        }
+    }
+
+    ScrollArea::vertical().auto_shrink([false; 2]).show_rows(
+        ui,
+        ui.text_style_height(&TextStyle::Body),
+        tab.footprints.len(),
+        |ui, row_range| {
+            for row_idx in row_range {
+                if tab.hide_items.contains(&row_idx) {
+                    continue;
+                }
+                ui.selectable_value(
+                    &mut tab.active_footprint_idx,
+                    Some(row_idx),
+                    tab.footprints[row_idx].name(),
+                );
+            }
+        },
+    );
+}
+
+/// A checkbox for each PCB layer, topmost first
+fn make_layer_list(ui: &mut Ui, layers: &mut PcbLayers) {
+    ui.collapsing("Layers", |ui| {
+        let order: Vec<Layer> = layers.draw_order.iter().rev().copied().collect();
+        for layer in order {
+            let mut visible = !layers.hidden.contains(&layer);
+            if ui.checkbox(&mut visible, layer.to_string()).changed() {
+                layers.hidden.retain(|l| *l != layer);
+                if !visible {
+                    layers.hidden.push(layer);
+                }
+            }
+        }
+    });
+}
+
 /// The central main content panel the region left after adding `TopPanel`'s and `SidePanel`'s
 #[allow(clippy::needless_pass_by_ref_mut)]
 fn make_center_panel(app: &mut GuiApp, ui: &mut Ui) {
@@ -252,30 +314,78 @@ fn make_center_panel(app: &mut GuiApp, ui: &mut Ui) {
         ui.input(|istate| view_state.update_with_input_state(istate));
     }
 
+    let opts = &app.render_options;
     match &mut tabdata.inner {
-        TabDataInner::SchLib(tab) => make_center_panel_schlib(ui, tab, view_state),
-        TabDataInner::SchDoc(tab) => make_center_panel_schdoc(ui, tab, view_state),
+        TabDataInner::SchLib(tab) => make_center_panel_schlib(ui, tab, view_state, opts),
+        TabDataInner::SchDoc(tab) => make_center_panel_schdoc(ui, tab, view_state, opts),
+        TabDataInner::PcbLib(tab) => make_center_panel_pcblib(ui, tab, view_state, opts),
+        TabDataInner::PcbDoc(tab) => make_center_panel_pcbdoc(ui, tab, view_state, opts),
     }
 }
 
+/// Theme and visibility settings
+fn make_view_menu(ui: &mut Ui, opts: &mut RenderOptions) {
+    ui.label("Theme");
+    ui.radio_value(&mut opts.theme, Theme::Altium, "Altium");
+    ui.radio_value(&mut opts.theme, Theme::Monochrome, "Monochrome");
+    ui.radio_value(&mut opts.theme, Theme::Dark, "Dark");
+    ui.separator();
+    ui.checkbox(&mut opts.show_hidden_pins, "Show hidden pins");
+    ui.checkbox(&mut opts.show_hidden_params, "Show hidden parameters");
+    ui.checkbox(&mut opts.show_pin_numbers, "Show pin numbers");
+}
+
 #[allow(clippy::needless_pass_by_ref_mut)]
-fn make_center_panel_schlib(ui: &mut Ui, tab: &SchLibTab, vs: &ViewState) {
+fn make_center_panel_schlib(ui: &mut Ui, tab: &SchLibTab, vs: &ViewState, opts: &RenderOptions) {
     let Some(comp) = tab.active_component() else {
         ui.label("no component selected");
         return;
     };
 
+    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+        ui.painter().add(crate::gfx::SchLibCallback::callback(
+            Arc::clone(comp),
+            vs,
+            opts,
+        ))
+    });
+}
+
+#[allow(clippy::needless_pass_by_ref_mut)]
+fn make_center_panel_schdoc(
+    ui: &mut Ui,
+    tab: &SchDocTab,
+    vs: &mut ViewState,
+    opts: &RenderOptions,
+) {
     egui::Frame::canvas(ui.style()).show(ui, |ui| {
         ui.painter()
-            .add(crate::gfx::SchLibCallback::callback(Arc::clone(comp), vs))
+            .add(crate::gfx::SchDocCallback::callback(tab, vs, opts))
+    });
+}
+
+#[allow(clippy::needless_pass_by_ref_mut)]
+fn make_center_panel_pcblib(ui: &mut Ui, tab: &PcbLibTab, vs: &ViewState, opts: &RenderOptions) {
+    let Some(footprint) = tab.active_footprint() else {
+        ui.label("no footprint selected");
+        return;
+    };
+
+    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+        ui.painter().add(crate::gfx::PcbLibCallback::callback(
+            Arc::clone(footprint),
+            &tab.layers,
+            vs,
+            opts,
+        ))
     });
 }
 
 #[allow(clippy::needless_pass_by_ref_mut)]
-fn make_center_panel_schdoc(ui: &mut Ui, tab: &SchDocTab, vs: &mut ViewState) {
+fn make_center_panel_pcbdoc(ui: &mut Ui, tab: &PcbDocTab, vs: &ViewState, opts: &RenderOptions) {
     egui::Frame::canvas(ui.style()).show(ui, |ui| {
         ui.painter()
-            .add(crate::gfx::SchDocCallback::callback(tab, vs))
+            .add(crate::gfx::PcbDocCallback::callback(tab, vs, opts))
     });
 }
 
@@ -288,6 +398,7 @@ fn make_right_panel(app: &mut GuiApp, ui: &mut Ui) {
         match &mut tab.inner {
             TabDataInner::SchLib(tab) => make_right_panel_schlib(ui, tab),
             TabDataInner::SchDoc(tab) => make_right_panel_schdoc(ui, tab),
+            TabDataInner::PcbLib(_) | TabDataInner::PcbDoc(_) => (),
         }
     }
 
@@ -11,9 +11,13 @@ use std::{
 };
 
 use altium::{
+    draw::RenderOptions,
     font::FontCollection,
+    pcb::{Footprint, Layer, PcbDrawCtx, PcbRecord},
     sch::{Component, SchRecord, Storage},
     Location,
+    PcbDoc,
+    PcbLib,
     SchDoc,
     SchLib,
 };
@@ -207,6 +211,8 @@ impl Default for ViewState {
 pub enum TabDataInner {
     SchLib(SchLibTab),
     SchDoc(SchDocTab),
+    PcbLib(PcbLibTab),
+    PcbDoc(PcbDocTab),
 }
 
 /// Data for a single schematic library tab. This needs to hold a list of components
@@ -236,6 +242,62 @@ pub struct SchDocTab {
     pub name: Arc<str>,
 }
 
+/// Which PCB layers get drawn, shared by footprint and board tabs
+#[derive(Clone, Debug, Default)]
+pub struct PcbLayers {
+    /// Copper layers from top to bottom
+    pub copper: Vec<Layer>,
+    /// Layers in drawing order, bottom-most first
+    pub draw_order: Vec<Layer>,
+    pub hidden: Vec<Layer>,
+}
+
+impl PcbLayers {
+    /// Default layer settings for a board with these copper layers
+    pub fn new(copper: Vec<Layer>) -> Self {
+        let options = RenderOptions::default();
+        let ctx = PcbDrawCtx::new(&options, &copper);
+        Self {
+            draw_order: ctx.draw_order,
+            hidden: ctx.hidden,
+            copper,
+        }
+    }
+
+    /// A context for drawing with these layer settings
+    pub fn draw_ctx<'a>(&self, options: &'a RenderOptions) -> PcbDrawCtx<'a> {
+        let mut ctx = PcbDrawCtx::new(options, &self.copper);
+        ctx.draw_order.clone_from(&self.draw_order);
+        ctx.hidden.clone_from(&self.hidden);
+        ctx
+    }
+}
+
+/// Data for a single PCB library tab, a list of footprints with a selection
+#[derive(Debug, Default)]
+pub struct PcbLibTab {
+    pub footprints: Vec<Arc<Footprint>>,
+    /// Index in `footprints` to display
+    pub active_footprint_idx: Option<usize>,
+    pub search_query: String,
+    /// Indices in `footprints` that don't match the search
+    pub hide_items: Vec<usize>,
+    pub layers: PcbLayers,
+}
+
+impl PcbLibTab {
+    pub fn active_footprint(&self) -> Option<&Arc<Footprint>> {
+        self.active_footprint_idx.map(|idx| &self.footprints[idx])
+    }
+}
+
+#[derive(Debug)]
+pub struct PcbDocTab {
+    pub records: Arc<[PcbRecord]>,
+    pub nets: Arc<[Box<str>]>,
+    pub layers: PcbLayers,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum FileTy {
     SchLib,
@@ -269,8 +331,8 @@ pub fn open_file_sync_err(path: PathBuf) -> Result<(), String> {
     let optional_new_tab = match file_ty {
         FileTy::SchLib => schlib_to_tab(path),
         FileTy::SchDoc => schdoc_to_tab(path),
-        FileTy::PcbLib => return Err("PcbLib not yet supported".into()),
-        FileTy::PcbDoc => return Err("PcbDoc not yet supported".into()),
+        FileTy::PcbLib => pcblib_to_tab(path),
+        FileTy::PcbDoc => pcbdoc_to_tab(path),
     };
 
     if let Some(new_tab) = optional_new_tab {
@@ -341,6 +403,70 @@ fn schdoc_to_tab(path: PathBuf) -> Option<TabData> {
     Some(new_tab)
 }
 
+/// Create a tab if everything is OK, push an error if not
+fn pcblib_to_tab(path: PathBuf) -> Option<TabData> {
+    let lib = match PcbLib::open(&path) {
+        Ok(lib) => lib,
+        Err(e) => {
+            GlobalQueue::push_err(e.to_string());
+            return None;
+        }
+    };
+
+    let mut footprints = Vec::new();
+    for meta in lib.footprint_meta() {
+        match lib.try_get_footprint(meta.name()) {
+            Ok(Some(footprint)) => footprints.push(Arc::new(footprint)),
+            Ok(None) => (),
+            Err(e) => GlobalQueue::push_err(e.to_string()),
+        }
+    }
+    footprints.sort_unstable_by(|a, b| a.name().cmp(b.name()));
+
+    let inner = PcbLibTab {
+        footprints,
+        layers: PcbLayers::new(vec![Layer::Top, Layer::Bottom]),
+        ..Default::default()
+    };
+
+    let new_tab = TabData {
+        title: make_title(&path),
+        view_state: ViewState::default(),
+        path,
+        inner: TabDataInner::PcbLib(inner),
+    };
+
+    Some(new_tab)
+}
+
+/// Create a tab if everything is OK, push an error if not
+fn pcbdoc_to_tab(path: PathBuf) -> Option<TabData> {
+    let read = || -> Result<PcbDocTab, altium::Error> {
+        let doc = PcbDoc::open(&path)?;
+        Ok(PcbDocTab {
+            records: doc.records()?.into(),
+            nets: doc.nets()?.into(),
+            layers: PcbLayers::new(doc.copper_layers()?),
+        })
+    };
+    let inner = match read() {
+        Ok(inner) => inner,
+        Err(e) => {
+            GlobalQueue::push_err(e.to_string());
+            return None;
+        }
+    };
+
+    let new_tab = TabData {
+        title: make_title(&path),
+        view_state: ViewState::default(),
+        path,
+        inner: TabDataInner::PcbDoc(inner),
+    };
+
+    Some(new_tab)
+}
+
 fn make_title(path: &Path) -> Box<str> {
     path.file_name()
         .unwrap_or("[Unnamed]".as_ref())
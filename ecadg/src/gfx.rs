@@ -10,14 +10,16 @@ mod triangle;
 
 use std::sync::Arc;
 
+use altium::draw::RenderOptions;
 use altium::font::FontCollection;
+use altium::pcb::{Footprint, PcbRecord};
 use altium::sch::{self, Component, SchDrawCtx, SchRecord};
 use eframe::egui_wgpu;
 use egui::PaintCallbackInfo;
 use egui_wgpu::wgpu::{CommandBuffer, CommandEncoder, Device, Queue, RenderPass};
 use egui_wgpu::CallbackResources;
 
-use crate::backend::{SchDocTab, ViewState};
+use crate::backend::{PcbDocTab, PcbLayers, SchDocTab, ViewState};
 
 // Called once
 pub fn init_graphics(cc: &eframe::CreationContext<'_>) {
@@ -53,14 +55,20 @@ struct GraphicsCtx {
 pub struct SchLibCallback {
     view_state: ViewState,
     comp: Arc<Component>,
+    options: RenderOptions,
 }
 
 impl SchLibCallback {
     /// Entrypoint for rendering a single component in a schematic library
-    pub fn callback(comp: Arc<Component>, vs: &ViewState) -> egui::PaintCallback {
+    pub fn callback(
+        comp: Arc<Component>,
+        vs: &ViewState,
+        options: &RenderOptions,
+    ) -> egui::PaintCallback {
         let cb_ctx = Self {
             view_state: *vs,
             comp,
+            options: options.clone(),
         };
         egui_wgpu::Callback::new_paint_callback(vs.rect, cb_ctx)
     }
@@ -79,7 +87,7 @@ impl egui_wgpu::CallbackTrait for SchLibCallback {
 
         ctx.grid.prepare(queue, self.view_state);
         ctx.tess
-            .prepare(queue, self.view_state, self.comp.as_ref(), &());
+            .prepare(queue, self.view_state, self.comp.as_ref(), &self.options);
         ctx.origin.prepare(queue, self.view_state);
 
         Vec::new()
@@ -106,17 +114,23 @@ pub struct SchDocCallback {
     storage: Arc<sch::Storage>,
     fonts: Arc<FontCollection>,
     name: Arc<str>,
+    options: RenderOptions,
 }
 
 impl SchDocCallback {
     /// Entrypoint for rendering a single component in a schematic library
-    pub fn callback(tab: &SchDocTab, vs: &ViewState) -> egui::PaintCallback {
+    pub fn callback(
+        tab: &SchDocTab,
+        vs: &ViewState,
+        options: &RenderOptions,
+    ) -> egui::PaintCallback {
         let cb_ctx = Self {
             view_state: *vs,
             records: Arc::clone(&tab.records),
             fonts: Arc::clone(&tab.fonts),
             storage: Arc::clone(&tab.storage),
             name: Arc::clone(&tab.name),
+            options: options.clone(),
         };
         egui_wgpu::Callback::new_paint_callback(vs.rect, cb_ctx)
     }
@@ -137,6 +151,7 @@ impl egui_wgpu::CallbackTrait for SchDocCallback {
             fonts: &self.fonts,
             storage: &self.storage,
             name: &self.name,
+            options: &self.options,
         };
 
         ctx.grid.prepare(queue, self.view_state);
@@ -160,3 +175,125 @@ impl egui_wgpu::CallbackTrait for SchDocCallback {
         ctx.origin.paint(render_pass, self.view_state);
     }
 }
+
+/// Callback for drawing a single footprint from a PCB library
+pub struct PcbLibCallback {
+    view_state: ViewState,
+    footprint: Arc<Footprint>,
+    layers: PcbLayers,
+    options: RenderOptions,
+}
+
+impl PcbLibCallback {
+    /// Entrypoint for rendering a single footprint in a PCB library
+    pub fn callback(
+        footprint: Arc<Footprint>,
+        layers: &PcbLayers,
+        vs: &ViewState,
+        options: &RenderOptions,
+    ) -> egui::PaintCallback {
+        let cb_ctx = Self {
+            view_state: *vs,
+            footprint,
+            layers: layers.clone(),
+            options: options.clone(),
+        };
+        egui_wgpu::Callback::new_paint_callback(vs.rect, cb_ctx)
+    }
+}
+
+impl egui_wgpu::CallbackTrait for PcbLibCallback {
+    fn prepare(
+        &self,
+        _device: &Device,
+        queue: &Queue,
+        _desc: &egui_wgpu::ScreenDescriptor,
+        _encoder: &mut CommandEncoder,
+        resources: &mut CallbackResources,
+    ) -> Vec<CommandBuffer> {
+        let ctx: &mut GraphicsCtx = resources.get_mut().unwrap();
+        let pcb_ctx = self.layers.draw_ctx(&self.options);
+
+        ctx.grid.prepare(queue, self.view_state);
+        ctx.tess
+            .prepare(queue, self.view_state, self.footprint.as_ref(), &pcb_ctx);
+        ctx.origin.prepare(queue, self.view_state);
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: PaintCallbackInfo,
+        render_pass: &mut RenderPass<'static>,
+        resources: &'a CallbackResources,
+    ) {
+        let ctx: &GraphicsCtx = resources.get().unwrap();
+
+        ctx.grid.paint(render_pass, self.view_state);
+        ctx.tess.paint(render_pass, self.view_state);
+        ctx.origin.paint(render_pass, self.view_state);
+    }
+}
+
+/// Callback for drawing a board
+pub struct PcbDocCallback {
+    view_state: ViewState,
+    records: Arc<[PcbRecord]>,
+    nets: Arc<[Box<str>]>,
+    layers: PcbLayers,
+    options: RenderOptions,
+}
+
+impl PcbDocCallback {
+    /// Entrypoint for rendering every primitive on a board
+    pub fn callback(
+        tab: &PcbDocTab,
+        vs: &ViewState,
+        options: &RenderOptions,
+    ) -> egui::PaintCallback {
+        let cb_ctx = Self {
+            view_state: *vs,
+            records: Arc::clone(&tab.records),
+            nets: Arc::clone(&tab.nets),
+            layers: tab.layers.clone(),
+            options: options.clone(),
+        };
+        egui_wgpu::Callback::new_paint_callback(vs.rect, cb_ctx)
+    }
+}
+
+impl egui_wgpu::CallbackTrait for PcbDocCallback {
+    fn prepare(
+        &self,
+        _device: &Device,
+        queue: &Queue,
+        _desc: &egui_wgpu::ScreenDescriptor,
+        _encoder: &mut CommandEncoder,
+        resources: &mut CallbackResources,
+    ) -> Vec<CommandBuffer> {
+        let ctx: &mut GraphicsCtx = resources.get_mut().unwrap();
+        let mut pcb_ctx = self.layers.draw_ctx(&self.options);
+        pcb_ctx.nets = self.nets.to_vec();
+
+        ctx.grid.prepare(queue, self.view_state);
+        ctx.tess
+            .prepare(queue, self.view_state, &self.records.as_ref(), &pcb_ctx);
+        ctx.origin.prepare(queue, self.view_state);
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: PaintCallbackInfo,
+        render_pass: &mut RenderPass<'static>,
+        resources: &'a CallbackResources,
+    ) {
+        let ctx: &GraphicsCtx = resources.get().unwrap();
+
+        ctx.grid.paint(render_pass, self.view_state);
+        ctx.tess.paint(render_pass, self.view_state);
+        ctx.origin.paint(render_pass, self.view_state);
+    }
+}
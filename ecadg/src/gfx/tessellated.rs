@@ -530,8 +530,20 @@ impl Canvas for TessCtx {
         for loc in locations {
             builder.line_to(point(loc.x_f32(), loc.y_f32()));
         }
-
         builder.close();
+
+        // Holes are extra subpaths, which the even-odd fill rule leaves empty
+        for hole in item.holes {
+            let Some((first_loc, locations)) = hole.split_first() else {
+                continue;
+            };
+            builder.begin(point(first_loc.x_f32(), first_loc.y_f32()));
+            for loc in locations {
+                builder.line_to(point(loc.x_f32(), loc.y_f32()));
+            }
+            builder.close();
+        }
+
         let path = builder.build();
 
         self.fill_tess